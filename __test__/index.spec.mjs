@@ -1,29 +1,1108 @@
-import { parse } from 'node:path';
+import { closeSync, fstatSync, mkdirSync, mkdtempSync, openSync, readFileSync, realpathSync, renameSync, rmSync, statSync, writeFileSync } from 'node:fs';
+import { homedir, tmpdir } from 'node:os';
+import { dirname, join, parse } from 'node:path';
 
 import test from 'ava';
-import alias from 'macos-alias';
 
-import { create } from '../index.js';
-import { fileURLToPath } from 'node:url';
+import {
+  Alias,
+  asDsStoreBlob,
+  create,
+  createAsync,
+  createBase64,
+  createBookmark,
+  createDebug,
+  createDetailed,
+  createInto,
+  decodeBookmark,
+  estimateRecordSize,
+  DS_STORE_MAX_ALIAS_LENGTH,
+  createForFd,
+  createForStagedDmg,
+  createBackgroundAlias,
+  createForTree,
+  createMany,
+  createFromInfo,
+  encodeBookmark,
+  encodeInfo,
+  extractFromResourceFork,
+  fileIds,
+  fromJSON,
+  decode,
+  decodeAll,
+  validate,
+  getVolumeInfo,
+  listVolumes,
+  parseBookmark,
+  readAliasFile,
+  rebaseAlias,
+  recordInfo,
+  relinkAlias,
+  resolve,
+  resolveAlias,
+  statAliasTarget,
+  systemAliasFor,
+  toJSON,
+  updateAlias,
+  verify,
+  wrapAsResourceFork,
+  writeAliasFile,
+} from '../index.js';
+import { fileURLToPath, pathToFileURL } from 'node:url';
+import { Worker } from 'node:worker_threads';
 
 const selfpath = fileURLToPath(import.meta.url);
+const bindingPath = fileURLToPath(new URL('../index.js', import.meta.url));
 
-test('create should work', (t) => {
-  const buf = create(selfpath);
-  const info = alias.decode(buf);
+/**
+ * Run `body` in `count` worker threads at once, each of which loads its own
+ * copy of the binding as `binding`, and collect what each posts back.
+ */
+function inWorkers(count, body, workerData) {
+  const source = `
+    const { parentPort, workerData } = require('node:worker_threads');
+    const binding = require(${JSON.stringify(bindingPath)});
+    parentPort.postMessage((() => { ${body} })());
+  `;
+  const run = () =>
+    new Promise((resolve, reject) => {
+      const worker = new Worker(source, { eval: true, workerData });
+      worker.once('message', resolve);
+      worker.once('error', reject);
+    });
+  return Promise.all(Array.from({ length: count }, run));
+}
 
-  t.is('file', info.target.type);
-  t.is(parse(selfpath).base, info.target.filename);
-});
+const FIXTURE = 'AAAAAAEqAAIAAApUZXN0IFRpdGxlAAAAAAAAAAAAAAAAAAAAAADO615USCsABQAAABMMVGVzdEJrZy50aWZmAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFM7rXlgAAAAAAAAAAP////8AAA0CAAAAAAAAAAAAAAAAAAAACy5iYWNrZ3JvdW5kAAABAAQAAAATAAIAJFRlc3QgVGl0bGU6LmJhY2tncm91bmQ6AFRlc3RCa2cudGlmZgAPABYACgBUAGUAcwB0ACAAVABpAHQAbABlABIAGS8uYmFja2dyb3VuZC9UZXN0QmtnLnRpZmYAABMAEy9Wb2x1bWVzL1Rlc3QgVGl0bGUA//8AAA==';
+
+if (process.platform === 'darwin') {
+  const { default: alias } = await import('macos-alias');
+
+  test('create should work', (t) => {
+    const buf = create(selfpath);
+    const info = alias.decode(buf);
+
+    t.is('file', info.target.type);
+    t.is(parse(selfpath).base, info.target.filename);
+  });
+
+  test('create should give the same records from several worker threads', async (t) => {
+    const targets = [selfpath, dirname(selfpath), homedir(), selfpath];
+    const expected = targets.map((target) => create(target));
+    const results = await inWorkers(
+      8,
+      `const records = [];
+      for (let i = 0; i < 50; i++) records.push(...workerData.targets.map((target) => binding.create(target)));
+      return records;`,
+      { targets },
+    );
+
+    for (const records of results) {
+      t.is(records.length, 50 * targets.length);
+      records.forEach((record, i) => t.true(Buffer.from(record).equals(expected[i % targets.length])));
+    }
+  });
+
+  test('create should slot extraRecords into the canonical order', (t) => {
+    const data = Buffer.from('custom');
+    const info = alias.decode(create(selfpath, { extraRecords: [{ type: 20, data }, { type: 2, data }] }));
+
+    t.deepEqual(info.extra.map((e) => e.type), [0, 1, 2, 14, 15, 18, 19, 20]);
+  });
+
+  test('create should allow a missing target with allowMissing', (t) => {
+    const missing = join(dirname(selfpath), 'not-there-yet.tiff');
+
+    t.throws(() => create(missing), { code: 'ERR_TARGET_NOT_FOUND' });
+    const info = alias.decode(create(missing, { allowMissing: true }));
+    t.is(info.target.filename, 'not-there-yet.tiff');
+    t.is(info.target.id, 0);
+  });
+
+  test('createDebug should expose the info behind the record', (t) => {
+    const { buffer, info, timings } = createDebug(selfpath);
+
+    t.deepEqual(buffer, create(selfpath));
+    t.false(Buffer.isBuffer(createDebug(selfpath, { output: 'uint8array' }).buffer));
+    t.is(info.targetFilename, parse(selfpath).base);
+    t.is(info.extra.find((e) => e.type === 18).preview.endsWith(parse(selfpath).base), true);
+    t.regex(info.targetCreated, /^\d{4}-\d\d-\d\dT/);
+    // APFS keeps creation dates.
+    t.is(info.targetCreatedSource, 'birthtime');
+    t.true(timings.lookup >= 0 && timings.encode >= 0);
+  });
+
+  test('createInto should write the record create returns', (t) => {
+    const record = create(selfpath);
+    const out = Buffer.alloc(1024, 0xaa);
+    const length = createInto(selfpath, out);
+
+    t.is(length, record.length);
+    t.deepEqual(out.subarray(0, length), record);
+    t.is(out[length], 0xaa);
+    t.is(createInto(selfpath, new Uint8Array(length)), length);
+    t.throws(() => createInto(selfpath, Buffer.alloc(length - 1)), { code: 'ERR_BUFFER_TOO_SMALL' });
+  });
+
+  test('createAsync should resolve to the record create returns', async (t) => {
+    t.deepEqual(await createAsync(selfpath), create(selfpath));
+    t.true(Buffer.isBuffer(await createAsync(pathToFileURL(selfpath))));
+    t.false(Buffer.isBuffer(await createAsync(selfpath, { output: 'uint8array' })));
+    await t.throwsAsync(createAsync(join(tmpdir(), 'macos-alias-missing')), { code: 'ERR_TARGET_NOT_FOUND' });
+    t.throws(() => createAsync(selfpath, { minimal: 'all' }), { code: 'ERR_INVALID_ARG' });
+  });
+
+  test('createBase64 should return the record create returns as base64', (t) => {
+    const base64 = createBase64(selfpath);
+
+    t.is(base64, create(selfpath).toString('base64'));
+    t.is(JSON.parse(toJSON(base64)).target.filename, parse(selfpath).base);
+  });
+
+  test('maxSize should refuse records longer than it', (t) => {
+    const size = estimateRecordSize(selfpath);
+
+    t.is(size, create(selfpath).length);
+    t.is(estimateRecordSize(pathToFileURL(selfpath), { minimal: true }), create(selfpath, { minimal: true }).length);
+    t.is(create(selfpath, { maxSize: size }).length, size);
+    t.throws(() => create(selfpath, { maxSize: size - 1 }), { code: 'ERR_RECORD_EXCEEDS_MAX_SIZE' });
+    t.throws(() => createDetailed(selfpath, { maxSize: size - 1 }), { code: 'ERR_RECORD_EXCEEDS_MAX_SIZE' });
+    t.is(estimateRecordSize(selfpath, { maxSize: 1 }), size);
+    t.true(size <= DS_STORE_MAX_ALIAS_LENGTH);
+  });
+
+  test('hiresDates should add the high-resolution creation dates', (t) => {
+    const types = (options) => decode(create(selfpath, options)).extra.map((e) => e.type);
+
+    t.false(types().includes(17));
+    t.deepEqual(types({ hiresDates: true }).filter((type) => type === 16 || type === 17), [16, 17]);
+    t.is(create(selfpath, { hiresDates: true }).length, create(selfpath).length + 24);
+  });
+
+  test('createDetailed should return the record and no warnings on APFS', (t) => {
+    const { buffer, warnings } = createDetailed(selfpath);
+
+    t.deepEqual(buffer, create(selfpath));
+    t.deepEqual(warnings, []);
+    t.false(Buffer.isBuffer(createDetailed(selfpath, { output: 'uint8array' }).buffer));
+  });
+
+  test('createDetailed should return the values encoded in the record', (t) => {
+    const detailed = createDetailed(selfpath);
+    const info = JSON.parse(toJSON(detailed.buffer));
+    const volume = getVolumeInfo(selfpath);
+
+    t.is(detailed.targetId, info.target.id);
+    t.is(detailed.parentId, info.parent.id);
+    t.is(detailed.parentName, info.parent.name);
+    t.is(detailed.volumeName, volume.name);
+    t.is(detailed.volumePath, volume.path);
+    t.is(detailed.volumeSignature, 'H+');
+    // The checkout is on the internal disk.
+    t.is(detailed.volumeType, 'local');
+    t.is(createDebug(selfpath).info.volumeTypeSource, 'diskarbitration');
+    t.is(detailed.targetCreated, info.target.created);
+    t.is(detailed.volumeCreated, info.volume.created);
+  });
+
+  test('volumeNameSource should pick where the volume name comes from', (t) => {
+    const volume = getVolumeInfo(selfpath);
+    const name = (volumeNameSource) => JSON.parse(toJSON(create(selfpath, { volumeNameSource }))).volume.name;
+
+    t.is(name('cf'), volume.displayName ?? volume.name);
+    t.is(name('attrlist'), volume.name);
+    if (volume.path === '/') {
+      // The root volume's mount point has no name to fall back to.
+      t.throws(() => create(selfpath, { volumeNameSource: 'mountpoint' }), { code: 'ERR_VOLUME_LOOKUP_FAILED' });
+    } else {
+      t.is(name('mountpoint'), parse(volume.path).base.replaceAll(':', '/'));
+    }
+    t.throws(() => create(selfpath, { volumeNameSource: 'disk' }), { code: 'ERR_INVALID_ARG' });
+  });
+
+  test('volumeNameStyle should pick the canonical or the display name', (t) => {
+    const volume = getVolumeInfo(selfpath);
+    const name = (options) => JSON.parse(toJSON(create(selfpath, options))).volume.name;
+
+    t.is(name(), volume.name);
+    t.is(name({ volumeNameStyle: 'canonical' }), volume.name);
+    t.is(name({ volumeNameStyle: 'display' }), volume.displayName ?? volume.name);
+    t.throws(() => create(selfpath, { volumeNameStyle: 'localized' }), { code: 'ERR_INVALID_ARG' });
+    t.throws(() => create(selfpath, { volumeNameStyle: 'display', volumeNameSource: 'cf' }), {
+      code: 'ERR_INVALID_ARG',
+    });
+  });
+
+  test('verify should resolve our record back to the target', (t) => {
+    const result = verify(selfpath);
+
+    t.true(result.resolved);
+    t.deepEqual(result.mismatches, []);
+  });
+
+  test('createForFd should match create and leave the fd open', (t) => {
+    const fd = openSync(selfpath, 'r');
+    try {
+      t.deepEqual(createForFd(fd, selfpath), create(selfpath));
+      t.notThrows(() => fstatSync(fd));
+    } finally {
+      closeSync(fd);
+    }
+  });
+
+  test('createForTree should match create for every file', (t) => {
+    const root = mkdtempSync(join(tmpdir(), 'macos-alias-tree-'));
+    try {
+      mkdirSync(join(root, 'Applications'));
+      writeFileSync(join(root, 'ReadMe.txt'), '');
+      writeFileSync(join(root, 'Applications/App.png'), '');
+      writeFileSync(join(root, '.DS_Store'), '');
+
+      const records = createForTree(root, { filter: (path) => !path.startsWith('.') });
+      t.deepEqual(Object.keys(records), ['Applications/App.png', 'ReadMe.txt']);
+      t.deepEqual(records['ReadMe.txt'], create(join(root, 'ReadMe.txt')));
+      t.deepEqual(Object.keys(createForTree(root, { filter: '*.png' })), ['Applications/App.png']);
+      t.deepEqual(Object.keys(createForTree(root, { maxDepth: 1, includeDirectories: true })), [
+        '.DS_Store',
+        'Applications',
+        'ReadMe.txt',
+      ]);
+      t.throws(
+        () =>
+          createForTree(root, {
+            filter: () => {
+              throw new Error('filter failed');
+            },
+          }),
+        { message: 'filter failed' },
+      );
+    } finally {
+      rmSync(root, { recursive: true });
+    }
+  });
+
+  test('createMany should match create for every target', (t) => {
+    const missing = join(tmpdir(), 'macos-alias-missing.tiff');
+    const records = createMany([selfpath, { path: pathToFileURL(selfpath), options: { minimal: true } }, missing]);
+
+    t.deepEqual(records[0], create(selfpath));
+    t.deepEqual(records[1], create(selfpath, { minimal: true }));
+    t.is(records[2].code, 'ERR_TARGET_NOT_FOUND');
+    t.deepEqual(createMany([selfpath], { output: 'uint8array' })[0], create(selfpath, { output: 'uint8array' }));
+  });
+
+  test('create should report files behind firmlinks on the / volume group', (t) => {
+    const info = alias.decode(create(homedir()));
+    const volumePath = info.extra.find((e) => e.type === 19);
+
+    t.is(volumePath.data.toString('utf8').replace(/\0$/, ''), '/');
+  });
+
+  test('create should not change local records with noIO', (t) => {
+    t.deepEqual(create(selfpath, { noIO: true }), create(selfpath));
+  });
+
+  test('create should accept file URLs', (t) => {
+    t.deepEqual(create(new URL(import.meta.url)), create(selfpath));
+    t.deepEqual(create(pathToFileURL(dirname(selfpath) + '/')), create(dirname(selfpath)));
+  });
+
+  test('recordInfo should encode to the bytes create returns', (t) => {
+    const info = recordInfo(selfpath);
+
+    t.deepEqual(encodeInfo(info), create(selfpath));
+    t.deepEqual(info, JSON.parse(toJSON(create(selfpath))));
+    t.deepEqual(
+      encodeInfo(recordInfo(selfpath, { compat: 'node-alias' }), { compat: 'node-alias' }),
+      create(selfpath, { compat: 'node-alias' }),
+    );
+  });
+
+  test('statAliasTarget should report fresh and replaced targets', (t) => {
+    const dir = mkdtempSync(join(tmpdir(), 'macos-alias-stat-'));
+    const target = join(dir, 'background.tiff');
+    writeFileSync(target, '');
+    const buf = create(target);
+
+    t.deepEqual(statAliasTarget(buf), {
+      exists: true,
+      pathMatches: true,
+      idMatches: true,
+      volumeMounted: true,
+      volumePath: Buffer.from(JSON.parse(toJSON(buf)).extra.find((e) => e.type === 19).data, 'base64').toString(),
+      staleFields: [],
+    });
+
+    rmSync(target);
+    writeFileSync(target, '');
+    const replaced = statAliasTarget(buf);
+    rmSync(dir, { recursive: true });
+
+    t.true(replaced.exists);
+    t.false(replaced.pathMatches);
+    t.true(replaced.staleFields.includes('target.id'));
+  });
+
+  test('relinkAlias should refresh a replaced target', (t) => {
+    const dir = mkdtempSync(join(tmpdir(), 'macos-alias-relink-'));
+    const target = join(dir, 'background.tiff');
+    writeFileSync(target, '');
+    const buf = create(target);
+    rmSync(target);
+    writeFileSync(target, '');
+
+    const relinked = relinkAlias(buf);
+    const fresh = statAliasTarget(relinked);
+    rmSync(target);
+    t.throws(() => relinkAlias(buf), { code: 'ERR_TARGET_NOT_FOUND' });
+    rmSync(dir, { recursive: true });
+
+    t.true(fresh.pathMatches);
+    t.deepEqual(fresh.staleFields, []);
+    t.is(JSON.parse(toJSON(relinked)).target.filename, 'background.tiff');
+  });
+
+  test('resolveAlias should follow the id or the path, as preferred', (t) => {
+    // Resolved paths are canonical, and the temporary directory is behind
+    // the /var symlink.
+    const dir = realpathSync(mkdtempSync(join(tmpdir(), 'macos-alias-resolve-')));
+    const target = join(dir, 'background.tiff');
+    const renamed = join(dir, 'renamed.tiff');
+    mkdirSync(join(dir, 'moved'));
+    const moved = join(dir, 'moved', 'background.tiff');
+    writeFileSync(target, '');
+    const buf = create(target);
+    const resolved = (prefer) => resolveAlias(buf, { prefer });
+
+    t.is(resolved('id'), target);
+    t.is(resolved('path'), target);
+    // Renamed or moved, only the id leads to it.
+    for (const to of [renamed, moved]) {
+      renameSync(target, to);
+      t.is(resolved('id'), to);
+      t.is(resolved('path'), to);
+      renameSync(to, target);
+    }
+    // Replaced, only the path does.
+    rmSync(target);
+    writeFileSync(target, '');
+    const replaced = create(target);
+    t.is(resolveAlias(buf), target);
+    t.is(resolved('path'), target);
+    // Moved and replaced, each leads somewhere else.
+    renameSync(target, moved);
+    writeFileSync(target, '');
+    t.is(resolveAlias(replaced, { prefer: 'id' }), moved);
+    t.is(resolveAlias(replaced, { prefer: 'path' }), target);
+    rmSync(dir, { recursive: true });
+
+    t.is(resolveAlias(buf), null);
+    t.throws(() => resolveAlias(buf, { prefer: 'name' }), { code: 'ERR_INVALID_ARG' });
+  });
+
+  test('writeAliasFile should write alias files Finder follows', (t) => {
+    const dir = realpathSync(mkdtempSync(join(tmpdir(), 'macos-alias-file-')));
+    const target = join(dir, 'background.tiff');
+    writeFileSync(target, '');
+    const classic = join(dir, 'classic alias');
+    const bookmark = join(dir, 'bookmark alias');
+    writeAliasFile(target, classic);
+    writeAliasFile(pathToFileURL(target), bookmark, { format: 'bookmark' });
+
+    const fork = readFileSync(join(classic, '..namedfork', 'rsrc'));
+    t.deepEqual(extractFromResourceFork(fork), create(target));
+    // Both are aliases to the target, so create follows them when asked.
+    for (const alias of [classic, bookmark]) {
+      t.deepEqual(create(alias, { resolveAliases: true }), create(target));
+    }
+    t.throws(() => writeAliasFile(target, classic, { format: 'symlink' }), { code: 'ERR_INVALID_ARG' });
+    t.throws(() => writeAliasFile(join(dir, 'missing'), classic), { code: 'ERR_TARGET_NOT_FOUND' });
+
+    const read = readAliasFile(classic);
+    t.is(read.format, 'classic');
+    t.deepEqual(read.record, create(target));
+    t.is(read.info.target.filename, 'background.tiff');
+    const modern = readAliasFile(bookmark);
+    t.is(modern.format, 'bookmark');
+    t.is(modern.bookmark.targetPath, target);
+    t.throws(() => readAliasFile(target), { code: 'ERR_INVALID_PATH' });
+    rmSync(dir, { recursive: true });
+  });
+
+  test('resolve should find a target replaced in a moved directory by its parent', (t) => {
+    const dir = realpathSync(mkdtempSync(join(tmpdir(), 'macos-alias-resolve-')));
+    mkdirSync(join(dir, 'parent'));
+    const target = join(dir, 'parent', 'background.tiff');
+    writeFileSync(target, '');
+    const buf = create(target);
+    t.is(resolve(buf), target);
+
+    renameSync(join(dir, 'parent'), join(dir, 'moved'));
+    const replaced = join(dir, 'moved', 'background.tiff');
+    rmSync(replaced);
+    writeFileSync(replaced, '');
+    t.is(resolve(buf), replaced);
+    rmSync(dir, { recursive: true });
+    t.is(resolve(buf), null);
+  });
+
+  test('resolveAlias should not look off the record\'s volume unless asked', (t) => {
+    const buf = updateAlias(Buffer.from(FIXTURE, 'base64'), { volumeName: 'Not Mounted Anywhere' });
+    t.is(resolveAlias(buf), null);
+    t.is(resolveAlias(buf, { crossVolumeFallback: true }), null);
+  });
+
+  test('statAliasTarget should report unmounted volumes', (t) => {
+    const buf = updateAlias(Buffer.from(FIXTURE, 'base64'), { volumeName: 'Not Mounted Anywhere' });
+    t.false(statAliasTarget(buf).volumeMounted);
+  });
+
+  test('getVolumeInfo should describe the volume of a path', (t) => {
+    const info = getVolumeInfo(selfpath);
+    const root = getVolumeInfo('/');
+
+    t.is(root.path, '/');
+    t.true(root.local);
+    t.is(info.fsType, root.fsType);
+    t.is(Buffer.from(JSON.parse(toJSON(create(selfpath))).extra.find((e) => e.type === 19).data, 'base64').toString(), info.path);
+    t.throws(() => getVolumeInfo(join(tmpdir(), 'macos-alias-missing', 'x')), { code: 'ERR_TARGET_NOT_FOUND' });
+  });
+
+  test('listVolumes should include the startup volume', (t) => {
+    const volumes = listVolumes();
+    const root = volumes.find((volume) => volume.path === '/');
+
+    t.truthy(root);
+    t.deepEqual(root, getVolumeInfo('/'));
+    t.true(root.browsable);
+    t.is(root.diskImage, undefined);
+    t.true(volumes.some((volume) => volume.path === getVolumeInfo(selfpath).path));
+  });
+
+  test('fileIds should return the full ids', (t) => {
+    const ids = fileIds(selfpath);
+
+    t.is(ids.fileId, statSync(selfpath, { bigint: true }).ino);
+    t.is(ids.parentId, statSync(dirname(selfpath), { bigint: true }).ino);
+    t.is(BigInt(decode(create(selfpath)).target.id), ids.fileId <= 0xffffffffn ? ids.fileId : 0n);
+    t.throws(() => fileIds(join(tmpdir(), 'macos-alias-missing', 'x')), { code: 'ERR_TARGET_NOT_FOUND' });
+  });
+
+  if (systemAliasFor) {
+    test('create should agree with the Alias Manager', (t) => {
+      const ours = alias.decode(create(selfpath));
+      const system = alias.decode(systemAliasFor(selfpath));
+
+      t.is(ours.volume.name, system.volume.name);
+      t.is(ours.parent.name, system.parent.name);
+      t.is(ours.target.filename, system.target.filename);
+      t.is(ours.target.type, system.target.type);
+    });
+  }
+
+  test('createForStagedDmg should record the file as it will be mounted', (t) => {
+    const stagingDir = mkdtempSync(join(tmpdir(), 'macos-alias-staged-'));
+    mkdirSync(join(stagingDir, '.background'));
+    writeFileSync(join(stagingDir, '.background', 'bg.tiff'), '');
+    const buf = createForStagedDmg({ stagingDir, volumeName: 'My App', internalPath: '.background/bg.tiff' });
+    rmSync(stagingDir, { recursive: true });
+    const info = JSON.parse(toJSON(buf));
+    const extra = (type) => Buffer.from(info.extra.find((e) => e.type === type).data, 'base64').toString();
+
+    t.is(info.volume.name, 'My App');
+    t.is(info.target.id, 0);
+    t.is(info.parent.name, '.background');
+    t.is(extra(18), '/.background/bg.tiff');
+    t.is(extra(19), '/Volumes/My App');
+  });
+
+  test('createForStagedDmg should make the same record from pinned dates', async (t) => {
+    const options = { targetCreated: 1600000000000, volumeCreated: 1600000000000 };
+    const build = () => {
+      const stagingDir = mkdtempSync(join(tmpdir(), 'macos-alias-staged-'));
+      writeFileSync(join(stagingDir, 'bg.tiff'), '');
+      const buf = createForStagedDmg({ stagingDir, volumeName: 'My App', internalPath: 'bg.tiff' }, options);
+      rmSync(stagingDir, { recursive: true });
+      return buf;
+    };
+    const first = build();
+    await new Promise((resolve) => setTimeout(resolve, 1100));
+
+    t.deepEqual(build(), first);
+    t.is(decode(first).target.created, 1600000000000);
+  });
+
+  test('createBackgroundAlias should record the image on the mounted volume', (t) => {
+    const stagingDir = mkdtempSync(join(tmpdir(), 'macos-alias-staged-'));
+    mkdirSync(join(stagingDir, '.background'));
+    const image = join(stagingDir, '.background', 'bg.tiff');
+    writeFileSync(image, '');
+    const byPath = createBackgroundAlias(stagingDir, image, 'My App');
+    const byName = createBackgroundAlias(stagingDir, 'bg.tiff', 'My App');
+    t.throws(() => createBackgroundAlias(stagingDir, join(tmpdir(), 'bg.tiff'), 'My App'), { code: 'ERR_INVALID_PATH' });
+    rmSync(stagingDir, { recursive: true });
+    const info = JSON.parse(toJSON(byPath));
+    const extra = (type) => Buffer.from(info.extra.find((e) => e.type === type).data, 'base64').toString();
+
+    t.deepEqual(byName, byPath);
+    t.is(info.volume.name, 'My App');
+    t.is(extra(18), '/.background/bg.tiff');
+    t.is(extra(19), '/Volumes/My App');
+  });
+
+  test('create should record the volume UUID when asked', (t) => {
+    const uuid = (options) => JSON.parse(toJSON(create(selfpath, options))).extra.find((e) => e.type === 0x2011)?.value;
+
+    t.is(uuid(), undefined);
+    t.is(uuid({ volumeUuid: '0a81f3b1-51d9-3335-b3e3-169c3640360d' }), '0A81F3B1-51D9-3335-B3E3-169C3640360D');
+    t.regex(uuid({ volumeUuid: true }), /^[0-9A-F]{8}(-[0-9A-F]{4}){3}-[0-9A-F]{12}$/);
+    t.true(statAliasTarget(create(selfpath, { volumeUuid: true })).pathMatches);
+    t.throws(() => create(selfpath, { volumeUuid: 'not a uuid' }), { code: 'ERR_INVALID_ARG' });
+  });
+
+  test('create should record the volume name given', (t) => {
+    const info = decode(create(selfpath, { volumeName: 'My App', volumeNameStyle: 'display' }));
+
+    t.is(info.volume.name, 'My App');
+    t.is(info.extra.find((e) => e.type === 15).value, 'My App');
+    t.is(info.extra.find((e) => e.type === 19).value, getVolumeInfo(selfpath).path);
+  });
 
-if (process.arch === "arm64") {
-  // following test would fail on x64
-  test('create should work (check extra field)', (t) => {
-    const buf0 = alias.create(selfpath);
-    const buf1 = create(selfpath);
-    const info0 = alias.decode(buf0);
-    const info1 = alias.decode(buf1);
-  
-    t.deepEqual(info0.extra, info1.extra);
+  test('create should record the ids and parent name given', (t) => {
+    const info = JSON.parse(toJSON(create(selfpath, { targetId: 20, parentId: 19, parentName: 'Other' })));
+    const extra = (type) => Buffer.from(info.extra.find((e) => e.type === type).data, 'base64');
+
+    t.is(info.target.id, 20);
+    t.is(info.parent.id, 19);
+    t.is(info.parent.name, 'Other');
+    t.is(extra(0).toString(), 'Other');
+    t.is(extra(1).readUInt32BE(0), 19);
+    t.true(selfpath.endsWith(extra(18).toString()));
+  });
+
+  test('create should keep only the posix path extras when minimal', (t) => {
+    const info = alias.decode(create(selfpath, { minimal: true }));
+
+    t.deepEqual(info.extra.map((e) => e.type), [18, 19]);
+    t.is('', info.target.filename);
+  });
+
+  test('create should write only the extras families asked for', (t) => {
+    const types = (options) => JSON.parse(toJSON(create(selfpath, options))).extra.map((e) => e.type);
+
+    t.deepEqual(types({ extras: ['classic'] }), [0, 1, 2]);
+    t.deepEqual(types({ extras: ['posix', 'unicode'] }), [14, 15, 18, 19]);
+    t.deepEqual(types({ extras: ['classic'], extraRecords: [{ type: 18, value: '/x' }] }), [0, 1, 2, 18]);
+    t.throws(() => create(selfpath, { extras: ['carbon'] }), { code: 'ERR_INVALID_ARG' });
+    t.throws(() => create(selfpath, { extras: ['classic'], minimal: true }), { code: 'ERR_INVALID_INFO' });
+  });
+
+  test('createBookmark should write bookmarks parseBookmark reads back', (t) => {
+    const path = realpathSync(selfpath);
+    const bookmark = parseBookmark(createBookmark(path));
+    t.is(bookmark.targetPath, path);
+    t.is(bookmark.fileId, Number(statSync(path, { bigint: true }).ino));
+    t.is(bookmark.cnidPath.length, bookmark.path.length);
+    t.is(bookmark.fileFlags, 1);
+    t.truthy(bookmark.volumePath);
+
+    const renamed = parseBookmark(createBookmark(path, { volumeName: 'My App', volumeUuid: '0A81F3B1-51D9-3335-B3E3-169C3640360D' }));
+    t.is(renamed.volumeName, 'My App');
+    t.is(renamed.volumeUuid, '0A81F3B1-51D9-3335-B3E3-169C3640360D');
+    t.throws(() => createBookmark(path, { volumeUuid: 'nope' }), { code: 'ERR_INVALID_ARG' });
+  });
+
+  if (process.arch === "arm64") {
+    // following test would fail on x64
+    test('create should work (check extra field)', (t) => {
+      const buf0 = alias.create(selfpath);
+      const buf1 = create(selfpath, { compat: 'node-alias' });
+      const info0 = alias.decode(buf0);
+      const info1 = alias.decode(buf1);
+    
+      t.deepEqual(info0.extra, info1.extra);
+    });
+
+    test('create should match node-alias byte for byte in compat mode', (t) => {
+      t.deepEqual(create(selfpath, { compat: 'node-alias' }), alias.create(selfpath));
+    });
+  }
+} else {
+  test('createAsync should reject off macOS', async (t) => {
+    await t.throwsAsync(createAsync(selfpath), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+  });
+
+  test('create should require macOS', (t) => {
+    t.throws(() => create(selfpath), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => createDetailed(selfpath), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => createInto(selfpath, Buffer.alloc(512)), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => verify(selfpath), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => recordInfo(selfpath), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => statAliasTarget(Buffer.from(FIXTURE, 'base64')), {
+      code: 'ERR_UNSUPPORTED_PLATFORM',
+    });
+    t.throws(() => relinkAlias(Buffer.from(FIXTURE, 'base64')), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => resolveAlias(Buffer.from(FIXTURE, 'base64')), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => resolve(Buffer.from(FIXTURE, 'base64')), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => createBookmark(selfpath), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => writeAliasFile(selfpath, join(tmpdir(), 'alias')), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => readAliasFile(selfpath), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => getVolumeInfo('/'), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => listVolumes(), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => fileIds(selfpath), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => createMany([selfpath]), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => estimateRecordSize(selfpath), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => createBase64(selfpath), { code: 'ERR_UNSUPPORTED_PLATFORM' });
+    t.throws(() => createForStagedDmg({ stagingDir: tmpdir(), volumeName: 'My App', internalPath: 'bg.tiff' }), {
+      code: 'ERR_UNSUPPORTED_PLATFORM',
+    });
+    t.throws(() => createBackgroundAlias(tmpdir(), 'bg.tiff', 'My App'), { code: 'ERR_UNSUPPORTED_PLATFORM' });
   });
 }
+
+test('create should reject URLs that are not file URLs', (t) => {
+  t.throws(() => create(new URL('https://example.com/bg.tiff')), { code: 'ERR_INVALID_PATH' });
+});
+
+test('createFromInfo should work without the filesystem', (t) => {
+  const buf = createFromInfo({
+    targetPath: '/Volumes/Test Title/.background/TestBkg.tiff',
+    targetId: 20,
+    targetCreated: 1388686808000,
+    parentId: 19,
+    volumePath: '/Volumes/Test Title',
+    volumeName: 'Test Title',
+    volumeCreated: 1388686804000,
+  });
+
+  t.is(buf.readUInt16BE(4), buf.length);
+  t.is(buf.subarray(11, 11 + buf[10]).toString(), 'Test Title');
+  t.is(buf.subarray(51, 51 + buf[50]).toString(), 'TestBkg.tiff');
+  t.is(decode(buf).extra.find((e) => e.type === 2).value, 'Test Title:.background:\0TestBkg.tiff');
+});
+
+test('createFromInfo should keep names too long for the classic fields', (t) => {
+  const volumeName = 'Example Application Installer 2.0';
+  const filename = `${'Hintergrundbild '.repeat(5)}.tiff`;
+  const buf = createFromInfo({
+    targetPath: `/Volumes/${volumeName}/.background/${filename}`,
+    targetId: 20,
+    targetCreated: 1388686808000,
+    parentId: 19,
+    volumePath: `/Volumes/${volumeName}`,
+    volumeName,
+    volumeCreated: 1388686804000,
+  });
+
+  t.is(buf[10], 27);
+  t.is(buf[50], 63);
+  const info = decode(buf);
+  t.is(info.volume.name, volumeName);
+  t.is(info.target.filename, filename);
+});
+
+test('createFromInfo should name the parent as Finder does unless told', (t) => {
+  const info = {
+    targetPath: '/bg.tiff',
+    targetId: 20,
+    targetCreated: 1388686808000,
+    parentId: 2,
+    volumePath: '/',
+    volumeName: 'Macintosh HD',
+    volumeCreated: 1388686804000,
+  };
+  const parent = (info) => {
+    const { parent, extra } = decode(createFromInfo(info));
+    return [parent.name, extra.find((e) => e.type === 0).value];
+  };
+
+  t.deepEqual(parent(info), ['Macintosh HD', 'Macintosh HD']);
+  t.deepEqual(parent({ ...info, targetPath: '/Users/bg.tiff', parentId: 3 }), ['Users', 'Users']);
+  t.deepEqual(parent({ ...info, parentName: 'Root' }), ['Root', 'Root']);
+});
+
+test('createFromInfo should accept a directory target type', (t) => {
+  const info = {
+    targetPath: '/Volumes/Test Title/Folder',
+    targetType: 'directory',
+    targetId: 21,
+    targetCreated: 1388686808000,
+    parentId: 2,
+    volumePath: '/Volumes/Test Title',
+    volumeName: 'Test Title',
+    volumeCreated: 1388686804000,
+  };
+
+  t.is(JSON.parse(toJSON(createFromInfo(info))).target.type, 'directory');
+  t.throws(() => createFromInfo({ ...info, fileCreator: '8BIM' }), { code: 'ERR_INVALID_ARG' });
+});
+
+test('createFromInfo should take the volume type and signature', (t) => {
+  const info = {
+    targetPath: '/Volumes/System Disk/System Folder/Finder',
+    targetId: 17,
+    targetCreated: 1388686808000,
+    parentId: 16,
+    volumePath: '/Volumes/System Disk',
+    volumeName: 'System Disk',
+    volumeCreated: 1388686804000,
+  };
+  const buf = createFromInfo({ ...info, volumeType: 'floppy800', volumeSignature: 'BD' });
+
+  t.is(buf.subarray(42, 44).toString(), 'BD');
+  t.is(buf.readUInt16BE(44), 3);
+  const { volume } = JSON.parse(toJSON(buf));
+  t.is(volume.type, 'floppy800');
+  t.is(volume.signature, 'BD');
+  t.deepEqual(fromJSON(toJSON(buf)), buf);
+
+  t.throws(() => createFromInfo({ ...info, volumeType: 'floppy800' }), { code: 'ERR_INVALID_INFO' });
+  t.throws(() => createFromInfo({ ...info, volumeType: 'zip' }), { code: 'ERR_INVALID_ARG' });
+  t.throws(() => createFromInfo({ ...info, volumeSignature: 'RW' }), { code: 'ERR_INVALID_ARG' });
+});
+
+test('estimateRecordSize should give the length createFromInfo returns', (t) => {
+  const info = {
+    targetPath: '/Volumes/Test Title/.background/TestBkg.tiff',
+    targetId: 20,
+    targetCreated: 1388686807000,
+    parentId: 19,
+    volumePath: '/Volumes/Test Title',
+    volumeName: 'Test Title',
+    volumeCreated: 1388686804000,
+  };
+
+  t.is(estimateRecordSize(info), createFromInfo(info).length);
+  t.is(DS_STORE_MAX_ALIAS_LENGTH, 4070);
+  t.throws(() => estimateRecordSize({ targetId: 20 }), { code: 'ERR_INVALID_ARG' });
+});
+
+test('toJSON and fromJSON should round-trip', (t) => {
+  const json = toJSON(FIXTURE);
+
+  t.is(JSON.parse(json).volume.name, 'Test Title');
+  t.is(fromJSON(json).toString('base64'), FIXTURE);
+  t.is(toJSON(Buffer.from(FIXTURE, 'base64')), json);
+});
+
+test('record inputs should accept base64 strings', (t) => {
+  const buf = Buffer.from(FIXTURE, 'base64');
+
+  t.deepEqual(updateAlias(FIXTURE, { volumeName: 'My App' }), updateAlias(buf, { volumeName: 'My App' }));
+  t.deepEqual(asDsStoreBlob(FIXTURE), asDsStoreBlob(buf));
+  t.deepEqual(decodeAll(FIXTURE), decodeAll(buf));
+  t.is(Alias.fromBuffer(FIXTURE).volumeName, 'Test Title');
+  t.deepEqual(Alias.fromBuffer(FIXTURE).toBuffer(), buf);
+  t.throws(() => toJSON('not base64!'), {
+    code: 'ERR_INVALID_ARG',
+    message: /^A string must hold the data as base64: Invalid byte 32, offset 3\.$/,
+  });
+  t.throws(() => updateAlias(FIXTURE.slice(0, -1), { volumeName: 'My App' }), { code: 'ERR_INVALID_ARG' });
+});
+
+test('decode should give the object toJSON describes', (t) => {
+  const info = decode(FIXTURE);
+
+  t.deepEqual(info, JSON.parse(toJSON(FIXTURE)));
+  t.is(info.target.filename, 'TestBkg.tiff');
+  t.is(info.parent.name, '.background');
+  t.is(info.volume.name, 'Test Title');
+  t.is(info.extra.find((e) => e.type === 18).value, '/.background/TestBkg.tiff');
+  t.deepEqual(encodeInfo(info), Buffer.from(FIXTURE, 'base64'));
+  t.throws(() => decode(Buffer.alloc(10)), { code: 'ERR_INVALID_RECORD' });
+});
+
+test('decodeAll should decode records stored back to back', (t) => {
+  const buf = Buffer.from(FIXTURE, 'base64');
+  const info = JSON.parse(toJSON(buf));
+
+  t.deepEqual(decodeAll(Buffer.concat([buf, buf, Buffer.alloc(6)])), [info, info]);
+  t.deepEqual(JSON.parse(toJSON(Buffer.concat([buf, Buffer.from([0xde, 0xad])]))), info);
+  t.throws(() => decodeAll(Buffer.concat([buf, Buffer.from([1])])), { code: 'ERR_INVALID_RECORD' });
+
+  const noMarker = Buffer.from(buf.subarray(0, buf.length - 4));
+  noMarker.writeUInt16BE(noMarker.length, 4);
+  t.throws(() => toJSON(noMarker), { message: `missing the -1 end-of-record marker, expected at offset ${noMarker.length}` });
+});
+
+test('validate should list the framing problems of a record', (t) => {
+  const buf = Buffer.from(FIXTURE, 'base64');
+  t.deepEqual(validate(buf), []);
+  t.deepEqual(validate(FIXTURE), []);
+
+  const bad = Buffer.from(buf);
+  bad[165] = 0xff;
+  bad.writeUInt16BE(2, bad.length - 2);
+  t.deepEqual(
+    validate(bad).map(({ code, offset }) => ({ code, offset })),
+    [
+      { code: 'NONZERO_PADDING', offset: 165 },
+      { code: 'END_MARKER_LENGTH', offset: buf.length - 4 },
+    ],
+  );
+  t.is(validate(buf.subarray(0, 100))[0].code, 'LENGTH_EXCEEDS_BUFFER');
+  t.is(validate(buf.subarray(0, 100))[0].message, `The header declares ${buf.length} bytes, but the buffer only holds 100.`);
+});
+
+test('fromJSON should give the same records from several worker threads', async (t) => {
+  const json = toJSON(Buffer.from(FIXTURE, 'base64'));
+  const results = await inWorkers(
+    8,
+    `const records = [];
+    for (let i = 0; i < 200; i++) records.push(binding.fromJSON(workerData.json));
+    return records;`,
+    { json },
+  );
+
+  for (const records of results) {
+    t.true(records.every((record) => Buffer.from(record).toString('base64') === FIXTURE));
+  }
+});
+
+test('toJSON should decode well-known extras into values', (t) => {
+  const info = JSON.parse(toJSON(Buffer.from(FIXTURE, 'base64')));
+
+  t.deepEqual(info.extra.map((e) => e.value), [
+    '.background',
+    19,
+    'Test Title:.background:\0TestBkg.tiff',
+    'Test Title',
+    '/.background/TestBkg.tiff',
+    '/Volumes/Test Title',
+  ]);
+
+  info.extra[3] = { type: 15, value: 'My App' };
+  const edited = JSON.parse(toJSON(fromJSON(JSON.stringify(info))));
+  t.is(edited.extra[3].value, 'My App');
+});
+
+test('encodeInfo should drop milliseconds unless asked to round them', (t) => {
+  const buf = Buffer.from(FIXTURE, 'base64');
+  const info = JSON.parse(toJSON(buf));
+  info.target.created += 500;
+  info.volume.created += 499;
+  const created = (record) => {
+    const { target, volume } = JSON.parse(toJSON(record));
+    return [target.created, volume.created];
+  };
+
+  t.deepEqual(encodeInfo(info), buf);
+  t.deepEqual(fromJSON(JSON.stringify(info)), buf);
+  t.deepEqual(created(encodeInfo(info, { dateRounding: 'round' })), [1388686809000, 1388686804000]);
+  t.deepEqual(created(encodeInfo(info, { compat: 'node-alias' })), [1388686809000, 1388686804000]);
+  t.deepEqual(encodeInfo(info, { compat: 'node-alias', dateRounding: 'floor' }), buf);
+  t.throws(() => encodeInfo(info, { dateRounding: 'ceil' }), { code: 'ERR_INVALID_ARG' });
+});
+
+test('fromJSON should take volume UUID extras as strings', (t) => {
+  const info = JSON.parse(toJSON(Buffer.from(FIXTURE, 'base64')));
+  info.extra.push({ type: 0x2011, value: '0A81F3B1-51D9-3335-B3E3-169C3640360D' });
+  const buf = fromJSON(JSON.stringify(info));
+
+  t.deepEqual(JSON.parse(toJSON(buf)).extra.at(-1), {
+    type: 0x2011,
+    length: 16,
+    data: 'CoHzsVHZMzWz4xacNkA2DQ==',
+    value: '0A81F3B1-51D9-3335-B3E3-169C3640360D',
+  });
+  info.extra.at(-1).value = '0A81F3B1';
+  t.throws(() => fromJSON(JSON.stringify(info)), { code: 'ERR_INVALID_ARG' });
+});
+
+test('encodeInfo should encode decoded records', (t) => {
+  const buf = Buffer.from(FIXTURE, 'base64');
+
+  t.deepEqual(encodeInfo(JSON.parse(toJSON(buf))), buf);
+  t.throws(() => encodeInfo({ version: 2 }), { code: 'ERR_INVALID_ARG' });
+});
+
+test('fromJSON should report records too large to encode', (t) => {
+  const info = JSON.parse(toJSON(Buffer.from(FIXTURE, 'base64')));
+  info.extra.push({ type: 20, data: Buffer.alloc(65535).toString('base64') });
+
+  t.throws(() => fromJSON(JSON.stringify(info)), { code: 'ERR_RECORD_TOO_LARGE' });
+});
+
+test('asDsStoreBlob should frame the record as a blob', (t) => {
+  const buf = Buffer.from(FIXTURE, 'base64');
+  const blob = asDsStoreBlob(buf);
+
+  t.is(blob.subarray(0, 4).toString(), 'blob');
+  t.is(blob.readUInt32BE(4), buf.length);
+  t.deepEqual(blob.subarray(8), buf);
+});
+
+test('wrapAsResourceFork and extractFromResourceFork should round-trip', (t) => {
+  const buf = Buffer.from(FIXTURE, 'base64');
+  const fork = wrapAsResourceFork(buf);
+
+  t.is(fork.readUInt32BE(0), 256);
+  t.is(fork.readUInt32BE(4), 256 + 4 + buf.length);
+  t.deepEqual(extractFromResourceFork(fork), buf);
+  t.deepEqual(extractFromResourceFork(wrapAsResourceFork(buf, 128, 'Test Title')), buf);
+  t.throws(() => wrapAsResourceFork(buf, 40000), { code: 'ERR_INVALID_ARG' });
+  t.throws(() => extractFromResourceFork(fork.subarray(0, 300)), { code: 'ERR_INVALID_RECORD' });
+});
+
+test('updateAlias should rename the volume', (t) => {
+  const buf = updateAlias(Buffer.from(FIXTURE, 'base64'), { volumeName: 'My App' });
+  const info = JSON.parse(toJSON(buf));
+
+  t.is(buf.readUInt16BE(4), buf.length);
+  t.is(info.volume.name, 'My App');
+  t.is(Buffer.from(info.extra.find((e) => e.type === 19).data, 'base64').toString(), '/Volumes/My App');
+});
+
+test('rebaseAlias should move a record to another volume', (t) => {
+  const buf = rebaseAlias(Buffer.from(FIXTURE, 'base64'), {
+    fromPath: '/Volumes/Test Title',
+    volumePath: '/Volumes/My App',
+    volumeName: 'My App',
+    zeroIds: true,
+  });
+  const info = JSON.parse(toJSON(buf));
+  const extra = (type) => Buffer.from(info.extra.find((e) => e.type === type).data, 'base64').toString();
+
+  t.is(info.volume.name, 'My App');
+  t.is(info.target.id, 0);
+  t.is(extra(2), 'My App:.background:\0TestBkg.tiff');
+  t.is(extra(18), '/.background/TestBkg.tiff');
+  t.is(extra(19), '/Volumes/My App');
+  t.throws(() => rebaseAlias(buf, { fromPath: '/tmp/dmgroot', volumePath: '/Volumes/X', volumeName: 'X' }), {
+    code: 'ERR_INVALID_PATH',
+  });
+});
+
+test('binary inputs should accept Uint8Array and ArrayBuffer', (t) => {
+  const buf = Buffer.from(FIXTURE, 'base64');
+  // A view into a larger buffer, as Bun and Deno code often holds.
+  const padded = new Uint8Array(buf.length + 4);
+  padded.set(buf, 4);
+  const bytes = padded.subarray(4);
+  const arrayBuffer = bytes.slice().buffer;
+
+  t.is(toJSON(bytes), toJSON(buf));
+  t.is(toJSON(arrayBuffer), toJSON(buf));
+  t.deepEqual(asDsStoreBlob(arrayBuffer), asDsStoreBlob(buf));
+  t.deepEqual(updateAlias(bytes, { volumeName: 'My App' }), updateAlias(buf, { volumeName: 'My App' }));
+  t.is(Alias.fromBuffer(arrayBuffer).volumeName, 'Test Title');
+  t.deepEqual(Alias.fromBuffer(bytes).toBuffer(), buf);
+  t.throws(() => toJSON(new Uint16Array(4)), { code: 'ERR_INVALID_RECORD' });
+
+  const data = new TextEncoder().encode('custom');
+  const info = JSON.parse(toJSON(encodeInfo(JSON.parse(toJSON(buf)), { extraRecords: [{ type: 99, data }] })));
+  t.is(info.volume.name, 'Test Title');
+});
+
+test("output: 'uint8array' should return plain Uint8Arrays", (t) => {
+  const buf = Buffer.from(FIXTURE, 'base64');
+  const output = 'uint8array';
+  const plain = (value) => {
+    t.true(value instanceof Uint8Array);
+    t.false(Buffer.isBuffer(value));
+    return Buffer.from(value);
+  };
+
+  t.deepEqual(plain(fromJSON(toJSON(buf), { output })), buf);
+  t.deepEqual(plain(encodeInfo(JSON.parse(toJSON(buf)), { output })), buf);
+  t.deepEqual(plain(asDsStoreBlob(buf, { output })), asDsStoreBlob(buf));
+  t.deepEqual(plain(updateAlias(buf, { volumeName: 'My App' }, { output })), updateAlias(buf, { volumeName: 'My App' }));
+  const rebase = { volumePath: '/Volumes/My App', volumeName: 'My App' };
+  t.deepEqual(plain(rebaseAlias(buf, { ...rebase, output })), rebaseAlias(buf, rebase));
+  t.deepEqual(plain(Alias.fromBuffer(buf).toUint8Array()), buf);
+  t.true(Buffer.isBuffer(fromJSON(toJSON(buf), { output: 'buffer' })));
+  t.throws(() => fromJSON(toJSON(buf), { output: 'blob' }), { code: 'ERR_INVALID_ARG' });
+});
+
+test('Alias should decode a record lazily', (t) => {
+  const buf = Buffer.from(FIXTURE, 'base64');
+  const alias = Alias.fromBuffer(buf);
+
+  t.is(alias.targetFilename, 'TestBkg.tiff');
+  t.is(alias.targetId, 20);
+  t.is(alias.volumeName, 'Test Title');
+  t.is(alias.volumePath, '/Volumes/Test Title');
+  t.is(alias.created, 1388686808000);
+  t.deepEqual(
+    alias.extras.map((e) => e.type),
+    [0, 1, 2, 15, 18, 19],
+  );
+  t.deepEqual(alias.toBuffer(), buf);
+  t.is(JSON.stringify(alias), JSON.stringify(JSON.parse(toJSON(buf))));
+});
+
+test('Alias setters should re-encode the record', (t) => {
+  const alias = Alias.fromBuffer(Buffer.from(FIXTURE, 'base64'));
+  alias.volumeName = 'My App';
+  const buf = alias.toBuffer();
+
+  t.is(buf.readUInt16BE(4), buf.length);
+  t.is(JSON.parse(toJSON(buf)).volume.name, 'My App');
+  t.is(alias.volumePath, '/Volumes/My App');
+});
+
+test('Alias.fromInfo should match createFromInfo', (t) => {
+  const info = {
+    targetPath: '/Volumes/Test Title/.background/TestBkg.tiff',
+    targetId: 20,
+    targetCreated: 1388686808000,
+    parentId: 19,
+    volumePath: '/Volumes/Test Title',
+    volumeName: 'Test Title',
+    volumeCreated: 1388686804000,
+  };
+
+  t.deepEqual(Alias.fromInfo(info).toBuffer(), createFromInfo(info));
+});
+
+test('Alias should report undecodable records on access', (t) => {
+  const alias = Alias.fromBuffer(Buffer.alloc(3));
+  t.throws(() => alias.targetId, { code: 'ERR_INVALID_RECORD' });
+});
+
+test('parseBookmark should reject alias records', (t) => {
+  t.throws(() => parseBookmark(Buffer.from(FIXTURE, 'base64')), {
+    code: 'ERR_INVALID_RECORD',
+  });
+});
+
+test('encodeBookmark should round-trip through parseBookmark', (t) => {
+  const properties = {
+    path: ['Volumes', 'Test Title', '.background', 'TestBkg.tiff'],
+    targetPath: '/Volumes/Test Title/.background/TestBkg.tiff',
+    cnidPath: [102, 2, 19, 20],
+    fileId: 20,
+    created: 1388686808000,
+    fileFlags: 1,
+    volumePath: '/Volumes/Test Title',
+    volumeName: 'Test Title',
+    volumeUuid: '0A81F3B1-51D9-3335-B3E3-169C3640360D',
+    volumeCreated: 1388686804000,
+    volumeFlags: 0xa1,
+  };
+  t.deepEqual(parseBookmark(encodeBookmark(properties)), properties);
+  t.true(encodeBookmark({ path: ['tmp'], cnidPath: [] }, { output: 'uint8array' }) instanceof Uint8Array);
+  t.throws(() => encodeBookmark({ path: [], cnidPath: [] }), { code: 'ERR_INVALID_INFO' });
+  t.throws(() => encodeBookmark({ path: ['tmp'], cnidPath: [-1] }), { code: 'ERR_INVALID_ARG' });
+});
+
+test('decodeBookmark should list every table of contents entry', (t) => {
+  const { properties, tocs } = decodeBookmark(
+    encodeBookmark({ path: ['Volumes', 'Test Title'], cnidPath: [102, 2], volumePath: '/Volumes/Test Title', volumeCreated: 1388686804000 }),
+  );
+  t.deepEqual(properties.path, ['Volumes', 'Test Title']);
+  t.is(tocs.length, 1);
+  t.is(tocs[0].identifier, 1);
+  const entries = Object.fromEntries(tocs[0].entries.map((entry) => [entry.name, entry]));
+  t.is(entries.path.key, 0x1004);
+  t.deepEqual(entries.cnidPath.value, {
+    type: 'array',
+    value: [
+      { type: 'number', value: 102 },
+      { type: 'number', value: 2 },
+    ],
+  });
+  t.deepEqual(entries.volumeUrl.value, { type: 'url', value: 'file:///Volumes/Test%20Title/' });
+  t.deepEqual(entries.volumeCreationDate.value, { type: 'date', value: 1388686804000 });
+  t.throws(() => decodeBookmark(Buffer.from(FIXTURE, 'base64')), { code: 'ERR_INVALID_RECORD' });
+});
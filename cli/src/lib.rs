@@ -0,0 +1,181 @@
+#![deny(clippy::all)]
+
+//! The `macos-alias` command line tool, shared by the cargo binary and the
+//! npm package's `bin` entry.
+
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+
+use base64::Engine;
+use macos_alias_core as alias;
+
+/// Everything worked.
+pub const EXIT_OK: i32 = 0;
+/// The command ran but failed, e.g. the target does not exist.
+pub const EXIT_FAILURE: i32 = 1;
+/// The command line could not be parsed.
+pub const EXIT_USAGE: i32 = 2;
+
+const USAGE: &str = "\
+Usage:
+  macos-alias create <path> [--base64 | --out <file>]
+  macos-alias inspect <file | - | --base64 <record>>
+  macos-alias resolve <file | - | --base64 <record>>
+  macos-alias convert <file | - | --base64 <record>> [--base64 | --out <file>]
+
+resolve finds the target by its ids and volume UUID on macOS, as the
+Alias Manager does; elsewhere it only checks the record's posix path.";
+
+enum Failure {
+  Usage(String),
+  Failed(String),
+}
+
+impl<E: fmt::Display> From<E> for Failure {
+  fn from(err: E) -> Self {
+    Failure::Failed(err.to_string())
+  }
+}
+
+type Result<T> = std::result::Result<T, Failure>;
+
+/// Run the tool with `args` (without the program name) and return the exit
+/// code. Records are written to `stdout`, diagnostics to `stderr`.
+pub fn run(
+  args: &[String],
+  stdin: &mut dyn Read,
+  stdout: &mut dyn Write,
+  stderr: &mut dyn Write,
+) -> i32 {
+  let args: Vec<&str> = args.iter().map(String::as_str).collect();
+  let result = match args.as_slice() {
+    ["create", rest @ ..] => create(rest, stdout),
+    ["inspect", rest @ ..] => inspect(rest, stdin, stdout),
+    ["resolve", rest @ ..] => resolve(rest, stdin, stdout),
+    ["convert", rest @ ..] => convert(rest, stdin, stdout),
+    ["help" | "--help" | "-h"] => writeln!(stdout, "{USAGE}").map_err(Failure::from),
+    [] => Err(Failure::Usage("missing command".to_owned())),
+    [other, ..] => Err(Failure::Usage(format!("unknown command {other:?}"))),
+  };
+  match result {
+    Ok(()) => EXIT_OK,
+    Err(Failure::Usage(message)) => {
+      let _ = writeln!(stderr, "macos-alias: {message}\n{USAGE}");
+      EXIT_USAGE
+    }
+    Err(Failure::Failed(message)) => {
+      let _ = writeln!(stderr, "macos-alias: {message}");
+      EXIT_FAILURE
+    }
+  }
+}
+
+fn create(args: &[&str], stdout: &mut dyn Write) -> Result<()> {
+  let (path, output) = match args {
+    [path] => (path, None),
+    [path, "--base64"] => (path, Some(None)),
+    [path, "--out", file] => (path, Some(Some(file))),
+    _ => {
+      return Err(Failure::Usage(
+        "create takes <path> [--base64 | --out <file>]".to_owned(),
+      ))
+    }
+  };
+  write_output(&alias::create_for_path(path)?, output, stdout)
+}
+
+/// Write `bytes` raw to stdout, as base64 to stdout (`Some(None)`), or to a
+/// file.
+fn write_output(bytes: &[u8], output: Option<Option<&&str>>, stdout: &mut dyn Write) -> Result<()> {
+  match output {
+    None => stdout.write_all(bytes)?,
+    Some(None) => writeln!(
+      stdout,
+      "{}",
+      base64::engine::general_purpose::STANDARD.encode(bytes)
+    )?,
+    Some(Some(file)) => fs::write(file, bytes)?,
+  }
+  Ok(())
+}
+
+/// Read the bytes named by `args`: a file, `-` for stdin, or an inline
+/// base64 string.
+fn read_input(command: &str, args: &[&str], stdin: &mut dyn Read) -> Result<Vec<u8>> {
+  Ok(match args {
+    ["-"] => {
+      let mut bytes = Vec::new();
+      stdin.read_to_end(&mut bytes)?;
+      bytes
+    }
+    ["--base64", record] => base64::engine::general_purpose::STANDARD
+      .decode(record.trim())
+      .map_err(|err| Failure::Failed(format!("invalid base64: {err}")))?,
+    [file] if !file.starts_with("--") => {
+      fs::read(file).map_err(|err| Failure::Failed(format!("{file}: {err}")))?
+    }
+    _ => {
+      return Err(Failure::Usage(format!(
+        "{command} takes <file | - | --base64 <record>>"
+      )))
+    }
+  })
+}
+
+/// Read the record named by `args`, as [`read_input`] does.
+fn read_record(command: &str, args: &[&str], stdin: &mut dyn Read) -> Result<alias::Info> {
+  Ok(alias::decode(&read_input(command, args, stdin)?)?)
+}
+
+fn inspect(args: &[&str], stdin: &mut dyn Read, stdout: &mut dyn Write) -> Result<()> {
+  let info = read_record("inspect", args, stdin)?;
+  writeln!(stdout, "{}", serde_json::to_string_pretty(&info)?)?;
+  Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn resolve(args: &[&str], stdin: &mut dyn Read, stdout: &mut dyn Write) -> Result<()> {
+  let record = read_input("resolve", args, stdin)?;
+  let path = alias::resolve_alias(&record, &Default::default())?
+    .ok_or_else(|| Failure::Failed("the record's target could not be found".to_owned()))?;
+  writeln!(stdout, "{path}")?;
+  Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn resolve(args: &[&str], stdin: &mut dyn Read, stdout: &mut dyn Write) -> Result<()> {
+  let info = read_record("resolve", args, stdin)?;
+  let path = info
+    .posix_path()
+    .ok_or_else(|| Failure::Failed("the record has no posix path extras".to_owned()))?;
+  if !std::path::Path::new(&path).exists() {
+    return Err(Failure::Failed(format!("{path} does not exist")));
+  }
+  writeln!(stdout, "{path}")?;
+  Ok(())
+}
+
+/// Convert an alias record to bookmark data, or bookmark data to an alias
+/// record, telling them apart by the bookmark's `book` magic.
+fn convert(args: &[&str], stdin: &mut dyn Read, stdout: &mut dyn Write) -> Result<()> {
+  let (input, output) = match args {
+    [input @ .., "--out", file] => (input, Some(Some(file))),
+    [input @ .., "--base64"] if !input.is_empty() => (input, Some(None)),
+    input => (input, None),
+  };
+  let bytes = read_input("convert", input, stdin).map_err(|err| match err {
+    Failure::Usage(_) => Failure::Usage(
+      "convert takes <file | - | --base64 <record>> [--base64 | --out <file>]".to_owned(),
+    ),
+    err => err,
+  })?;
+  let converted = if bytes.starts_with(b"book") {
+    let bookmark = alias::parse_bookmark(&bytes)?;
+    alias::encode(&alias::info_from_bookmark(&bookmark)?)?
+  } else {
+    let info = alias::decode(&bytes)?;
+    alias::encode_bookmark(&alias::bookmark_from_info(&info)?)?
+  };
+  write_output(&converted, output, stdout)
+}
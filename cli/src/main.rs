@@ -0,0 +1,11 @@
+#![deny(clippy::all)]
+
+fn main() {
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  std::process::exit(macos_alias_cli::run(
+    &args,
+    &mut std::io::stdin(),
+    &mut std::io::stdout(),
+    &mut std::io::stderr(),
+  ));
+}
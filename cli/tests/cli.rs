@@ -0,0 +1,125 @@
+use assert_cmd::Command;
+use base64::Engine;
+
+const FIXTURE: &str = "AAAAAAEqAAIAAApUZXN0IFRpdGxlAAAAAAAAAAAAAAAAAAAAAADO615USCsABQAAABMMVGVzdEJrZy50aWZmAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFM7rXlgAAAAAAAAAAP////8AAA0CAAAAAAAAAAAAAAAAAAAACy5iYWNrZ3JvdW5kAAABAAQAAAATAAIAJFRlc3QgVGl0bGU6LmJhY2tncm91bmQ6AFRlc3RCa2cudGlmZgAPABYACgBUAGUAcwB0ACAAVABpAHQAbABlABIAGS8uYmFja2dyb3VuZC9UZXN0QmtnLnRpZmYAABMAEy9Wb2x1bWVzL1Rlc3QgVGl0bGUA//8AAA==";
+
+fn macos_alias() -> Command {
+  Command::cargo_bin("macos-alias").unwrap()
+}
+
+fn stdout_of(command: &mut Command) -> String {
+  String::from_utf8(command.assert().get_output().stdout.clone()).unwrap()
+}
+
+#[test]
+fn inspect_base64() {
+  let output = stdout_of(macos_alias().args(["inspect", "--base64", FIXTURE]));
+  let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+  assert_eq!(json["volume"]["name"], "Test Title");
+  assert_eq!(json["target"]["filename"], "TestBkg.tiff");
+}
+
+#[test]
+fn inspect_stdin() {
+  let record = base64::engine::general_purpose::STANDARD
+    .decode(FIXTURE)
+    .unwrap();
+  macos_alias()
+    .args(["inspect", "-"])
+    .write_stdin(record)
+    .assert()
+    .success();
+}
+
+#[test]
+fn inspect_malformed_record() {
+  macos_alias()
+    .args(["inspect", "--base64", "AAAAAAEq"])
+    .assert()
+    .code(1)
+    .stderr("macos-alias: declared record length 298 exceeds the 6 available bytes at offset 4\n");
+}
+
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn resolve_missing_target() {
+  macos_alias()
+    .args(["resolve", "--base64", FIXTURE])
+    .assert()
+    .code(1)
+    .stdout("")
+    .stderr("macos-alias: /Volumes/Test Title/.background/TestBkg.tiff does not exist\n");
+}
+
+#[test]
+fn convert_round_trips() {
+  let bookmark = stdout_of(macos_alias().args(["convert", "--base64", FIXTURE, "--base64"]));
+  let bytes = base64::engine::general_purpose::STANDARD
+    .decode(bookmark.trim())
+    .unwrap();
+  assert_eq!(&bytes[..4], b"book");
+
+  let record = macos_alias()
+    .args(["convert", "-"])
+    .write_stdin(bytes)
+    .assert()
+    .success()
+    .get_output()
+    .stdout
+    .clone();
+  let output = stdout_of(macos_alias().args(["inspect", "-"]).write_stdin(record));
+  let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+  assert_eq!(json["volume"]["name"], "Test Title");
+  assert_eq!(json["target"]["id"], 20);
+  assert_eq!(json["parent"]["name"], ".background");
+}
+
+#[test]
+fn usage_errors() {
+  macos_alias().assert().code(2);
+  macos_alias().arg("frobnicate").assert().code(2);
+  macos_alias().args(["create"]).assert().code(2);
+  macos_alias().args(["inspect", "--base64"]).assert().code(2);
+  macos_alias().args(["convert", "--base64"]).assert().code(2);
+  macos_alias()
+    .args(["convert", "--out", "x"])
+    .assert()
+    .code(2);
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn create_round_trips_through_resolve() {
+  let path = std::env::current_dir().unwrap().join("Cargo.toml");
+  let record = stdout_of(macos_alias().args(["create", path.to_str().unwrap(), "--base64"]));
+  macos_alias()
+    .args(["resolve", "--base64", record.trim()])
+    .assert()
+    .success();
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn resolve_follows_a_renamed_target() {
+  let dir = std::fs::canonicalize(std::env::temp_dir())
+    .unwrap()
+    .join(format!("macos-alias-cli-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  let (before, after) = (dir.join("before.txt"), dir.join("after.txt"));
+  std::fs::write(&before, b"").unwrap();
+  let record = stdout_of(macos_alias().args(["create", before.to_str().unwrap(), "--base64"]));
+  std::fs::rename(&before, &after).unwrap();
+  let resolved = stdout_of(macos_alias().args(["resolve", "--base64", record.trim()]));
+  std::fs::remove_dir_all(&dir).unwrap();
+  assert_eq!(resolved.trim(), after.to_str().unwrap());
+}
+
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn create_requires_macos() {
+  macos_alias()
+    .args(["create", "Cargo.toml", "--base64"])
+    .assert()
+    .code(1)
+    .stderr("macos-alias: Creating an alias from a path requires macOS\n");
+}
@@ -0,0 +1,451 @@
+//! Finder alias files: detecting them, following them to their targets,
+//! writing them and reading them back.
+
+#[cfg(target_os = "macos")]
+use std::ffi::CString;
+#[cfg(target_os = "macos")]
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+#[cfg(target_os = "macos")]
+use std::path::PathBuf;
+#[cfg(target_os = "macos")]
+use std::ptr;
+
+#[cfg(target_os = "macos")]
+use core_foundation::{
+  base::{kCFAllocatorDefault, TCFType},
+  data::CFData,
+  error::CFError,
+  url::{
+    kCFBookmarkResolutionWithoutMountingMask, kCFBookmarkResolutionWithoutUIMask,
+    kCFURLBookmarkCreationSuitableForBookmarkFile, CFURLCreateBookmarkData,
+    CFURLCreateBookmarkDataFromFile, CFURLCreateByResolvingBookmarkData,
+    CFURLWriteBookmarkDataToFile, CFURL,
+  },
+};
+
+use crate::{Bookmark, CreateOptions, Error, Info, Result};
+
+/// How many alias files [`resolve_alias_chain`] follows before giving up.
+#[cfg(target_os = "macos")]
+const MAX_ALIAS_CHAIN: usize = 16;
+
+/// `kIsAlias` in the Finder flags, bytes 8 and 9 of `com.apple.FinderInfo`.
+#[cfg(target_os = "macos")]
+const IS_ALIAS: u16 = 0x8000;
+
+/// What [`write_alias_file`] puts in the alias file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AliasFileFormat {
+  /// The alias record in an `alis` resource of the resource fork, as Finder
+  /// wrote alias files before Mac OS X 10.6 and as DMG tooling expects.
+  #[default]
+  Classic,
+  /// Bookmark data in the data fork, as current Finder writes them, made by
+  /// `CFURLCreateBookmarkData`.
+  Bookmark,
+}
+
+/// How [`write_alias_file`] writes the alias file.
+#[derive(Debug, Clone, Default)]
+pub struct AliasFileOptions {
+  pub format: AliasFileFormat,
+  /// How the record of a [`Classic`](AliasFileFormat::Classic) alias file is
+  /// made.
+  pub record: CreateOptions,
+}
+
+/// Whether `path` is a Finder alias file.
+///
+/// Reading Finder flags needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn is_alias_file<P: AsRef<Path>>(path: P) -> Result<bool> {
+  let _ = path;
+  Err(Error::Unsupported("Checking for an alias file"))
+}
+
+/// Whether `path` is a Finder alias file, judging by its Finder flags.
+#[cfg(target_os = "macos")]
+pub fn is_alias_file<P: AsRef<Path>>(path: P) -> Result<bool> {
+  let path = CString::new(path.as_ref().as_os_str().as_bytes())
+    .map_err(|_| Error::InvalidPath("The target path contains a NUL byte.".to_owned()))?;
+  let mut finder_info = [0u8; 32];
+  let length = unsafe {
+    libc::getxattr(
+      path.as_ptr(),
+      c"com.apple.FinderInfo".as_ptr(),
+      finder_info.as_mut_ptr().cast(),
+      finder_info.len(),
+      0,
+      0,
+    )
+  };
+  if length < 0 {
+    let err = std::io::Error::last_os_error();
+    return match err.raw_os_error() {
+      Some(libc::ENOATTR) => Ok(false),
+      _ => Err(err.into()),
+    };
+  }
+  Ok(length >= 10 && u16::from_be_bytes([finder_info[8], finder_info[9]]) & IS_ALIAS != 0)
+}
+
+/// What [`read_alias_file`] found in an alias file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AliasFileContents {
+  /// The record from the `alis` resource of the resource fork.
+  Classic { record: Vec<u8>, info: Info },
+  /// The bookmark data CoreFoundation reads from the file.
+  Bookmark { data: Vec<u8>, bookmark: Bookmark },
+}
+
+/// Read the alias record or bookmark stored in the alias file at `path`,
+/// without resolving it: the `alis` resource of its resource fork, if it
+/// has one, or else the bookmark data of a modern Finder alias.
+///
+/// A file that holds neither is reported as [`Error::InvalidPath`].
+#[cfg(target_os = "macos")]
+pub fn read_alias_file<P: AsRef<Path>>(path: P) -> Result<AliasFileContents> {
+  let path = path.as_ref();
+  let fork = match std::fs::read(path.join("..namedfork/rsrc")) {
+    Ok(fork) => fork,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+      return Err(Error::TargetNotFound(path.display().to_string()))
+    }
+    Err(err) => return Err(err.into()),
+  };
+  if let Ok(record) = crate::extract_from_resource_fork(&fork) {
+    let info = crate::decode(record)?;
+    return Ok(AliasFileContents::Classic {
+      record: record.to_vec(),
+      info,
+    });
+  }
+  if !is_alias_file(path)? {
+    return Err(Error::InvalidPath(format!(
+      "{} is not an alias file.",
+      path.display()
+    )));
+  }
+  let data = bookmark_data_from_file(path)?.bytes().to_vec();
+  let bookmark = crate::parse_bookmark(&data)?;
+  Ok(AliasFileContents::Bookmark { data, bookmark })
+}
+
+/// Read the alias record or bookmark stored in the alias file at `path`.
+///
+/// Reading resource forks and Finder flags needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn read_alias_file<P: AsRef<Path>>(path: P) -> Result<AliasFileContents> {
+  let _ = path;
+  Err(Error::Unsupported("Reading an alias file"))
+}
+
+/// Write a Finder alias file at `alias_path` pointing to `target_path`, in
+/// the format `options` asks for, and set its `kIsAlias` Finder flag so
+/// that Finder opens the target when it is double-clicked. An existing file
+/// at `alias_path` is replaced.
+///
+/// Classic alias files to a directory get Finder's `fdrp` type and `MACS`
+/// creator; those to a file get the type and creator in the record.
+#[cfg(target_os = "macos")]
+pub fn write_alias_file<P: AsRef<Path>, Q: AsRef<Path>>(
+  target_path: P,
+  alias_path: Q,
+  options: &AliasFileOptions,
+) -> Result<()> {
+  let (target_path, alias_path) = (target_path.as_ref(), alias_path.as_ref());
+  let (file_type, file_creator) = match options.format {
+    AliasFileFormat::Classic => {
+      let record = crate::create_for_path_with(target_path, &options.record)?;
+      let info = crate::decode(&record)?;
+      let fork = crate::wrap_as_resource_fork(&record, 0, None)?;
+      std::fs::write(alias_path, b"")?;
+      std::fs::write(alias_path.join("..namedfork/rsrc"), fork)?;
+      match info.target.type_ {
+        crate::TargetType::Directory => (*b"fdrp", *b"MACS"),
+        crate::TargetType::File => (info.classic.file_type, info.classic.file_creator),
+      }
+    }
+    AliasFileFormat::Bookmark => {
+      write_bookmark_file(target_path, alias_path)?;
+      return Ok(());
+    }
+  };
+  let mut finder_info = [0u8; 32];
+  finder_info[..4].copy_from_slice(&file_type);
+  finder_info[4..8].copy_from_slice(&file_creator);
+  finder_info[8..10].copy_from_slice(&IS_ALIAS.to_be_bytes());
+  let path = CString::new(alias_path.as_os_str().as_bytes())
+    .map_err(|_| Error::InvalidPath("The alias path contains a NUL byte.".to_owned()))?;
+  let result = unsafe {
+    libc::setxattr(
+      path.as_ptr(),
+      c"com.apple.FinderInfo".as_ptr(),
+      finder_info.as_ptr().cast(),
+      finder_info.len(),
+      0,
+      0,
+    )
+  };
+  if result < 0 {
+    return Err(std::io::Error::last_os_error().into());
+  }
+  Ok(())
+}
+
+/// Write a Finder alias file at `alias_path` pointing to `target_path`.
+///
+/// Writing Finder flags needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn write_alias_file<P: AsRef<Path>, Q: AsRef<Path>>(
+  target_path: P,
+  alias_path: Q,
+  options: &AliasFileOptions,
+) -> Result<()> {
+  let _ = (target_path, alias_path, options);
+  Err(Error::Unsupported("Writing an alias file"))
+}
+
+/// Write bookmark data for `target_path` to an alias file at `alias_path`,
+/// which CoreFoundation marks as an alias itself.
+#[cfg(target_os = "macos")]
+fn write_bookmark_file(target_path: &Path, alias_path: &Path) -> Result<()> {
+  let failed = |reason: String| {
+    Error::Io(std::io::Error::other(format!(
+      "Writing the alias file {} failed: {reason}",
+      alias_path.display()
+    )))
+  };
+  let target = CFURL::from_path(target_path, false)
+    .filter(|_| target_path.exists())
+    .ok_or_else(|| Error::TargetNotFound(target_path.display().to_string()))?;
+  let alias =
+    CFURL::from_path(alias_path, false).ok_or_else(|| failed("not a valid file URL".to_owned()))?;
+
+  let mut error = ptr::null_mut();
+  let data = unsafe {
+    CFURLCreateBookmarkData(
+      kCFAllocatorDefault,
+      target.as_concrete_TypeRef(),
+      kCFURLBookmarkCreationSuitableForBookmarkFile,
+      ptr::null(),
+      ptr::null(),
+      &mut error,
+    )
+  };
+  if data.is_null() {
+    let error = unsafe { CFError::wrap_under_create_rule(error) };
+    return Err(failed(error.description().to_string()));
+  }
+  let data = unsafe { CFData::wrap_under_create_rule(data) };
+  let mut error = ptr::null_mut();
+  let written = unsafe {
+    CFURLWriteBookmarkDataToFile(
+      data.as_concrete_TypeRef(),
+      alias.as_concrete_TypeRef(),
+      0,
+      &mut error,
+    )
+  };
+  if written == 0 {
+    let error = unsafe { CFError::wrap_under_create_rule(error) };
+    return Err(failed(error.description().to_string()));
+  }
+  Ok(())
+}
+
+/// The file the alias file at `path` points to, without showing UI or
+/// mounting volumes.
+#[cfg(target_os = "macos")]
+fn resolve_alias_file(path: &Path) -> Result<PathBuf> {
+  let unresolved = |reason: &str| Error::UnresolvedAlias {
+    path: path.display().to_string(),
+    reason: reason.to_owned(),
+  };
+  let bookmark = bookmark_data_from_file(path)?;
+
+  let mut is_stale = 0;
+  let mut error = ptr::null_mut();
+  let resolved = unsafe {
+    CFURLCreateByResolvingBookmarkData(
+      kCFAllocatorDefault,
+      bookmark.as_concrete_TypeRef(),
+      kCFBookmarkResolutionWithoutUIMask | kCFBookmarkResolutionWithoutMountingMask,
+      ptr::null(),
+      ptr::null(),
+      &mut is_stale,
+      &mut error,
+    )
+  };
+  if resolved.is_null() {
+    let error = unsafe { CFError::wrap_under_create_rule(error) };
+    return Err(unresolved(&error.description().to_string()));
+  }
+  if !error.is_null() {
+    drop(unsafe { CFError::wrap_under_create_rule(error) });
+  }
+  unsafe { CFURL::wrap_under_create_rule(resolved) }
+    .to_path()
+    .ok_or_else(|| unresolved("the target has no file system path"))
+}
+
+/// The bookmark data of the alias file at `path`, which CoreFoundation makes
+/// up from the record of a classic one.
+#[cfg(target_os = "macos")]
+fn bookmark_data_from_file(path: &Path) -> Result<CFData> {
+  let unresolved = |reason: &str| Error::UnresolvedAlias {
+    path: path.display().to_string(),
+    reason: reason.to_owned(),
+  };
+  let url = CFURL::from_path(path, false).ok_or_else(|| unresolved("not a valid file URL"))?;
+
+  let mut error = ptr::null_mut();
+  let bookmark = unsafe {
+    CFURLCreateBookmarkDataFromFile(kCFAllocatorDefault, url.as_concrete_TypeRef(), &mut error)
+  };
+  if bookmark.is_null() {
+    let error = unsafe { CFError::wrap_under_create_rule(error) };
+    return Err(unresolved(&error.description().to_string()));
+  }
+  Ok(unsafe { CFData::wrap_under_create_rule(bookmark) })
+}
+
+/// Follow alias files starting at `path` until reaching something that is
+/// not one.
+#[cfg(target_os = "macos")]
+pub(crate) fn resolve_alias_chain(path: &Path) -> Result<PathBuf> {
+  let mut current = path.to_path_buf();
+  for _ in 0..MAX_ALIAS_CHAIN {
+    if !is_alias_file(&current)? {
+      return Ok(current);
+    }
+    current = resolve_alias_file(&current)?;
+  }
+  Err(Error::UnresolvedAlias {
+    path: path.display().to_string(),
+    reason: format!("more than {MAX_ALIAS_CHAIN} aliases in a row"),
+  })
+}
+
+#[cfg(all(test, target_os = "macos"))]
+mod test {
+  use std::path::Path;
+
+  use super::{AliasFileFormat, AliasFileOptions};
+  use crate::CreateOptions;
+
+  /// Write a Finder alias file at `alias` pointing to `target`.
+  fn write_alias(target: &Path, alias: &Path) {
+    let options = AliasFileOptions {
+      format: AliasFileFormat::Bookmark,
+      ..Default::default()
+    };
+    super::write_alias_file(target, alias, &options).unwrap();
+  }
+
+  #[test]
+  fn alias_chain() {
+    // Canonical, so that the resolved path matches the one we started from.
+    let dir = std::env::temp_dir()
+      .canonicalize()
+      .unwrap()
+      .join(format!("macos-alias-chain-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("background.tiff");
+    std::fs::write(&target, b"").unwrap();
+    let first = dir.join("first alias");
+    let second = dir.join("second alias");
+    write_alias(&target, &first);
+    write_alias(&first, &second);
+
+    assert!(!super::is_alias_file(&target).unwrap());
+    assert!(super::is_alias_file(&first).unwrap());
+    assert!(super::is_alias_file(&second).unwrap());
+
+    let resolve = CreateOptions {
+      resolve_aliases: true,
+      ..Default::default()
+    };
+    let direct = crate::create_for_path(&target).unwrap();
+    let own = crate::decode(&crate::create_for_path(&second).unwrap()).unwrap();
+    let followed = crate::create_for_path_with(&second, &resolve).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(own.target.filename, "second alias");
+    assert_eq!(followed, direct);
+  }
+
+  #[test]
+  fn classic_alias_file() {
+    let dir = std::env::temp_dir()
+      .canonicalize()
+      .unwrap()
+      .join(format!("macos-alias-classic-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("background.tiff");
+    std::fs::write(&target, b"").unwrap();
+    let alias = dir.join("background alias");
+    super::write_alias_file(&target, &alias, &Default::default()).unwrap();
+    let folder_alias = dir.join("folder alias");
+    super::write_alias_file(&dir, &folder_alias, &Default::default()).unwrap();
+
+    let is_alias = super::is_alias_file(&alias).unwrap();
+    let fork = std::fs::read(alias.join("..namedfork/rsrc")).unwrap();
+    let record = crate::extract_from_resource_fork(&fork).unwrap().to_vec();
+    let finder_info = xattr_finder_info(&folder_alias);
+    let resolved = super::resolve_alias_file(&alias);
+    let contents = super::read_alias_file(&alias);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(is_alias);
+    assert_eq!(record, crate::create_for_path(&target).unwrap());
+    assert_eq!(&finder_info[..10], b"fdrpMACS\x80\x00");
+    assert_eq!(resolved.unwrap(), target);
+    match contents.unwrap() {
+      super::AliasFileContents::Classic { record: read, info } => {
+        assert_eq!(read, record);
+        assert_eq!(info.target.filename, "background.tiff");
+      }
+      other => panic!("expected a classic alias file, got {other:?}"),
+    }
+  }
+
+  #[test]
+  fn read_bookmark_alias_file() {
+    let dir = std::env::temp_dir()
+      .canonicalize()
+      .unwrap()
+      .join(format!("macos-alias-read-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("background.tiff");
+    std::fs::write(&target, b"").unwrap();
+    let alias = dir.join("background alias");
+    write_alias(&target, &alias);
+    let contents = super::read_alias_file(&alias);
+    let not_alias = super::read_alias_file(&target);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    match contents.unwrap() {
+      super::AliasFileContents::Bookmark { data, bookmark } => {
+        assert_eq!(&data[..4], b"book");
+        assert_eq!(bookmark.posix_path(), target.to_str().map(str::to_owned));
+      }
+      other => panic!("expected a bookmark alias file, got {other:?}"),
+    }
+    assert!(matches!(not_alias, Err(crate::Error::InvalidPath(_))));
+  }
+
+  fn xattr_finder_info(path: &Path) -> Vec<u8> {
+    let output = std::process::Command::new("xattr")
+      .args(["-px", "com.apple.FinderInfo"])
+      .arg(path)
+      .output()
+      .unwrap();
+    String::from_utf8(output.stdout)
+      .unwrap()
+      .split_whitespace()
+      .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+      .collect()
+  }
+}
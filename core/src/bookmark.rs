@@ -0,0 +1,1438 @@
+//! The bookmark data (`book`) that modern macOS stores in alias files in
+//! place of classic alias records: parsing it, and writing it from known
+//! properties or from a path.
+//!
+//! The layout follows the reverse-engineered description of CFURL bookmarks:
+//! a header pointing at one or more tables of contents, whose entries map
+//! numeric keys to typed items stored elsewhere in the data.
+
+use std::cell::Cell;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::create::display_name;
+#[cfg(any(target_os = "macos", test))]
+use crate::source::{MetadataSource, MNT_DONTBROWSE, MNT_LOCAL, MNT_RDONLY};
+#[cfg(any(target_os = "macos", test))]
+use crate::{CreateOptions, TargetType};
+use crate::{
+  Error, Extra, Info, Parent, Result, Target, Volume, VolumeSignature, VolumeType, VolumeUuid,
+  VOLUME_UUID_EXTRA,
+};
+
+const TOC_MAGIC: u32 = 0xffff_fffe;
+/// Seconds from the Unix epoch to 2001-01-01, which bookmark dates count from.
+const REFERENCE_DATE: u64 = 978_307_200;
+/// How deep arrays may nest; real bookmarks only use one level.
+const MAX_DEPTH: usize = 4;
+/// How many chained tables of contents are followed.
+const MAX_TOCS: usize = 32;
+/// How many items are read in total, so that arrays sharing their elements
+/// cannot blow up exponentially.
+const MAX_ITEMS: usize = 1 << 16;
+
+const PATH: u32 = 0x1004;
+const CNID_PATH: u32 = 0x1005;
+const FILE_PROPERTIES: u32 = 0x1010;
+const FILE_ID: u32 = 0x1030;
+const FILE_CREATION_DATE: u32 = 0x1040;
+const VOLUME_PATH: u32 = 0x2002;
+const VOLUME_URL: u32 = 0x2005;
+const VOLUME_NAME: u32 = 0x2010;
+const VOLUME_UUID: u32 = 0x2011;
+const VOLUME_CREATION_DATE: u32 = 0x2013;
+const VOLUME_PROPERTIES: u32 = 0x2020;
+
+/// The properties of a bookmark that [`parse_bookmark`] understands.
+///
+/// Anything the bookmark does not carry is `None` or empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bookmark {
+  /// Components of the target's absolute path, from the root down.
+  pub path: Vec<String>,
+  /// File ids of the components of `path`.
+  pub cnid_path: Vec<u64>,
+  pub file_id: Option<u64>,
+  pub created: Option<SystemTime>,
+  /// The target's `kCFURLResourceIs*` bits (1 regular file, 2 directory,
+  /// 4 symbolic link, 8 volume, 16 package, ...).
+  pub file_flags: Option<u64>,
+  /// Mount point of the target's volume.
+  pub volume_path: Option<String>,
+  pub volume_name: Option<String>,
+  pub volume_uuid: Option<String>,
+  pub volume_created: Option<SystemTime>,
+  /// The volume's property bits (1 local, 2 automounted, ...).
+  pub volume_flags: Option<u64>,
+}
+
+impl Bookmark {
+  /// The absolute posix path of the target, joined from `path`.
+  pub fn posix_path(&self) -> Option<String> {
+    if self.path.is_empty() {
+      return None;
+    }
+    Some(format!("/{}", self.path.join("/")))
+  }
+}
+
+/// An item of bookmark data, as [`decode_bookmark`] returns them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookmarkValue {
+  String(String),
+  Data(Vec<u8>),
+  /// Any of the integer types, 8 to 64 bits.
+  Number(i64),
+  /// A 32 or 64 bit float.
+  Float(f64),
+  Date(SystemTime),
+  Bool(bool),
+  Array(Vec<BookmarkValue>),
+  /// Key and value pairs, in the order stored.
+  Dictionary(Vec<(BookmarkValue, BookmarkValue)>),
+  /// Formatted like `0A81F3B1-51D9-3335-B3E3-169C3640360D`.
+  Uuid(String),
+  /// An absolute URL.
+  Url(String),
+  Null,
+  /// An item of a type not listed above, such as a relative URL, and its
+  /// raw bytes.
+  Other {
+    type_: u32,
+    data: Vec<u8>,
+  },
+}
+
+/// One entry of a bookmark's table of contents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookmarkEntry {
+  /// The numeric key, e.g. `0x1004` for the path. For keys naming a string
+  /// item, the high bit is set and the rest is that item's offset.
+  pub key: u32,
+  /// The string a string key names, or the usual name of a numeric key,
+  /// e.g. `path`, if it is one of the well-known ones.
+  pub name: Option<String>,
+  pub value: BookmarkValue,
+}
+
+/// A table of contents of a bookmark. Bookmarks usually have one, with
+/// identifier 1; some have more, chained one after another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookmarkToc {
+  pub identifier: u32,
+  pub entries: Vec<BookmarkEntry>,
+}
+
+/// Everything [`decode_bookmark`] reads from bookmark data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedBookmark {
+  /// The properties [`parse_bookmark`] understands.
+  pub properties: Bookmark,
+  pub tocs: Vec<BookmarkToc>,
+}
+
+/// The common name of the well-known numeric key `key`, after the
+/// `kBookmark*` constants of the reverse-engineered format.
+pub fn bookmark_key_name(key: u32) -> Option<&'static str> {
+  Some(match key {
+    PATH => "path",
+    CNID_PATH => "cnidPath",
+    FILE_PROPERTIES => "fileProperties",
+    0x1020 => "fileName",
+    FILE_ID => "fileId",
+    FILE_CREATION_DATE => "fileCreationDate",
+    VOLUME_PATH => "volumePath",
+    VOLUME_URL => "volumeUrl",
+    VOLUME_NAME => "volumeName",
+    VOLUME_UUID => "volumeUuid",
+    0x2012 => "volumeSize",
+    VOLUME_CREATION_DATE => "volumeCreationDate",
+    VOLUME_PROPERTIES => "volumeProperties",
+    0x2030 => "volumeWasBoot",
+    0x2050 => "volumeMountPoint",
+    0xc001 => "containingFolderIndex",
+    0xc011 => "userName",
+    0xc012 => "uid",
+    0xd001 => "wasFileReference",
+    0xd010 => "creationOptions",
+    0xe003 => "urlLengths",
+    0xf017 => "displayName",
+    0xf020 => "iconData",
+    0xf022 => "iconImageData",
+    0xf030 => "typeBindingInfo",
+    0xf080 => "securityExtension",
+    _ => return None,
+  })
+}
+
+struct Parser<'a> {
+  buf: &'a [u8],
+  /// Offset of the data area, which item and table offsets are relative to.
+  header: usize,
+  items: Cell<usize>,
+}
+
+fn invalid(offset: usize, reason: impl Into<String>) -> Error {
+  Error::InvalidRecord {
+    offset,
+    reason: reason.into(),
+  }
+}
+
+impl<'a> Parser<'a> {
+  fn bytes(&self, offset: usize, length: usize, what: &str) -> Result<&'a [u8]> {
+    offset
+      .checked_add(length)
+      .and_then(|end| self.buf.get(offset..end))
+      .ok_or_else(|| {
+        invalid(
+          offset,
+          format!(
+            "{what} needs {length} bytes but only {} remain",
+            self.buf.len().saturating_sub(offset)
+          ),
+        )
+      })
+  }
+
+  fn u32(&self, offset: usize, what: &str) -> Result<u32> {
+    let bytes = self.bytes(offset, 4, what)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+  }
+
+  /// Absolute offset of `relative`, an offset into the data area.
+  fn absolute(&self, relative: u32) -> Result<usize> {
+    self
+      .header
+      .checked_add(relative as usize)
+      .filter(|offset| *offset < self.buf.len())
+      .ok_or_else(|| {
+        invalid(
+          self.header,
+          format!("offset {relative} is past the bookmark"),
+        )
+      })
+  }
+
+  fn item(&self, relative: u32, depth: usize) -> Result<BookmarkValue> {
+    let offset = self.absolute(relative)?;
+    if depth > MAX_DEPTH {
+      return Err(invalid(offset, "items nested too deeply"));
+    }
+    self.items.set(self.items.get() + 1);
+    if self.items.get() > MAX_ITEMS {
+      return Err(invalid(offset, "too many items"));
+    }
+    let length = self.u32(offset, "an item length")? as usize;
+    let type_ = self.u32(offset + 4, "an item type")?;
+    let data = self.bytes(offset + 8, length, "item data")?;
+    let wrong_size = || {
+      invalid(
+        offset,
+        format!("item type {type_:#06x} has length {length}"),
+      )
+    };
+    let offsets = || {
+      data
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+    };
+    Ok(match (type_ & 0xffff_ff00, type_ & 0xff) {
+      (0x0100, _) => BookmarkValue::String(String::from_utf8_lossy(data).into_owned()),
+      (0x0200, _) => BookmarkValue::Data(data.to_vec()),
+      (0x0300, _) => match (type_ & 0xff, data) {
+        (1, [a]) => BookmarkValue::Number(*a as i8 as i64),
+        (2, [a, b]) => BookmarkValue::Number(i16::from_le_bytes([*a, *b]) as i64),
+        (3, &[a, b, c, d]) => BookmarkValue::Number(i32::from_le_bytes([a, b, c, d]) as i64),
+        (4, data) => BookmarkValue::Number(i64::from_le_bytes(
+          data.try_into().map_err(|_| wrong_size())?,
+        )),
+        (5, &[a, b, c, d]) => BookmarkValue::Float(f32::from_le_bytes([a, b, c, d]) as f64),
+        (6, data) => BookmarkValue::Float(f64::from_le_bytes(
+          data.try_into().map_err(|_| wrong_size())?,
+        )),
+        _ => BookmarkValue::Other {
+          type_,
+          data: data.to_vec(),
+        },
+      },
+      (0x0400, _) => {
+        let seconds = f64::from_be_bytes(data.try_into().map_err(|_| wrong_size())?);
+        BookmarkValue::Date(
+          date(seconds).ok_or_else(|| invalid(offset, format!("invalid date {seconds}")))?,
+        )
+      }
+      (0x0500, 0) => BookmarkValue::Bool(false),
+      (0x0500, 1) => BookmarkValue::Bool(true),
+      (0x0600, _) => BookmarkValue::Array(
+        offsets()
+          .map(|offset| self.item(offset, depth + 1))
+          .collect::<Result<_>>()?,
+      ),
+      (0x0700, _) => {
+        let items = offsets()
+          .map(|offset| self.item(offset, depth + 1))
+          .collect::<Result<Vec<_>>>()?;
+        let mut items = items.into_iter();
+        let mut pairs = Vec::new();
+        while let (Some(key), Some(value)) = (items.next(), items.next()) {
+          pairs.push((key, value));
+        }
+        BookmarkValue::Dictionary(pairs)
+      }
+      (0x0800, _) => {
+        BookmarkValue::Uuid(VolumeUuid(data.try_into().map_err(|_| wrong_size())?).to_string())
+      }
+      (0x0900, 1) => BookmarkValue::Url(String::from_utf8_lossy(data).into_owned()),
+      (0x0a00, _) => BookmarkValue::Null,
+      _ => BookmarkValue::Other {
+        type_,
+        data: data.to_vec(),
+      },
+    })
+  }
+}
+
+const STRING: u32 = 0x0101;
+const DATA: u32 = 0x0201;
+const NUMBER: u32 = 0x0304;
+const DATE: u32 = 0x0400;
+const ARRAY: u32 = 0x0601;
+const URL: u32 = 0x0901;
+
+/// `kCFURLResourceIs*` bits.
+const IS_REGULAR_FILE: u64 = 0x01;
+const IS_DIRECTORY: u64 = 0x02;
+const IS_SYMBOLIC_LINK: u64 = 0x04;
+const IS_VOLUME: u64 = 0x08;
+/// The file property bits [`create_bookmark`] knows the value of. It never
+/// sets `IS_SYMBOLIC_LINK`, since the target is looked up through symlinks.
+const FILE_PROPERTIES_KNOWN: u64 = IS_REGULAR_FILE | IS_DIRECTORY | IS_SYMBOLIC_LINK | IS_VOLUME;
+
+/// A bookmark date: seconds since 2001-01-01 as a big-endian double.
+fn date(seconds: f64) -> Option<SystemTime> {
+  let reference = UNIX_EPOCH + Duration::from_secs(REFERENCE_DATE);
+  let delta = Duration::try_from_secs_f64(seconds.abs()).ok()?;
+  if seconds < 0.0 {
+    reference.checked_sub(delta)
+  } else {
+    reference.checked_add(delta)
+  }
+}
+
+/// The inverse of [`date`].
+fn seconds(time: SystemTime) -> f64 {
+  let reference = UNIX_EPOCH + Duration::from_secs(REFERENCE_DATE);
+  match time.duration_since(reference) {
+    Ok(after) => after.as_secs_f64(),
+    Err(before) => -before.duration().as_secs_f64(),
+  }
+}
+
+fn flags(data: &[u8]) -> Option<u64> {
+  Some(u64::from_le_bytes(data.get(..8)?.try_into().ok()?))
+}
+
+/// Parse bookmark data, as stored in alias files and produced by
+/// `CFURLCreateBookmarkData`, into its best-known properties.
+///
+/// Nothing is resolved. Malformed input is reported as
+/// [`Error::InvalidRecord`] with the offset of the offending bytes; no input
+/// makes this panic. Items whose type does not match their key are ignored.
+pub fn parse_bookmark(buf: &[u8]) -> Result<Bookmark> {
+  decode_bookmark(buf).map(|decoded| decoded.properties)
+}
+
+/// Parse bookmark data into every entry of its tables of contents, with
+/// the properties [`parse_bookmark`] returns, for inspecting what Finder
+/// and other apps stored.
+///
+/// Nothing is resolved, and malformed input is reported as
+/// [`parse_bookmark`] reports it.
+pub fn decode_bookmark(buf: &[u8]) -> Result<DecodedBookmark> {
+  if buf.get(..4) != Some(b"book") {
+    return Err(invalid(0, "missing the 'book' magic"));
+  }
+  let parser = Parser {
+    buf,
+    header: 0,
+    items: Cell::new(0),
+  };
+  let size = parser.u32(4, "the bookmark length")? as usize;
+  if size > buf.len() {
+    return Err(invalid(
+      4,
+      format!(
+        "declared bookmark length {size} exceeds the {} available bytes",
+        buf.len()
+      ),
+    ));
+  }
+  let buf = &buf[..size];
+  let parser = Parser {
+    buf,
+    header: 0,
+    items: Cell::new(0),
+  };
+  let header = parser.u32(12, "the header length")? as usize;
+  if header < 16 || header > size {
+    return Err(invalid(12, format!("invalid header length {header}")));
+  }
+  let parser = Parser {
+    buf,
+    header,
+    items: Cell::new(0),
+  };
+
+  let mut bookmark = Bookmark::default();
+  let mut tocs = Vec::new();
+  let mut toc = parser.u32(header, "the first table of contents offset")?;
+  while toc != 0 {
+    if tocs.len() >= MAX_TOCS {
+      return Err(invalid(header, "too many tables of contents"));
+    }
+    let base = parser.absolute(toc)?;
+    if parser.u32(base + 4, "the table of contents magic")? != TOC_MAGIC {
+      return Err(invalid(base + 4, "missing the table of contents magic"));
+    }
+    let identifier = parser.u32(base + 8, "the table of contents identifier")?;
+    let next = parser.u32(base + 12, "the next table of contents offset")?;
+    let count = parser.u32(base + 16, "the table of contents length")?;
+    let mut entries = Vec::new();
+    for index in 0..count as usize {
+      let entry = base + 20 + index * 12;
+      let key = parser.u32(entry, "a table of contents key")?;
+      let offset = parser.u32(entry + 4, "a table of contents offset")?;
+      let value = parser.item(offset, 0)?;
+      // Keys with the high bit set name a string item; none of those back
+      // the properties of `Bookmark`.
+      let name = if key & 0x8000_0000 != 0 {
+        match parser.item(key & 0x7fff_ffff, 0)? {
+          BookmarkValue::String(name) => Some(name),
+          _ => None,
+        }
+      } else {
+        apply(&mut bookmark, key, &value);
+        bookmark_key_name(key).map(str::to_owned)
+      };
+      entries.push(BookmarkEntry { key, name, value });
+    }
+    tocs.push(BookmarkToc {
+      identifier,
+      entries,
+    });
+    toc = next;
+  }
+  Ok(DecodedBookmark {
+    properties: bookmark,
+    tocs,
+  })
+}
+
+/// Record `value` under `key`, unless an earlier table of contents already
+/// did.
+fn apply(bookmark: &mut Bookmark, key: u32, value: &BookmarkValue) {
+  match (key, value) {
+    (PATH, BookmarkValue::Array(items)) if bookmark.path.is_empty() => {
+      bookmark.path = items
+        .iter()
+        .filter_map(|item| match item {
+          BookmarkValue::String(component) => Some(component.clone()),
+          _ => None,
+        })
+        .collect();
+    }
+    (CNID_PATH, BookmarkValue::Array(items)) if bookmark.cnid_path.is_empty() => {
+      bookmark.cnid_path = items
+        .iter()
+        .filter_map(|item| match item {
+          BookmarkValue::Number(id) => Some(*id as u64),
+          _ => None,
+        })
+        .collect();
+    }
+    (FILE_ID, BookmarkValue::Number(id)) => {
+      bookmark.file_id.get_or_insert(*id as u64);
+    }
+    (FILE_CREATION_DATE, BookmarkValue::Date(date)) => {
+      bookmark.created.get_or_insert(*date);
+    }
+    (FILE_PROPERTIES, BookmarkValue::Data(data)) => {
+      if let (None, Some(flags)) = (bookmark.file_flags, flags(data)) {
+        bookmark.file_flags = Some(flags);
+      }
+    }
+    (VOLUME_PATH, BookmarkValue::String(path)) => {
+      bookmark.volume_path.get_or_insert_with(|| path.clone());
+    }
+    (VOLUME_NAME, BookmarkValue::String(name)) => {
+      bookmark.volume_name.get_or_insert_with(|| name.clone());
+    }
+    (VOLUME_UUID, BookmarkValue::String(uuid)) => {
+      bookmark.volume_uuid.get_or_insert_with(|| uuid.clone());
+    }
+    (VOLUME_CREATION_DATE, BookmarkValue::Date(date)) => {
+      bookmark.volume_created.get_or_insert(*date);
+    }
+    (VOLUME_PROPERTIES, BookmarkValue::Data(data)) => {
+      if let (None, Some(flags)) = (bookmark.volume_flags, flags(data)) {
+        bookmark.volume_flags = Some(flags);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Lays out bookmark data the way CFURL does: a 48 byte header, the items,
+/// then a single table of contents sorted by key.
+#[derive(Default)]
+struct Writer {
+  data: Vec<u8>,
+  entries: Vec<(u32, u32)>,
+}
+
+impl Writer {
+  fn item(&mut self, type_: u32, payload: &[u8]) -> u32 {
+    if self.data.is_empty() {
+      // Room for the offset of the table of contents.
+      self.data.extend_from_slice(&[0; 4]);
+    }
+    let offset = self.data.len() as u32;
+    self
+      .data
+      .extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    self.data.extend_from_slice(&type_.to_le_bytes());
+    self.data.extend_from_slice(payload);
+    while !self.data.len().is_multiple_of(4) {
+      self.data.push(0);
+    }
+    offset
+  }
+
+  fn number(&mut self, n: u64) -> u32 {
+    self.item(NUMBER, &n.to_le_bytes())
+  }
+
+  /// Property bits as CFURL stores them: the bits, which of them are known,
+  /// and 8 reserved bytes.
+  fn properties(&mut self, flags: u64, known: u64) -> u32 {
+    let mut payload = [0; 24];
+    payload[..8].copy_from_slice(&flags.to_le_bytes());
+    payload[8..16].copy_from_slice(&known.to_le_bytes());
+    self.item(DATA, &payload)
+  }
+
+  fn array(&mut self, offsets: &[u32]) -> u32 {
+    let payload: Vec<u8> = offsets.iter().flat_map(|o| o.to_le_bytes()).collect();
+    self.item(ARRAY, &payload)
+  }
+
+  fn entry(&mut self, key: u32, offset: u32) {
+    self.entries.push((key, offset));
+  }
+
+  fn finish(mut self) -> Result<Vec<u8>> {
+    self.entries.sort_unstable();
+    let toc = self.data.len() as u32;
+    self.data[..4].copy_from_slice(&toc.to_le_bytes());
+    let count = self.entries.len() as u32;
+    for value in [12 + count * 12, TOC_MAGIC, 1, 0, count] {
+      self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    for (key, offset) in self.entries {
+      for value in [key, offset, 0] {
+        self.data.extend_from_slice(&value.to_le_bytes());
+      }
+    }
+    let size = u32::try_from(48 + self.data.len())
+      .map_err(|_| Error::InvalidInfo("The bookmark would be larger than 4 GiB.".to_owned()))?;
+    let mut buf = b"book".to_vec();
+    buf.extend_from_slice(&size.to_le_bytes());
+    buf.extend_from_slice(&0x1004_0000u32.to_le_bytes());
+    buf.extend_from_slice(&48u32.to_le_bytes());
+    buf.resize(48, 0);
+    buf.extend_from_slice(&self.data);
+    Ok(buf)
+  }
+}
+
+/// The `file:` URL of the directory `path`, escaped the way CFURL does.
+fn directory_url(path: &str) -> String {
+  let mut url = "file://".to_owned();
+  for byte in path.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => url.push(byte as char),
+      b'-' | b'.' | b'_' | b'~' | b'/' | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+'
+      | b',' | b';' | b'=' | b':' | b'@' => url.push(byte as char),
+      _ => url.push_str(&format!("%{byte:02X}")),
+    }
+  }
+  if !url.ends_with('/') {
+    url.push('/');
+  }
+  url
+}
+
+/// Encode `bookmark` as bookmark data that [`parse_bookmark`] reads back, and
+/// that `CFURLCreateByResolvingBookmarkData` resolves by path and file id.
+///
+/// Properties that are `None` or empty are left out; the volume's URL
+/// (key `0x2005`) is written from `volume_path`. The path must not be
+/// empty, and its components must not contain `/`.
+pub fn encode_bookmark(bookmark: &Bookmark) -> Result<Vec<u8>> {
+  if bookmark.path.is_empty() {
+    return Err(Error::InvalidInfo(
+      "A bookmark needs the components of its target's path.".to_owned(),
+    ));
+  }
+  if let Some(component) = bookmark
+    .path
+    .iter()
+    .find(|component| component.is_empty() || component.contains('/'))
+  {
+    return Err(Error::InvalidInfo(format!(
+      "Bookmark path components must be non-empty and without a /, not {component:?}."
+    )));
+  }
+  if let Some(uuid) = &bookmark.volume_uuid {
+    uuid.parse::<VolumeUuid>()?;
+  }
+
+  let mut w = Writer::default();
+  let components: Vec<u32> = bookmark
+    .path
+    .iter()
+    .map(|component| w.item(STRING, component.as_bytes()))
+    .collect();
+  let path = w.array(&components);
+  w.entry(PATH, path);
+  if !bookmark.cnid_path.is_empty() {
+    let ids: Vec<u32> = bookmark.cnid_path.iter().map(|id| w.number(*id)).collect();
+    let cnid_path = w.array(&ids);
+    w.entry(CNID_PATH, cnid_path);
+  }
+  if let Some(flags) = bookmark.file_flags {
+    let properties = w.properties(flags, FILE_PROPERTIES_KNOWN | flags);
+    w.entry(FILE_PROPERTIES, properties);
+  }
+  if let Some(id) = bookmark.file_id {
+    let file_id = w.number(id);
+    w.entry(FILE_ID, file_id);
+  }
+  if let Some(created) = bookmark.created {
+    let created = w.item(DATE, &seconds(created).to_be_bytes());
+    w.entry(FILE_CREATION_DATE, created);
+  }
+  if let Some(volume_path) = &bookmark.volume_path {
+    let path = w.item(STRING, volume_path.as_bytes());
+    w.entry(VOLUME_PATH, path);
+    let url = w.item(URL, directory_url(volume_path).as_bytes());
+    w.entry(VOLUME_URL, url);
+  }
+  if let Some(name) = &bookmark.volume_name {
+    let name = w.item(STRING, name.as_bytes());
+    w.entry(VOLUME_NAME, name);
+  }
+  if let Some(uuid) = &bookmark.volume_uuid {
+    let uuid = w.item(STRING, uuid.as_bytes());
+    w.entry(VOLUME_UUID, uuid);
+  }
+  if let Some(created) = bookmark.volume_created {
+    let created = w.item(DATE, &seconds(created).to_be_bytes());
+    w.entry(VOLUME_CREATION_DATE, created);
+  }
+  if let Some(flags) = bookmark.volume_flags {
+    let properties = w.properties(flags, flags);
+    w.entry(VOLUME_PROPERTIES, properties);
+  }
+  w.finish()
+}
+
+/// The [`Bookmark`] for the target of the record `info`, which describes it
+/// in bookmark data the way the record does: by its path, file id and
+/// creation date, and its volume's mount point, name, UUID and creation
+/// date. A record only knows the ids of the target and its parent, so the
+/// bookmark has no ids for the other components of the path, and none of
+/// the volume's properties.
+pub fn bookmark_from_info(info: &Info) -> Result<Bookmark> {
+  let (Some(volume_path), Some(posix_path)) = (
+    info
+      .extra(19)
+      .and_then(|extra| String::from_utf8(extra.data.clone()).ok()),
+    info.posix_path(),
+  ) else {
+    return Err(Error::InvalidInfo(
+      "The record has no posix path (extras 18 and 19) to convert.".to_owned(),
+    ));
+  };
+  let mut file_flags = match info.target.type_ {
+    crate::TargetType::File => IS_REGULAR_FILE,
+    crate::TargetType::Directory => IS_DIRECTORY,
+  };
+  if posix_path == volume_path {
+    file_flags |= IS_VOLUME;
+  }
+  Ok(Bookmark {
+    path: posix_path
+      .split('/')
+      .filter(|component| !component.is_empty())
+      .map(str::to_owned)
+      .collect(),
+    cnid_path: Vec::new(),
+    file_id: (info.target.id != 0).then_some(info.target.id.into()),
+    created: Some(info.target.created),
+    file_flags: Some(file_flags),
+    volume_path: Some(volume_path),
+    volume_name: Some(info.volume.name.clone()),
+    volume_uuid: info.volume_uuid().map(|uuid| uuid.to_string()),
+    volume_created: Some(info.volume.created),
+    volume_flags: None,
+  })
+}
+
+/// The record for the target of `bookmark`, which needs its path, volume
+/// path and volume name. The parent id comes from `cnid_path`, and ids past
+/// 32 bits are recorded as 0. Dates the bookmark lacks are 1904-01-01,
+/// which records store as 0. The volume type follows the volume's
+/// properties, as [`crate::create_for_path`] follows DiskArbitration.
+pub fn info_from_bookmark(bookmark: &Bookmark) -> Result<Info> {
+  let missing = |what: &str| Error::InvalidInfo(format!("The bookmark has no {what} to convert."));
+  let target_path = bookmark.posix_path().ok_or_else(|| missing("path"))?;
+  let volume_path = bookmark
+    .volume_path
+    .clone()
+    .ok_or_else(|| missing("volume path"))?;
+  let volume_name = bookmark
+    .volume_name
+    .clone()
+    .ok_or_else(|| missing("volume name"))?;
+  let id = |id: Option<u64>| id.and_then(|id| u32::try_from(id).ok()).unwrap_or(0);
+  let parent_id = match bookmark.cnid_path.len() {
+    len if len == bookmark.path.len() && len >= 2 => Some(bookmark.cnid_path[len - 2]),
+    _ => None,
+  };
+  let unknown = crate::time::from_apple_date(0);
+
+  let target = Path::new(&target_path);
+  let parent_path = target.parent().ok_or_else(|| {
+    Error::InvalidInfo("The bookmark's target has no parent directory.".to_owned())
+  })?;
+  let name = |path: &Path| {
+    path
+      .file_name()
+      .map(|name| display_name(&name.to_string_lossy()))
+      .unwrap_or_default()
+  };
+  let is_dir = bookmark
+    .file_flags
+    .is_some_and(|flags| flags & IS_DIRECTORY != 0);
+  let volume_type = match bookmark.volume_flags {
+    // Not local.
+    Some(flags) if flags & 0x01 == 0 => VolumeType::Network,
+    // Ejectable, removable or a disk image.
+    Some(flags) if flags & (0x20 | 0x40 | 0x200) != 0 => VolumeType::Other,
+    Some(_) => VolumeType::Local,
+    None if volume_path == "/" => VolumeType::Local,
+    None => VolumeType::Other,
+  };
+  let mut info = crate::build_info(
+    &target_path,
+    &volume_path,
+    Target {
+      type_: if is_dir {
+        crate::TargetType::Directory
+      } else {
+        crate::TargetType::File
+      },
+      filename: name(target),
+      id: id(bookmark.file_id),
+      created: bookmark.created.unwrap_or(unknown),
+    },
+    Parent {
+      id: id(parent_id),
+      name: if parent_path == Path::new(&volume_path) {
+        volume_name.clone()
+      } else {
+        name(parent_path)
+      },
+    },
+    Volume {
+      name: volume_name,
+      created: bookmark.volume_created.unwrap_or(unknown),
+      signature: VolumeSignature::HPlus,
+      type_: volume_type,
+    },
+  )?;
+  if let Some(uuid) = &bookmark.volume_uuid {
+    let uuid: VolumeUuid = uuid.parse()?;
+    crate::add_extra_records(
+      &mut info,
+      &[Extra::new(VOLUME_UUID_EXTRA, uuid.0.to_vec())],
+      false,
+    )?;
+  }
+  Ok(info)
+}
+
+/// How [`create_bookmark`] describes the target's volume.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookmarkOptions {
+  /// Record this volume name instead of the one looked up, as
+  /// [`CreateOptions::volume_name`] does.
+  pub volume_name: Option<String>,
+  /// Record this volume UUID instead of the volume's own, e.g. that of a
+  /// disk image that has not been made yet.
+  pub volume_uuid: Option<VolumeUuid>,
+}
+
+/// Bookmark data for the file or directory at `target_path`, like
+/// `CFURLCreateBookmarkData` writes for Finder aliases on current macOS:
+/// the path with the file id of every component, the target's creation
+/// date and kind, and its volume's mount point, URL, name, UUID, creation
+/// date and properties.
+///
+/// Security-scoped and app-specific properties are not written.
+#[cfg(target_os = "macos")]
+pub fn create_bookmark<P: AsRef<Path>>(
+  target_path: P,
+  options: &BookmarkOptions,
+) -> Result<Vec<u8>> {
+  let target_path = target_path
+    .as_ref()
+    .to_str()
+    .ok_or_else(|| Error::InvalidPath("The target path is not a valid UTF-8 string.".to_owned()))?;
+  encode_bookmark(&bookmark_for_path_in(
+    &crate::volume::System,
+    target_path,
+    options,
+  )?)
+}
+
+/// Bookmark data for the file or directory at `target_path`.
+///
+/// Looking up the target needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn create_bookmark<P: AsRef<std::path::Path>>(
+  _target_path: P,
+  _options: &BookmarkOptions,
+) -> Result<Vec<u8>> {
+  Err(Error::Unsupported("Creating a bookmark"))
+}
+
+/// The [`Bookmark`] for `target_path`, looked up in `source`.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn bookmark_for_path_in(
+  source: &impl MetadataSource,
+  target_path: &str,
+  options: &BookmarkOptions,
+) -> Result<Bookmark> {
+  let info = crate::create::info_for_path_in(
+    source,
+    target_path,
+    &CreateOptions {
+      no_disk_image: true,
+      embed_volume_uuid: true,
+      volume_name: options.volume_name.clone(),
+      volume_uuid: options.volume_uuid,
+      ..Default::default()
+    },
+  )?;
+  // Both are recorded in full layout, whatever the target.
+  let (Some(volume_path), Some(posix_path)) = (
+    info
+      .extra(19)
+      .and_then(|extra| String::from_utf8(extra.data.clone()).ok()),
+    info.posix_path(),
+  ) else {
+    return Err(Error::InvalidPath(format!(
+      "The volume of {target_path} has no posix path."
+    )));
+  };
+  let path: Vec<String> = posix_path
+    .split('/')
+    .filter(|component| !component.is_empty())
+    .map(str::to_owned)
+    .collect();
+  // Without the id of every component, CFURL would pair ids with the wrong
+  // components, so one failed lookup leaves them all out.
+  let cnid_path = (1..=path.len())
+    .map(|depth| {
+      let prefix = format!("/{}", path[..depth].join("/"));
+      source.file_ids(Path::new(&prefix)).ok().map(|ids| ids.id)
+    })
+    .collect::<Option<Vec<u64>>>()
+    .unwrap_or_default();
+
+  let mut file_flags = match info.target.type_ {
+    TargetType::File => IS_REGULAR_FILE,
+    TargetType::Directory => IS_DIRECTORY,
+  };
+  if posix_path == volume_path {
+    file_flags |= IS_VOLUME;
+  }
+
+  // The `kCFURLVolume*` bits that the lookups here can tell.
+  let volume = Path::new(&volume_path);
+  let mut volume_flags = 0;
+  if let Ok(statfs) = source.statfs(volume) {
+    for (mount_flag, flag) in [
+      // Is local, is read-only, doesn't browse.
+      (MNT_LOCAL, 0x01),
+      (MNT_RDONLY, 0x08),
+      (MNT_DONTBROWSE, 0x04),
+    ] {
+      if statfs.flags & mount_flag != 0 {
+        volume_flags |= flag;
+      }
+    }
+  }
+  if let Some(disk) = source.disk_description(volume) {
+    // Is ejectable, is removable, is internal, is external.
+    for (known, flag) in [
+      (disk.ejectable, 0x20),
+      (disk.removable, 0x40),
+      (disk.internal, 0x80),
+      (disk.internal.map(|internal| !internal), 0x100),
+    ] {
+      if known == Some(true) {
+        volume_flags |= flag;
+      }
+    }
+  }
+  if source.disk_image(volume).is_some() {
+    // Is a disk image.
+    volume_flags |= 0x200;
+  }
+
+  Ok(Bookmark {
+    file_id: cnid_path.last().copied(),
+    cnid_path,
+    path,
+    created: Some(info.target.created),
+    file_flags: Some(file_flags),
+    volume_uuid: info.volume_uuid().map(|uuid| uuid.to_string()),
+    volume_name: Some(info.volume.name),
+    volume_created: Some(info.volume.created),
+    volume_flags: Some(volume_flags),
+    volume_path: Some(volume_path),
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::{Duration, UNIX_EPOCH};
+
+  use crate::fixture::Rng;
+  use crate::Error;
+
+  /// Lays out bookmark data the way CFURL does: a 48 byte header, the items,
+  /// then a single table of contents.
+  #[derive(Default)]
+  struct Builder {
+    data: Vec<u8>,
+    records: Vec<(u32, u32)>,
+  }
+
+  impl Builder {
+    fn item(&mut self, type_: u32, payload: &[u8]) -> u32 {
+      if self.data.is_empty() {
+        // Room for the offset of the table of contents.
+        self.data.extend_from_slice(&[0; 4]);
+      }
+      let offset = self.data.len() as u32;
+      self
+        .data
+        .extend_from_slice(&(payload.len() as u32).to_le_bytes());
+      self.data.extend_from_slice(&type_.to_le_bytes());
+      self.data.extend_from_slice(payload);
+      while !self.data.len().is_multiple_of(4) {
+        self.data.push(0);
+      }
+      offset
+    }
+
+    fn string(&mut self, s: &str) -> u32 {
+      self.item(0x0101, s.as_bytes())
+    }
+
+    fn number(&mut self, n: i64) -> u32 {
+      self.item(0x0304, &n.to_le_bytes())
+    }
+
+    fn date(&mut self, unix_seconds: f64) -> u32 {
+      self.item(0x0400, &(unix_seconds - 978_307_200.0).to_be_bytes())
+    }
+
+    fn array(&mut self, offsets: &[u32]) -> u32 {
+      let payload: Vec<u8> = offsets.iter().flat_map(|o| o.to_le_bytes()).collect();
+      self.item(0x0601, &payload)
+    }
+
+    fn record(&mut self, key: u32, offset: u32) {
+      self.records.push((key, offset));
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+      let toc = self.data.len() as u32;
+      self.data[..4].copy_from_slice(&toc.to_le_bytes());
+      let count = self.records.len() as u32;
+      for value in [12 + count * 12, super::TOC_MAGIC, 1, 0, count] {
+        self.data.extend_from_slice(&value.to_le_bytes());
+      }
+      for (key, offset) in self.records {
+        for value in [key, offset, 0] {
+          self.data.extend_from_slice(&value.to_le_bytes());
+        }
+      }
+      let mut buf = b"book".to_vec();
+      buf.extend_from_slice(&(48 + self.data.len() as u32).to_le_bytes());
+      buf.extend_from_slice(&0x1004_0000u32.to_le_bytes());
+      buf.extend_from_slice(&48u32.to_le_bytes());
+      buf.resize(48, 0);
+      buf.extend_from_slice(&self.data);
+      buf
+    }
+  }
+
+  /// A bookmark for the shared fixture's target. It is assembled by
+  /// `Builder`, not captured from `CFURLCreateBookmarkData`; the macOS-only
+  /// test below covers real output.
+  fn fixture() -> Vec<u8> {
+    let mut b = Builder::default();
+    let components: Vec<u32> = ["Volumes", "Test Title", ".background", "TestBkg.tiff"]
+      .iter()
+      .map(|c| b.string(c))
+      .collect();
+    let path = b.array(&components);
+    b.record(0x1004, path);
+    let ids: Vec<u32> = [2, 3, 19, 20].iter().map(|id| b.number(*id)).collect();
+    let cnid_path = b.array(&ids);
+    b.record(0x1005, cnid_path);
+    let mut properties = 1u64.to_le_bytes().to_vec();
+    properties.extend_from_slice(&[0xff; 16]);
+    let properties = b.item(0x0201, &properties);
+    b.record(0x1010, properties);
+    let file_id = b.number(20);
+    b.record(0x1030, file_id);
+    let created = b.date(1388686808.5);
+    b.record(0x1040, created);
+    let volume_path = b.string("/Volumes/Test Title");
+    b.record(0x2002, volume_path);
+    let volume_name = b.string("Test Title");
+    b.record(0x2010, volume_name);
+    let uuid = b.string("0A81F3B1-51D9-3335-B3E3-169C3640360D");
+    b.record(0x2011, uuid);
+    let volume_created = b.date(1388686804.0);
+    b.record(0x2013, volume_created);
+    let volume_properties = b.item(0x0201, &[0x81, 0, 0, 0, 0, 0, 0, 0]);
+    b.record(0x2020, volume_properties);
+    // A float and a string key, both of which are skipped.
+    let other = b.item(0x0306, &1.5f64.to_le_bytes());
+    b.record(0xf017, other);
+    b.record(0x8000_0000 | components[0], other);
+    b.finish()
+  }
+
+  fn parse_error(buf: &[u8]) -> String {
+    match super::parse_bookmark(buf) {
+      Err(err @ Error::InvalidRecord { .. }) => err.to_string(),
+      other => panic!("expected an invalid record error, got {other:?}"),
+    }
+  }
+
+  #[test]
+  fn parse_fixture() {
+    let bookmark = super::parse_bookmark(&fixture()).unwrap();
+    assert_eq!(
+      bookmark.posix_path().as_deref(),
+      Some("/Volumes/Test Title/.background/TestBkg.tiff")
+    );
+    assert_eq!(bookmark.cnid_path, [2, 3, 19, 20]);
+    assert_eq!(bookmark.file_id, Some(20));
+    assert_eq!(
+      bookmark.created,
+      Some(UNIX_EPOCH + Duration::from_millis(1388686808500))
+    );
+    assert_eq!(bookmark.file_flags, Some(1));
+    assert_eq!(bookmark.volume_path.as_deref(), Some("/Volumes/Test Title"));
+    assert_eq!(bookmark.volume_name.as_deref(), Some("Test Title"));
+    assert_eq!(
+      bookmark.volume_uuid.as_deref(),
+      Some("0A81F3B1-51D9-3335-B3E3-169C3640360D")
+    );
+    assert_eq!(
+      bookmark.volume_created,
+      Some(UNIX_EPOCH + Duration::from_secs(1388686804))
+    );
+    assert_eq!(bookmark.volume_flags, Some(0x81));
+  }
+
+  #[test]
+  fn decode_fixture() {
+    use super::BookmarkValue;
+
+    let decoded = super::decode_bookmark(&fixture()).unwrap();
+    assert_eq!(
+      decoded.properties,
+      super::parse_bookmark(&fixture()).unwrap()
+    );
+    let [toc] = &decoded.tocs[..] else {
+      panic!("expected one table of contents, got {:?}", decoded.tocs);
+    };
+    assert_eq!(toc.identifier, 1);
+    let names: Vec<_> = toc
+      .entries
+      .iter()
+      .map(|entry| entry.name.as_deref())
+      .collect();
+    assert_eq!(
+      names,
+      [
+        Some("path"),
+        Some("cnidPath"),
+        Some("fileProperties"),
+        Some("fileId"),
+        Some("fileCreationDate"),
+        Some("volumePath"),
+        Some("volumeName"),
+        Some("volumeUuid"),
+        Some("volumeCreationDate"),
+        Some("volumeProperties"),
+        Some("displayName"),
+        // The string key names the first path component.
+        Some("Volumes"),
+      ]
+    );
+    assert_eq!(toc.entries[3].value, BookmarkValue::Number(20));
+    assert_eq!(toc.entries[10].value, BookmarkValue::Float(1.5));
+    assert_eq!(toc.entries[11].key & 0x8000_0000, 0x8000_0000);
+  }
+
+  #[test]
+  fn decode_value_types() {
+    use super::BookmarkValue;
+
+    let mut b = Builder::default();
+    let values = [
+      b.item(0x0500, &[]),
+      b.item(0x0501, &[]),
+      b.item(0x0303, &(-7i32).to_le_bytes()),
+      b.item(0x0305, &0.5f32.to_le_bytes()),
+      b.item(0x0801, &[0x0a; 16]),
+      b.item(0x0901, b"file:///Volumes/Test%20Title/"),
+      b.item(0x0a01, &[]),
+      b.item(0x0902, &[0; 8]),
+    ];
+    let (key, value) = (b.string("key"), b.number(1));
+    let dictionary = b.item(0x0701, &[key.to_le_bytes(), value.to_le_bytes()].concat());
+    let array = b.array(&[values.as_slice(), &[dictionary]].concat());
+    b.record(0xf030, array);
+
+    let decoded = super::decode_bookmark(&b.finish()).unwrap();
+    let entry = &decoded.tocs[0].entries[0];
+    assert_eq!(entry.name.as_deref(), Some("typeBindingInfo"));
+    assert_eq!(
+      entry.value,
+      BookmarkValue::Array(vec![
+        BookmarkValue::Bool(false),
+        BookmarkValue::Bool(true),
+        BookmarkValue::Number(-7),
+        BookmarkValue::Float(0.5),
+        BookmarkValue::Uuid("0A0A0A0A-0A0A-0A0A-0A0A-0A0A0A0A0A0A".to_owned()),
+        BookmarkValue::Url("file:///Volumes/Test%20Title/".to_owned()),
+        BookmarkValue::Null,
+        BookmarkValue::Other {
+          type_: 0x0902,
+          data: vec![0; 8]
+        },
+        BookmarkValue::Dictionary(vec![(
+          BookmarkValue::String("key".to_owned()),
+          BookmarkValue::Number(1)
+        )]),
+      ])
+    );
+  }
+
+  #[test]
+  fn missing_magic() {
+    assert_eq!(
+      parse_error(&crate::fixture::fixture_bytes()),
+      "missing the 'book' magic at offset 0"
+    );
+  }
+
+  #[test]
+  fn declared_length_past_buffer() {
+    let buf = fixture();
+    assert_eq!(
+      parse_error(&buf[..100]),
+      format!(
+        "declared bookmark length {} exceeds the 100 available bytes at offset 4",
+        buf.len()
+      )
+    );
+  }
+
+  #[test]
+  fn self_referential_array() {
+    let mut b = Builder::default();
+    // The array's only element is the array itself.
+    let array = b.array(&[4]);
+    assert_eq!(array, 4);
+    b.record(0x1004, array);
+    assert_eq!(
+      parse_error(&b.finish()),
+      "items nested too deeply at offset 52"
+    );
+  }
+
+  #[test]
+  fn shared_array_elements() {
+    let mut b = Builder::default();
+    let mut array = b.string("a");
+    for _ in 0..4 {
+      array = b.array(&[array; 64]);
+    }
+    b.record(0x1004, array);
+    assert!(parse_error(&b.finish()).starts_with("too many items at offset"));
+  }
+
+  #[test]
+  fn never_panics_on_truncation_or_corruption() {
+    let fixture = fixture();
+    for length in 0..fixture.len() {
+      let mut buf = fixture[..length].to_vec();
+      let _ = super::parse_bookmark(&buf);
+      // Also with the length fixed up, so the tables are walked.
+      if length >= 8 {
+        buf[4..8].copy_from_slice(&(length as u32).to_le_bytes());
+        let _ = super::parse_bookmark(&buf);
+      }
+    }
+    let mut rng = Rng(0x5851_f42d_4c95_7f2d);
+    for _ in 0..10_000 {
+      let mut buf = fixture.clone();
+      for _ in 0..4 {
+        let state = rng.next();
+        let index = (state % buf.len() as u64) as usize;
+        buf[index] = (state >> 32) as u8;
+      }
+      let _ = super::parse_bookmark(&buf);
+    }
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn parse_system_bookmark() {
+    use std::os::unix::fs::MetadataExt;
+    use std::ptr;
+
+    use core_foundation::base::{kCFAllocatorDefault, TCFType};
+    use core_foundation::data::CFData;
+    use core_foundation::url::{CFURLCreateBookmarkData, CFURL};
+
+    let path = std::fs::canonicalize(env!("CARGO_MANIFEST_DIR"))
+      .unwrap()
+      .join("Cargo.toml");
+    let url = CFURL::from_path(&path, false).unwrap();
+    let data = unsafe {
+      let data = CFURLCreateBookmarkData(
+        kCFAllocatorDefault,
+        url.as_concrete_TypeRef(),
+        0,
+        ptr::null(),
+        ptr::null(),
+        ptr::null_mut(),
+      );
+      assert!(!data.is_null());
+      CFData::wrap_under_create_rule(data)
+    };
+
+    let bookmark = super::parse_bookmark(data.bytes()).unwrap();
+    assert_eq!(
+      bookmark.posix_path().as_deref(),
+      Some(path.to_str().unwrap())
+    );
+    assert_eq!(
+      bookmark.file_id,
+      Some(std::fs::metadata(&path).unwrap().ino())
+    );
+    assert!(bookmark.volume_path.is_some());
+  }
+
+  fn fixture_bookmark() -> super::Bookmark {
+    super::Bookmark {
+      path: ["Volumes", "Test Title", ".background", "TestBkg.tiff"]
+        .map(str::to_owned)
+        .to_vec(),
+      cnid_path: vec![102, 2, 19, 20],
+      file_id: Some(20),
+      created: Some(UNIX_EPOCH + Duration::from_secs(1388686808)),
+      file_flags: Some(1),
+      volume_path: Some("/Volumes/Test Title".to_owned()),
+      volume_name: Some("Test Title".to_owned()),
+      volume_uuid: Some("0A81F3B1-51D9-3335-B3E3-169C3640360D".to_owned()),
+      volume_created: Some(UNIX_EPOCH + Duration::from_secs(1388686804)),
+      volume_flags: Some(0xa1),
+    }
+  }
+
+  #[test]
+  fn encode_round_trip() {
+    let bookmark = fixture_bookmark();
+    let buf = super::encode_bookmark(&bookmark).unwrap();
+    assert_eq!(&buf[..4], b"book");
+    assert_eq!(super::parse_bookmark(&buf).unwrap(), bookmark);
+    assert!(buf
+      .windows(32)
+      .any(|window| window == b"file:///Volumes/Test%20Title/\0\0\0"));
+
+    let minimal = super::Bookmark {
+      path: vec!["tmp".to_owned()],
+      ..Default::default()
+    };
+    let buf = super::encode_bookmark(&minimal).unwrap();
+    assert_eq!(super::parse_bookmark(&buf).unwrap(), minimal);
+  }
+
+  #[test]
+  fn encode_errors() {
+    for path in [vec![], vec!["a/b"], vec!["Volumes", ""]] {
+      let bookmark = super::Bookmark {
+        path: path.iter().map(|c| c.to_string()).collect(),
+        ..Default::default()
+      };
+      assert!(
+        matches!(
+          super::encode_bookmark(&bookmark),
+          Err(Error::InvalidInfo(_))
+        ),
+        "{path:?}"
+      );
+    }
+    let bookmark = super::Bookmark {
+      volume_uuid: Some("not a uuid".to_owned()),
+      ..fixture_bookmark()
+    };
+    assert!(matches!(
+      super::encode_bookmark(&bookmark),
+      Err(Error::InvalidInfo(_))
+    ));
+  }
+
+  #[test]
+  fn bookmark_for_fake_volume() {
+    let mut volumes = crate::fixture::FakeVolumes::test_title();
+    volumes.set_volume_uuid(3, "0A81F3B1-51D9-3335-B3E3-169C3640360D".parse().unwrap());
+    let bookmark = super::bookmark_for_path_in(
+      &volumes,
+      "/Volumes/Test Title/.background/TestBkg.tiff",
+      &Default::default(),
+    )
+    .unwrap();
+    // Local, ejectable and internal.
+    assert_eq!(bookmark, fixture_bookmark());
+
+    let options = super::BookmarkOptions {
+      volume_name: Some("My App".to_owned()),
+      volume_uuid: Some(crate::VolumeUuid([7; 16])),
+    };
+    let volume = super::bookmark_for_path_in(&volumes, "/Volumes/Test Title", &options).unwrap();
+    assert_eq!(volume.path, ["Volumes", "Test Title"]);
+    assert_eq!(volume.file_flags, Some(0x02 | 0x08));
+    assert_eq!(volume.volume_name.as_deref(), Some("My App"));
+    assert_eq!(
+      volume.volume_uuid.as_deref(),
+      Some("07070707-0707-0707-0707-070707070707")
+    );
+
+    // Through a firmlink, the path is the one asked for, on `/`.
+    let firmlinked =
+      super::bookmark_for_path_in(&volumes, "/Users/me/bg.tiff", &Default::default()).unwrap();
+    assert_eq!(firmlinked.volume_path.as_deref(), Some("/"));
+    assert_eq!(firmlinked.path, ["Users", "me", "bg.tiff"]);
+    assert_eq!(firmlinked.cnid_path, [300, 301, 302]);
+  }
+
+  #[test]
+  fn convert_fixture() {
+    // The record keeps what a fresh one for the target has, and the volume
+    // UUID.
+    let info = super::info_from_bookmark(&fixture_bookmark()).unwrap();
+    let fixture = crate::fixture::fixture_info();
+    assert_eq!(
+      (&info.target, &info.parent, &info.volume),
+      (&fixture.target, &fixture.parent, &fixture.volume)
+    );
+    assert_eq!(info.posix_path(), fixture.posix_path());
+    assert_eq!(
+      info.volume_uuid().map(|uuid| uuid.to_string()),
+      fixture_bookmark().volume_uuid
+    );
+
+    // Back again, the bookmark has no ids but the target's, and no volume
+    // properties.
+    assert_eq!(
+      super::bookmark_from_info(&info).unwrap(),
+      super::Bookmark {
+        cnid_path: vec![],
+        volume_flags: None,
+        ..fixture_bookmark()
+      }
+    );
+    let bookmark = super::bookmark_from_info(&fixture).unwrap();
+    assert_eq!(bookmark.volume_uuid, None);
+    assert_eq!(
+      super::parse_bookmark(&super::encode_bookmark(&bookmark).unwrap()).unwrap(),
+      bookmark
+    );
+  }
+
+  #[test]
+  fn convert_errors() {
+    let bookmark = super::Bookmark {
+      volume_name: None,
+      ..fixture_bookmark()
+    };
+    assert!(matches!(
+      super::info_from_bookmark(&bookmark),
+      Err(Error::InvalidInfo(_))
+    ));
+    let mut info = crate::fixture::fixture_info();
+    info.extra.retain(|extra| extra.type_ != 18);
+    assert!(matches!(
+      super::bookmark_from_info(&info),
+      Err(Error::InvalidInfo(_))
+    ));
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn system_resolves_created_bookmark() {
+    use std::ptr;
+
+    use core_foundation::base::{kCFAllocatorDefault, TCFType};
+    use core_foundation::data::CFData;
+    use core_foundation::url::{CFURLCreateByResolvingBookmarkData, CFURL};
+
+    let path = std::fs::canonicalize(env!("CARGO_MANIFEST_DIR"))
+      .unwrap()
+      .join("Cargo.toml");
+    let buf = super::create_bookmark(&path, &Default::default()).unwrap();
+    let data = CFData::from_buffer(&buf);
+    let url = unsafe {
+      let url = CFURLCreateByResolvingBookmarkData(
+        kCFAllocatorDefault,
+        data.as_concrete_TypeRef(),
+        0,
+        ptr::null(),
+        ptr::null(),
+        ptr::null_mut(),
+        ptr::null_mut(),
+      );
+      assert!(!url.is_null());
+      CFURL::wrap_under_create_rule(url)
+    };
+    assert_eq!(url.to_path().unwrap(), path);
+  }
+}
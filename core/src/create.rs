@@ -0,0 +1,2429 @@
+use std::path::Path;
+#[cfg(any(target_os = "macos", test))]
+use std::path::PathBuf;
+#[cfg(any(target_os = "macos", test))]
+use std::time::SystemTime;
+
+#[cfg(any(target_os = "macos", test))]
+use crate::filesystem::Filesystem;
+use crate::macroman;
+#[cfg(any(target_os = "macos", test))]
+use crate::source::{find_volume, mount_point, FileStat, MetadataSource};
+#[cfg(any(target_os = "macos", test))]
+use crate::volume_info::{volume_info_in, VolumeInfo};
+use crate::{
+  ClassicFields, Compat, CreateOptions, Error, Extra, Extras, Info, Layout, Parent, Result, Target,
+  Volume, VolumeTypeSource, Warning,
+};
+#[cfg(any(target_os = "macos", test))]
+use crate::{TargetType, VolumeType, VolumeUuid, VOLUME_UUID_EXTRA};
+
+/// A UTF-16BE string prefixed with its length in code units, as stored in
+/// the type 14 and 15 extras.
+pub(crate) fn unicode_name(s: &str) -> Vec<u8> {
+  let units = s.encode_utf16().count();
+  let mut buffer = Vec::with_capacity(2 + units * 2);
+  buffer.extend_from_slice(&(units as u16).to_be_bytes());
+  buffer.extend_from_slice(&utf16be(s));
+  buffer
+}
+
+/// The name Finder shows for the posix path component `name`. Names can
+/// contain `/` but not `:`, so the filesystem stores a `/` as `:`.
+pub(crate) fn display_name(name: &str) -> String {
+  name.replace(':', "/")
+}
+
+/// The posix path component for the name Finder shows as `name`.
+pub(crate) fn posix_name(name: &str) -> String {
+  name.replace('/', ":")
+}
+
+/// `path` with its `.` components dropped and each `..` applied to the
+/// component before it, without looking at the filesystem, so symlinks are
+/// left alone. Repeated and trailing slashes go too.
+///
+/// Once an absolute path has reached `floor`, a `..` must not take it back
+/// above: that would leave the volume mounted there. Relative paths keep
+/// the `..`s they start with.
+pub(crate) fn normalize_path(path: &str, floor: &str) -> Result<String> {
+  if path.is_empty() {
+    return Ok(String::new());
+  }
+  let absolute = path.starts_with('/');
+  let floor: Vec<&str> = floor.split('/').filter(|c| !c.is_empty()).collect();
+  let mut components: Vec<&str> = Vec::new();
+  let mut below_floor = absolute && floor.is_empty();
+  for component in path.split('/') {
+    match component {
+      "" | "." => {}
+      ".." if absolute => {
+        if below_floor && components.len() <= floor.len() {
+          return Err(Error::InvalidPath(format!(
+            "The path {path} climbs above /{}, out of the volume.",
+            floor.join("/")
+          )));
+        }
+        components.pop();
+      }
+      ".." if components.last().is_some_and(|&last| last != "..") => {
+        components.pop();
+      }
+      component => components.push(component),
+    }
+    below_floor |= absolute && components.starts_with(&floor);
+  }
+  let path = components.join("/");
+  Ok(match (absolute, path.is_empty()) {
+    (true, _) => format!("/{path}"),
+    (false, true) => ".".to_owned(),
+    (false, false) => path,
+  })
+}
+
+/// The HFS path of a target at `lp` below the root of `volume`, as the
+/// Alias Manager stores it in extra 2: `Test Title:.background:\0TestBkg.tiff`
+/// for `/.background/TestBkg.tiff`, in Mac OS Roman, with the names Finder
+/// shows and a NUL before the file name. `None` for the volume itself.
+fn carbon_path(lp: &str, volume: &Volume, target: &Target) -> Option<Vec<u8>> {
+  let components: Vec<&str> = lp.split('/').filter(|c| !c.is_empty()).collect();
+  let [parents @ .., _] = components.as_slice() else {
+    return None;
+  };
+  let mut path = vec![volume.name.clone()];
+  path.extend(parents.iter().map(|c| display_name(c)));
+  path.push(format!("\0{}", target.filename));
+  Some(macroman::encode(&path.join(":")))
+}
+
+fn utf16be(s: &str) -> Vec<u8> {
+  let b: Vec<u16> = s.encode_utf16().collect();
+  let mut result: Vec<u8> = Vec::new();
+  for &number in &b {
+    result.extend_from_slice(&number.to_be_bytes());
+  }
+  result
+}
+
+/// Assemble the record for `target_path` on the volume mounted at
+/// `volume_path` from metadata the caller already knows.
+///
+/// Names in `target`, `parent` and `volume` are the ones Finder shows, while
+/// the paths are posix paths: a volume named `App 1/2` is mounted at
+/// `/Volumes/App 1:2`.
+///
+/// Nothing is looked up on the filesystem, so this works on every platform.
+pub fn build_info(
+  target_path: &str,
+  volume_path: &str,
+  target: Target,
+  parent: Parent,
+  volume: Volume,
+) -> Result<Info> {
+  build_info_with(
+    target_path,
+    volume_path,
+    target,
+    parent,
+    volume,
+    Compat::Native,
+  )
+}
+
+/// Like [`build_info`], but laying out the extras the way `compat` does.
+pub fn build_info_with(
+  target_path: &str,
+  volume_path: &str,
+  target: Target,
+  parent: Parent,
+  volume: Volume,
+  compat: Compat,
+) -> Result<Info> {
+  let volume_path = &normalize_path(volume_path, "/")?;
+  let target_path = &normalize_path(target_path, volume_path)?;
+  let lp = relative_posix_path(target_path, volume_path).ok_or_else(|| {
+    Error::InvalidPath(format!(
+      "The target path {target_path} is not on the volume mounted at {volume_path}."
+    ))
+  })?;
+  let lp = match compat {
+    Compat::Native => lp,
+    // node-alias slices the mount point's bytes off the path, which leaves
+    // no leading `/` on the startup volume.
+    Compat::NodeAlias => target_path[volume_path.len()..].to_owned(),
+  };
+
+  let mut extra = Vec::new();
+
+  let parent_name = match compat {
+    Compat::Native => macroman::encode(&parent.name),
+    // node-alias declares `name.length` UTF-16 code units and only the first
+    // that many UTF-8 bytes survive in the record.
+    Compat::NodeAlias => {
+      parent.name.as_bytes()[..parent.name.encode_utf16().count().min(parent.name.len())].to_vec()
+    }
+  };
+  extra.push(Extra::new(0, parent_name));
+  extra.push(Extra::new(1, parent.id.to_be_bytes().to_vec()));
+  // node-alias writes no carbon path.
+  if let (Compat::Native, Some(path)) = (compat, carbon_path(&lp, &volume, &target)) {
+    extra.push(Extra::new(2, path));
+  }
+  extra.push(Extra::new(14, unicode_name(&target.filename)));
+  extra.push(Extra::new(15, unicode_name(&volume.name)));
+  extra.push(Extra::new(18, lp.as_bytes().to_vec()));
+  extra.push(Extra::new(19, volume_path.as_bytes().to_vec()));
+
+  Ok(Info {
+    version: 2,
+    target,
+    volume,
+    parent,
+    extra,
+    classic: ClassicFields::default(),
+  })
+}
+
+/// Add `records` to the extras of `info`.
+///
+/// A record replaces the extra of the same type in place. The others are
+/// slotted into the canonical order Finder writes, ascending by type
+/// (0, 1, 2, 14, 15, 18, 19, ...), or appended after the existing extras in
+/// the order given when `append_raw` is set.
+pub fn add_extra_records(info: &mut Info, records: &[Extra], append_raw: bool) -> Result<()> {
+  for record in records {
+    if record.type_ == -1 {
+      return Err(Error::InvalidInfo(
+        "Extra type -1 is reserved for the end-of-record marker.".to_owned(),
+      ));
+    }
+    if record.data.len() > u16::MAX as usize {
+      return Err(Error::InvalidInfo(format!(
+        "Extra type {} holds {} bytes, more than the {} an extra can.",
+        record.type_,
+        record.data.len(),
+        u16::MAX
+      )));
+    }
+    let record = Extra::new(record.type_, record.data.clone());
+    match info.extra.iter_mut().find(|e| e.type_ == record.type_) {
+      Some(existing) => *existing = record,
+      None if append_raw => info.extra.push(record),
+      None => {
+        let index = info.extra.partition_point(|e| e.type_ <= record.type_);
+        info.extra.insert(index, record);
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Strip `info` down to what `layout` keeps, e.g. to make a record from
+/// [`build_info`] as small as [`Layout::Minimal`] ones.
+pub fn apply_layout(info: &mut Info, layout: Layout) {
+  let keep: &[i16] = match layout {
+    Layout::Full => return,
+    Layout::Minimal => &[18, 19],
+    Layout::MinimalWithNames => &[14, 15, 18, 19],
+  };
+  info.extra.retain(|e| keep.contains(&e.type_));
+  info.volume.name.clear();
+  info.target.filename.clear();
+}
+
+/// Drop the extras of `info` that are not in `extras`.
+///
+/// The record has to stay resolvable and keep its names, so this fails
+/// with [`Error::InvalidInfo`] when dropping the posix extras would leave
+/// nothing but the classic names, which [`apply_layout`] emptied, or when
+/// dropping the unicode extras would leave a name the classic fields
+/// cannot spell in Mac OS Roman.
+pub fn apply_extras(info: &mut Info, extras: Extras) -> Result<()> {
+  if !extras.posix && (info.target.filename.is_empty() || info.volume.name.is_empty()) {
+    return Err(Error::InvalidInfo(
+      "Without the posix extras the record needs the classic names, which minimal layouts leave out."
+        .to_owned(),
+    ));
+  }
+  if !extras.unicode {
+    for name in [&info.target.filename, &info.volume.name] {
+      if !macroman::is_representable(name) {
+        return Err(Error::InvalidInfo(format!(
+          "{name:?} cannot be spelled in Mac OS Roman, so the record needs the unicode extras."
+        )));
+      }
+    }
+  }
+  info.extra.retain(|e| extras.keeps(e.type_));
+  Ok(())
+}
+
+/// Build the alias record for the file or directory at `target_path`.
+///
+/// The target, its parent and its volume are looked up on the live
+/// filesystem, so this only works on macOS.
+pub fn create_for_path<P: AsRef<Path>>(target_path: P) -> Result<Vec<u8>> {
+  create_for_path_with(target_path, &CreateOptions::default())
+}
+
+/// What [`create_for_path_with`] learns about a target from the filesystem.
+#[cfg(any(target_os = "macos", test))]
+#[derive(Debug, Clone)]
+pub(crate) struct Stat {
+  pub(crate) target_path: String,
+  pub(crate) is_dir: bool,
+  /// The target's file id, or 0 if it does not exist.
+  pub(crate) target_ino: u64,
+  pub(crate) target_created: SystemTime,
+  pub(crate) target_created_source: DateSource,
+  pub(crate) parent_ino: u64,
+  /// Whether the parent directory could not be looked up.
+  pub(crate) parent_unreadable: bool,
+  pub(crate) volume: VolumeInfo,
+  /// The target's `st_flags`, or 0 if it does not exist.
+  pub(crate) target_flags: u32,
+}
+
+/// The path of `target_path` below the volume mounted at `volume_path`, as
+/// Finder writes it in extra 18: each component after a `/`, on `/` as on
+/// any other volume, e.g. `/Users/me/bg.tiff`. `None` if the target is not
+/// on that volume. Both paths must be normalized.
+pub(crate) fn relative_posix_path(target_path: &str, volume_path: &str) -> Option<String> {
+  Path::new(target_path)
+    .strip_prefix(volume_path)
+    .ok()?
+    .iter()
+    .map(|component| component.to_str().map(|c| format!("/{c}")))
+    .collect()
+}
+
+/// The classic 32-bit id field for a file whose inode is `ino`.
+///
+/// APFS file ids are 64-bit. An id that does not fit is written as 0, which
+/// resolvers treat as unknown, rather than truncated to the id of some other
+/// file.
+#[cfg(any(target_os = "macos", test))]
+fn cnid(ino: u64) -> u32 {
+  u32::try_from(ino).unwrap_or(0)
+}
+
+/// Set in `st_flags` for files and directories whose contents are in the
+/// cloud rather than on disk.
+#[cfg(any(target_os = "macos", test))]
+const SF_DATALESS: u32 = 0x4000_0000;
+
+/// Assemble the record for what `stat` found.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn info_from_stat(stat: Stat, options: &CreateOptions) -> Result<Info> {
+  if options.no_io && stat.target_flags & SF_DATALESS != 0 {
+    return Err(Error::WouldMaterialize(stat.target_path));
+  }
+  let target_path = Path::new(&stat.target_path);
+  let parent_path = target_path
+    .parent()
+    .ok_or_else(|| Error::InvalidPath("The target path has no parent directory.".to_owned()))?;
+  let filesystem = Filesystem::from_type_name(&stat.volume.fs_type);
+  let id = |ino: u64| {
+    if filesystem.persistent_ids() {
+      cnid(ino)
+    } else {
+      0
+    }
+  };
+  // Path components spell a `/` in a name as `:`; the classic fields hold
+  // the name Finder shows. node-alias copies the component as it is.
+  let name = |component: &str| match options.compat {
+    Compat::Native => display_name(component),
+    Compat::NodeAlias => component.to_owned(),
+  };
+
+  let target = Target {
+    id: id(stat.target_ino),
+    type_: if stat.is_dir {
+      TargetType::Directory
+    } else {
+      TargetType::File
+    },
+    filename: target_path
+      .file_name()
+      .and_then(|s| s.to_str())
+      .map(name)
+      .ok_or_else(|| Error::InvalidPath("The target path has no file name.".to_owned()))?,
+    created: stat.target_created,
+  };
+
+  let volume_path = stat.volume.path.as_str();
+  let volume_name = options
+    .volume_name
+    .clone()
+    .unwrap_or_else(|| stat.volume.name.clone());
+
+  let parent = Parent {
+    id: id(stat.parent_ino),
+    // The root directory of a volume goes by the volume's name, which
+    // node-alias does not know.
+    name: if options.compat == Compat::Native && parent_path == Path::new(volume_path) {
+      volume_name.clone()
+    } else {
+      parent_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .map(name)
+        .ok_or_else(|| Error::InvalidPath("The parent directory has no name.".to_owned()))?
+    },
+  };
+  let volume = Volume {
+    name: volume_name,
+    created: stat.volume.created,
+    signature: options
+      .volume_signature
+      .unwrap_or_else(|| filesystem.signature()),
+    type_: match (options.volume_type, options.compat) {
+      (Some(type_), _) => type_,
+      (None, Compat::Native) => stat.volume.type_,
+      // node-alias only knows the startup volume as local.
+      (None, Compat::NodeAlias) if volume_path == "/" => VolumeType::Local,
+      (None, Compat::NodeAlias) => VolumeType::Other,
+    },
+  };
+  volume.check_kind()?;
+
+  let mut info = build_info_with(
+    &stat.target_path,
+    volume_path,
+    target,
+    parent,
+    volume,
+    options.compat,
+  )?;
+  info.classic.volume_attributes = filesystem.volume_attributes();
+  if options.compat == Compat::Native {
+    info.classic.volume_fs_id = Filesystem::volume_fs_id(&stat.volume.fs_type);
+  }
+  if let Some(rounding) = options.date_rounding {
+    info.round_dates(rounding);
+  }
+  apply_overrides(&mut info, options);
+  apply_layout(&mut info, options.layout);
+  apply_extras(&mut info, options.extras)?;
+  add_extra_records(&mut info, &options.extra_records, options.append_raw)?;
+  Ok(info)
+}
+
+/// Put the ids, dates and parent name that `options` override into `info`:
+/// in the header, in the parent name and id extras (types 0 and 1) and in
+/// the carbon path (type 2). The posix path is left as it is, since it still
+/// says where the target is.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn apply_overrides(info: &mut Info, options: &CreateOptions) {
+  if let Some(id) = options.target_id {
+    info.target.id = id;
+  }
+  if let Some(created) = options.target_created {
+    info.target.created = created;
+  }
+  if let Some(created) = options.volume_created {
+    info.volume.created = created;
+  }
+  if let Some(id) = options.parent_id {
+    info.parent.id = id;
+    if let Some(extra) = info.extra.iter_mut().find(|e| e.type_ == 1) {
+      *extra = Extra::new(1, id.to_be_bytes().to_vec());
+    }
+  }
+  if let Some(name) = &options.parent_name {
+    let old = std::mem::replace(&mut info.parent.name, name.clone());
+    if let Some(extra) = info.extra.iter_mut().find(|e| e.type_ == 0) {
+      *extra = Extra::new(0, macroman::encode(name));
+    }
+    crate::update::rename_component(info, 2, ':', 1, &old, name);
+  }
+}
+
+/// Which timestamp a creation date in a record was taken from.
+///
+/// Not every filesystem keeps creation dates: NFS mounts, some FUSE volumes
+/// and older exFAT drivers report none, or 0. Records then fall back to the
+/// first of these, in order, that the file has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateSource {
+  /// The creation date, `st_birthtime`.
+  Birthtime,
+  /// The modification date.
+  Mtime,
+  /// The inode change date, which is all node-alias looks at.
+  Ctime,
+  /// None of them: the Unix epoch.
+  Epoch,
+}
+
+/// Where the dates and the volume type of an [`Info`] from
+/// [`info_with_sources_for_path`] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateSources {
+  pub target_created: DateSource,
+  pub volume_created: DateSource,
+  pub volume_type: VolumeTypeSource,
+}
+
+/// The catalog node ids of a file or directory, from `getattrlist(2)`.
+/// APFS ids can pass the 32 bits a record holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileIds {
+  /// `ATTR_CMN_FILEID`.
+  pub id: u64,
+  /// `ATTR_CMN_PARENTID`: for a hard link, the directory of the link that
+  /// was looked up.
+  pub parent_id: u64,
+}
+
+/// The creation date the record stores for something `stat` describes,
+/// and where it came from.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn created(stat: &FileStat, compat: Compat) -> (SystemTime, DateSource) {
+  use std::time::{Duration, UNIX_EPOCH};
+
+  match compat {
+    Compat::Native => {
+      let (secs, source) = [
+        (stat.birthtime, DateSource::Birthtime),
+        (stat.mtime, DateSource::Mtime),
+        (Some(stat.ctime), DateSource::Ctime),
+      ]
+      .into_iter()
+      .find_map(|(time, source)| Some((time.filter(|&time| time > 0)?, source)))
+      .unwrap_or((0, DateSource::Epoch));
+      (UNIX_EPOCH + Duration::from_secs(secs as u64), source)
+    }
+    // Node's `stat().ctime` is a Date, which keeps millisecond precision.
+    Compat::NodeAlias => (
+      UNIX_EPOCH
+        + Duration::from_millis(stat.ctime as u64 * 1000 + stat.ctime_nsec as u64 / 1_000_000),
+      DateSource::Ctime,
+    ),
+  }
+}
+
+/// Like [`created`], keeping the fraction of a second the filesystem has,
+/// for the high-resolution date extras.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn created_precisely(stat: &FileStat, compat: Compat) -> SystemTime {
+  let (created, source) = created(stat, compat);
+  let nanos = match (compat, source) {
+    (Compat::NodeAlias, _) | (_, DateSource::Epoch) => 0,
+    (_, DateSource::Birthtime) => stat.birthtime_nsec,
+    (_, DateSource::Mtime) => stat.mtime_nsec,
+    (_, DateSource::Ctime) => stat.ctime_nsec,
+  };
+  created + std::time::Duration::from_nanos(nanos.clamp(0, 999_999_999) as u64)
+}
+
+/// `precise`, if it is `classic` with its fraction of a second, or else
+/// `classic`, e.g. for a target that was overridden or resolved elsewhere.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn precisely(precise: Option<SystemTime>, classic: SystemTime) -> SystemTime {
+  let near = |precise: &SystemTime| {
+    let difference = precise
+      .duration_since(classic)
+      .or_else(|_| classic.duration_since(*precise));
+    difference.is_ok_and(|difference| difference < std::time::Duration::from_secs(1))
+  };
+  precise.filter(near).unwrap_or(classic)
+}
+
+/// Add the high-resolution creation dates of the volume (extra 16) and the
+/// target (17), unless `info` has them already.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn add_hires_dates(
+  info: &mut Info,
+  target: SystemTime,
+  volume: SystemTime,
+) -> Result<()> {
+  for (type_, date) in [(16, volume), (17, target)] {
+    if info.extra(type_).is_none() {
+      let data = crate::time::hires_apple_date(date).to_be_bytes().to_vec();
+      add_extra_records(info, &[Extra::new(type_, data)], false)?;
+    }
+  }
+  Ok(())
+}
+
+/// Look up what the record needs about `target_path` in `source`, apart
+/// from the target's own metadata, which the caller passes in: `None` for a
+/// target that does not exist (yet).
+#[cfg(any(target_os = "macos", test))]
+fn stat(
+  source: &impl MetadataSource,
+  target_path: &str,
+  target_stat: Option<FileStat>,
+  options: &CreateOptions,
+) -> Result<Stat> {
+  let parent_path = Path::new(target_path)
+    .parent()
+    .ok_or_else(|| Error::InvalidPath("The target path has no parent directory.".to_owned()))?;
+  // A parent directory that cannot be looked up, say for want of
+  // permission, only costs a native record its parent id, as long as the
+  // target itself can be. A missing target is made from its parent.
+  let (parent_stat, on_disk) = match (source.stat(parent_path), target_stat.clone()) {
+    (Ok(parent), Some(target)) => (Some(parent), target),
+    (Ok(parent), None) => (Some(parent.clone()), parent),
+    (Err(_), Some(target)) if options.compat == Compat::Native => (None, target),
+    (Err(err), _) => return Err(not_found(target_path, err)),
+  };
+  let volume_error = |error| Error::VolumeLookupFailed {
+    path: target_path.to_owned(),
+    error,
+  };
+  let (resolved_path, volume_path) = match options.compat {
+    // The kernel's mount point, and the path below it with the symlinks
+    // resolved, so that extras 18 and 19 always add up to the target.
+    Compat::Native => {
+      let file_name = Path::new(target_path)
+        .file_name()
+        .ok_or_else(|| Error::InvalidPath("The target path has no file name.".to_owned()))?;
+      let resolved = match source.canonicalize(parent_path) {
+        Ok(parent) => parent.join(file_name),
+        // The path as given is the best there is.
+        Err(_) if parent_stat.is_none() => PathBuf::from(target_path),
+        Err(err) => return Err(volume_error(err)),
+      };
+      let on_volume = if target_stat.is_some() {
+        resolved.as_path()
+      } else {
+        resolved.parent().unwrap_or(&resolved)
+      };
+      let volume_path = mount_point(source, on_volume).map_err(volume_error)?;
+      (resolved, volume_path)
+    }
+    // node-alias walks up the path as given until the device changes.
+    Compat::NodeAlias => {
+      let start_path = match &target_stat {
+        Some(_) => Path::new(target_path),
+        None => parent_path,
+      };
+      let volume_path = find_volume(source, start_path, &on_disk).map_err(volume_error)?;
+      (PathBuf::from(target_path), volume_path.to_path_buf())
+    }
+  };
+  if let Some(stat) = &target_stat {
+    if !stat.is_file && !stat.is_dir {
+      return Err(Error::UnsupportedFileType(target_path.to_owned()));
+    }
+  }
+
+  let resolved_path = resolved_path
+    .to_str()
+    .ok_or_else(|| Error::InvalidPath("The target path is not a valid UTF-8 string.".to_owned()))?;
+  let volume_path = volume_path
+    .to_str()
+    .ok_or_else(|| Error::InvalidPath("The volume path is not a valid UTF-8 string.".to_owned()))?;
+  let volume = volume_info_in(
+    source,
+    volume_path,
+    options.compat,
+    options.volume_name_source,
+  )
+  .map_err(volume_error)?;
+  let (target_created, target_created_source) = created(&on_disk, options.compat);
+  // Finder records the catalog node ids, which `st_ino` can differ from
+  // for hard links. Taking both from one lookup also keeps the parent id
+  // from racing a rename. node-alias uses `st_ino`, which stays the
+  // fallback.
+  let ids = match options.compat {
+    Compat::Native if target_stat.is_some() => source.file_ids(Path::new(target_path)).ok(),
+    Compat::Native => source.file_ids(parent_path).ok().map(|ids| FileIds {
+      id: 0,
+      parent_id: ids.id,
+    }),
+    Compat::NodeAlias => None,
+  }
+  .unwrap_or(FileIds {
+    id: target_stat.as_ref().map_or(0, |m| m.ino),
+    parent_id: parent_stat.as_ref().map_or(0, |m| m.ino),
+  });
+  Ok(Stat {
+    target_path: resolved_path.to_owned(),
+    is_dir: target_stat.as_ref().is_some_and(|m| m.is_dir),
+    target_ino: ids.id,
+    target_created,
+    target_created_source,
+    parent_ino: ids.parent_id,
+    parent_unreadable: parent_stat.is_none(),
+    volume,
+    target_flags: target_stat.as_ref().map_or(0, |m| m.flags),
+  })
+}
+
+/// What the record for `stat` gets wrong or leaves out, compared to what
+/// the filesystem should have told.
+#[cfg(any(target_os = "macos", test))]
+fn warnings(stat: &Stat, options: &CreateOptions) -> Vec<Warning> {
+  let mut warnings = Vec::new();
+  // node-alias only ever looks at the change date.
+  if options.compat == Compat::Native {
+    for (field, source) in [
+      ("target.created", stat.target_created_source),
+      ("volume.created", stat.volume.created_source),
+    ] {
+      if source != DateSource::Birthtime {
+        warnings.push(Warning::DateFallback { field, source });
+      }
+    }
+  }
+  let filesystem = Filesystem::from_type_name(&stat.volume.fs_type);
+  // FAT volumes are named after their mount point on purpose.
+  if filesystem != Filesystem::Fat
+    && options.volume_name.is_none()
+    && stat.volume.name_source > options.volume_name_source
+  {
+    warnings.push(Warning::VolumeNameFallback(stat.volume.name_source));
+  }
+  if options.compat == Compat::Native
+    && options.volume_type.is_none()
+    && stat.volume.type_source == VolumeTypeSource::Statfs
+  {
+    warnings.push(Warning::VolumeTypeGuessed(stat.volume.type_));
+  }
+  if stat.parent_unreadable && stat.parent_ino == 0 && options.parent_id.is_none() {
+    warnings.push(Warning::ParentUnreadable);
+  }
+  if filesystem.persistent_ids() {
+    for (field, ino, overridden) in [
+      ("target.id", stat.target_ino, options.target_id),
+      ("parent.id", stat.parent_ino, options.parent_id),
+    ] {
+      if overridden.is_none() && ino > u32::MAX.into() {
+        warnings.push(Warning::IdTooLarge { field, ino });
+      }
+    }
+  }
+  warnings
+}
+
+/// An [`Info`], where its dates came from and what it lost on the way.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) type Derived = (Info, DateSources, Vec<Warning>);
+
+/// The [`Info`] for `target_path`, looked up in `source`.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn info_for_path_in(
+  source: &impl MetadataSource,
+  target_path: &str,
+  options: &CreateOptions,
+) -> Result<Info> {
+  derive_in(source, target_path, options).map(|(info, ..)| info)
+}
+
+/// Like [`info_for_path_in`], also saying where the dates came from and
+/// what the record lost.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn derive_in(
+  source: &impl MetadataSource,
+  target_path: &str,
+  options: &CreateOptions,
+) -> Result<Derived> {
+  let target_path = &normalize_path(target_path, "/")?;
+  let target_stat = match source.stat(Path::new(target_path)) {
+    Ok(stat) => Some(stat),
+    Err(err) if options.allow_missing && err.kind() == std::io::ErrorKind::NotFound => None,
+    Err(err) => return Err(not_found(target_path, err)),
+  };
+  info_for_stat_in(source, target_path, target_stat, options)
+}
+
+/// The [`Info`] for `target_path`, whose own metadata is `target_stat`.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn info_for_stat_in(
+  source: &impl MetadataSource,
+  target_path: &str,
+  target_stat: Option<FileStat>,
+  options: &CreateOptions,
+) -> Result<Derived> {
+  let hires_dates = options.hires_dates && options.layout == Layout::Full;
+  let target_created = match &target_stat {
+    _ if !hires_dates => None,
+    _ if options.target_created.is_some() => options.target_created,
+    Some(stat) => Some(created_precisely(stat, options.compat)),
+    None => source
+      .stat(Path::new(target_path))
+      .ok()
+      .map(|stat| created_precisely(&stat, options.compat)),
+  };
+  let stat = stat(source, target_path, target_stat, options)?;
+  let sources = DateSources {
+    target_created: stat.target_created_source,
+    volume_created: stat.volume.created_source,
+    volume_type: stat.volume.type_source,
+  };
+  let warnings = warnings(&stat, options);
+  let disk_image = match options.layout {
+    Layout::Full if !options.no_disk_image && options.extras.disk_image => {
+      source.disk_image(Path::new(&stat.volume.path))
+    }
+    _ => None,
+  };
+  let volume_uuid = options.volume_uuid.or_else(|| {
+    options
+      .embed_volume_uuid
+      .then(|| source.volume_uuid(Path::new(&stat.volume.path)))
+      .flatten()
+  });
+  let volume_created = match options.volume_created {
+    _ if !hires_dates => None,
+    Some(created) => Some(created),
+    None => source
+      .stat(Path::new(&stat.volume.path))
+      .ok()
+      .map(|root| created_precisely(&root, options.compat)),
+  };
+  let mut info = info_from_stat(stat, options)?;
+  if let Some(record) = disk_image.and_then(|image| disk_image_record(source, &image, options)) {
+    if info.extra(20).is_none() {
+      add_extra_records(&mut info, &[Extra::new(20, record)], false)?;
+    }
+  }
+  if hires_dates {
+    let (target, volume) = (info.target.created, info.volume.created);
+    add_hires_dates(
+      &mut info,
+      precisely(target_created, target),
+      precisely(volume_created, volume),
+    )?;
+  }
+  add_volume_uuid(&mut info, volume_uuid)?;
+  check_max_size(&info, options)?;
+  Ok((info, sources, warnings))
+}
+
+/// Record `uuid` in extra [`VOLUME_UUID_EXTRA`], whatever the layout, unless
+/// the extra records already hold one.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn add_volume_uuid(info: &mut Info, uuid: Option<VolumeUuid>) -> Result<()> {
+  match uuid {
+    Some(uuid) if info.extra(VOLUME_UUID_EXTRA).is_none() => add_extra_records(
+      info,
+      &[Extra::new(VOLUME_UUID_EXTRA, uuid.0.to_vec())],
+      false,
+    ),
+    _ => Ok(()),
+  }
+}
+
+/// Fail if the record for `info` would be longer than `options` allow.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn check_max_size(info: &Info, options: &CreateOptions) -> Result<()> {
+  let Some(max) = options.max_size else {
+    return Ok(());
+  };
+  let size = crate::encoded_length(info)?;
+  if size > max {
+    return Err(Error::RecordExceedsMaxSize { size, max });
+  }
+  Ok(())
+}
+
+/// The record of the disk image at `image` for extra 20, or `None` if it
+/// cannot be made, say because the image is on a volume that is gone: the
+/// alias still resolves without it.
+#[cfg(any(target_os = "macos", test))]
+fn disk_image_record(
+  source: &impl MetadataSource,
+  image: &Path,
+  options: &CreateOptions,
+) -> Option<Vec<u8>> {
+  let options = CreateOptions {
+    compat: options.compat,
+    ..Default::default()
+  };
+  let info = info_for_path_in(source, image.to_str()?, &options).ok()?;
+  crate::encode_with(&info, options.compat).ok()
+}
+
+/// Like [`create_for_path`], with the knobs in `options`.
+pub fn create_for_path_with<P: AsRef<Path>>(
+  target_path: P,
+  options: &CreateOptions,
+) -> Result<Vec<u8>> {
+  crate::encode_with(&info_for_path_with(target_path, options)?, options.compat)
+}
+
+/// The [`Info`] that [`create_for_path_with`] encodes, for inspecting what
+/// was derived from the filesystem.
+#[cfg(target_os = "macos")]
+pub fn info_for_path_with<P: AsRef<Path>>(target_path: P, options: &CreateOptions) -> Result<Info> {
+  derive_for_path(target_path.as_ref(), options).map(|(info, ..)| info)
+}
+
+/// Like [`info_for_path_with`], also saying which timestamps the creation
+/// dates were taken from, to diagnose records with odd dates.
+#[cfg(target_os = "macos")]
+pub fn info_with_sources_for_path<P: AsRef<Path>>(
+  target_path: P,
+  options: &CreateOptions,
+) -> Result<(Info, DateSources)> {
+  derive_for_path(target_path.as_ref(), options).map(|(info, sources, _)| (info, sources))
+}
+
+/// Like [`info_for_path_with`], also listing where the record falls short
+/// of what the filesystem should have told, such as a modification date
+/// standing in for a missing creation date.
+#[cfg(target_os = "macos")]
+pub fn info_with_warnings_for_path<P: AsRef<Path>>(
+  target_path: P,
+  options: &CreateOptions,
+) -> Result<(Info, Vec<Warning>)> {
+  derive_for_path(target_path.as_ref(), options).map(|(info, _, warnings)| (info, warnings))
+}
+
+/// The full 64-bit ids of `path` and its parent directory. Records store
+/// ids past `u32::MAX` as 0, with a [`Warning::IdTooLarge`], so this is
+/// where to find the id a record could not hold.
+#[cfg(target_os = "macos")]
+pub fn file_ids_for_path<P: AsRef<Path>>(path: P) -> Result<FileIds> {
+  let path = path.as_ref();
+  crate::volume::System
+    .file_ids(path)
+    .map_err(|err| not_found(&path.to_string_lossy(), err))
+}
+
+#[cfg(target_os = "macos")]
+fn derive_for_path(target_path: &Path, options: &CreateOptions) -> Result<Derived> {
+  let resolved;
+  let target_path = if options.resolve_aliases {
+    resolved = crate::alias_file::resolve_alias_chain(target_path)?;
+    &resolved
+  } else {
+    target_path
+  };
+  let target_path = target_path
+    .to_str()
+    .ok_or_else(|| Error::InvalidPath("The target path is not a valid UTF-8 string.".to_owned()))?;
+  without_materializing(target_path, options, || {
+    derive_in(&crate::volume::System, target_path, options)
+  })
+}
+
+/// Report a missing target or parent as [`Error::TargetNotFound`].
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn not_found(target_path: &str, err: std::io::Error) -> Error {
+  match err.kind() {
+    std::io::ErrorKind::NotFound => Error::TargetNotFound(target_path.to_owned()),
+    _ => Error::Io(err),
+  }
+}
+
+/// Run `create` with materialization turned off if `options` ask for
+/// `no_io`, reporting the `EDEADLK` the kernel fails with instead as
+/// [`Error::WouldMaterialize`] for `path`.
+#[cfg(target_os = "macos")]
+pub(crate) fn without_materializing<T>(
+  path: &str,
+  options: &CreateOptions,
+  create: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+  if !options.no_io {
+    return create();
+  }
+  let _policy = crate::volume::MaterializationOff::new()?;
+  create().map_err(|err| would_materialize(path, err))
+}
+
+/// `err`, or [`Error::WouldMaterialize`] for `path` if it is the `EDEADLK`
+/// the kernel fails with while materialization is off.
+#[cfg(target_os = "macos")]
+pub(crate) fn would_materialize(path: &str, err: Error) -> Error {
+  match err {
+    Error::Io(err) | Error::VolumeLookupFailed { error: err, .. }
+      if err.raw_os_error() == Some(libc::EDEADLK) =>
+    {
+      Error::WouldMaterialize(path.to_owned())
+    }
+    err => err,
+  }
+}
+
+/// Like [`create_for_path_with`], for the file or directory open as `fd`.
+///
+/// The target's metadata comes from the descriptor itself, so the record
+/// describes the object the caller opened even if its path has since been
+/// replaced. The path is `path_hint` when that still names the same object,
+/// and otherwise the one the kernel reports for `fd`. `fd` is not closed.
+#[cfg(target_os = "macos")]
+pub fn create_for_fd(
+  fd: i32,
+  path_hint: Option<&Path>,
+  options: &CreateOptions,
+) -> Result<Vec<u8>> {
+  use std::fs::{self, File};
+  use std::os::fd::FromRawFd;
+  use std::os::unix::fs::MetadataExt;
+
+  // Work on a duplicate, so that dropping it leaves the caller's fd open.
+  let duplicate = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) };
+  if duplicate == -1 {
+    return Err(std::io::Error::last_os_error().into());
+  }
+  let file = unsafe { File::from_raw_fd(duplicate) };
+  let metadata = file.metadata()?;
+
+  let same_object = |path: &&Path| {
+    fs::metadata(path).is_ok_and(|m| m.dev() == metadata.dev() && m.ino() == metadata.ino())
+  };
+  let target_path = match path_hint.filter(same_object) {
+    Some(hint) => hint.to_path_buf(),
+    None => crate::volume::fd_path(duplicate)?,
+  };
+  let target_path = target_path
+    .to_str()
+    .ok_or_else(|| Error::InvalidPath("The target path is not a valid UTF-8 string.".to_owned()))?;
+
+  without_materializing(target_path, options, || {
+    let (info, ..) = info_for_stat_in(
+      &crate::volume::System,
+      target_path,
+      Some(FileStat::from(&metadata)),
+      options,
+    )?;
+    crate::encode_with(&info, options.compat)
+  })
+}
+
+/// The [`Info`] that [`create_for_path_with`] encodes.
+///
+/// Looking up the target needs the macOS filesystem APIs; use
+/// [`build_info`] to synthesize records on other platforms.
+#[cfg(not(target_os = "macos"))]
+pub fn info_for_path_with<P: AsRef<Path>>(
+  _target_path: P,
+  _options: &CreateOptions,
+) -> Result<Info> {
+  Err(Error::Unsupported("Creating an alias from a path"))
+}
+
+/// Like [`info_for_path_with`], also saying where the dates came from.
+///
+/// Looking up the target needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn info_with_sources_for_path<P: AsRef<Path>>(
+  _target_path: P,
+  _options: &CreateOptions,
+) -> Result<(Info, DateSources)> {
+  Err(Error::Unsupported("Creating an alias from a path"))
+}
+
+/// Like [`info_for_path_with`], also listing where the record falls short.
+///
+/// Looking up the target needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn info_with_warnings_for_path<P: AsRef<Path>>(
+  _target_path: P,
+  _options: &CreateOptions,
+) -> Result<(Info, Vec<Warning>)> {
+  Err(Error::Unsupported("Creating an alias from a path"))
+}
+
+/// The full 64-bit ids of `path` and its parent directory.
+///
+/// Looking up the ids needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn file_ids_for_path<P: AsRef<Path>>(_path: P) -> Result<FileIds> {
+  Err(Error::Unsupported("Looking up file ids"))
+}
+
+/// Like [`create_for_path_with`], for the file or directory open as `fd`.
+///
+/// Looking up the target needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn create_for_fd(
+  _fd: i32,
+  _path_hint: Option<&Path>,
+  _options: &CreateOptions,
+) -> Result<Vec<u8>> {
+  Err(Error::Unsupported(
+    "Creating an alias from a file descriptor",
+  ))
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::{Duration, UNIX_EPOCH};
+
+  use base64::Engine;
+
+  use crate::fixture::FakeVolumes;
+  use crate::{
+    Compat, CreateOptions, Error, Extra, Extras, FileIds, Layout, Parent, Target, TargetType,
+    Volume, VolumeSignature, VolumeType, Warning,
+  };
+
+  // Expected node-alias output for the inputs of the `node_alias_*` tests,
+  // obtained by running node-alias's create() layout and encode() on them.
+  const NODE_ALIAS_FILE: &str = "AAAAAAEaAAIAAAXDhHJnZXIAAAAAAAAAAAAAAAAAAAAAAAAAAADO615USCsABQAAABMOQmlsZCDDvGJlci50aWZmAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFM7rXlkAAAAAAAAAAP////8AAA0CAAAAAAAAAAAAAAAAAAAADEhpbnRlcmdyw7xuZAABAAQAAAATAA4AHgAOAEIAaQBsAGQAIAD8AGIAZQByAC4AdABpAGYAZgAPAAwABQDEAHIAZwBlAHIAEgAeL0hpbnRlcmdyw7xuZGUvQmlsZCDDvGJlci50aWZmABMADy9Wb2x1bWVzL8OEcmdlcgD//wAA";
+  const NODE_ALIAS_DIRECTORY: &str = "AAAAAAEAAAIAAQpUZXN0IFRpdGxlAAAAAAAAAAAAAAAAAAAAAADO615VSCsABQAAAAIGT3JkbmVyAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFc7rXlgAAAAAAAAAAP////8AAA0CAAAAAAAAAAAAAAAAAAAAClRlc3QgVGl0bGUAAQAEAAAAAgAOAA4ABgBPAHIAZABuAGUAcgAPABYACgBUAGUAcwB0ACAAVABpAHQAbABlABIABy9PcmRuZXIAABMAEy9Wb2x1bWVzL1Rlc3QgVGl0bGUA//8AAA==";
+
+  fn node_alias_record(
+    target_path: &str,
+    volume_path: &str,
+    target: Target,
+    parent: Parent,
+    volume: Volume,
+  ) -> String {
+    let info = super::build_info_with(
+      target_path,
+      volume_path,
+      target,
+      parent,
+      volume,
+      Compat::NodeAlias,
+    )
+    .unwrap();
+    base64::engine::general_purpose::STANDARD
+      .encode(crate::encode_with(&info, Compat::NodeAlias).unwrap())
+  }
+
+  fn parts() -> (Target, Parent, Volume) {
+    (
+      Target {
+        id: 20,
+        type_: TargetType::File,
+        filename: "TestBkg.tiff".to_owned(),
+        created: UNIX_EPOCH + Duration::from_millis(1388686808000),
+      },
+      Parent {
+        id: 19,
+        name: ".background".to_owned(),
+      },
+      Volume {
+        name: "Test Title".to_owned(),
+        created: UNIX_EPOCH + Duration::from_millis(1388686804000),
+        signature: VolumeSignature::HPlus,
+        type_: VolumeType::Other,
+      },
+    )
+  }
+
+  #[test]
+  fn build_info_posix_extras() {
+    let (target, parent, volume) = parts();
+    let info = super::build_info(
+      "/Volumes/Test Title/.background/TestBkg.tiff",
+      "/Volumes/Test Title",
+      target,
+      parent,
+      volume,
+    )
+    .unwrap();
+    let types: Vec<i16> = info.extra.iter().map(|e| e.type_).collect();
+    assert_eq!(types, [0, 1, 2, 14, 15, 18, 19]);
+    assert_eq!(info.extra[2].data, b"Test Title:.background:\0TestBkg.tiff");
+    assert_eq!(info.extra[5].data, b"/.background/TestBkg.tiff");
+    assert_eq!(info.extra[6].data, b"/Volumes/Test Title");
+  }
+
+  fn layout_record(layout: Layout) -> Vec<u8> {
+    let (target, parent, volume) = parts();
+    let mut info = super::build_info(
+      "/Volumes/Test Title/.background/TestBkg.tiff",
+      "/Volumes/Test Title",
+      target,
+      parent,
+      volume,
+    )
+    .unwrap();
+    super::apply_layout(&mut info, layout);
+    crate::encode(&info).unwrap()
+  }
+
+  #[test]
+  fn minimal_layout() {
+    let full = layout_record(Layout::Full);
+    let minimal = layout_record(Layout::Minimal);
+    let with_names = layout_record(Layout::MinimalWithNames);
+    assert_eq!(
+      (full.len(), minimal.len(), with_names.len()),
+      (328, 208, 264)
+    );
+
+    // Empty classic volume and file names.
+    assert_eq!(minimal[10], 0);
+    assert_eq!(minimal[50], 0);
+
+    let info = crate::decode(&minimal).unwrap();
+    let types: Vec<i16> = info.extra.iter().map(|e| e.type_).collect();
+    assert_eq!(types, [18, 19]);
+    assert_eq!(
+      info.posix_path().as_deref(),
+      Some("/Volumes/Test Title/.background/TestBkg.tiff")
+    );
+    assert_eq!(info.target.id, 20);
+
+    let info = crate::decode(&with_names).unwrap();
+    let types: Vec<i16> = info.extra.iter().map(|e| e.type_).collect();
+    assert_eq!(types, [14, 15, 18, 19]);
+  }
+
+  #[test]
+  fn hires_dates() {
+    let mut volumes = FakeVolumes::test_title();
+    for (path, nsec) in [
+      (TEST_BKG, 500_000_000),
+      ("/Volumes/Test Title", 250_000_000),
+    ] {
+      let mut stat = volumes.remove(path);
+      stat.birthtime = Some(stat.ctime);
+      stat.birthtime_nsec = nsec;
+      volumes.insert_stat(path, stat);
+    }
+    let info = |hires_dates, compat| {
+      let options = CreateOptions {
+        hires_dates,
+        compat,
+        ..Default::default()
+      };
+      super::info_for_path_in(&volumes, TEST_BKG, &options).unwrap()
+    };
+    assert!(info(false, Compat::Native).extra(16).is_none());
+
+    let hires = info(true, Compat::Native);
+    let types: Vec<i16> = hires.extra.iter().map(|e| e.type_).collect();
+    assert_eq!(types, [0, 1, 2, 14, 15, 16, 17, 18, 19]);
+    let date = |type_| u64::from_be_bytes(hires.extra(type_).unwrap().data[..].try_into().unwrap());
+    assert_eq!(date(16), (1388686804 + 2082844800) << 16 | 0x4000);
+    assert_eq!(date(17), (1388686808 + 2082844800) << 16 | 0x8000);
+    // The classic dates are unchanged.
+    assert_eq!(
+      hires.target.created,
+      info(false, Compat::Native).target.created
+    );
+    let length = crate::encoded_length(&hires).unwrap();
+    assert_eq!(
+      length,
+      crate::encoded_length(&info(false, Compat::Native)).unwrap() + 24
+    );
+    assert_eq!(
+      crate::decode(&crate::encode(&hires).unwrap()).unwrap(),
+      hires
+    );
+  }
+
+  #[test]
+  fn max_size() {
+    let volumes = FakeVolumes::test_title();
+    let info = |max_size| {
+      let options = CreateOptions {
+        max_size,
+        ..Default::default()
+      };
+      super::info_for_path_in(&volumes, TEST_BKG, &options)
+    };
+    let size = crate::encoded_length(&info(None).unwrap()).unwrap();
+    assert_eq!(size, 328);
+
+    assert!(info(Some(size)).is_ok());
+    let err = info(Some(size - 1)).unwrap_err();
+    assert!(
+      matches!(
+        err,
+        Error::RecordExceedsMaxSize {
+          size: 328,
+          max: 327
+        }
+      ),
+      "{err}"
+    );
+    assert_eq!(err.code(), "ERR_RECORD_EXCEEDS_MAX_SIZE");
+  }
+
+  #[test]
+  fn extra_families() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.file("/Users/me/Test Title.dmg", 2, 303, 1388686800);
+    volumes.attach("/Users/me/Test Title.dmg", "/Volumes/Test Title");
+    let types = |extras, layout| {
+      let options = CreateOptions {
+        extras,
+        layout,
+        ..Default::default()
+      };
+      let info = super::info_for_path_in(&volumes, TEST_BKG, &options)?;
+      let record = crate::encode(&info)?;
+      let types: Vec<i16> = crate::decode(&record)?
+        .extra
+        .iter()
+        .map(|e| e.type_)
+        .collect();
+      crate::Result::Ok(types)
+    };
+    let only = |classic, unicode, posix| Extras {
+      classic,
+      unicode,
+      posix,
+      disk_image: false,
+    };
+
+    assert_eq!(
+      types(Extras::default(), Layout::Full).unwrap(),
+      [0, 1, 2, 14, 15, 18, 19, 20]
+    );
+    assert_eq!(
+      types(only(true, false, false), Layout::Full).unwrap(),
+      [0, 1, 2]
+    );
+    assert_eq!(
+      types(only(false, false, true), Layout::Full).unwrap(),
+      [18, 19]
+    );
+    assert_eq!(
+      types(only(false, true, true), Layout::MinimalWithNames).unwrap(),
+      [14, 15, 18, 19]
+    );
+    // Minimal layouts leave nothing to resolve by but the posix paths.
+    assert!(matches!(
+      types(only(true, true, false), Layout::Minimal),
+      Err(Error::InvalidInfo(_))
+    ));
+
+    // Without the unicode extras, nothing may be lost to Mac OS Roman.
+    volumes.file("/Volumes/Test Title/Привет.tiff", 3, 21, 1388686808);
+    let options = CreateOptions {
+      extras: only(true, false, true),
+      ..Default::default()
+    };
+    let error = super::info_for_path_in(&volumes, "/Volumes/Test Title/Привет.tiff", &options);
+    assert!(matches!(error, Err(Error::InvalidInfo(_))), "{error:?}");
+    volumes.file("/Volumes/Test Title/Café.tiff", 3, 22, 1388686808);
+    let info = super::info_for_path_in(&volumes, "/Volumes/Test Title/Café.tiff", &options);
+    assert_eq!(info.unwrap().target.filename, "Café.tiff");
+  }
+
+  fn extra_types(records: &[Extra], append_raw: bool) -> crate::Result<Vec<i16>> {
+    let (target, parent, volume) = parts();
+    let mut info = super::build_info(
+      "/Volumes/Test Title/.background/TestBkg.tiff",
+      "/Volumes/Test Title",
+      target,
+      parent,
+      volume,
+    )?;
+    super::add_extra_records(&mut info, records, append_raw)?;
+    Ok(info.extra.iter().map(|e| e.type_).collect())
+  }
+
+  #[test]
+  fn extra_records_canonical_order() {
+    let records = [
+      Extra::new(20, b"custom".to_vec()),
+      Extra::new(16, vec![0; 8]),
+      Extra::new(18, b"/elsewhere".to_vec()),
+    ];
+    assert_eq!(
+      extra_types(&records, false).unwrap(),
+      [0, 1, 2, 14, 15, 16, 18, 19, 20]
+    );
+    assert_eq!(
+      extra_types(&records, true).unwrap(),
+      [0, 1, 2, 14, 15, 18, 19, 20, 16]
+    );
+  }
+
+  #[test]
+  fn extra_records_validation() {
+    let too_long = Extra::new(20, vec![0; u16::MAX as usize + 1]);
+    assert!(matches!(
+      extra_types(&[too_long], false),
+      Err(Error::InvalidInfo(_))
+    ));
+    assert!(matches!(
+      extra_types(&[Extra::new(-1, Vec::new())], false),
+      Err(Error::InvalidInfo(_))
+    ));
+  }
+
+  /// What the macOS lookups return for `TestBkg.tiff` on a USB stick
+  /// labelled "Test Title".
+  fn exfat_stat() -> super::Stat {
+    super::Stat {
+      target_path: "/Volumes/Test Title/.background/TestBkg.tiff".to_owned(),
+      is_dir: false,
+      target_ino: 1234,
+      target_created: UNIX_EPOCH + Duration::from_secs(1388686808),
+      target_created_source: crate::DateSource::Birthtime,
+      parent_ino: 567,
+      parent_unreadable: false,
+      volume: crate::VolumeInfo {
+        name: "Test Title".to_owned(),
+        name_source: crate::VolumeNameSource::Attrlist,
+        display_name: None,
+        path: "/Volumes/Test Title".to_owned(),
+        device: "/dev/disk3s1".to_owned(),
+        fs_type: "exfat".to_owned(),
+        created: UNIX_EPOCH + Duration::from_secs(1388686804),
+        created_source: crate::DateSource::Birthtime,
+        local: true,
+        read_only: false,
+        browsable: true,
+        disk_image: None,
+        type_: VolumeType::Other,
+        type_source: crate::VolumeTypeSource::DiskArbitration,
+      },
+      target_flags: 0,
+    }
+  }
+
+  #[test]
+  fn exfat_volume() {
+    let info = super::info_from_stat(exfat_stat(), &Default::default()).unwrap();
+    assert_eq!(info.volume.signature, VolumeSignature::Bd);
+    assert_eq!(info.volume.type_, VolumeType::Other);
+    assert_eq!(info.volume.name, "Test Title");
+    assert_eq!(info.classic.volume_attributes, 3328);
+    assert_eq!((info.target.id, info.parent.id), (0, 0));
+    assert_eq!(info.extra(1).unwrap().data, [0; 4]);
+    assert_eq!(
+      info.posix_path().as_deref(),
+      Some("/Volumes/Test Title/.background/TestBkg.tiff")
+    );
+
+    let record = crate::encode(&info).unwrap();
+    assert_eq!(&record[42..44], b"BD");
+    assert_eq!(&record[134..138], &3328u32.to_be_bytes());
+    assert_eq!(&record[138..140], b"xF");
+
+    let mut stat = exfat_stat();
+    stat.volume.fs_type = "msdos".to_owned();
+    let info = super::info_from_stat(stat, &Default::default()).unwrap();
+    assert_eq!(info.classic.volume_fs_id, u16::from_be_bytes(*b"IS"));
+    let node_alias = crate::CreateOptions {
+      compat: crate::Compat::NodeAlias,
+      ..Default::default()
+    };
+    let info = super::info_from_stat(exfat_stat(), &node_alias).unwrap();
+    assert_eq!(info.classic.volume_fs_id, 0);
+  }
+
+  #[test]
+  fn apfs_volume() {
+    let mut stat = exfat_stat();
+    stat.volume.fs_type = "apfs".to_owned();
+    stat.volume.name = "TEST TITLE".to_owned();
+    let info = super::info_from_stat(stat, &Default::default()).unwrap();
+    assert_eq!(info.volume.signature, VolumeSignature::HPlus);
+    assert_eq!(info.volume.name, "TEST TITLE");
+    assert_eq!(info.classic.volume_attributes, 3330);
+    assert_eq!(info.classic.volume_fs_id, 0);
+    assert_eq!((info.target.id, info.parent.id), (1234, 567));
+  }
+
+  #[test]
+  fn cnid() {
+    assert_eq!(super::cnid(1234), 1234);
+    assert_eq!(super::cnid(u32::MAX.into()), u32::MAX);
+    assert_eq!(super::cnid(0x1_0000_04d2), 0);
+  }
+
+  #[test]
+  fn no_io_dataless_target() {
+    let stat = super::Stat {
+      target_flags: super::SF_DATALESS,
+      ..exfat_stat()
+    };
+    let no_io = crate::CreateOptions {
+      no_io: true,
+      ..Default::default()
+    };
+    assert!(super::info_from_stat(stat.clone(), &Default::default()).is_ok());
+    let err = super::info_from_stat(stat, &no_io).unwrap_err();
+    assert!(
+      matches!(&err, Error::WouldMaterialize(path) if path.ends_with("TestBkg.tiff")),
+      "{err}"
+    );
+    assert!(super::info_from_stat(exfat_stat(), &no_io).is_ok());
+  }
+
+  const TEST_BKG: &str = "/Volumes/Test Title/.background/TestBkg.tiff";
+
+  #[test]
+  fn fake_volume_matches_fixture() {
+    let volumes = FakeVolumes::test_title();
+    // The fixture leaves out the unicode file name.
+    let fixture = crate::fixture::fixture_info();
+    let mut info = super::info_for_path_in(&volumes, TEST_BKG, &Default::default()).unwrap();
+    // What `info_for_path_with` hands out is what decoding the record gives.
+    assert_eq!(crate::decode(&crate::encode(&info).unwrap()).unwrap(), info);
+    info.extra.retain(|e| e.type_ != 14);
+    assert_eq!(info, fixture);
+    assert_eq!(
+      crate::encode(&info).unwrap(),
+      crate::fixture::fixture_bytes()
+    );
+  }
+
+  #[test]
+  fn fake_volume_firmlink() {
+    let volumes = FakeVolumes::test_title();
+    let native =
+      super::info_for_path_in(&volumes, "/Users/me/bg.tiff", &Default::default()).unwrap();
+    assert_eq!(native.extra(19).unwrap().data, b"/");
+    assert_eq!(native.volume.name, "Macintosh HD");
+    assert_eq!(native.volume.type_, VolumeType::Local);
+
+    let node_alias = crate::CreateOptions {
+      compat: Compat::NodeAlias,
+      ..Default::default()
+    };
+    let node_alias = super::info_for_path_in(&volumes, "/Users/me/bg.tiff", &node_alias).unwrap();
+    assert_eq!(node_alias.extra(19).unwrap().data, b"/Users");
+  }
+
+  #[test]
+  fn fake_volume_symlinked_components() {
+    let volumes = FakeVolumes::test_title();
+    let direct = super::info_for_path_in(&volumes, TEST_BKG, &Default::default()).unwrap();
+    for path in [
+      "/Volumes/Link/.background/TestBkg.tiff",
+      "/Users/me/Backgrounds/TestBkg.tiff",
+    ] {
+      let info = super::info_for_path_in(&volumes, path, &Default::default()).unwrap();
+      assert_eq!(info, direct, "{path}");
+    }
+
+    // node-alias records the path as given, up to the symlink.
+    let node_alias = crate::CreateOptions {
+      compat: Compat::NodeAlias,
+      ..Default::default()
+    };
+    let info = super::info_for_path_in(
+      &volumes,
+      "/Volumes/Link/.background/TestBkg.tiff",
+      &node_alias,
+    )
+    .unwrap();
+    assert_eq!(info.extra(19).unwrap().data, b"/Volumes/Link");
+    assert_eq!(info.extra(18).unwrap().data, b"/.background/TestBkg.tiff");
+  }
+
+  #[test]
+  fn fake_volume_name_with_slash() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.mount(4, "/Volumes/App 1:2", "hfs", "App 1/2");
+    volumes.directory("/Volumes/App 1:2", 4, 2, 1388686804);
+    volumes.file("/Volumes/App 1:2/bg.tiff", 4, 20, 1388686808);
+    volumes.mount(5, "/Volumes/USB 1:2", "exfat", "USB 1/2");
+    volumes.directory("/Volumes/USB 1:2", 5, 2, 1388686804);
+    volumes.file("/Volumes/USB 1:2/bg.tiff", 5, 20, 1388686808);
+
+    let info =
+      super::info_for_path_in(&volumes, "/Volumes/App 1:2/bg.tiff", &Default::default()).unwrap();
+    assert_eq!(info.volume.name, "App 1/2");
+    assert_eq!(info.parent.name, "App 1/2");
+    assert_eq!(info.extra(0).unwrap().data, b"App 1/2");
+    assert_eq!(info.extra(15).unwrap().data, super::unicode_name("App 1/2"));
+    assert_eq!(info.extra(18).unwrap().data, b"/bg.tiff");
+    assert_eq!(info.extra(19).unwrap().data, b"/Volumes/App 1:2");
+    let record = crate::encode(&info).unwrap();
+    assert_eq!(&record[10..18], b"\x07App 1/2");
+
+    let info =
+      super::info_for_path_in(&volumes, "/Volumes/USB 1:2/bg.tiff", &Default::default()).unwrap();
+    assert_eq!(info.volume.name, "USB 1/2");
+    assert_eq!(info.extra(19).unwrap().data, b"/Volumes/USB 1:2");
+  }
+
+  #[test]
+  fn fake_volume_colon_in_file_name() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.directory("/Volumes/Test Title/Q1:Q4", 3, 30, 1388686804);
+    volumes.file(
+      "/Volumes/Test Title/Q1:Q4/Report 2023:Q4.pdf",
+      3,
+      31,
+      1388686808,
+    );
+    let path = "/Volumes/Test Title/Q1:Q4/Report 2023:Q4.pdf";
+
+    // Finder shows the `:` stored on disk as `/`.
+    let info = super::info_for_path_in(&volumes, path, &Default::default()).unwrap();
+    assert_eq!(info.target.filename, "Report 2023/Q4.pdf");
+    assert_eq!(info.parent.name, "Q1/Q4");
+    assert_eq!(info.extra(0).unwrap().data, b"Q1/Q4");
+    assert_eq!(
+      info.extra(14).unwrap().data,
+      super::unicode_name("Report 2023/Q4.pdf")
+    );
+    assert_eq!(info.extra(18).unwrap().data, b"/Q1:Q4/Report 2023:Q4.pdf");
+    let record = crate::encode(&info).unwrap();
+    assert_eq!(&record[50..69], b"\x12Report 2023/Q4.pdf");
+
+    // node-alias copies the path component as it is.
+    let options = CreateOptions {
+      compat: Compat::NodeAlias,
+      ..Default::default()
+    };
+    let info = super::info_for_path_in(&volumes, path, &options).unwrap();
+    assert_eq!(info.target.filename, "Report 2023:Q4.pdf");
+  }
+
+  #[test]
+  fn relative_posix_paths() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.file("/bg.tiff", 1, 103, 1388686808);
+    volumes.directory("/Library", 1, 104, 1388686804);
+    volumes.file("/Library/bg.tiff", 1, 105, 1388686808);
+    volumes.directory("/Users/me/Art", 2, 303, 1388686804);
+    volumes.file("/Users/me/Art/bg.tiff", 2, 304, 1388686808);
+    volumes.file("/Volumes/Test Title/bg.tiff", 3, 21, 1388686808);
+    volumes.directory("/Volumes/Test Title/a", 3, 22, 1388686804);
+    volumes.directory("/Volumes/Test Title/a/b", 3, 23, 1388686804);
+    volumes.file("/Volumes/Test Title/a/b/bg.tiff", 3, 24, 1388686808);
+
+    for (path, relative, volume) in [
+      ("/bg.tiff", "/bg.tiff", "/"),
+      ("/Library/bg.tiff", "/Library/bg.tiff", "/"),
+      ("/Users/me/Art/bg.tiff", "/Users/me/Art/bg.tiff", "/"),
+      (
+        "/Volumes/Test Title/bg.tiff",
+        "/bg.tiff",
+        "/Volumes/Test Title",
+      ),
+      (TEST_BKG, "/.background/TestBkg.tiff", "/Volumes/Test Title"),
+      (
+        "/Volumes/Test Title/a/b/bg.tiff",
+        "/a/b/bg.tiff",
+        "/Volumes/Test Title",
+      ),
+      // An alias to the volume itself.
+      ("/Volumes/Test Title", "", "/Volumes/Test Title"),
+    ] {
+      let info = super::info_for_path_in(&volumes, path, &Default::default()).unwrap();
+      assert_eq!(info.extra(18).unwrap().data, relative.as_bytes(), "{path}");
+      assert_eq!(info.extra(19).unwrap().data, volume.as_bytes(), "{path}");
+      assert_eq!(info.posix_path().unwrap(), path);
+    }
+
+    // The root directory goes by the volume's name on `/` too.
+    let info = super::info_for_path_in(&volumes, "/bg.tiff", &Default::default()).unwrap();
+    assert_eq!(info.parent.name, "Macintosh HD");
+
+    // node-alias slices the mount point off, `/` and all.
+    let node_alias = CreateOptions {
+      compat: Compat::NodeAlias,
+      ..Default::default()
+    };
+    let info = super::info_for_path_in(&volumes, "/Library/bg.tiff", &node_alias).unwrap();
+    assert_eq!(info.extra(18).unwrap().data, b"Library/bg.tiff");
+    assert_eq!(info.posix_path().unwrap(), "/Library/bg.tiff");
+
+    // Mount points are spelled without a trailing `/`, and only a whole
+    // component of the target path is one.
+    let build = |target_path: &str, volume_path: &str| {
+      let mut info = crate::fixture::fixture_info();
+      info.target.filename = "bg.tiff".to_owned();
+      super::build_info(
+        target_path,
+        volume_path,
+        info.target,
+        info.parent,
+        info.volume,
+      )
+    };
+    let info = build("/Volumes/Test Title/bg.tiff", "/Volumes/Test Title/").unwrap();
+    assert_eq!(info.extra(18).unwrap().data, b"/bg.tiff");
+    assert_eq!(info.extra(19).unwrap().data, b"/Volumes/Test Title");
+    assert_eq!(
+      build("/bg.tiff", "/").unwrap().extra(19).unwrap().data,
+      b"/"
+    );
+    assert!(matches!(
+      build("/Volumes/Test Title/bg.tiff", "/Volumes/Test"),
+      Err(Error::InvalidPath(_))
+    ));
+  }
+
+  #[test]
+  fn unreadable_parent() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.deny("/Volumes/Test Title/.background");
+    let (info, _, warnings) = super::derive_in(&volumes, TEST_BKG, &Default::default()).unwrap();
+    assert_eq!(info.target.id, 20);
+    assert_eq!(info.parent.id, 0);
+    assert_eq!(info.parent.name, ".background");
+    assert_eq!(info.posix_path().unwrap(), TEST_BKG);
+    let types: Vec<_> = info.extra.iter().map(|e| e.type_).collect();
+    assert_eq!(types, [0, 1, 2, 14, 15, 18, 19]);
+    assert!(
+      warnings.contains(&Warning::ParentUnreadable),
+      "{warnings:?}"
+    );
+
+    // node-alias stats the parent, and missing targets are made from it.
+    let node_alias = CreateOptions {
+      compat: Compat::NodeAlias,
+      ..Default::default()
+    };
+    assert!(super::info_for_path_in(&volumes, TEST_BKG, &node_alias).is_err());
+    let allow_missing = CreateOptions {
+      allow_missing: true,
+      ..Default::default()
+    };
+    let missing = "/Volumes/Test Title/.background/Other.tiff";
+    assert!(super::info_for_path_in(&volumes, missing, &allow_missing).is_err());
+  }
+
+  #[test]
+  fn id_and_parent_overrides() {
+    let options = CreateOptions {
+      target_id: Some(20),
+      parent_id: Some(19),
+      parent_name: Some("Hintergrund".to_owned()),
+      ..Default::default()
+    };
+    let mut volumes = FakeVolumes::test_title();
+    // Ids too large for the record would be warned about, but are not used.
+    volumes.remove(TEST_BKG);
+    volumes.file(TEST_BKG, 3, 1 << 40, 1388686807);
+    let (info, _, warnings) = super::derive_in(&volumes, TEST_BKG, &options).unwrap();
+    assert_eq!(info.target.id, 20);
+    assert_eq!(info.parent.id, 19);
+    assert_eq!(info.parent.name, "Hintergrund");
+    assert_eq!(info.extra(0).unwrap().data, b"Hintergrund");
+    assert_eq!(info.extra(1).unwrap().data, 19u32.to_be_bytes());
+    assert_eq!(info.posix_path().unwrap(), TEST_BKG);
+    assert!(
+      !warnings
+        .iter()
+        .any(|warning| matches!(warning, Warning::IdTooLarge { .. })),
+      "{warnings:?}"
+    );
+  }
+
+  #[test]
+  fn volume_uuid() {
+    let uuid = crate::VolumeUuid([0xa5; 16]);
+    let mut volumes = FakeVolumes::test_title();
+    volumes.set_volume_uuid(3, uuid);
+    let info = |options: &CreateOptions| super::derive_in(&volumes, TEST_BKG, options).unwrap().0;
+    // Finder records no UUID, so neither does a plain record.
+    assert_eq!(info(&Default::default()).volume_uuid(), None);
+    let embedded = info(&CreateOptions {
+      embed_volume_uuid: true,
+      ..Default::default()
+    });
+    assert_eq!(embedded.volume_uuid(), Some(uuid));
+    assert_eq!(
+      embedded.extra(crate::VOLUME_UUID_EXTRA).unwrap().value(),
+      Some(crate::ExtraValue::String(
+        "A5A5A5A5-A5A5-A5A5-A5A5-A5A5A5A5A5A5".to_owned()
+      ))
+    );
+    // A given UUID wins over the volume's, and is kept in minimal records.
+    let given = crate::VolumeUuid([1; 16]);
+    let minimal = info(&CreateOptions {
+      volume_uuid: Some(given),
+      embed_volume_uuid: true,
+      layout: Layout::Minimal,
+      ..Default::default()
+    });
+    assert_eq!(minimal.volume_uuid(), Some(given));
+  }
+
+  #[test]
+  fn volume_name_override() {
+    let mut volumes = dated_volumes();
+    // A name that had to be guessed is not warned about if it is not used.
+    volumes.fail_volume_names(&[crate::VolumeNameSource::Attrlist]);
+    assert_eq!(warnings(&volumes, &Default::default()).len(), 1);
+    let options = CreateOptions {
+      volume_name: Some("My App".to_owned()),
+      ..Default::default()
+    };
+    let (info, _, warnings) = super::derive_in(&volumes, TEST_BKG, &options).unwrap();
+    assert_eq!(warnings, []);
+    assert_eq!(info.volume.name, "My App");
+    assert_eq!(
+      info.extra(15).unwrap().value(),
+      Some(crate::ExtraValue::String("My App".to_owned()))
+    );
+    assert_eq!(info.extra(19).unwrap().data, b"/Volumes/Test Title");
+
+    let root_file = "/Volumes/Test Title/bg.tiff";
+    volumes.file(root_file, 3, 22, 1388686808);
+    let info = super::info_for_path_in(&volumes, root_file, &options).unwrap();
+    assert_eq!(info.parent.name, "My App");
+  }
+
+  #[test]
+  fn floppy_overrides() {
+    // An 800K HFS floppy image, attached the way emulators and DMG tools
+    // attach it.
+    let mut volumes = FakeVolumes::test_title();
+    volumes.mount(4, "/Volumes/System Disk", "hfs", "System Disk");
+    // Left to guess, the type would be `Other`.
+    volumes.set_disk(4, None);
+    volumes.directory("/Volumes/System Disk", 4, 2, 1388686804);
+    volumes.directory("/Volumes/System Disk/System Folder", 4, 16, 1388686804);
+    volumes.file(
+      "/Volumes/System Disk/System Folder/Finder",
+      4,
+      17,
+      1388686808,
+    );
+    let path = "/Volumes/System Disk/System Folder/Finder";
+    let options = CreateOptions {
+      volume_type: Some(VolumeType::Floppy800),
+      volume_signature: Some(VolumeSignature::Bd),
+      ..Default::default()
+    };
+
+    let info = super::info_for_path_in(&volumes, path, &options).unwrap();
+    assert_eq!(info.volume.type_, VolumeType::Floppy800);
+    assert_eq!(info.volume.signature, VolumeSignature::Bd);
+    let record = crate::encode(&info).unwrap();
+    // The signature and drive type follow the volume name and date.
+    assert_eq!(&record[42..46], b"BD\0\x03");
+    assert_eq!(crate::decode(&record).unwrap().volume, info.volume);
+
+    let (.., warnings) = super::derive_in(&volumes, path, &options).unwrap();
+    assert!(
+      !warnings
+        .iter()
+        .any(|warning| matches!(warning, Warning::VolumeTypeGuessed(_))),
+      "{warnings:?}"
+    );
+
+    // HFS+ volumes were never floppies.
+    let options = CreateOptions {
+      volume_type: Some(VolumeType::Floppy800),
+      ..Default::default()
+    };
+    assert!(matches!(
+      super::info_for_path_in(&volumes, path, &options),
+      Err(Error::InvalidInfo(_))
+    ));
+  }
+
+  #[test]
+  fn fake_volume_disk_image() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.file("/Users/me/Test Title.dmg", 2, 303, 1388686800);
+    volumes.attach("/Users/me/Test Title.dmg", "/Volumes/Test Title");
+
+    let info = super::info_for_path_in(&volumes, TEST_BKG, &Default::default()).unwrap();
+    let types: Vec<i16> = info.extra.iter().map(|e| e.type_).collect();
+    assert_eq!(types, [0, 1, 2, 14, 15, 18, 19, 20]);
+    let image = info.disk_image().unwrap();
+    assert_eq!(image.target.filename, "Test Title.dmg");
+    assert_eq!(image.target.id, 303);
+    assert_eq!(
+      image.posix_path().as_deref(),
+      Some("/Users/me/Test Title.dmg")
+    );
+    assert_eq!(image.volume.name, "Macintosh HD");
+
+    for options in [
+      crate::CreateOptions {
+        no_disk_image: true,
+        ..Default::default()
+      },
+      crate::CreateOptions {
+        layout: Layout::Minimal,
+        ..Default::default()
+      },
+    ] {
+      let info = super::info_for_path_in(&volumes, TEST_BKG, &options).unwrap();
+      assert!(info.extra(20).is_none(), "{options:?}");
+    }
+
+    // An image that is gone leaves the record without it.
+    let mut volumes = FakeVolumes::test_title();
+    volumes.attach("/Users/me/Gone.dmg", "/Volumes/Test Title");
+    let info = super::info_for_path_in(&volumes, TEST_BKG, &Default::default()).unwrap();
+    assert!(info.extra(20).is_none());
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn disk_image() {
+    let image = crate::fixture::DiskImage::attach("dmg", "Alias Test");
+    let target = image.mount_point.join("bg.tiff");
+    std::fs::write(&target, b"").unwrap();
+    let record = super::create_for_path(&target);
+
+    let info = crate::decode(&record.unwrap()).unwrap();
+    let image_info = info.disk_image().expect("no disk image extra");
+    assert_eq!(image_info.target.filename, "Alias Test.dmg");
+    assert_eq!(
+      image_info.posix_path().unwrap(),
+      image.image.to_str().unwrap()
+    );
+  }
+
+  #[test]
+  fn fake_volume_missing_target() {
+    let volumes = FakeVolumes::test_title();
+    let missing = "/Volumes/Test Title/.background/Missing.tiff";
+    assert!(matches!(
+      super::info_for_path_in(&volumes, missing, &Default::default()),
+      Err(Error::TargetNotFound(path)) if path == missing
+    ));
+
+    let allow_missing = crate::CreateOptions {
+      allow_missing: true,
+      ..Default::default()
+    };
+    let info = super::info_for_path_in(&volumes, missing, &allow_missing).unwrap();
+    assert_eq!((info.target.id, info.parent.id), (0, 19));
+    assert_eq!(info.extra(19).unwrap().data, b"/Volumes/Test Title");
+    assert!(matches!(
+      super::info_for_path_in(&volumes, "/Volumes/Gone/bg.tiff", &allow_missing),
+      Err(Error::TargetNotFound(_))
+    ));
+  }
+
+  #[test]
+  fn fake_volume_dataless_target() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.set_flags(TEST_BKG, super::SF_DATALESS);
+    let no_io = crate::CreateOptions {
+      no_io: true,
+      ..Default::default()
+    };
+    assert!(super::info_for_path_in(&volumes, TEST_BKG, &Default::default()).is_ok());
+    assert!(matches!(
+      super::info_for_path_in(&volumes, TEST_BKG, &no_io),
+      Err(Error::WouldMaterialize(_))
+    ));
+  }
+
+  #[test]
+  fn build_info_mac_roman_parent_name() {
+    let (target, mut parent, volume) = parts();
+    parent.name = "Hintergründe".to_owned();
+    let info = super::build_info(
+      "/Volumes/Test Title/Hintergründe/TestBkg.tiff",
+      "/Volumes/Test Title",
+      target,
+      parent,
+      volume,
+    )
+    .unwrap();
+    assert_eq!(info.extra[0].data, b"Hintergr\x9fnde");
+    assert_eq!(
+      info.extra[2].data,
+      b"Test Title:Hintergr\x9fnde:\0TestBkg.tiff"
+    );
+    assert_eq!(info.extra[5].data, "/Hintergründe/TestBkg.tiff".as_bytes());
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn allow_missing_target() {
+    let dir = std::env::temp_dir().join(format!("macos-alias-missing-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("background.tiff");
+    let options = crate::CreateOptions {
+      allow_missing: true,
+      ..Default::default()
+    };
+
+    assert!(matches!(
+      super::create_for_path(&target),
+      Err(Error::TargetNotFound(path)) if path.ends_with("background.tiff")
+    ));
+    let before = crate::decode(&super::create_for_path_with(&target, &options).unwrap()).unwrap();
+    std::fs::write(&target, b"").unwrap();
+    let after = crate::decode(&super::create_for_path_with(&target, &options).unwrap()).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(before.target.id, 0);
+    let same = |mut info: crate::Info| {
+      info.target.id = 0;
+      info.target.created = UNIX_EPOCH;
+      info
+    };
+    assert_eq!(same(before), same(after));
+    assert!(super::create_for_path_with(dir.join("gone/background.tiff"), &options).is_err());
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn execute_only_parent() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("macos-alias-denied-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("background.tiff");
+    std::fs::write(&target, b"").unwrap();
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o111)).unwrap();
+    let record = super::create_for_path(&target);
+    let exists = record
+      .as_deref()
+      .ok()
+      .and_then(|record| crate::stat_target(record).ok())
+      .map(|status| status.exists);
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let info = crate::decode(&record.unwrap()).unwrap();
+    assert_eq!(info.target.filename, "background.tiff");
+    assert_eq!(exists, Some(true));
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn file_ids_for_path() {
+    use std::os::unix::fs::MetadataExt;
+
+    let path = env!("CARGO_MANIFEST_DIR");
+    let ids = super::file_ids_for_path(path).unwrap();
+    assert_eq!(ids.id, std::fs::metadata(path).unwrap().ino());
+    let parent = std::path::Path::new(path).parent().unwrap();
+    assert_eq!(ids.parent_id, std::fs::metadata(parent).unwrap().ino());
+    assert!(matches!(
+      super::file_ids_for_path(format!("{path}/missing")),
+      Err(Error::TargetNotFound(_))
+    ));
+  }
+
+  #[test]
+  fn send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<CreateOptions>();
+    assert_send_sync::<crate::Info>();
+    assert_send_sync::<crate::VolumeInfo>();
+    assert_send_sync::<crate::TreeOptions>();
+    assert_send_sync::<crate::TreeEntry>();
+    assert_send_sync::<Error>();
+    assert_send_sync::<Warning>();
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn create_from_threads() {
+    let exe = std::env::current_exe().unwrap();
+    let targets = [exe.clone(), exe.parent().unwrap().to_owned(), exe];
+    let expected: Vec<_> = targets
+      .iter()
+      .map(|target| super::create_for_path(target).unwrap())
+      .collect();
+    std::thread::scope(|scope| {
+      let threads: Vec<_> = (0..8)
+        .map(|_| {
+          scope.spawn(|| {
+            for _ in 0..50 {
+              for (target, expected) in targets.iter().zip(&expected) {
+                assert_eq!(super::create_for_path(target).unwrap(), *expected);
+              }
+            }
+          })
+        })
+        .collect();
+      for thread in threads {
+        thread.join().unwrap();
+      }
+    });
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn create_for_fd() {
+    use std::os::fd::AsRawFd;
+
+    let dir = std::env::temp_dir().join(format!("macos-alias-fd-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("background.tiff");
+    std::fs::write(&target, b"").unwrap();
+    let file = std::fs::File::open(&target).unwrap();
+    let fd = file.as_raw_fd();
+    let options = crate::CreateOptions::default();
+
+    let canonical = target.canonicalize().unwrap();
+    let from_fd = super::create_for_fd(fd, None, &options).unwrap();
+    assert_eq!(from_fd, super::create_for_path(&canonical).unwrap());
+    let hinted = super::create_for_fd(fd, Some(&target), &options).unwrap();
+    assert_eq!(hinted, super::create_for_path(&target).unwrap());
+
+    // A stale hint falls back to the path the descriptor now has.
+    let renamed = dir.join("renamed.tiff");
+    std::fs::rename(&target, &renamed).unwrap();
+    let moved = crate::decode(&super::create_for_fd(fd, Some(&target), &options).unwrap()).unwrap();
+    assert_eq!(moved.target.filename, "renamed.tiff");
+
+    assert!(file.metadata().is_ok());
+    drop(file);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(super::create_for_fd(-1, None, &options).is_err());
+  }
+
+  #[test]
+  fn build_info_rejects_target_outside_volume() {
+    let (target, parent, volume) = parts();
+    let err = super::build_info(
+      "/tmp/.background/TestBkg.tiff",
+      "/Volumes/Test Title",
+      target,
+      parent,
+      volume,
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::InvalidPath(_)));
+  }
+
+  #[test]
+  fn created_fallbacks() {
+    use crate::source::FileStat;
+    use crate::DateSource;
+
+    let stat = FileStat {
+      birthtime: Some(1388686804),
+      mtime: Some(1388686806),
+      ctime: 1388686808,
+      ctime_nsec: 250_000_000,
+      ..Default::default()
+    };
+    let created = |stat: &FileStat| super::created(stat, Compat::Native);
+    let secs = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+    assert_eq!(created(&stat), (secs(1388686804), DateSource::Birthtime));
+    let stat = FileStat {
+      birthtime: Some(0),
+      ..stat
+    };
+    assert_eq!(created(&stat), (secs(1388686806), DateSource::Mtime));
+    let stat = FileStat {
+      mtime: None,
+      ..stat
+    };
+    assert_eq!(created(&stat), (secs(1388686808), DateSource::Ctime));
+    assert_eq!(
+      super::created(&stat, Compat::NodeAlias),
+      (
+        UNIX_EPOCH + Duration::from_millis(1388686808250),
+        DateSource::Ctime
+      )
+    );
+    let stat = FileStat { ctime: 0, ..stat };
+    assert_eq!(created(&stat), (UNIX_EPOCH, DateSource::Epoch));
+  }
+
+  #[test]
+  fn fake_volume_date_sources() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.set_times(TEST_BKG, Some(1388686800), None);
+    volumes.set_times("/Volumes/Test Title", None, Some(1388686802));
+    let (info, sources, warnings) =
+      super::derive_in(&volumes, TEST_BKG, &Default::default()).unwrap();
+    assert_eq!(
+      sources,
+      crate::DateSources {
+        target_created: crate::DateSource::Birthtime,
+        volume_created: crate::DateSource::Mtime,
+        volume_type: crate::VolumeTypeSource::DiskArbitration,
+      }
+    );
+    assert_eq!(
+      warnings,
+      [Warning::DateFallback {
+        field: "volume.created",
+        source: crate::DateSource::Mtime
+      }]
+    );
+    assert_eq!(warnings[0].code(), "WARN_DATE_FALLBACK");
+    assert_eq!(
+      warnings[0].to_string(),
+      "The filesystem keeps no creation date for volume.created, so the modification date was used."
+    );
+    assert_eq!(
+      info.target.created,
+      UNIX_EPOCH + Duration::from_secs(1388686800)
+    );
+    assert_eq!(
+      info.volume.created,
+      UNIX_EPOCH + Duration::from_secs(1388686802)
+    );
+
+    // The fixture volumes only have change times.
+    let (_, sources, warnings) =
+      super::derive_in(&FakeVolumes::test_title(), TEST_BKG, &Default::default()).unwrap();
+    assert_eq!(sources.target_created, crate::DateSource::Ctime);
+    assert_eq!(warnings.len(), 2);
+
+    // node-alias records are made from change times on purpose.
+    let options = CreateOptions {
+      compat: Compat::NodeAlias,
+      ..Default::default()
+    };
+    let (.., warnings) = super::derive_in(&FakeVolumes::test_title(), TEST_BKG, &options).unwrap();
+    assert_eq!(warnings, []);
+  }
+
+  /// The fixture volumes, with creation dates so that only the warnings a
+  /// test sets up are left.
+  fn dated_volumes() -> FakeVolumes {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.set_times(TEST_BKG, Some(1388686808), None);
+    volumes.set_times("/Volumes/Test Title", Some(1388686804), None);
+    volumes
+  }
+
+  fn warnings(volumes: &FakeVolumes, options: &CreateOptions) -> Vec<Warning> {
+    let (.., warnings) = super::derive_in(volumes, TEST_BKG, options).unwrap();
+    warnings
+  }
+
+  #[test]
+  fn volume_name_fallback_warning() {
+    use crate::VolumeNameSource::{Attrlist, CoreFoundation, MountPoint};
+
+    let mut volumes = dated_volumes();
+    assert_eq!(warnings(&volumes, &Default::default()), []);
+    volumes.fail_volume_names(&[CoreFoundation]);
+    let display = CreateOptions {
+      volume_name_source: CoreFoundation,
+      ..Default::default()
+    };
+    assert_eq!(
+      warnings(&volumes, &display),
+      [Warning::VolumeNameFallback(Attrlist)]
+    );
+    // The on-disk name is the default, which needs no CoreFoundation.
+    assert_eq!(warnings(&volumes, &Default::default()), []);
+    volumes.fail_volume_names(&[CoreFoundation, Attrlist]);
+    let warning = warnings(&volumes, &Default::default()).remove(0);
+    assert_eq!(warning, Warning::VolumeNameFallback(MountPoint));
+    assert_eq!(warning.code(), "WARN_VOLUME_NAME_FALLBACK");
+    assert_eq!(warning.field(), "volume.name");
+
+    // Asking for the mount point is not a fallback.
+    let options = CreateOptions {
+      volume_name_source: MountPoint,
+      ..Default::default()
+    };
+    assert_eq!(warnings(&volumes, &options), []);
+  }
+
+  #[test]
+  fn fake_volume_types() {
+    let mut volumes = dated_volumes();
+    let volume_type = |volumes: &FakeVolumes, options: &CreateOptions| {
+      let (info, sources, found) = super::derive_in(volumes, TEST_BKG, options).unwrap();
+      (info.volume.type_, sources.volume_type, found)
+    };
+    // Test Title is a disk image.
+    assert_eq!(
+      volume_type(&volumes, &Default::default()),
+      (
+        VolumeType::Other,
+        crate::VolumeTypeSource::DiskArbitration,
+        vec![]
+      )
+    );
+    volumes.set_disk(3, Some(crate::fixture::INTERNAL_DISK));
+    assert_eq!(
+      volume_type(&volumes, &Default::default()).0,
+      VolumeType::Local
+    );
+    let node_alias = CreateOptions {
+      compat: Compat::NodeAlias,
+      ..Default::default()
+    };
+    assert_eq!(volume_type(&volumes, &node_alias).0, VolumeType::Other);
+
+    volumes.set_disk(3, None);
+    let (type_, source, found) = volume_type(&volumes, &Default::default());
+    assert_eq!(
+      (type_, source),
+      (VolumeType::Other, crate::VolumeTypeSource::Statfs)
+    );
+    assert_eq!(found, [Warning::VolumeTypeGuessed(VolumeType::Other)]);
+    assert_eq!(found[0].code(), "WARN_VOLUME_TYPE_GUESSED");
+  }
+
+  #[test]
+  fn id_too_large_warning() {
+    let mut volumes = dated_volumes();
+    let mut stat = volumes.remove(TEST_BKG);
+    stat.ino = 0x1_0000_0014;
+    volumes.insert_stat(TEST_BKG, stat);
+    let (info, _, found) = super::derive_in(&volumes, TEST_BKG, &Default::default()).unwrap();
+    assert_eq!(info.target.id, 0);
+    assert_eq!(
+      found,
+      [Warning::IdTooLarge {
+        field: "target.id",
+        ino: 0x1_0000_0014
+      }]
+    );
+    assert_eq!(found[0].code(), "WARN_ID_TOO_LARGE");
+
+    // FAT volumes have no ids to lose.
+    volumes.mount(3, "/Volumes/Test Title", "exfat", "TEST TITLE");
+    assert_eq!(warnings(&volumes, &Default::default()), []);
+  }
+
+  #[test]
+  fn fake_volume_hard_link() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.directory("/Volumes/Test Title/Other", 3, 21, 1388686804);
+    let link = "/Volumes/Test Title/Other/TestBkg.tiff";
+    volumes.hard_link(link, TEST_BKG);
+    let ids = |path| {
+      let info = super::info_for_path_in(&volumes, path, &Default::default()).unwrap();
+      (info.target.id, info.parent.id)
+    };
+    // One file, recorded in the directory of each link.
+    assert_eq!(ids(TEST_BKG), (20, 19));
+    assert_eq!(ids(link), (20, 21));
+  }
+
+  #[test]
+  fn fake_volume_file_ids() {
+    let mut volumes = FakeVolumes::test_title();
+    let file_ids = FileIds {
+      id: 40,
+      parent_id: 41,
+    };
+    volumes.set_file_ids(TEST_BKG, Some(file_ids));
+    let ids = |volumes: &FakeVolumes, options: &CreateOptions| {
+      let info = super::info_for_path_in(volumes, TEST_BKG, options).unwrap();
+      (info.target.id, info.parent.id)
+    };
+    assert_eq!(ids(&volumes, &Default::default()), (40, 41));
+    let node_alias = CreateOptions {
+      compat: Compat::NodeAlias,
+      ..Default::default()
+    };
+    assert_eq!(ids(&volumes, &node_alias), (20, 19));
+
+    // The inodes stand in when getattrlist fails.
+    volumes.set_file_ids(TEST_BKG, None);
+    assert_eq!(ids(&volumes, &Default::default()), (20, 19));
+  }
+
+  #[test]
+  fn normalize_path() {
+    let normalize = |path| super::normalize_path(path, "/").unwrap();
+    assert_eq!(
+      normalize("/Volumes/Test/./background/../.background/bkg.tiff"),
+      "/Volumes/Test/.background/bkg.tiff"
+    );
+    assert_eq!(
+      normalize("/Volumes//Test/.background/"),
+      "/Volumes/Test/.background"
+    );
+    assert_eq!(normalize("/Volumes/Test/.."), "/Volumes");
+    assert_eq!(normalize("/."), "/");
+    assert_eq!(normalize("../a/./b/../../c"), "../c");
+    assert_eq!(normalize("a/.."), ".");
+    assert!(matches!(
+      super::normalize_path("/Volumes/../..", "/"),
+      Err(Error::InvalidPath(_))
+    ));
+
+    let within = |path| super::normalize_path(path, "/Volumes/Test");
+    assert_eq!(within("/Volumes/Test/a/../b").unwrap(), "/Volumes/Test/b");
+    // Paths that have not reached the floor yet may still climb.
+    assert_eq!(
+      within("/Volumes/Other/../Test/b").unwrap(),
+      "/Volumes/Test/b"
+    );
+    for path in ["/Volumes/Test/..", "/Volumes/Test/a/../../Other/b"] {
+      assert!(
+        matches!(within(path), Err(Error::InvalidPath(message)) if message.contains("out of the volume")),
+        "{path}"
+      );
+    }
+  }
+
+  #[test]
+  fn build_info_normalizes_paths() {
+    let (target, parent, volume) = parts();
+    let info = super::build_info(
+      "/Volumes/Test Title/./background/../.background/TestBkg.tiff",
+      "/Volumes/Test Title/",
+      target.clone(),
+      parent.clone(),
+      volume.clone(),
+    )
+    .unwrap();
+    assert_eq!(info.extra(18).unwrap().data, b"/.background/TestBkg.tiff");
+    assert_eq!(info.extra(19).unwrap().data, b"/Volumes/Test Title");
+
+    let err = super::build_info(
+      "/Volumes/Test Title/../Other/TestBkg.tiff",
+      "/Volumes/Test Title",
+      target,
+      parent,
+      volume,
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::InvalidPath(_)), "{err}");
+  }
+
+  #[test]
+  fn fake_volume_dot_components() {
+    let volumes = FakeVolumes::test_title();
+    let info = |path| super::info_for_path_in(&volumes, path, &Default::default());
+    assert_eq!(
+      info("/Volumes/Test Title/./background/../.background/TestBkg.tiff").unwrap(),
+      info(TEST_BKG).unwrap()
+    );
+    // `..` applies to the symlink itself, not to where it points.
+    assert_eq!(
+      info("/Users/me/Backgrounds/../bg.tiff").unwrap(),
+      info("/Users/me/bg.tiff").unwrap()
+    );
+    assert!(matches!(
+      info("/Volumes/../../TestBkg.tiff"),
+      Err(Error::InvalidPath(_))
+    ));
+  }
+
+  #[test]
+  fn node_alias_file() {
+    let record = node_alias_record(
+      "/Volumes/Ärger/Hintergründe/Bild über.tiff",
+      "/Volumes/Ärger",
+      Target {
+        id: 20,
+        type_: TargetType::File,
+        filename: "Bild über.tiff".to_owned(),
+        created: UNIX_EPOCH + Duration::from_millis(1388686808500),
+      },
+      Parent {
+        id: 19,
+        name: "Hintergründe".to_owned(),
+      },
+      Volume {
+        name: "Ärger".to_owned(),
+        created: UNIX_EPOCH + Duration::from_millis(1388686804499),
+        signature: VolumeSignature::HPlus,
+        type_: VolumeType::Other,
+      },
+    );
+    assert_eq!(record, NODE_ALIAS_FILE);
+  }
+
+  #[test]
+  fn node_alias_directory() {
+    let record = node_alias_record(
+      "/Volumes/Test Title/Ordner",
+      "/Volumes/Test Title",
+      Target {
+        id: 21,
+        type_: TargetType::Directory,
+        filename: "Ordner".to_owned(),
+        created: UNIX_EPOCH + Duration::from_millis(1388686808499),
+      },
+      Parent {
+        id: 2,
+        name: "Test Title".to_owned(),
+      },
+      Volume {
+        name: "Test Title".to_owned(),
+        created: UNIX_EPOCH + Duration::from_millis(1388686804500),
+        signature: VolumeSignature::HPlus,
+        type_: VolumeType::Other,
+      },
+    );
+    assert_eq!(record, NODE_ALIAS_DIRECTORY);
+  }
+}
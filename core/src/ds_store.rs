@@ -0,0 +1,47 @@
+//! Framing for alias records stored in `.DS_Store` files.
+//!
+//! The background picture of a Finder window is stored as a `pict` entry of
+//! the `blob` data type on the `.` record: the four-character type code, a
+//! big-endian `u32` byte length and the alias record itself.
+
+/// Size of a `.DS_Store` B-tree node. An entry has to fit in a single node.
+pub const DS_STORE_NODE_SIZE: usize = 4096;
+
+/// Largest alias record whose `pict` entry on `.` still fits in one node.
+///
+/// A leaf node spends 8 bytes on its header, and the entry spends 6 on the
+/// `.` filename, 4 on the `pict` code and 8 on the blob framing.
+pub const DS_STORE_MAX_ALIAS_LENGTH: usize = DS_STORE_NODE_SIZE - 8 - 6 - 4 - 8;
+
+/// Wrap `alias` as the value of a `blob` `.DS_Store` entry.
+pub fn ds_store_blob(alias: &[u8]) -> Vec<u8> {
+  let mut blob = Vec::with_capacity(8 + alias.len());
+  blob.extend_from_slice(b"blob");
+  blob.extend_from_slice(&(alias.len() as u32).to_be_bytes());
+  blob.extend_from_slice(alias);
+  blob
+}
+
+#[cfg(test)]
+mod test {
+  use crate::fixture::fixture_bytes;
+
+  #[test]
+  fn pict_entry() {
+    let alias = fixture_bytes();
+    // The `.` / `pict` entry as it appears inside a `.DS_Store` leaf node.
+    let mut entry = vec![0, 0, 0, 1, 0, b'.'];
+    entry.extend_from_slice(b"pict");
+    entry.extend_from_slice(b"blob");
+    entry.extend_from_slice(&[0, 0, 0x01, 0x2a]);
+    entry.extend_from_slice(&alias);
+
+    assert_eq!(&entry[10..], super::ds_store_blob(&alias).as_slice());
+  }
+
+  #[test]
+  fn max_alias_length_fills_a_node() {
+    let blob = super::ds_store_blob(&vec![0; super::DS_STORE_MAX_ALIAS_LENGTH]);
+    assert_eq!(8 + 6 + 4 + blob.len(), super::DS_STORE_NODE_SIZE);
+  }
+}
@@ -0,0 +1,476 @@
+use std::io::Cursor;
+use std::io::Write;
+
+use byteorder::BigEndian;
+use byteorder::WriteBytesExt;
+
+use crate::macroman;
+use crate::time::apple_date;
+use crate::{Compat, Error, Extra, ExtraValue, Info, Result, TargetType};
+
+/// Length byte and bytes of a classic Pascal string field. A name too long
+/// for the field is cut to fit, as Finder does, when the record carries it in
+/// full in the UTF-16 extra `carried_in`; node-alias never cuts.
+fn classic_name(
+  info: &Info,
+  name: &str,
+  capacity: usize,
+  carried_in: Option<i16>,
+  compat: Compat,
+) -> Option<(u8, Vec<u8>)> {
+  let (length, mut bytes) = match compat {
+    Compat::Native => {
+      let bytes = macroman::encode(name);
+      (bytes.len(), bytes)
+    }
+    // node-alias writes `name.length` (UTF-16 code units) as the length and
+    // then as many UTF-8 bytes as fit in the field.
+    Compat::NodeAlias => (name.encode_utf16().count(), name.as_bytes().to_vec()),
+  };
+  if length > capacity {
+    // Mac OS Roman has a byte per character, so any cut is on a boundary.
+    let carried = carried_in
+      .and_then(|type_| info.extra(type_))
+      .and_then(Extra::value)
+      == Some(ExtraValue::String(name.to_owned()));
+    if compat == Compat::NodeAlias || !carried {
+      return None;
+    }
+    bytes.truncate(capacity);
+    return Some((capacity as u8, bytes));
+  }
+  bytes.truncate(capacity);
+  Some((length as u8, bytes))
+}
+
+/// Serialize `info` into the binary alias record format. The creation dates
+/// lose their milliseconds, as Finder's do; see [`Info::round_dates`] to
+/// round them instead.
+pub fn encode(info: &Info) -> Result<Vec<u8>> {
+  encode_with(info, Compat::Native)
+}
+
+/// Like [`encode`], but following the classic field and date rounding
+/// conventions of `compat`.
+pub fn encode_with(info: &Info, compat: Compat) -> Result<Vec<u8>> {
+  let mut buf = vec![0; encoded_length(info)?];
+  encode_into_with(info, compat, &mut buf)?;
+  Ok(buf)
+}
+
+/// Serialize `info` into the start of `out`, returning the length of the
+/// record. Fails with [`Error::BufferTooSmall`] if `out` cannot hold it.
+pub fn encode_into(info: &Info, out: &mut [u8]) -> Result<usize> {
+  encode_into_with(info, Compat::Native, out)
+}
+
+/// Like [`encode_into`], but following the classic field and date rounding
+/// conventions of `compat`.
+pub fn encode_into_with(info: &Info, compat: Compat, out: &mut [u8]) -> Result<usize> {
+  let total = encoded_length(info)?;
+  let available = out.len();
+  let buf = out.get_mut(..total).ok_or(Error::BufferTooSmall {
+    required: total,
+    available,
+  })?;
+  let mut cursor = Cursor::new(buf);
+
+  let classic = &info.classic;
+  cursor.write_all(&classic.user_type)?;
+
+  cursor.write_u16::<BigEndian>(total as u16)?;
+  cursor.write_u16::<BigEndian>(info.version)?;
+
+  cursor.write_u16::<BigEndian>(info.target.type_ as _)?;
+
+  let (vol_name_length, vol_name) = classic_name(info, &info.volume.name, 27, Some(15), compat)
+    .ok_or_else(|| Error::VolumeNameTooLong(info.volume.name.clone()))?;
+
+  cursor.write_u8(vol_name_length)?;
+  let padding = vec![0u8; 27 - vol_name.len()];
+
+  cursor.write_all(&vol_name)?;
+  cursor.write_all(&padding)?;
+  cursor.write_u32::<BigEndian>(apple_date(info.volume.created, compat.date_rounding()))?;
+  let signature = info.volume.signature.as_ref().as_bytes();
+  cursor.write_all(signature)?;
+  cursor.write_u16::<BigEndian>(info.volume.type_ as _)?;
+  cursor.write_u32::<BigEndian>(info.parent.id)?;
+
+  let (file_name_len, file_name) = classic_name(info, &info.target.filename, 63, Some(14), compat)
+    .ok_or_else(|| Error::FileNameTooLong(info.target.filename.clone()))?;
+  cursor.write_u8(file_name_len)?;
+  let filename_padding = vec![0u8; 63 - file_name.len()];
+  cursor.write_all(&file_name)?;
+  cursor.write_all(&filename_padding)?;
+  cursor.write_u32::<BigEndian>(info.target.id)?;
+  cursor.write_u32::<BigEndian>(apple_date(info.target.created, compat.date_rounding()))?;
+
+  cursor.write_all(&classic.file_type)?;
+  cursor.write_all(&classic.file_creator)?;
+
+  cursor.write_i16::<BigEndian>(classic.nlvl_from)?;
+  cursor.write_i16::<BigEndian>(classic.nlvl_to)?;
+
+  cursor.write_u32::<BigEndian>(classic.volume_attributes)?;
+
+  cursor.write_u16::<BigEndian>(classic.volume_fs_id)?;
+
+  cursor.write_all(&classic.reserved)?;
+  for e in info.extra.iter() {
+    cursor.write_i16::<BigEndian>(e.type_)?;
+    cursor.write_u16::<BigEndian>(e.length)?;
+    cursor.write_all(&e.data)?;
+
+    if e.length % 2 == 1 {
+      cursor.write_u8(e.padding)?;
+    }
+  }
+
+  cursor.write_i16::<BigEndian>(-1)?;
+  cursor.write_u16::<BigEndian>(0)?;
+
+  // The header declares `total`; anything else would shift the trailer.
+  let written = cursor.position() as usize;
+  debug_assert_eq!(written, total);
+  if written != total {
+    return Err(Error::InvalidInfo(format!(
+      "The record declares {total} bytes but {written} were written."
+    )));
+  }
+  Ok(total)
+}
+
+/// The exact length of the record [`encode`] writes for `info`, without
+/// writing it, after checking that it can be written at all.
+pub fn encoded_length(info: &Info) -> Result<usize> {
+  if info.version != 2 {
+    return Err(Error::InvalidInfo(format!(
+      "Only version 2 records can be written, not version {}; set the version to 2 to re-encode a decoded record.",
+      info.version
+    )));
+  }
+  if info.target.type_ == TargetType::Directory
+    && (info.classic.file_type != [0; 4] || info.classic.file_creator != [0; 4])
+  {
+    return Err(Error::InvalidInfo(
+      "Directory targets cannot carry a file type or creator code.".to_owned(),
+    ));
+  }
+
+  for e in &info.extra {
+    if e.type_ == -1 {
+      return Err(Error::InvalidInfo(
+        "Extra type -1 is reserved for the end-of-record marker.".to_owned(),
+      ));
+    }
+    if e.data.len() != e.length as usize {
+      return Err(Error::InvalidInfo(format!(
+        "Extra type {} declares {} bytes but holds {}.",
+        e.type_,
+        e.length,
+        e.data.len()
+      )));
+    }
+  }
+
+  let base_length = 150;
+  let extra_length: usize = info
+    .extra
+    .iter()
+    .map(|e| 4 + e.length as usize + (e.length % 2) as usize)
+    .sum();
+  let trailer_length = 4;
+
+  let total = base_length + extra_length + trailer_length;
+  if total > u16::MAX as usize {
+    return Err(Error::RecordTooLarge(total));
+  }
+  Ok(total)
+}
+
+#[cfg(test)]
+mod test {
+  use base64::Engine;
+
+  use crate::fixture::{fixture_info, FIXTURE};
+  use crate::{Error, TargetType};
+
+  #[test]
+  fn directory_with_file_type() {
+    let mut info = fixture_info();
+    info.target.type_ = TargetType::Directory;
+    assert!(super::encode(&info).is_ok());
+    info.classic.file_creator = *b"8BIM";
+    assert!(matches!(super::encode(&info), Err(Error::InvalidInfo(_))));
+  }
+
+  #[test]
+  fn classic_names_in_mac_roman() {
+    let mut info = fixture_info();
+    info.volume.name = "Café".to_owned();
+    info.target.filename = "Привет.tiff".to_owned();
+    let record = super::encode(&info).unwrap();
+    assert_eq!(&record[10..15], b"\x04Caf\x8e");
+    assert_eq!(&record[50..62], b"\x0b??????.tiff");
+
+    let decoded = crate::decode(&record).unwrap();
+    assert_eq!(decoded.volume.name, "Café");
+    assert_eq!(decoded.target.filename, "??????.tiff");
+  }
+
+  #[test]
+  fn non_ascii_names() {
+    for (volume_name, filename) in [
+      ("Crème Brûlée", "Hintergrund für Ölgemälde.tiff"),
+      ("日本語のインストーラ", "背景画像.png"),
+      ("🚀 Launch", "🎉 party 👍🏽.tiff"),
+    ] {
+      let volume_path = format!("/Volumes/{volume_name}");
+      let info = crate::build_info(
+        &format!("{volume_path}/.background/{filename}"),
+        &volume_path,
+        crate::Target {
+          filename: filename.to_owned(),
+          ..fixture_info().target
+        },
+        fixture_info().parent,
+        crate::Volume {
+          name: volume_name.to_owned(),
+          ..fixture_info().volume
+        },
+      )
+      .unwrap();
+      let record = super::encode(&info).unwrap();
+      assert_eq!(
+        u16::from_be_bytes([record[4], record[5]]) as usize,
+        record.len()
+      );
+      // The length bytes count Mac OS Roman bytes, one per character.
+      let volume_bytes = crate::macroman::encode(volume_name);
+      assert_eq!(&record[11..11 + record[10] as usize], &volume_bytes[..]);
+      let file_bytes = crate::macroman::encode(filename);
+      assert_eq!(&record[51..51 + record[50] as usize], &file_bytes[..]);
+      // The UTF-16 extras count code units, two bytes each.
+      let units = filename.encode_utf16().count();
+      assert_eq!(info.extra(14).unwrap().length as usize, 2 + 2 * units);
+
+      let decoded = crate::decode(&record).unwrap();
+      assert_eq!(decoded.volume.name, volume_name);
+      assert_eq!(decoded.target.filename, filename);
+      assert_eq!(decoded.extra, info.extra);
+      assert_eq!(super::encode(&decoded).unwrap(), record);
+    }
+  }
+
+  #[test]
+  fn half_second_dates() {
+    use std::time::Duration;
+
+    use crate::{Compat, DateRounding};
+
+    let seconds = |record: &[u8]| {
+      let info = crate::decode(record).unwrap();
+      [info.target.created, info.volume.created]
+    };
+    let fixture = fixture_info();
+    let (target, volume) = (fixture.target.created, fixture.volume.created);
+    let second = Duration::from_secs(1);
+    let mut info = fixture_info();
+    // Exactly on the half, and just before the next second.
+    info.target.created += Duration::from_millis(500);
+    info.volume.created += Duration::from_millis(999);
+    assert_eq!(
+      super::encode(&info).unwrap(),
+      crate::fixture::fixture_bytes()
+    );
+    let rounded = super::encode_with(&info, Compat::NodeAlias).unwrap();
+    assert_eq!(seconds(&rounded), [target + second, volume + second]);
+
+    // Exactly on the half, and just before it, of the second before.
+    info.target.created = target - Duration::from_millis(500);
+    info.volume.created = volume - Duration::from_millis(501);
+    let floored = super::encode(&info).unwrap();
+    assert_eq!(seconds(&floored), [target - second, volume - second]);
+    let rounded = super::encode_with(&info, Compat::NodeAlias).unwrap();
+    assert_eq!(seconds(&rounded), [target, volume - second]);
+    info.round_dates(DateRounding::Round);
+    assert_eq!(super::encode(&info).unwrap(), rounded);
+  }
+
+  #[test]
+  fn encoded_length() {
+    let mut info = fixture_info();
+    // An odd-length extra is padded.
+    info.extra.push(crate::Extra::new(100, vec![1, 2, 3]));
+    assert_eq!(
+      super::encoded_length(&info).unwrap(),
+      super::encode(&info).unwrap().len()
+    );
+  }
+
+  #[test]
+  fn oversized_record() {
+    let mut info = fixture_info();
+    info
+      .extra
+      .push(crate::Extra::new(20, vec![0; u16::MAX as usize - 300]));
+    let err = super::encode(&info).unwrap_err();
+    assert!(matches!(err, Error::RecordTooLarge(65538)), "{err}");
+    assert_eq!(err.code(), "ERR_RECORD_TOO_LARGE");
+    info.extra.last_mut().unwrap().data.truncate(10);
+    assert!(matches!(super::encode(&info), Err(Error::InvalidInfo(_))));
+  }
+
+  #[test]
+  fn version() {
+    let mut info = fixture_info();
+    info.version = 3;
+    let err = super::encode(&info).unwrap_err();
+    assert!(matches!(err, Error::InvalidInfo(_)), "{err}");
+    assert_eq!(err.code(), "ERR_INVALID_INFO");
+  }
+
+  #[test]
+  fn extra_length_mismatch() {
+    let mut info = fixture_info();
+    info.extra[0].length += 1;
+    let err = super::encode(&info).unwrap_err();
+    assert!(
+      matches!(&err, Error::InvalidInfo(message) if message.contains("declares 12 bytes but holds 11")),
+      "{err}"
+    );
+
+    let mut info = fixture_info();
+    info.extra[0].data.push(b'x');
+    assert!(matches!(super::encode(&info), Err(Error::InvalidInfo(_))));
+  }
+
+  #[test]
+  fn end_marker_extra() {
+    let mut info = fixture_info();
+    info.extra.insert(1, crate::Extra::new(-1, Vec::new()));
+    assert!(matches!(super::encode(&info), Err(Error::InvalidInfo(_))));
+  }
+
+  #[test]
+  fn classic_name_too_long() {
+    let mut info = fixture_info();
+    info.volume.name = "V".repeat(28);
+    let err = super::encode(&info).unwrap_err();
+    assert!(matches!(err, Error::VolumeNameTooLong(_)), "{err}");
+    assert_eq!(err.code(), "ERR_VOLUME_NAME_TOO_LONG");
+    info.volume.name = "V".repeat(27);
+    assert!(super::encode(&info).is_ok());
+
+    info.target.filename = "f".repeat(64);
+    let err = super::encode(&info).unwrap_err();
+    assert!(matches!(err, Error::FileNameTooLong(_)), "{err}");
+    assert_eq!(err.code(), "ERR_FILENAME_TOO_LONG");
+
+    // node-alias counts UTF-16 code units: 32 of "ü" are 64 UTF-8 bytes.
+    info.target.filename = "ü".repeat(32);
+    assert!(super::encode_with(&info, crate::Compat::NodeAlias).is_ok());
+    info.target.filename = "ü".repeat(64);
+    assert!(matches!(
+      super::encode_with(&info, crate::Compat::NodeAlias),
+      Err(Error::FileNameTooLong(_))
+    ));
+  }
+
+  #[test]
+  fn long_volume_name() {
+    // Finder cuts the classic field and keeps the full name in extra 15.
+    let name = "Ünïcödé Installer for Version 2.0";
+    let mut info = fixture_info();
+    info.volume.name = name.to_owned();
+    crate::add_extra_records(
+      &mut info,
+      &[crate::Extra::new(15, crate::create::unicode_name(name))],
+      false,
+    )
+    .unwrap();
+    let record = super::encode(&info).unwrap();
+    assert_eq!(record[10], 27);
+    assert_eq!(&record[11..38], &crate::macroman::encode(name)[..27]);
+    let decoded = crate::decode(&record).unwrap();
+    assert_eq!(decoded.volume.name, name);
+    assert_eq!(super::encode(&decoded).unwrap(), record);
+
+    // Without the full name elsewhere, cutting would lose it.
+    info.extra.retain(|e| e.type_ != 15);
+    assert!(matches!(
+      super::encode(&info),
+      Err(Error::VolumeNameTooLong(_))
+    ));
+    info.volume.name = "V".repeat(28);
+    info.extra.push(crate::Extra::new(
+      15,
+      crate::create::unicode_name(&info.volume.name),
+    ));
+    assert!(matches!(
+      super::encode_with(&info, crate::Compat::NodeAlias),
+      Err(Error::VolumeNameTooLong(_))
+    ));
+  }
+
+  #[test]
+  fn long_file_name() {
+    // Localized names easily pass 63 bytes; extra 14 keeps them whole.
+    let name = "Hintergrundbild für das Installationsprogramm (hohe Auflösung).tiff";
+    let mut info = fixture_info();
+    info.target.filename = name.to_owned();
+    info
+      .extra
+      .push(crate::Extra::new(14, crate::create::unicode_name(name)));
+    let record = super::encode(&info).unwrap();
+    assert_eq!(record[50], 63);
+    assert_eq!(&record[51..114], &crate::macroman::encode(name)[..63]);
+    let decoded = crate::decode(&record).unwrap();
+    assert_eq!(decoded.target.filename, name);
+    assert_eq!(super::encode(&decoded).unwrap(), record);
+
+    info.extra.retain(|e| e.type_ != 14);
+    assert!(matches!(
+      super::encode(&info),
+      Err(Error::FileNameTooLong(_))
+    ));
+  }
+
+  #[test]
+  fn decode() {
+    let encoded = super::encode(&fixture_info()).expect("Should be able to encode");
+    assert_eq!(
+      base64::engine::general_purpose::STANDARD
+        .decode(FIXTURE)
+        .unwrap(),
+      encoded
+    );
+  }
+
+  #[test]
+  fn encode_into() {
+    let fixture = base64::engine::general_purpose::STANDARD
+      .decode(FIXTURE)
+      .unwrap();
+    let mut out = vec![0xaa; fixture.len() + 10];
+    let length = super::encode_into(&fixture_info(), &mut out).unwrap();
+    assert_eq!(length, fixture.len());
+    assert_eq!(out[..length], fixture);
+    // The rest of the buffer is left alone.
+    assert_eq!(out[length..], [0xaa; 10]);
+
+    let err = super::encode_into(&fixture_info(), &mut out[..length - 1]).unwrap_err();
+    assert!(
+      matches!(
+        err,
+        Error::BufferTooSmall {
+          required: 298,
+          available: 297
+        }
+      ),
+      "{err}"
+    );
+    assert_eq!(err.code(), "ERR_BUFFER_TOO_SMALL");
+  }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+  Io(io::Error),
+  /// The target path has no usable parent directory, or one of its
+  /// components is not valid UTF-8.
+  InvalidPath(String),
+  /// The target is neither a regular file nor a directory.
+  UnsupportedFileType(String),
+  VolumeNameTooLong(String),
+  FileNameTooLong(String),
+  /// The [`crate::Info`] handed to [`crate::encode`] is inconsistent.
+  InvalidInfo(String),
+  /// The bytes handed to [`crate::decode`] or [`crate::parse_bookmark`] are
+  /// not a valid alias record or bookmark.
+  InvalidRecord {
+    offset: usize,
+    reason: String,
+  },
+  /// The operation needs the macOS filesystem APIs.
+  Unsupported(&'static str),
+  /// Creating the record with `no_io` would have downloaded the contents
+  /// of this path.
+  WouldMaterialize(String),
+  /// The target, or its parent directory, does not exist.
+  TargetNotFound(String),
+  /// Finding or inspecting the volume that holds this path failed.
+  VolumeLookupFailed {
+    path: String,
+    error: io::Error,
+  },
+  /// The encoded record would be this many bytes, more than its 16-bit
+  /// length field can hold.
+  RecordTooLarge(usize),
+  /// The record would be `size` bytes, more than the `max` asked for with
+  /// [`max_size`](crate::CreateOptions::max_size).
+  RecordExceedsMaxSize {
+    size: usize,
+    max: usize,
+  },
+  /// The buffer handed to [`crate::encode_into`] holds `available` bytes,
+  /// fewer than the `required` the record needs.
+  BufferTooSmall {
+    required: usize,
+    available: usize,
+  },
+  /// Following the alias file at `path` to its target failed.
+  UnresolvedAlias {
+    path: String,
+    reason: String,
+  },
+}
+
+impl Error {
+  /// A stable identifier for the kind of failure, e.g.
+  /// `ERR_TARGET_NOT_FOUND`. Unlike the message, it does not change between
+  /// releases.
+  pub fn code(&self) -> &'static str {
+    match self {
+      Error::Io(_) => "ERR_IO",
+      Error::InvalidPath(_) => "ERR_INVALID_PATH",
+      Error::UnsupportedFileType(_) => "ERR_NOT_A_FILE_OR_DIR",
+      Error::VolumeNameTooLong(_) => "ERR_VOLUME_NAME_TOO_LONG",
+      Error::FileNameTooLong(_) => "ERR_FILENAME_TOO_LONG",
+      Error::InvalidInfo(_) => "ERR_INVALID_INFO",
+      Error::InvalidRecord { .. } => "ERR_INVALID_RECORD",
+      Error::Unsupported(_) => "ERR_UNSUPPORTED_PLATFORM",
+      Error::WouldMaterialize(_) => "ERR_WOULD_MATERIALIZE",
+      Error::TargetNotFound(_) => "ERR_TARGET_NOT_FOUND",
+      Error::VolumeLookupFailed { .. } => "ERR_VOLUME_LOOKUP_FAILED",
+      Error::RecordTooLarge(_) => "ERR_RECORD_TOO_LARGE",
+      Error::RecordExceedsMaxSize { .. } => "ERR_RECORD_EXCEEDS_MAX_SIZE",
+      Error::BufferTooSmall { .. } => "ERR_BUFFER_TOO_SMALL",
+      Error::UnresolvedAlias { .. } => "ERR_UNRESOLVED_ALIAS",
+    }
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Error::Io(err) => err.fmt(f),
+      Error::InvalidPath(reason) => f.write_str(reason),
+      Error::UnsupportedFileType(path) => {
+        write!(f, "The target {path} is neither a file nor a directory.")
+      }
+      Error::VolumeNameTooLong(_) => f.write_str("Volume name is not longer than 27 chars"),
+      Error::FileNameTooLong(_) => f.write_str("File name is not longer than 63 chars"),
+      Error::InvalidInfo(reason) => f.write_str(reason),
+      Error::InvalidRecord { offset, reason } => write!(f, "{reason} at offset {offset}"),
+      Error::Unsupported(operation) => write!(f, "{operation} requires macOS"),
+      Error::WouldMaterialize(path) => {
+        write!(f, "The contents of {path} are not on disk and noIO is set.")
+      }
+      Error::TargetNotFound(path) => write!(f, "The target {path} does not exist."),
+      Error::VolumeLookupFailed { path, error } => {
+        write!(f, "Could not look up the volume of {path}: {error}")
+      }
+      Error::RecordTooLarge(total) => write!(
+        f,
+        "The record would be {total} bytes, more than the {} its length field can hold.",
+        u16::MAX
+      ),
+      Error::RecordExceedsMaxSize { size, max } => write!(
+        f,
+        "The record would be {size} bytes, more than the maximum of {max} asked for."
+      ),
+      Error::BufferTooSmall {
+        required,
+        available,
+      } => write!(
+        f,
+        "The record needs {required} bytes, but the buffer only holds {available}."
+      ),
+      Error::UnresolvedAlias { path, reason } => {
+        write!(f, "Could not resolve the alias file {path}: {reason}")
+      }
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Io(err) | Error::VolumeLookupFailed { error: err, .. } => Some(err),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for Error {
+  fn from(err: io::Error) -> Self {
+    Error::Io(err)
+  }
+}
@@ -0,0 +1,118 @@
+//! `file:` URLs as targets, as Node code often holds them.
+
+use std::path::PathBuf;
+
+use crate::{Error, Result};
+
+/// The absolute path a `file:` URL names, like Node's `fileURLToPath`.
+///
+/// The host must be empty or `localhost`. Percent escapes are decoded as
+/// UTF-8, except `%2F`, which cannot be part of a path component. The query
+/// and fragment are ignored, as are trailing slashes on directory URLs.
+pub fn file_url_to_path(url: &str) -> Result<PathBuf> {
+  let invalid = |reason: String| Error::InvalidPath(format!("{reason}: {url}"));
+
+  let (scheme, rest) = url
+    .split_once(':')
+    .ok_or_else(|| invalid("Not a URL".to_owned()))?;
+  if !scheme.eq_ignore_ascii_case("file") {
+    return Err(invalid("Only file: URLs can name a target".to_owned()));
+  }
+  let rest = rest.split(['?', '#']).next().unwrap_or_default();
+
+  let path = match rest.strip_prefix("//") {
+    Some(authority_and_path) => {
+      let (host, path) = match authority_and_path.find('/') {
+        Some(slash) => authority_and_path.split_at(slash),
+        None => (authority_and_path, "/"),
+      };
+      if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+        return Err(invalid(format!(
+          "The file URL host must be empty or localhost, not {host:?}"
+        )));
+      }
+      path
+    }
+    None if rest.starts_with('/') => rest,
+    None => return Err(invalid("The file URL path must be absolute".to_owned())),
+  };
+
+  let mut bytes = Vec::with_capacity(path.len());
+  let mut input = path.bytes();
+  while let Some(byte) = input.next() {
+    if byte != b'%' {
+      bytes.push(byte);
+      continue;
+    }
+    let escape = [input.next(), input.next()];
+    let decoded = match escape {
+      [Some(high), Some(low)] => std::str::from_utf8(&[high, low])
+        .ok()
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+      _ => None,
+    }
+    .ok_or_else(|| invalid("The file URL has a malformed percent escape".to_owned()))?;
+    match decoded {
+      b'/' => {
+        return Err(invalid(
+          "The file URL encodes a / inside a path component".to_owned(),
+        ))
+      }
+      0 => return Err(invalid("The file URL encodes a NUL byte".to_owned())),
+      byte => bytes.push(byte),
+    }
+  }
+
+  let mut path = String::from_utf8(bytes)
+    .map_err(|_| invalid("The file URL path is not valid UTF-8".to_owned()))?;
+  while path.len() > 1 && path.ends_with('/') {
+    path.pop();
+  }
+  Ok(PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::Path;
+
+  use crate::Error;
+
+  #[test]
+  fn file_url_to_path() {
+    let path = |url| super::file_url_to_path(url).unwrap();
+    assert_eq!(
+      path("file:///Volumes/Test/bg.tiff"),
+      Path::new("/Volumes/Test/bg.tiff")
+    );
+    assert_eq!(
+      path("file:///Volumes/My%20App/.background/Caf%C3%A9.tiff"),
+      Path::new("/Volumes/My App/.background/Café.tiff")
+    );
+    assert_eq!(path("FILE://localhost/tmp/a%25b"), Path::new("/tmp/a%b"));
+    assert_eq!(path("file:/tmp/dir/"), Path::new("/tmp/dir"));
+    assert_eq!(path("file:///tmp/dir//?query#hash"), Path::new("/tmp/dir"));
+    assert_eq!(path("file:///"), Path::new("/"));
+    assert_eq!(path("file://localhost"), Path::new("/"));
+  }
+
+  #[test]
+  fn file_url_to_path_errors() {
+    for url in [
+      "/Volumes/Test/bg.tiff",
+      "https://example.com/bg.tiff",
+      "file://server/share/bg.tiff",
+      "file:relative/bg.tiff",
+      "file:///tmp/a%2Fb",
+      "file:///tmp/a%2fb",
+      "file:///tmp/nul%00",
+      "file:///tmp/bad%zz",
+      "file:///tmp/short%4",
+      "file:///tmp/latin1-%E9",
+    ] {
+      assert!(
+        matches!(super::file_url_to_path(url), Err(Error::InvalidPath(_))),
+        "{url}"
+      );
+    }
+  }
+}
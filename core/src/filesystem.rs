@@ -0,0 +1,63 @@
+use crate::VolumeSignature;
+
+/// The volume attributes of an HFS+ volume: bits 1 (supports HFS+ APIs),
+/// 8 (subtree iterators), 10 and 11 (parent and ancestor modification dates
+/// change) of the `vMExtendedAttributes` word.
+pub(crate) const HFS_VOLUME_ATTRIBUTES: u32 = 3330;
+/// The `vMExtendedAttributes` bit Finder takes to mean the volume keeps
+/// persistent catalog node ids.
+#[cfg_attr(not(any(target_os = "macos", test)), allow(dead_code))]
+const SUPPORTS_HFS_PLUS_APIS: u32 = 1 << 1;
+
+/// How the filesystem of the target's volume shows up in the record.
+#[cfg_attr(not(any(target_os = "macos", test)), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Filesystem {
+  /// HFS+, APFS and anything else that keeps persistent file ids.
+  Hfs,
+  /// FAT12/16/32 and exFAT, whose inode numbers do not survive a remount.
+  Fat,
+}
+
+#[cfg_attr(not(any(target_os = "macos", test)), allow(dead_code))]
+impl Filesystem {
+  /// The family of a `statfs` `f_fstypename`, e.g. `apfs` or `exfat`.
+  pub(crate) fn from_type_name(name: &str) -> Self {
+    match name {
+      "msdos" | "exfat" => Filesystem::Fat,
+      _ => Filesystem::Hfs,
+    }
+  }
+
+  pub(crate) fn signature(self) -> VolumeSignature {
+    match self {
+      Filesystem::Hfs => VolumeSignature::HPlus,
+      Filesystem::Fat => VolumeSignature::Bd,
+    }
+  }
+
+  pub(crate) fn volume_attributes(self) -> u32 {
+    match self {
+      Filesystem::Hfs => HFS_VOLUME_ATTRIBUTES,
+      Filesystem::Fat => HFS_VOLUME_ATTRIBUTES & !SUPPORTS_HFS_PLUS_APIS,
+    }
+  }
+
+  /// The classic filesystem id of the `statfs` type `name`, which tells
+  /// foreign volumes behind the `BD` signature apart: `IS` for FAT and `xF`
+  /// for exFAT, as version 3 records spell them out after the signature.
+  /// HFS+ and APFS volumes, signed `H+` like Finder signs them, have none.
+  pub(crate) fn volume_fs_id(name: &str) -> u16 {
+    match name {
+      "msdos" => u16::from_be_bytes(*b"IS"),
+      "exfat" => u16::from_be_bytes(*b"xF"),
+      _ => 0,
+    }
+  }
+
+  /// Whether file ids are stable enough to be stored. Without them only the
+  /// path extras resolve the alias.
+  pub(crate) fn persistent_ids(self) -> bool {
+    self == Filesystem::Hfs
+  }
+}
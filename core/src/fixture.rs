@@ -0,0 +1,474 @@
+//! Reference record for `.background/TestBkg.tiff` on a volume titled
+//! "Test Title", shared by the tests of every module.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::source::{
+  DirEntry, DiskDescription, FileIds, FileStat, FsStat, MetadataSource, MNT_LOCAL,
+};
+use crate::{
+  ClassicFields, Extra, Info, Parent, Target, TargetType, Volume, VolumeNameSource,
+  VolumeSignature, VolumeType, VolumeUuid,
+};
+
+pub(crate) const FIXTURE: &str = "AAAAAAEqAAIAAApUZXN0IFRpdGxlAAAAAAAAAAAAAAAAAAAAAADO615USCsABQAAABMMVGVzdEJrZy50aWZmAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFM7rXlgAAAAAAAAAAP////8AAA0CAAAAAAAAAAAAAAAAAAAACy5iYWNrZ3JvdW5kAAABAAQAAAATAAIAJFRlc3QgVGl0bGU6LmJhY2tncm91bmQ6AFRlc3RCa2cudGlmZgAPABYACgBUAGUAcwB0ACAAVABpAHQAbABlABIAGS8uYmFja2dyb3VuZC9UZXN0QmtnLnRpZmYAABMAEy9Wb2x1bWVzL1Rlc3QgVGl0bGUA//8AAA==";
+
+pub(crate) fn fixture_bytes() -> Vec<u8> {
+  use base64::Engine;
+
+  base64::engine::general_purpose::STANDARD
+    .decode(FIXTURE)
+    .unwrap()
+}
+
+pub(crate) fn fixture_info() -> Info {
+  Info {
+    version: 2,
+    volume: Volume {
+      name: "Test Title".to_owned(),
+      created: UNIX_EPOCH + Duration::from_millis(1388686804000),
+      signature: VolumeSignature::HPlus,
+      type_: VolumeType::Other,
+    },
+    parent: Parent {
+      id: 19,
+      name: ".background".to_owned(),
+    },
+    target: Target {
+      id: 20,
+      type_: TargetType::File,
+      filename: "TestBkg.tiff".to_owned(),
+      created: UNIX_EPOCH + Duration::from_millis(1388686808000),
+    },
+    extra: vec![
+      Extra::new(0, b".background".to_vec()),
+      Extra::new(1, vec![0, 0, 0, 19]),
+      Extra::new(2, b"Test Title:.background:\0TestBkg.tiff".to_vec()),
+      Extra::new(
+        15,
+        vec![
+          0, 10, 0, 84, 0, 101, 0, 115, 0, 116, 0, 32, 0, 84, 0, 105, 0, 116, 0, 108, 0, 101,
+        ],
+      ),
+      Extra::new(18, b"/.background/TestBkg.tiff".to_vec()),
+      Extra::new(19, b"/Volumes/Test Title".to_vec()),
+    ],
+    classic: ClassicFields::default(),
+  }
+}
+
+/// Deterministic xorshift so failures are reproducible.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+  pub(crate) fn next(&mut self) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+
+  pub(crate) fn bytes(&mut self, length: usize) -> Vec<u8> {
+    (0..length).map(|_| self.next() as u8).collect()
+  }
+
+  pub(crate) fn name(&mut self, max: usize) -> String {
+    let length = (self.next() % (max as u64 + 1)) as usize;
+    (0..length)
+      .map(|_| (b' ' + (self.next() % 95) as u8) as char)
+      .collect()
+  }
+}
+
+/// What DiskArbitration says about an internal SSD.
+pub(crate) const INTERNAL_DISK: DiskDescription = DiskDescription {
+  network: Some(false),
+  removable: Some(false),
+  ejectable: Some(false),
+  internal: Some(true),
+};
+
+/// An in-memory filesystem for driving [`MetadataSource`] users, with
+/// `/Volumes/Test Title/.background/TestBkg.tiff` on an HFS+ volume as in
+/// [`FIXTURE`].
+pub(crate) struct FakeVolumes {
+  files: HashMap<PathBuf, FileStat>,
+  /// Absolute symlink targets.
+  symlinks: HashMap<PathBuf, PathBuf>,
+  /// Keyed by device number.
+  mounts: HashMap<u64, (FsStat, String)>,
+  /// Disk images by the mount point they are attached at.
+  images: HashMap<PathBuf, PathBuf>,
+  /// Volume name lookups that fail, as CoreFoundation does in sandboxes.
+  failing_names: Vec<VolumeNameSource>,
+  /// Names CoreFoundation shows instead of the one on disk, by mount point,
+  /// as on a localized system.
+  display_names: HashMap<PathBuf, String>,
+  /// Catalog node ids that differ from the inode, or `None` where
+  /// `getattrlist` fails. Elsewhere the ids are the inodes of the file and
+  /// its directory.
+  file_ids: HashMap<PathBuf, Option<FileIds>>,
+  /// Directories whose own lookups fail with `EACCES`, though the files in
+  /// them can still be looked up.
+  unreadable: Vec<PathBuf>,
+  /// Volume UUIDs by device number, for volumes that have one.
+  uuids: HashMap<u64, VolumeUuid>,
+  /// What DiskArbitration says about each device, or `None` where it cannot
+  /// be asked.
+  disks: HashMap<u64, Option<DiskDescription>>,
+  /// The volume name lookups made so far, in order.
+  pub(crate) name_lookups: RefCell<Vec<VolumeNameSource>>,
+}
+
+impl FakeVolumes {
+  pub(crate) fn new() -> Self {
+    FakeVolumes {
+      files: HashMap::new(),
+      symlinks: HashMap::new(),
+      mounts: HashMap::new(),
+      images: HashMap::new(),
+      failing_names: Vec::new(),
+      display_names: HashMap::new(),
+      file_ids: HashMap::new(),
+      unreadable: Vec::new(),
+      uuids: HashMap::new(),
+      disks: HashMap::new(),
+      name_lookups: RefCell::new(Vec::new()),
+    }
+  }
+
+  /// `/` and its data volume, and "Test Title" at `/Volumes/Test Title`.
+  pub(crate) fn test_title() -> Self {
+    let mut volumes = FakeVolumes::new();
+    volumes.mount(1, "/", "apfs", "Macintosh HD");
+    volumes.mount(2, "/System/Volumes/Data", "apfs", "Macintosh HD - Data");
+    volumes.mount(3, "/Volumes/Test Title", "hfs", "Test Title");
+    // An attached disk image.
+    volumes.set_disk(
+      3,
+      Some(DiskDescription {
+        ejectable: Some(true),
+        ..INTERNAL_DISK
+      }),
+    );
+    for (path, dev, ino) in [
+      ("/", 1, 2),
+      ("/System", 1, 100),
+      ("/System/Volumes", 1, 101),
+      ("/System/Volumes/Data", 2, 2),
+      ("/System/Volumes/Data/Users", 2, 300),
+      // A firmlink into the data volume.
+      ("/Users", 2, 300),
+      ("/Users/me", 2, 301),
+      ("/Volumes", 1, 102),
+      ("/Volumes/Test Title", 3, 2),
+      ("/Volumes/Test Title/.background", 3, 19),
+    ] {
+      volumes.directory(path, dev, ino, 1388686804);
+    }
+    volumes.file("/Users/me/bg.tiff", 2, 302, 1388686808);
+    volumes.symlink("/Users/me/Backgrounds", "/Volumes/Test Title/.background");
+    volumes.symlink("/Volumes/Link", "/Volumes/Test Title");
+    volumes.file(
+      "/Volumes/Test Title/.background/TestBkg.tiff",
+      3,
+      20,
+      1388686808,
+    );
+    volumes
+  }
+
+  /// Mount a local, writable volume on an internal disk.
+  pub(crate) fn mount(&mut self, dev: u64, mount_point: &str, fs_type: &str, name: &str) {
+    let stat = FsStat {
+      mount_point: PathBuf::from(mount_point),
+      device: format!("/dev/disk{dev}s1"),
+      fs_type: fs_type.to_owned(),
+      flags: MNT_LOCAL,
+    };
+    self.mounts.insert(dev, (stat, name.to_owned()));
+    self.disks.entry(dev).or_insert(Some(INTERNAL_DISK));
+  }
+
+  /// Make DiskArbitration describe device `dev` as `disk`, or fail to for
+  /// `None`.
+  pub(crate) fn set_disk(&mut self, dev: u64, disk: Option<DiskDescription>) {
+    self.disks.insert(dev, disk);
+  }
+
+  pub(crate) fn set_mount_flags(&mut self, dev: u64, flags: u32) {
+    self.mounts.get_mut(&dev).unwrap().0.flags = flags;
+  }
+
+  pub(crate) fn directory(&mut self, path: &str, dev: u64, ino: u64, ctime: i64) {
+    self.insert(path, dev, ino, ctime, true);
+  }
+
+  pub(crate) fn file(&mut self, path: &str, dev: u64, ino: u64, ctime: i64) {
+    self.insert(path, dev, ino, ctime, false);
+  }
+
+  fn insert(&mut self, path: &str, dev: u64, ino: u64, ctime: i64, is_dir: bool) {
+    let stat = FileStat {
+      dev,
+      ino,
+      is_dir,
+      is_file: !is_dir,
+      ctime,
+      ..Default::default()
+    };
+    self.files.insert(PathBuf::from(path), stat);
+  }
+
+  pub(crate) fn symlink(&mut self, path: &str, target: &str) {
+    self
+      .symlinks
+      .insert(PathBuf::from(path), PathBuf::from(target));
+  }
+
+  /// `path` with the symlinks among its components replaced.
+  fn resolve(&self, path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+      resolved.push(component);
+      if let Some(target) = self.symlinks.get(&resolved) {
+        resolved = target.clone();
+      }
+    }
+    resolved
+  }
+
+  pub(crate) fn attach(&mut self, image: &str, mount_point: &str) {
+    self
+      .images
+      .insert(PathBuf::from(mount_point), PathBuf::from(image));
+  }
+
+  pub(crate) fn remove(&mut self, path: &str) -> FileStat {
+    self.files.remove(Path::new(path)).unwrap()
+  }
+
+  pub(crate) fn insert_stat(&mut self, path: &str, stat: FileStat) {
+    self.files.insert(PathBuf::from(path), stat);
+  }
+
+  /// Make a hard link at `path` to the file at `existing`.
+  pub(crate) fn hard_link(&mut self, path: &str, existing: &str) {
+    let stat = self.files[Path::new(existing)].clone();
+    self.insert_stat(path, stat);
+  }
+
+  /// Make `getattrlist` report `ids` for `path`, or fail for `None`.
+  pub(crate) fn set_file_ids(&mut self, path: &str, ids: Option<FileIds>) {
+    self.file_ids.insert(PathBuf::from(path), ids);
+  }
+
+  pub(crate) fn set_volume_uuid(&mut self, dev: u64, uuid: VolumeUuid) {
+    self.uuids.insert(dev, uuid);
+  }
+
+  /// Make looking up the directory at `path` fail, as for a parent the
+  /// process may not search.
+  pub(crate) fn deny(&mut self, path: &str) {
+    self.unreadable.push(PathBuf::from(path));
+  }
+
+  /// Give `path` the creation and modification dates the fixture leaves
+  /// out, as on filesystems that keep them.
+  pub(crate) fn set_times(&mut self, path: &str, birthtime: Option<i64>, mtime: Option<i64>) {
+    let stat = self.files.get_mut(Path::new(path)).unwrap();
+    stat.birthtime = birthtime;
+    stat.mtime = mtime;
+  }
+
+  pub(crate) fn set_flags(&mut self, path: &str, flags: u32) {
+    self.files.get_mut(Path::new(path)).unwrap().flags = flags;
+  }
+
+  /// Make CoreFoundation show the volume at `mount_point` as `name`.
+  pub(crate) fn localize(&mut self, mount_point: &str, name: &str) {
+    self
+      .display_names
+      .insert(PathBuf::from(mount_point), name.to_owned());
+  }
+
+  /// Make the volume name lookups through `sources` fail.
+  pub(crate) fn fail_volume_names(&mut self, sources: &[VolumeNameSource]) {
+    self.failing_names = sources.to_vec();
+  }
+
+  fn volume_name(&self, volume_path: &str, source: VolumeNameSource) -> Option<String> {
+    self.name_lookups.borrow_mut().push(source);
+    if self.failing_names.contains(&source) {
+      return None;
+    }
+    if let Some(name) = self.display_names.get(Path::new(volume_path)) {
+      if source == VolumeNameSource::CoreFoundation {
+        return Some(name.clone());
+      }
+    }
+    self
+      .mounts
+      .values()
+      .find(|(stat, _)| stat.mount_point == Path::new(volume_path))
+      .map(|(_, name)| name.clone())
+  }
+}
+
+impl MetadataSource for FakeVolumes {
+  fn stat(&self, path: &Path) -> io::Result<FileStat> {
+    let path = self.resolve(path);
+    if self.unreadable.contains(&path) {
+      return Err(io::ErrorKind::PermissionDenied.into());
+    }
+    self
+      .files
+      .get(&path)
+      .cloned()
+      .ok_or_else(|| io::ErrorKind::NotFound.into())
+  }
+
+  fn statfs(&self, path: &Path) -> io::Result<FsStat> {
+    let dev = self.stat(path)?.dev;
+    Ok(self.mounts[&dev].0.clone())
+  }
+
+  fn mounts(&self) -> io::Result<Vec<FsStat>> {
+    Ok(self.mounts.values().map(|(stat, _)| stat.clone()).collect())
+  }
+
+  fn file_ids(&self, path: &Path) -> io::Result<FileIds> {
+    let path = self.resolve(path);
+    if let Some(ids) = self.file_ids.get(&path) {
+      return ids.ok_or_else(|| io::ErrorKind::PermissionDenied.into());
+    }
+    let parent = path.parent().unwrap_or(&path);
+    Ok(FileIds {
+      id: self.stat(&path)?.ino,
+      parent_id: self.stat(parent)?.ino,
+    })
+  }
+
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+    self.stat(path)?;
+    Ok(self.resolve(path))
+  }
+
+  fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+    let path = self.resolve(path);
+    if !self.stat(&path)?.is_dir {
+      return Err(io::ErrorKind::NotADirectory.into());
+    }
+    let files = self.files.keys().map(|file| (file, false));
+    let symlinks = self.symlinks.keys().map(|link| (link, true));
+    Ok(
+      files
+        .chain(symlinks)
+        .filter(|(entry, _)| entry.parent() == Some(&path))
+        .map(|(entry, is_symlink)| DirEntry {
+          name: entry.file_name().unwrap_or_default().to_owned(),
+          is_symlink,
+        })
+        .collect(),
+    )
+  }
+
+  fn cf_volume_name(&self, volume_path: &str) -> Option<String> {
+    self.volume_name(volume_path, VolumeNameSource::CoreFoundation)
+  }
+
+  fn attrlist_volume_name(&self, volume_path: &str) -> Option<String> {
+    self.volume_name(volume_path, VolumeNameSource::Attrlist)
+  }
+
+  fn disk_image(&self, volume_path: &Path) -> Option<PathBuf> {
+    self.images.get(volume_path).cloned()
+  }
+
+  fn disk_description(&self, volume_path: &Path) -> Option<DiskDescription> {
+    let (dev, _) = self
+      .mounts
+      .iter()
+      .find(|(_, (stat, _))| stat.mount_point == volume_path)?;
+    self.disks.get(dev).copied().flatten()
+  }
+
+  fn path_for_id(&self, volume_path: &Path, id: u64) -> Option<PathBuf> {
+    let dev = self.stat(volume_path).ok()?.dev;
+    self
+      .files
+      .iter()
+      .find(|(_, stat)| stat.dev == dev && stat.ino == id)
+      .map(|(path, _)| path.clone())
+  }
+
+  fn volume_uuid(&self, volume_path: &Path) -> Option<VolumeUuid> {
+    self.uuids.get(&self.stat(volume_path).ok()?.dev).copied()
+  }
+}
+
+/// A blank 1 MB HFS+ disk image named `<volume name>.dmg`, attached at a
+/// private mount point for as long as it lives.
+#[cfg(target_os = "macos")]
+pub(crate) struct DiskImage {
+  dir: PathBuf,
+  pub(crate) image: PathBuf,
+  pub(crate) mount_point: PathBuf,
+}
+
+#[cfg(target_os = "macos")]
+impl DiskImage {
+  /// `tag` keeps the images of tests running at the same time apart.
+  pub(crate) fn attach(tag: &str, volume_name: &str) -> Self {
+    // Canonical, so that paths in records match the ones built here.
+    let dir = std::env::temp_dir()
+      .canonicalize()
+      .unwrap()
+      .join(format!("macos-alias-{tag}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let image = DiskImage {
+      image: dir.join(format!("{volume_name}.dmg")),
+      mount_point: dir.join("mnt"),
+      dir,
+    };
+    hdiutil(&[
+      "create".as_ref(),
+      "-size".as_ref(),
+      "1m".as_ref(),
+      "-fs".as_ref(),
+      "HFS+".as_ref(),
+      "-volname".as_ref(),
+      volume_name.as_ref(),
+      image.image.as_os_str(),
+    ]);
+    hdiutil(&[
+      "attach".as_ref(),
+      "-nobrowse".as_ref(),
+      "-mountpoint".as_ref(),
+      image.mount_point.as_os_str(),
+      image.image.as_os_str(),
+    ]);
+    image
+  }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for DiskImage {
+  fn drop(&mut self) {
+    hdiutil(&["detach".as_ref(), self.mount_point.as_os_str()]);
+    std::fs::remove_dir_all(&self.dir).unwrap();
+  }
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn hdiutil(args: &[&std::ffi::OsStr]) {
+  let status = std::process::Command::new("/usr/bin/hdiutil")
+    .args(args)
+    .status()
+    .unwrap();
+  assert!(status.success(), "hdiutil {args:?}");
+}
@@ -0,0 +1,580 @@
+use std::time::SystemTime;
+
+use crate::{DateRounding, Error, Result};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum TargetType {
+  File = 0,
+  Directory = 1,
+}
+
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum VolumeType {
+  Local = 0,
+  Network,
+  Floppy400,
+  Floppy800,
+  Floppy1400,
+  Other,
+}
+
+impl AsRef<str> for VolumeType {
+  fn as_ref(&self) -> &str {
+    match self {
+      VolumeType::Local => "local",
+      VolumeType::Network => "network",
+      VolumeType::Floppy400 => "floppy400",
+      VolumeType::Floppy800 => "floppy800",
+      VolumeType::Floppy1400 => "floppy1400",
+      VolumeType::Other => "other",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum VolumeSignature {
+  #[cfg_attr(feature = "serde", serde(rename = "BD"))]
+  Bd,
+  #[cfg_attr(feature = "serde", serde(rename = "H+"))]
+  HPlus,
+  #[cfg_attr(feature = "serde", serde(rename = "HX"))]
+  Hx,
+}
+
+impl AsRef<str> for VolumeSignature {
+  fn as_ref(&self) -> &str {
+    match self {
+      VolumeSignature::Bd => "BD",
+      VolumeSignature::HPlus => "H+",
+      VolumeSignature::Hx => "HX",
+    }
+  }
+}
+
+/// Everything that goes into an alias record.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Info {
+  /// 2 for everything this crate writes. [`crate::decode`] also reads
+  /// version 3 records, whose names only live in the unicode extras.
+  pub version: u16,
+  pub target: Target,
+  pub volume: Volume,
+  pub parent: Parent,
+  pub extra: Vec<Extra>,
+  /// Fixed-size fields with no better home, kept so that decoded records
+  /// re-encode bit-exact.
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub classic: ClassicFields,
+}
+
+impl Info {
+  /// The first extra of type `type_`.
+  pub fn extra(&self, type_: i16) -> Option<&Extra> {
+    self.extra.iter().find(|e| e.type_ == type_)
+  }
+
+  /// The absolute posix path of the target, joined from the mount point
+  /// (type 19) and volume-relative path (type 18) extras.
+  pub fn posix_path(&self) -> Option<String> {
+    self.posix_path_on(std::str::from_utf8(&self.extra(19)?.data).ok()?)
+  }
+
+  /// The posix path of the target on the volume mounted at `volume_path`,
+  /// which may be somewhere other than the mount point it was recorded at.
+  pub(crate) fn posix_path_on(&self, volume_path: &str) -> Option<String> {
+    let relative_path = std::str::from_utf8(&self.extra(18)?.data).ok()?;
+    if relative_path.is_empty() {
+      // An alias to the volume itself.
+      return Some(volume_path.to_owned());
+    }
+    // Records made by node-alias on `/` leave off the `/` between them.
+    Some(format!(
+      "{}/{}",
+      volume_path.trim_end_matches('/'),
+      relative_path.trim_start_matches('/')
+    ))
+  }
+
+  /// Round the target and volume creation dates to the whole seconds the
+  /// record holds, the way `rounding` says. Encoding otherwise drops their
+  /// milliseconds, as Finder does.
+  pub fn round_dates(&mut self, rounding: DateRounding) {
+    self.target.created = crate::time::whole_seconds(self.target.created, rounding);
+    self.volume.created = crate::time::whole_seconds(self.volume.created, rounding);
+  }
+
+  /// The UUID of the target's volume, for records made with
+  /// [`volume_uuid`](crate::CreateOptions::volume_uuid) or
+  /// [`embed_volume_uuid`](crate::CreateOptions::embed_volume_uuid).
+  pub fn volume_uuid(&self) -> Option<VolumeUuid> {
+    Some(VolumeUuid(
+      self
+        .extra(VOLUME_UUID_EXTRA)?
+        .data
+        .as_slice()
+        .try_into()
+        .ok()?,
+    ))
+  }
+
+  /// The record of the disk image the target's volume was mounted from
+  /// (type 20), for targets on disk images.
+  pub fn disk_image(&self) -> Option<Info> {
+    crate::decode(&self.extra(20)?.data).ok()
+  }
+}
+
+/// The fixed-size fields of the record that `Info` does not otherwise model.
+///
+/// The defaults are what every record this crate creates carries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ClassicFields {
+  /// Application-specific tag at the start of the record.
+  pub user_type: [u8; 4],
+  pub file_type: [u8; 4],
+  pub file_creator: [u8; 4],
+  pub nlvl_from: i16,
+  pub nlvl_to: i16,
+  pub volume_attributes: u32,
+  pub volume_fs_id: u16,
+  pub reserved: [u8; 10],
+}
+
+impl Default for ClassicFields {
+  fn default() -> Self {
+    ClassicFields {
+      user_type: [0; 4],
+      // I have only encountered 00 00 00 00
+      file_type: [0; 4],
+      file_creator: [0; 4],
+      // I have only encountered -1
+      nlvl_from: -1,
+      nlvl_to: -1,
+      volume_attributes: crate::filesystem::HFS_VOLUME_ATTRIBUTES,
+      volume_fs_id: 0x0000,
+      reserved: [0; 10],
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Target {
+  #[cfg_attr(feature = "serde", serde(rename = "type"))]
+  pub type_: TargetType,
+  pub filename: String,
+  pub id: u32,
+  #[cfg_attr(feature = "serde", serde(with = "crate::time::millis"))]
+  pub created: SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Volume {
+  pub name: String,
+  #[cfg_attr(feature = "serde", serde(with = "crate::time::millis"))]
+  pub created: SystemTime,
+  pub signature: VolumeSignature,
+  #[cfg_attr(feature = "serde", serde(rename = "type"))]
+  pub type_: VolumeType,
+}
+
+impl Volume {
+  /// Fails for a type and signature no Mac formatted together: floppies are
+  /// HFS (or FAT) volumes, signed `BD`, since HFS+ needs 32 MB.
+  ///
+  /// [`crate::encode`] does not check this, so decoded records always
+  /// re-encode.
+  pub fn check_kind(&self) -> Result<()> {
+    let floppy = matches!(
+      self.type_,
+      VolumeType::Floppy400 | VolumeType::Floppy800 | VolumeType::Floppy1400
+    );
+    if floppy && self.signature != VolumeSignature::Bd {
+      return Err(Error::InvalidInfo(format!(
+        "A {} volume is signed BD, not {}.",
+        self.type_.as_ref(),
+        self.signature.as_ref()
+      )));
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Parent {
+  pub id: u32,
+  pub name: String,
+}
+
+/// A tagged field appended after the fixed-size part of the record.
+///
+/// With the `serde` feature, well-known extras also carry their decoded
+/// [`ExtraValue`] as `value`. When deserializing, `value` takes precedence
+/// over `data`, so either may be edited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Serialize, Deserialize),
+  serde(into = "json::Extra", try_from = "json::Extra")
+)]
+pub struct Extra {
+  pub type_: i16,
+  pub length: u16,
+  pub data: Vec<u8>,
+  /// The byte following odd-length data, zero in well-formed records.
+  pub padding: u8,
+}
+
+/// The type of the extra holding the 16 bytes of the volume's UUID. Finder
+/// writes no such extra; this crate uses the number bookmarks give the
+/// volume UUID property, which no alias extra type is known to take.
+pub const VOLUME_UUID_EXTRA: i16 = 0x2011;
+
+/// A volume's UUID, as `getattrlist(2)` reports it in `ATTR_VOL_UUID`.
+/// It is written and parsed in the uppercase hyphenated form
+/// `diskutil info` shows, e.g. `0A81F3B1-51D9-3335-B3E3-169C3640360D`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VolumeUuid(pub [u8; 16]);
+
+impl std::fmt::Display for VolumeUuid {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    for (i, byte) in self.0.iter().enumerate() {
+      if matches!(i, 4 | 6 | 8 | 10) {
+        f.write_str("-")?;
+      }
+      write!(f, "{byte:02X}")?;
+    }
+    Ok(())
+  }
+}
+
+impl std::str::FromStr for VolumeUuid {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    let invalid = || {
+      Error::InvalidInfo(format!(
+        "{s:?} is not a UUID like 0A81F3B1-51D9-3335-B3E3-169C3640360D."
+      ))
+    };
+    let groups: Vec<&str> = s.split('-').collect();
+    if groups.iter().map(|group| group.len()).ne([8, 4, 4, 4, 12])
+      || !groups
+        .iter()
+        .all(|group| group.bytes().all(|b| b.is_ascii_hexdigit()))
+    {
+      return Err(invalid());
+    }
+    let hex = groups.concat();
+    let mut uuid = [0; 16];
+    for (byte, pair) in uuid.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+      let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+      *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+    }
+    Ok(VolumeUuid(uuid))
+  }
+}
+
+/// The meaning of a well-known extra.
+///
+/// Types 0 (parent name) and 2 (carbon path) hold Mac OS Roman text; 18
+/// (volume-relative path) and 19 (mount point) hold UTF-8 text; 14 (file
+/// name) and 15 (volume name) hold a length-prefixed UTF-16BE string; 1
+/// (parent id) holds a big-endian number; [`VOLUME_UUID_EXTRA`] holds a
+/// UUID, given as a string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum ExtraValue {
+  String(String),
+  Number(u32),
+}
+
+impl Extra {
+  pub fn new(type_: i16, data: Vec<u8>) -> Self {
+    Extra {
+      type_,
+      length: data.len() as u16,
+      data,
+      padding: 0,
+    }
+  }
+
+  /// An extra of type `type_` holding `value`, encoded the way that type
+  /// expects.
+  pub fn from_value(type_: i16, value: ExtraValue) -> Result<Self> {
+    let data = match (type_, value) {
+      (0 | 2, ExtraValue::String(s)) => crate::macroman::encode(&s),
+      (18 | 19, ExtraValue::String(s)) => s.into_bytes(),
+      (14 | 15, ExtraValue::String(s)) => crate::create::unicode_name(&s),
+      (1, ExtraValue::Number(n)) => n.to_be_bytes().to_vec(),
+      (VOLUME_UUID_EXTRA, ExtraValue::String(s)) => s.parse::<VolumeUuid>()?.0.to_vec(),
+      (type_, value) => {
+        return Err(Error::InvalidInfo(format!(
+          "Extra type {type_} cannot hold the value {value:?}."
+        )))
+      }
+    };
+    if data.len() > u16::MAX as usize {
+      return Err(Error::InvalidInfo(format!(
+        "Extra type {type_} would hold {} bytes, more than the {} an extra can.",
+        data.len(),
+        u16::MAX
+      )));
+    }
+    Ok(Extra::new(type_, data))
+  }
+
+  /// The decoded value of a well-known extra, or `None` for other types and
+  /// for data that does not decode exactly.
+  pub fn value(&self) -> Option<ExtraValue> {
+    match self.type_ {
+      0 | 2 => Some(ExtraValue::String(crate::macroman::decode(&self.data))),
+      18 | 19 => String::from_utf8(self.data.clone())
+        .ok()
+        .map(ExtraValue::String),
+      14 | 15 => {
+        let (length, units) = self.data.split_first_chunk::<2>()?;
+        if units.len() != u16::from_be_bytes(*length) as usize * 2 {
+          return None;
+        }
+        let units: Vec<u16> = units
+          .chunks_exact(2)
+          .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+          .collect();
+        String::from_utf16(&units).ok().map(ExtraValue::String)
+      }
+      1 => Some(ExtraValue::Number(u32::from_be_bytes(
+        self.data.as_slice().try_into().ok()?,
+      ))),
+      VOLUME_UUID_EXTRA => Some(ExtraValue::String(
+        VolumeUuid(self.data.as_slice().try_into().ok()?).to_string(),
+      )),
+      _ => None,
+    }
+  }
+}
+
+/// The JSON shape of [`Extra`].
+#[cfg(feature = "serde")]
+mod json {
+  use serde::{Deserialize, Serialize};
+
+  use super::ExtraValue;
+
+  #[derive(Serialize, Deserialize)]
+  pub struct Extra {
+    #[serde(rename = "type")]
+    type_: i16,
+    #[serde(default)]
+    length: Option<u16>,
+    #[serde(default, with = "super::base64_data")]
+    data: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    padding: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<ExtraValue>,
+  }
+
+  fn is_zero(value: &u8) -> bool {
+    *value == 0
+  }
+
+  impl From<super::Extra> for Extra {
+    fn from(extra: super::Extra) -> Self {
+      Extra {
+        type_: extra.type_,
+        length: Some(extra.length),
+        value: extra.value(),
+        data: Some(extra.data),
+        padding: extra.padding,
+      }
+    }
+  }
+
+  impl TryFrom<Extra> for super::Extra {
+    type Error = crate::Error;
+
+    fn try_from(json: Extra) -> crate::Result<Self> {
+      let extra = match (json.value, json.data) {
+        (Some(value), _) => super::Extra::from_value(json.type_, value)?,
+        (None, Some(data)) => super::Extra {
+          length: json.length.unwrap_or(data.len() as u16),
+          ..super::Extra::new(json.type_, data)
+        },
+        (None, None) => {
+          return Err(crate::Error::InvalidInfo(format!(
+            "Extra type {} needs either data or a value.",
+            json.type_
+          )))
+        }
+      };
+      Ok(super::Extra {
+        padding: json.padding,
+        ..extra
+      })
+    }
+  }
+}
+
+#[cfg(feature = "serde")]
+mod base64_data {
+  use base64::Engine;
+  use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+  pub fn serialize<S: Serializer>(
+    value: &Option<Vec<u8>>,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    match value {
+      Some(value) => {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(value))
+      }
+      None => serializer.serialize_none(),
+    }
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Option<Vec<u8>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+      .map(|encoded| {
+        base64::engine::general_purpose::STANDARD
+          .decode(encoded)
+          .map_err(D::Error::custom)
+      })
+      .transpose()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::fixture::fixture_info;
+
+  #[test]
+  #[cfg(feature = "serde")]
+  fn json_round_trip() {
+    use crate::fixture::fixture_bytes;
+    use crate::Info;
+
+    let json = serde_json::to_string(&crate::decode(&fixture_bytes()).unwrap()).unwrap();
+    let info: Info = serde_json::from_str(&json).unwrap();
+    assert_eq!(info, fixture_info());
+    assert_eq!(crate::encode(&info).unwrap(), fixture_bytes());
+  }
+
+  #[test]
+  fn posix_path() {
+    assert_eq!(
+      fixture_info().posix_path().as_deref(),
+      Some("/Volumes/Test Title/.background/TestBkg.tiff")
+    );
+  }
+
+  #[test]
+  fn volume_uuid_strings() {
+    let uuid: super::VolumeUuid = "0a81f3b1-51d9-3335-b3e3-169c3640360d".parse().unwrap();
+    assert_eq!(uuid.0[..4], [0x0a, 0x81, 0xf3, 0xb1]);
+    assert_eq!(uuid.to_string(), "0A81F3B1-51D9-3335-B3E3-169C3640360D");
+    for s in [
+      "0A81F3B151D93335B3E3169C3640360D",
+      "0A81F3B1-51D9-3335-B3E3-169C3640360",
+      "0A81F3B1-51D9-3335-B3E3-169C3640360G",
+      "+A81F3B1-51D9-3335-B3E3-169C3640360D",
+    ] {
+      assert!(
+        matches!(
+          s.parse::<super::VolumeUuid>(),
+          Err(crate::Error::InvalidInfo(_))
+        ),
+        "{s}"
+      );
+    }
+  }
+
+  #[test]
+  #[cfg(feature = "serde")]
+  fn json_shape() {
+    let json = serde_json::to_value(fixture_info()).unwrap();
+    assert_eq!(json["target"]["type"], "file");
+    assert_eq!(json["target"]["created"], 1388686808000i64);
+    assert_eq!(json["volume"]["signature"], "H+");
+    assert_eq!(json["volume"]["type"], "other");
+    assert_eq!(json["extra"][0]["data"], "LmJhY2tncm91bmQ=");
+    let values: Vec<serde_json::Value> = json["extra"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|e| e["value"].clone())
+      .collect();
+    assert_eq!(
+      serde_json::Value::from(values),
+      serde_json::json!([
+        ".background",
+        19,
+        "Test Title:.background:\0TestBkg.tiff",
+        "Test Title",
+        "/.background/TestBkg.tiff",
+        "/Volumes/Test Title",
+      ])
+    );
+  }
+
+  #[test]
+  #[cfg(feature = "serde")]
+  fn json_values_take_precedence() {
+    let mut json = serde_json::to_value(fixture_info()).unwrap();
+    let extras = json["extra"].as_array_mut().unwrap();
+    // A value without data, and an edited value next to stale data.
+    extras[0] = serde_json::json!({ "type": 0, "value": ".background" });
+    extras[3]["value"] = "My App".into();
+    let info: crate::Info = serde_json::from_value(json).unwrap();
+    assert_eq!(info.extra[0], fixture_info().extra[0]);
+    assert_eq!(info.extra[3].data, crate::create::unicode_name("My App"));
+
+    let json = serde_json::json!({ "type": 1, "value": "nineteen" });
+    assert!(serde_json::from_value::<crate::Extra>(json).is_err());
+  }
+
+  #[test]
+  #[cfg(feature = "serde")]
+  fn json_float_dates() {
+    let mut json = serde_json::to_value(fixture_info()).unwrap();
+    json["target"]["created"] = 1388686808000.0.into();
+    let info: crate::Info = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(info, fixture_info());
+
+    json["target"]["created"] = 1388686808000.5.into();
+    assert!(serde_json::from_value::<crate::Info>(json).is_err());
+  }
+
+  #[test]
+  fn extra_values() {
+    use crate::{Extra, ExtraValue};
+
+    for extra in fixture_info().extra {
+      let value = extra.value().unwrap();
+      assert_eq!(Extra::from_value(extra.type_, value).unwrap(), extra);
+    }
+    // Unknown types, and known ones that do not decode exactly.
+    assert_eq!(Extra::new(20, b"data".to_vec()).value(), None);
+    assert_eq!(Extra::new(15, vec![0, 2, 0, 65]).value(), None);
+    assert_eq!(Extra::new(1, vec![0, 19]).value(), None);
+    assert!(Extra::from_value(14, ExtraValue::Number(1)).is_err());
+  }
+}
@@ -0,0 +1,88 @@
+#![deny(clippy::all)]
+
+//! Encoder for classic macOS alias records.
+//!
+//! This crate holds the record format and the filesystem lookups behind the
+//! `@napi-rs/macos-alias` Node.js package, so that Rust tools (DMG builders,
+//! `.DS_Store` writers) can use them without pulling in Node bindings.
+//!
+//! Only [`create_for_path`] needs macOS; [`build_info`] and [`encode`] work on
+//! every platform, so records can be synthesized on Linux CI from known
+//! metadata.
+//!
+//! Nothing is cached between calls and there is no global state, so every
+//! function can be called from several threads at once.
+//!
+//! With the `serde` feature, [`Info`] and its parts implement `Serialize` and
+//! `Deserialize`, with dates as Unix milliseconds and extra data as base64.
+
+mod alias_file;
+mod bookmark;
+mod create;
+mod decode;
+mod ds_store;
+mod encode;
+mod error;
+mod file_url;
+mod filesystem;
+#[cfg(test)]
+mod fixture;
+mod info;
+mod macroman;
+mod many;
+mod options;
+mod resolve;
+mod resource_fork;
+#[cfg(any(target_os = "macos", test))]
+mod source;
+mod staged;
+mod status;
+#[cfg(all(target_os = "macos", feature = "system-alias"))]
+mod system;
+mod time;
+mod tree;
+mod update;
+mod validate;
+mod verify;
+#[cfg(target_os = "macos")]
+mod volume;
+mod volume_info;
+mod warning;
+
+pub use alias_file::{
+  is_alias_file, read_alias_file, write_alias_file, AliasFileContents, AliasFileFormat,
+  AliasFileOptions,
+};
+pub use bookmark::{
+  bookmark_from_info, bookmark_key_name, create_bookmark, decode_bookmark, encode_bookmark,
+  info_from_bookmark, parse_bookmark, Bookmark, BookmarkEntry, BookmarkOptions, BookmarkToc,
+  BookmarkValue, DecodedBookmark,
+};
+pub use create::{
+  add_extra_records, apply_extras, apply_layout, build_info, build_info_with, create_for_fd,
+  create_for_path, create_for_path_with, file_ids_for_path, info_for_path_with,
+  info_with_sources_for_path, info_with_warnings_for_path, DateSource, DateSources, FileIds,
+};
+pub use decode::{decode, decode_all, decode_prefix};
+pub use ds_store::{ds_store_blob, DS_STORE_MAX_ALIAS_LENGTH, DS_STORE_NODE_SIZE};
+pub use encode::{encode, encode_into, encode_into_with, encode_with, encoded_length};
+pub use error::{Error, Result};
+pub use file_url::file_url_to_path;
+pub use info::{
+  ClassicFields, Extra, ExtraValue, Info, Parent, Target, TargetType, Volume, VolumeSignature,
+  VolumeType, VolumeUuid, VOLUME_UUID_EXTRA,
+};
+pub use many::create_many;
+pub use options::{Compat, CreateOptions, DateRounding, Extras, Layout, VolumeNameSource};
+pub use resolve::{resolve_alias, Prefer, ResolveOptions};
+pub use resource_fork::{extract_from_resource_fork, wrap_as_resource_fork};
+pub use staged::{create_background_alias, create_for_staged, info_for_staged, StagedTarget};
+pub use status::{relink_alias, stat_target, TargetStatus};
+#[cfg(all(target_os = "macos", feature = "system-alias"))]
+pub use system::system_alias_for;
+pub use tree::{create_for_tree, create_for_tree_filtered, TreeEntry, TreeOptions};
+pub use update::{apply_changes, apply_rebase, rebase_alias, update_alias, Changes, Rebase};
+pub use validate::{validate, Problem};
+pub use verify::{verify_path, Mismatch, Verification};
+pub use volume_info::{list_volumes, volume_info, VolumeInfo, VolumeTypeSource};
+pub use warning::Warning;
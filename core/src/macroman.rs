@@ -0,0 +1,158 @@
+//! Mac OS Roman, the legacy encoding of the classic name fields and the
+//! type 0 and 2 extras.
+
+/// The characters of bytes 0x80 to 0xFF; the low half is ASCII.
+const HIGH: [char; 128] = [
+  '\u{00C4}', '\u{00C5}', '\u{00C7}', '\u{00C9}', '\u{00D1}', '\u{00D6}', '\u{00DC}', '\u{00E1}',
+  '\u{00E0}', '\u{00E2}', '\u{00E4}', '\u{00E3}', '\u{00E5}', '\u{00E7}', '\u{00E9}', '\u{00E8}',
+  '\u{00EA}', '\u{00EB}', '\u{00ED}', '\u{00EC}', '\u{00EE}', '\u{00EF}', '\u{00F1}', '\u{00F3}',
+  '\u{00F2}', '\u{00F4}', '\u{00F6}', '\u{00F5}', '\u{00FA}', '\u{00F9}', '\u{00FB}', '\u{00FC}',
+  '\u{2020}', '\u{00B0}', '\u{00A2}', '\u{00A3}', '\u{00A7}', '\u{2022}', '\u{00B6}', '\u{00DF}',
+  '\u{00AE}', '\u{00A9}', '\u{2122}', '\u{00B4}', '\u{00A8}', '\u{2260}', '\u{00C6}', '\u{00D8}',
+  '\u{221E}', '\u{00B1}', '\u{2264}', '\u{2265}', '\u{00A5}', '\u{00B5}', '\u{2202}', '\u{2211}',
+  '\u{220F}', '\u{03C0}', '\u{222B}', '\u{00AA}', '\u{00BA}', '\u{03A9}', '\u{00E6}', '\u{00F8}',
+  '\u{00BF}', '\u{00A1}', '\u{00AC}', '\u{221A}', '\u{0192}', '\u{2248}', '\u{2206}', '\u{00AB}',
+  '\u{00BB}', '\u{2026}', '\u{00A0}', '\u{00C0}', '\u{00C3}', '\u{00D5}', '\u{0152}', '\u{0153}',
+  '\u{2013}', '\u{2014}', '\u{201C}', '\u{201D}', '\u{2018}', '\u{2019}', '\u{00F7}', '\u{25CA}',
+  '\u{00FF}', '\u{0178}', '\u{2044}', '\u{20AC}', '\u{2039}', '\u{203A}', '\u{FB01}', '\u{FB02}',
+  '\u{2021}', '\u{00B7}', '\u{201A}', '\u{201E}', '\u{2030}', '\u{00C2}', '\u{00CA}', '\u{00C1}',
+  '\u{00CB}', '\u{00C8}', '\u{00CD}', '\u{00CE}', '\u{00CF}', '\u{00CC}', '\u{00D3}', '\u{00D4}',
+  '\u{F8FF}', '\u{00D2}', '\u{00DA}', '\u{00DB}', '\u{00D9}', '\u{0131}', '\u{02C6}', '\u{02DC}',
+  '\u{00AF}', '\u{02D8}', '\u{02D9}', '\u{02DA}', '\u{00B8}', '\u{02DD}', '\u{02DB}', '\u{02C7}',
+];
+
+/// Decomposed sequences (as stored by HFS+) of the characters in `HIGH`:
+/// base character, combining mark and the byte they compose to.
+const COMPOSED: [(char, char, u8); 53] = [
+  ('A', '\u{0308}', 0x80),
+  ('A', '\u{030A}', 0x81),
+  ('C', '\u{0327}', 0x82),
+  ('E', '\u{0301}', 0x83),
+  ('N', '\u{0303}', 0x84),
+  ('O', '\u{0308}', 0x85),
+  ('U', '\u{0308}', 0x86),
+  ('a', '\u{0301}', 0x87),
+  ('a', '\u{0300}', 0x88),
+  ('a', '\u{0302}', 0x89),
+  ('a', '\u{0308}', 0x8A),
+  ('a', '\u{0303}', 0x8B),
+  ('a', '\u{030A}', 0x8C),
+  ('c', '\u{0327}', 0x8D),
+  ('e', '\u{0301}', 0x8E),
+  ('e', '\u{0300}', 0x8F),
+  ('e', '\u{0302}', 0x90),
+  ('e', '\u{0308}', 0x91),
+  ('i', '\u{0301}', 0x92),
+  ('i', '\u{0300}', 0x93),
+  ('i', '\u{0302}', 0x94),
+  ('i', '\u{0308}', 0x95),
+  ('n', '\u{0303}', 0x96),
+  ('o', '\u{0301}', 0x97),
+  ('o', '\u{0300}', 0x98),
+  ('o', '\u{0302}', 0x99),
+  ('o', '\u{0308}', 0x9A),
+  ('o', '\u{0303}', 0x9B),
+  ('u', '\u{0301}', 0x9C),
+  ('u', '\u{0300}', 0x9D),
+  ('u', '\u{0302}', 0x9E),
+  ('u', '\u{0308}', 0x9F),
+  ('=', '\u{0338}', 0xAD),
+  ('A', '\u{0300}', 0xCB),
+  ('A', '\u{0303}', 0xCC),
+  ('O', '\u{0303}', 0xCD),
+  ('y', '\u{0308}', 0xD8),
+  ('Y', '\u{0308}', 0xD9),
+  ('A', '\u{0302}', 0xE5),
+  ('E', '\u{0302}', 0xE6),
+  ('A', '\u{0301}', 0xE7),
+  ('E', '\u{0308}', 0xE8),
+  ('E', '\u{0300}', 0xE9),
+  ('I', '\u{0301}', 0xEA),
+  ('I', '\u{0302}', 0xEB),
+  ('I', '\u{0308}', 0xEC),
+  ('I', '\u{0300}', 0xED),
+  ('O', '\u{0301}', 0xEE),
+  ('O', '\u{0302}', 0xEF),
+  ('O', '\u{0300}', 0xF1),
+  ('U', '\u{0301}', 0xF2),
+  ('U', '\u{0302}', 0xF3),
+  ('U', '\u{0300}', 0xF4),
+];
+
+/// What unmappable characters are replaced with.
+const SUBSTITUTE: u8 = b'?';
+
+/// `s` in Mac OS Roman. Decomposed accents are composed first; characters
+/// without a Mac OS Roman byte become `?`.
+pub(crate) fn encode(s: &str) -> Vec<u8> {
+  let mut bytes: Vec<u8> = Vec::with_capacity(s.len());
+  let mut previous = None;
+  for c in s.chars() {
+    if let Some(&(_, _, byte)) = COMPOSED
+      .iter()
+      .find(|&&(base, mark, _)| previous == Some(base) && mark == c)
+    {
+      *bytes.last_mut().unwrap() = byte;
+      previous = None;
+      continue;
+    }
+    bytes.push(if c.is_ascii() {
+      c as u8
+    } else {
+      HIGH
+        .iter()
+        .position(|&h| h == c)
+        .map_or(SUBSTITUTE, |index| 0x80 + index as u8)
+    });
+    previous = Some(c);
+  }
+  bytes
+}
+
+/// Whether every character of `s` has a Mac OS Roman byte, so that
+/// [`encode`] loses nothing but the decomposition of accents.
+pub(crate) fn is_representable(s: &str) -> bool {
+  let substitutes = encode(s).iter().filter(|&&b| b == SUBSTITUTE).count();
+  substitutes == s.matches(SUBSTITUTE as char).count()
+}
+
+/// Mac OS Roman `bytes` as a string. Every byte maps to a character, so
+/// `encode(&decode(bytes)) == bytes`.
+pub(crate) fn decode(bytes: &[u8]) -> String {
+  bytes
+    .iter()
+    .map(|&b| {
+      if b < 0x80 {
+        b as char
+      } else {
+        HIGH[b as usize - 0x80]
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  #[test]
+  fn accented_latin() {
+    assert_eq!(super::encode("café"), b"caf\x8e");
+    // As HFS+ stores it, with a combining acute accent.
+    assert_eq!(super::encode("cafe\u{301}"), b"caf\x8e");
+    assert_eq!(super::encode("Ärger über Öl"), b"\x80rger \x9fber \x85l");
+    assert_eq!(super::decode(b"caf\x8e"), "café");
+  }
+
+  #[test]
+  fn unmappable_characters() {
+    assert_eq!(super::encode("日本語"), b"???");
+    assert_eq!(super::encode("Привет.txt"), b"??????.txt");
+    assert!(!super::is_representable("Привет.txt"));
+    assert!(super::is_representable("Why? cafe\u{301}.txt"));
+  }
+
+  #[test]
+  fn every_byte_round_trips() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    assert_eq!(super::encode(&super::decode(&bytes)), bytes);
+  }
+}
@@ -0,0 +1,101 @@
+//! Records for several targets at once, as DMG builds that link the
+//! background, the app and `/Applications` need.
+
+use std::path::Path;
+
+#[cfg(any(target_os = "macos", test))]
+use crate::source::MetadataSource;
+#[cfg(not(target_os = "macos"))]
+use crate::Error;
+use crate::{CreateOptions, Result};
+
+/// The records of `targets`, each made with its options, in the order
+/// given, with one set of volume lookups for all of them rather than one
+/// per target. Failing to create one record does not stop the others.
+#[cfg(target_os = "macos")]
+pub fn create_many<P: AsRef<Path>>(targets: &[(P, CreateOptions)]) -> Result<Vec<Result<Vec<u8>>>> {
+  let source = crate::source::Cached::new(&crate::volume::System);
+  let record = |target_path: &Path, options: &CreateOptions| {
+    let resolved;
+    let target_path = if options.resolve_aliases {
+      resolved = crate::alias_file::resolve_alias_chain(target_path)?;
+      &resolved
+    } else {
+      target_path
+    };
+    let target_path = target_path.to_str().ok_or_else(|| {
+      crate::Error::InvalidPath("The target path is not a valid UTF-8 string.".to_owned())
+    })?;
+    crate::create::without_materializing(target_path, options, || {
+      record_in(&source, target_path, options)
+    })
+  };
+  Ok(
+    targets
+      .iter()
+      .map(|(target_path, options)| record(target_path.as_ref(), options))
+      .collect(),
+  )
+}
+
+/// The records of `targets`, each made with its options.
+///
+/// Looking up the targets needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn create_many<P: AsRef<Path>>(targets: &[(P, CreateOptions)]) -> Result<Vec<Result<Vec<u8>>>> {
+  let _ = targets;
+  Err(Error::Unsupported("Creating several aliases"))
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn record_in(
+  source: &impl MetadataSource,
+  target_path: &str,
+  options: &CreateOptions,
+) -> Result<Vec<u8>> {
+  let info = crate::create::info_for_path_in(source, target_path, options)?;
+  crate::encode_with(&info, options.compat)
+}
+
+#[cfg(test)]
+mod test {
+  use crate::fixture::FakeVolumes;
+  use crate::source::Cached;
+  use crate::{CreateOptions, Error, Layout, VolumeNameSource};
+
+  const TEST_BKG: &str = "/Volumes/Test Title/.background/TestBkg.tiff";
+
+  #[test]
+  fn shared_lookups() {
+    let volumes = FakeVolumes::test_title();
+    let source = Cached::new(&volumes);
+    let options = CreateOptions {
+      volume_name_source: VolumeNameSource::CoreFoundation,
+      ..Default::default()
+    };
+    let minimal = CreateOptions {
+      layout: Layout::Minimal,
+      ..options.clone()
+    };
+    let targets = [
+      (TEST_BKG, &options),
+      ("/Volumes/Test Title/.background", &minimal),
+      ("/Volumes/Test Title/Missing.tiff", &options),
+    ];
+    let records: Vec<_> = targets
+      .iter()
+      .map(|(path, options)| super::record_in(&source, path, options))
+      .collect();
+    assert_eq!(
+      *volumes.name_lookups.borrow(),
+      [VolumeNameSource::CoreFoundation]
+    );
+
+    // Each record is the one the target gets alone, with its own options.
+    for ((path, options), record) in targets.iter().zip(&records).take(2) {
+      let info = crate::create::info_for_path_in(&volumes, path, options).unwrap();
+      assert_eq!(*record.as_ref().unwrap(), crate::encode(&info).unwrap());
+    }
+    assert!(matches!(records[2], Err(Error::TargetNotFound(_))));
+  }
+}
@@ -0,0 +1,193 @@
+use std::time::SystemTime;
+
+use crate::{Extra, VolumeSignature, VolumeType, VolumeUuid};
+
+/// Whose output a record should reproduce.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compat {
+  #[default]
+  Native,
+  /// Byte-for-byte what node-alias writes for the same inputs: classic names
+  /// and type 0 hold UTF-8 rather than Mac OS Roman, their lengths count
+  /// UTF-16 code units, and creation dates keep
+  /// their milliseconds until they are rounded to Apple seconds. The
+  /// volume is found by walking up the path as given until the device
+  /// changes, so files behind the firmlinks into the data volume (such as
+  /// `/Users`) or behind symlinks onto another volume get that directory as
+  /// their volume instead of its mount point.
+  NodeAlias,
+}
+
+impl Compat {
+  /// How the implementation `self` reproduces drops the milliseconds of
+  /// creation dates.
+  pub fn date_rounding(self) -> DateRounding {
+    match self {
+      Compat::Native => DateRounding::Floor,
+      Compat::NodeAlias => DateRounding::Round,
+    }
+  }
+}
+
+/// How creation dates lose their milliseconds, since the classic date
+/// fields hold whole seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateRounding {
+  /// Drop them, as Finder does: `12:00:00.999` is recorded as `12:00:00`.
+  #[default]
+  Floor,
+  /// Round to the nearest second, halves up, as node-alias does.
+  Round,
+}
+
+/// Which parts of the record get filled in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+  /// Every extra and classic field this crate knows how to write.
+  #[default]
+  Full,
+  /// Only the posix path extras (types 18 and 19). The classic volume and
+  /// file name fields are left empty and the parent name and id extras are
+  /// dropped; the ids and dates in the header are kept.
+  Minimal,
+  /// Like `Minimal`, but also keeping the unicode file and volume name
+  /// extras (types 14 and 15).
+  MinimalWithNames,
+}
+
+/// Which families of extras a record gets. Extras of types outside these
+/// families, such as ones added with
+/// [`extra_records`](CreateOptions::extra_records), are always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extras {
+  /// The parent directory's name and id and the carbon path (types 0, 1
+  /// and 2), which is all classic Mac OS reads.
+  pub classic: bool,
+  /// The UTF-16 file and volume names (types 14 and 15). Without them the
+  /// classic name fields have to spell the names in full.
+  pub unicode: bool,
+  /// The posix paths of the target and its volume (types 18 and 19).
+  pub posix: bool,
+  /// The record of the disk image the volume is mounted from (type 20).
+  pub disk_image: bool,
+}
+
+impl Extras {
+  /// Whether extras of type `type_` are kept.
+  pub fn keeps(&self, type_: i16) -> bool {
+    match type_ {
+      0..=2 => self.classic,
+      14 | 15 => self.unicode,
+      18 | 19 => self.posix,
+      20 => self.disk_image,
+      _ => true,
+    }
+  }
+}
+
+impl Default for Extras {
+  fn default() -> Self {
+    Extras {
+      classic: true,
+      unicode: true,
+      posix: true,
+      disk_image: true,
+    }
+  }
+}
+
+/// Where the name of the target's volume is looked up first. Each source
+/// falls back to the ones after it, and looking up the volume only fails
+/// when none of them knows a name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VolumeNameSource {
+  /// `kCFURLVolumeNameKey`, the name Finder shows. It can be localized,
+  /// e.g. for the startup volume on a non-English system, and it can fail
+  /// in sandboxed and hardened-runtime processes.
+  CoreFoundation,
+  /// `ATTR_VOL_NAME` from `getattrlist(2)`, the name stored on the volume.
+  /// This is the canonical name Finder records, and the same on every
+  /// machine that mounts the volume.
+  #[default]
+  Attrlist,
+  /// The last component of the mount point, with `:` read as `/`. The
+  /// volume mounted at `/` has none.
+  MountPoint,
+}
+
+/// Knobs for [`crate::create_for_path_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CreateOptions {
+  pub compat: Compat,
+  pub layout: Layout,
+  /// Which families of extras to write; see [`crate::apply_extras`].
+  pub extras: Extras,
+  /// Extras to add, replacing any of the same type; see
+  /// [`crate::add_extra_records`].
+  pub extra_records: Vec<Extra>,
+  /// Append `extra_records` after the other extras instead of slotting them
+  /// into the canonical order.
+  pub append_raw: bool,
+  /// Create the record even if the target does not exist yet, as a file with
+  /// id 0 and its parent directory's dates. The parent still has to exist.
+  pub allow_missing: bool,
+  /// Fail with [`crate::Error::WouldMaterialize`] instead of downloading
+  /// cloud content, such as an iCloud file whose contents are not on disk.
+  pub no_io: bool,
+  /// If the target is a Finder alias file, follow it (and any aliases it
+  /// points to) and record the final target instead.
+  pub resolve_aliases: bool,
+  /// Leave out the record of the disk image (extra 20) that lets Finder
+  /// mount the image again when the target is on a mounted disk image.
+  pub no_disk_image: bool,
+  /// Where the volume name is looked up first; skipping CoreFoundation
+  /// saves its cost when the name on disk will do.
+  pub volume_name_source: VolumeNameSource,
+  /// The volume type to record instead of the one looked up, e.g.
+  /// [`VolumeType::Floppy800`] for a volume standing in for a floppy.
+  pub volume_type: Option<VolumeType>,
+  /// The volume name to record instead of the one looked up, in the header,
+  /// extra 15 and the carbon path, and as the parent name of targets at the
+  /// root of the volume. The mount point in extra 19 stays as it is.
+  pub volume_name: Option<String>,
+  /// The volume signature to record instead of the filesystem's, e.g.
+  /// [`VolumeSignature::Bd`] for a classic HFS image. See
+  /// [`Volume::check_kind`](crate::Volume::check_kind) for the combinations
+  /// allowed.
+  pub volume_signature: Option<VolumeSignature>,
+  /// The target's id to record instead of its inode number, e.g. the
+  /// CNID it will get in an HFS+ image built from a staging directory.
+  pub target_id: Option<u32>,
+  /// The parent directory's id to record instead of its inode number, in
+  /// the header and in extra 1.
+  pub parent_id: Option<u32>,
+  /// The parent directory's name to record, in the header, extra 0 and the
+  /// carbon path. The posix path keeps the real one.
+  pub parent_name: Option<String>,
+  /// The target's creation date to record instead of the file's, e.g. a
+  /// fixed one so that builds on different machines make the same record.
+  pub target_created: Option<SystemTime>,
+  /// The volume's creation date to record instead of the volume's.
+  pub volume_created: Option<SystemTime>,
+  /// A volume UUID to record in extra
+  /// [`VOLUME_UUID_EXTRA`](crate::VOLUME_UUID_EXTRA), e.g. the one a disk
+  /// image will be built with, so that resolving the record can tell
+  /// volumes of the same name apart.
+  pub volume_uuid: Option<VolumeUuid>,
+  /// Record the UUID of the target's volume, from `ATTR_VOL_UUID`, when
+  /// `volume_uuid` gives none. Volumes without one, such as FAT ones, get
+  /// no extra.
+  pub embed_volume_uuid: bool,
+  /// How the creation dates are rounded to whole seconds, if not the way
+  /// [`Compat::date_rounding`] says.
+  pub date_rounding: Option<DateRounding>,
+  /// Fail with [`crate::Error::RecordExceedsMaxSize`] rather than make a
+  /// record longer than this, e.g.
+  /// [`DS_STORE_MAX_ALIAS_LENGTH`](crate::DS_STORE_MAX_ALIAS_LENGTH) for
+  /// one that has to fit a `.DS_Store` node.
+  pub max_size: Option<usize>,
+  /// Add the high-resolution creation dates of the volume and the target
+  /// (extras 16 and 17), which keep the fraction of a second the classic
+  /// date fields drop. Finder writes them; full layouts only.
+  pub hires_dates: bool,
+}
@@ -0,0 +1,272 @@
+//! Finding the file or directory a record points to now.
+
+#[cfg(any(target_os = "macos", test))]
+use std::path::Path;
+
+#[cfg(not(target_os = "macos"))]
+use crate::Error;
+use crate::Result;
+#[cfg(any(target_os = "macos", test))]
+use crate::{source::MetadataSource, Info};
+
+/// Which of the record's two ways to its target is tried first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Prefer {
+  /// The target id, then the posix path, as the Alias Manager does. This
+  /// follows a target that was renamed or moved on its volume.
+  #[default]
+  Id,
+  /// The posix path, then the target id. This suits records made from a
+  /// staging directory, whose ids mean nothing on the mounted image, and
+  /// targets that were replaced in place.
+  Path,
+}
+
+/// How [`resolve_alias`] looks for the target.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolveOptions {
+  pub prefer: Prefer,
+  /// Attach the disk image the record says its volume was mounted from
+  /// (extra 20) when the volume is not mounted. The image is left attached.
+  pub mount_if_needed: bool,
+  /// Accept something at the record's posix path even though the record's
+  /// volume is not mounted there, e.g. a leftover mount point directory or
+  /// a copy of the files on another volume.
+  pub cross_volume_fallback: bool,
+}
+
+/// The path of the target of `record` on the live filesystem, found by its
+/// id and its posix path on the record's volume: the mounted volume with
+/// its volume UUID, if it has one, or else the volume mounted where it
+/// says. Failing both, the target's name is looked for in the directory
+/// with the record's parent id, which finds a target replaced in a
+/// directory that was moved. `None` if nothing leads anywhere.
+#[cfg(target_os = "macos")]
+pub fn resolve_alias(record: &[u8], options: &ResolveOptions) -> Result<Option<String>> {
+  let info = crate::decode(record)?;
+  let source = crate::volume::System;
+  if options.mount_if_needed && crate::status::mounted_volume(&source, &info).is_none() {
+    if let Some(image) = info.disk_image().and_then(|image| image.posix_path()) {
+      crate::volume::attach_disk_image(Path::new(&image))?;
+    }
+  }
+  Ok(resolve_in(&source, &info, options))
+}
+
+/// The path of the target of `record` on the live filesystem.
+///
+/// Looking up the target needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn resolve_alias(record: &[u8], _options: &ResolveOptions) -> Result<Option<String>> {
+  crate::decode(record)?;
+  Err(Error::Unsupported("Resolving an alias"))
+}
+
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn resolve_in(
+  source: &impl MetadataSource,
+  info: &Info,
+  options: &ResolveOptions,
+) -> Option<String> {
+  let exists = |path: &String| source.stat(Path::new(path)).is_ok();
+  let on_volume = crate::status::mounted_volume(source, info).and_then(|volume| {
+    let by_path = || info.posix_path_on(&volume.to_string_lossy()).filter(exists);
+    let by_id = || {
+      (info.target.id != 0)
+        .then(|| source.path_for_id(&volume, info.target.id.into()))
+        .flatten()
+        .map(|path| path.to_string_lossy().into_owned())
+    };
+    let by_parent = || {
+      let name = info
+        .extra(18)
+        .and_then(|extra| std::str::from_utf8(&extra.data).ok())
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned)
+        .unwrap_or_else(|| crate::create::posix_name(&info.target.filename));
+      (info.parent.id != 0)
+        .then(|| source.path_for_id(&volume, info.parent.id.into()))
+        .flatten()
+        .map(|parent| parent.join(name).to_string_lossy().into_owned())
+        .filter(exists)
+    };
+    match options.prefer {
+      Prefer::Id => by_id().or_else(by_path),
+      Prefer::Path => by_path().or_else(by_id),
+    }
+    .or_else(by_parent)
+  });
+  on_volume.or_else(|| {
+    options
+      .cross_volume_fallback
+      .then(|| info.posix_path().filter(exists))
+      .flatten()
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::{Prefer, ResolveOptions};
+  use crate::fixture::{fixture_info, FakeVolumes};
+
+  const TEST_BKG: &str = "/Volumes/Test Title/.background/TestBkg.tiff";
+  const MOVED: &str = "/Volumes/Test Title/TestBkg.tiff";
+  const RENAMED: &str = "/Volumes/Test Title/.background/Renamed.tiff";
+
+  fn resolved(volumes: &FakeVolumes, prefer: Prefer) -> Option<String> {
+    let options = ResolveOptions {
+      prefer,
+      ..Default::default()
+    };
+    super::resolve_in(volumes, &fixture_info(), &options)
+  }
+
+  #[test]
+  fn fresh() {
+    let volumes = FakeVolumes::test_title();
+    for prefer in [Prefer::Id, Prefer::Path] {
+      assert_eq!(resolved(&volumes, prefer).as_deref(), Some(TEST_BKG));
+    }
+  }
+
+  #[test]
+  fn renamed_or_moved() {
+    for to in [RENAMED, MOVED] {
+      let mut volumes = FakeVolumes::test_title();
+      let stat = volumes.remove(TEST_BKG);
+      volumes.insert_stat(to, stat);
+      // Nothing is left at the path, so both find the id.
+      for prefer in [Prefer::Id, Prefer::Path] {
+        assert_eq!(
+          resolved(&volumes, prefer).as_deref(),
+          Some(to),
+          "{prefer:?}"
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn replaced() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.remove(TEST_BKG);
+    volumes.file(TEST_BKG, 3, 21, 1388686900);
+    // The id is gone, so both take the new file at the path.
+    for prefer in [Prefer::Id, Prefer::Path] {
+      assert_eq!(resolved(&volumes, prefer).as_deref(), Some(TEST_BKG));
+    }
+  }
+
+  #[test]
+  fn moved_and_replaced() {
+    let mut volumes = FakeVolumes::test_title();
+    let stat = volumes.remove(TEST_BKG);
+    volumes.insert_stat(MOVED, stat);
+    volumes.file(TEST_BKG, 3, 21, 1388686900);
+    assert_eq!(resolved(&volumes, Prefer::Id).as_deref(), Some(MOVED));
+    assert_eq!(resolved(&volumes, Prefer::Path).as_deref(), Some(TEST_BKG));
+  }
+
+  #[test]
+  fn replaced_in_moved_parent() {
+    const REPLACED: &str = "/Volumes/Test Title/Moved/TestBkg.tiff";
+    let mut volumes = FakeVolumes::test_title();
+    volumes.remove(TEST_BKG);
+    let parent = volumes.remove("/Volumes/Test Title/.background");
+    volumes.insert_stat("/Volumes/Test Title/Moved", parent);
+    volumes.file(REPLACED, 3, 21, 1388686900);
+    for prefer in [Prefer::Id, Prefer::Path] {
+      assert_eq!(resolved(&volumes, prefer).as_deref(), Some(REPLACED));
+    }
+
+    // A different name in the parent is not the target.
+    let stat = volumes.remove(REPLACED);
+    volumes.insert_stat("/Volumes/Test Title/Moved/Other.tiff", stat);
+    assert_eq!(resolved(&volumes, Prefer::Id), None);
+  }
+
+  #[test]
+  fn gone() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.remove(TEST_BKG);
+    for prefer in [Prefer::Id, Prefer::Path] {
+      assert_eq!(resolved(&volumes, prefer), None);
+    }
+  }
+
+  #[test]
+  fn cross_volume_fallback() {
+    // The volume is not mounted, but a copy of its files is where it was.
+    let mut volumes = FakeVolumes::new();
+    volumes.mount(1, "/", "apfs", "Macintosh HD");
+    volumes.directory("/", 1, 2, 0);
+    volumes.directory("/Volumes", 1, 3, 0);
+    volumes.directory("/Volumes/Test Title", 1, 4, 0);
+    volumes.directory("/Volumes/Test Title/.background", 1, 5, 0);
+    volumes.file(TEST_BKG, 1, 20, 0);
+    for prefer in [Prefer::Id, Prefer::Path] {
+      let options = |cross_volume_fallback| ResolveOptions {
+        prefer,
+        cross_volume_fallback,
+        ..Default::default()
+      };
+      let info = fixture_info();
+      assert_eq!(super::resolve_in(&volumes, &info, &options(false)), None);
+      assert_eq!(
+        super::resolve_in(&volumes, &info, &options(true)).as_deref(),
+        Some(TEST_BKG)
+      );
+    }
+
+    // On the record's volume, the fallback changes nothing.
+    let mut volumes = FakeVolumes::test_title();
+    volumes.remove(TEST_BKG);
+    let options = ResolveOptions {
+      cross_volume_fallback: true,
+      ..Default::default()
+    };
+    assert_eq!(super::resolve_in(&volumes, &fixture_info(), &options), None);
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn mount_if_needed() {
+    use std::time::Duration;
+
+    let image = crate::fixture::DiskImage::attach("resolve", "Resolve Test");
+    let target = image.mount_point.join("background.tiff");
+    std::fs::write(&target, b"").unwrap();
+    let record = crate::create_for_path_with(
+      target.to_str().unwrap(),
+      &crate::CreateOptions {
+        embed_volume_uuid: true,
+        ..Default::default()
+      },
+    )
+    .unwrap();
+    let options = ResolveOptions {
+      mount_if_needed: true,
+      ..Default::default()
+    };
+    crate::fixture::hdiutil(&["detach".as_ref(), image.mount_point.as_os_str()]);
+    let unmounted = super::resolve_alias(&record, &Default::default());
+    let remounted = super::resolve_alias(&record, &options);
+    // Put the image back where the fixture detaches it from.
+    if let Ok(Some(path)) = &remounted {
+      let volume = std::path::Path::new(path).parent().unwrap();
+      crate::fixture::hdiutil(&["detach".as_ref(), volume.as_os_str()]);
+      std::thread::sleep(Duration::from_millis(100));
+    }
+    crate::fixture::hdiutil(&[
+      "attach".as_ref(),
+      "-nobrowse".as_ref(),
+      "-mountpoint".as_ref(),
+      image.mount_point.as_os_str(),
+      image.image.as_os_str(),
+    ]);
+    assert_eq!(unmounted.unwrap(), None);
+    let remounted = remounted.unwrap().unwrap();
+    assert!(remounted.ends_with("/background.tiff"), "{remounted}");
+  }
+}
@@ -0,0 +1,283 @@
+//! The resource fork container that classic alias files keep their record
+//! in, as an `alis` resource.
+//!
+//! The layout follows Inside Macintosh: More Macintosh Toolbox, "Resource
+//! Manager": a 16-byte header with the offsets and lengths of the resource
+//! data and the resource map, reserved space up to offset 256, the data of
+//! each resource behind a `u32` length, and the map. The map lists resource
+//! types, each with a reference list of ids, names and data offsets, and
+//! ends with the names as Pascal strings. Every number is big-endian.
+
+use crate::{Error, Result};
+
+/// Where the resource data starts. The Resource Manager reserves the bytes
+/// between the header and the data for the system and the application.
+const DATA_OFFSET: usize = 256;
+/// The header, copied at the start of the map, then the handle to the next
+/// map, the file reference number, the attributes and the offsets of the
+/// type and name lists.
+const MAP_HEADER_LENGTH: usize = 16 + 4 + 2 + 2 + 2 + 2;
+const TYPE_ENTRY_LENGTH: usize = 8;
+const REFERENCE_LENGTH: usize = 12;
+/// The name offset of a reference without a name.
+const NO_NAME: u16 = 0xffff;
+const ALIS: &[u8; 4] = b"alis";
+
+/// Resource fork bytes for an alias file: `alias` as the only resource, of
+/// type `alis`, with `id` and, optionally, `name`. Finder gives the alias
+/// resource id 0 and no name.
+///
+/// The name is stored in Mac OS Roman and may be at most 255 bytes long.
+pub fn wrap_as_resource_fork(alias: &[u8], id: i16, name: Option<&str>) -> Result<Vec<u8>> {
+  let name = name.map(crate::macroman::encode);
+  if let Some(name) = &name {
+    if name.len() > u8::MAX as usize {
+      return Err(Error::InvalidInfo(format!(
+        "The resource name is {} bytes in Mac OS Roman, more than the 255 a resource name can hold.",
+        name.len()
+      )));
+    }
+  }
+  let data_length = 4 + alias.len();
+  let type_list_offset = MAP_HEADER_LENGTH;
+  let name_list_offset = type_list_offset + 2 + TYPE_ENTRY_LENGTH + REFERENCE_LENGTH;
+  let map_offset = DATA_OFFSET + data_length;
+  let map_length = name_list_offset + name.as_ref().map_or(0, |name| 1 + name.len());
+  let too_large = |_| Error::RecordTooLarge(map_offset + map_length);
+  let header = [
+    u32::try_from(DATA_OFFSET).map_err(too_large)?,
+    u32::try_from(map_offset).map_err(too_large)?,
+    u32::try_from(data_length).map_err(too_large)?,
+    u32::try_from(map_length).map_err(too_large)?,
+  ];
+
+  let mut fork = Vec::with_capacity(map_offset + map_length);
+  for field in header {
+    fork.extend_from_slice(&field.to_be_bytes());
+  }
+  fork.resize(DATA_OFFSET, 0);
+  fork.extend_from_slice(&(alias.len() as u32).to_be_bytes());
+  fork.extend_from_slice(alias);
+
+  for field in header {
+    fork.extend_from_slice(&field.to_be_bytes());
+  }
+  // The handle to the next map and the file reference number, which the
+  // Resource Manager fills in when it opens the fork, and the attributes.
+  fork.extend_from_slice(&[0; 4 + 2 + 2]);
+  fork.extend_from_slice(&(type_list_offset as u16).to_be_bytes());
+  fork.extend_from_slice(&(name_list_offset as u16).to_be_bytes());
+
+  // One type, with one resource. Both counts are stored minus one.
+  fork.extend_from_slice(&0u16.to_be_bytes());
+  fork.extend_from_slice(ALIS);
+  fork.extend_from_slice(&0u16.to_be_bytes());
+  fork.extend_from_slice(&((2 + TYPE_ENTRY_LENGTH) as u16).to_be_bytes());
+
+  fork.extend_from_slice(&id.to_be_bytes());
+  fork.extend_from_slice(&name.as_ref().map_or(NO_NAME, |_| 0).to_be_bytes());
+  // No attributes, the data at offset 0, and the handle, which is only
+  // used in memory.
+  fork.extend_from_slice(&[0; 1 + 3 + 4]);
+
+  if let Some(name) = &name {
+    fork.push(name.len() as u8);
+    fork.extend_from_slice(name);
+  }
+  Ok(fork)
+}
+
+fn invalid(offset: usize, reason: impl Into<String>) -> Error {
+  Error::InvalidRecord {
+    offset,
+    reason: reason.into(),
+  }
+}
+
+fn bytes<'a>(fork: &'a [u8], offset: usize, length: usize, what: &str) -> Result<&'a [u8]> {
+  offset
+    .checked_add(length)
+    .and_then(|end| fork.get(offset..end))
+    .ok_or_else(|| {
+      invalid(
+        offset,
+        format!(
+          "{what} needs {length} bytes but only {} remain",
+          fork.len().saturating_sub(offset)
+        ),
+      )
+    })
+}
+
+/// Moves the offset of an [`Error::InvalidRecord`] about a part of the fork
+/// that starts at `base` to be relative to the whole fork.
+fn within(base: usize) -> impl Fn(Error) -> Error {
+  move |err| match err {
+    Error::InvalidRecord { offset, reason } => invalid(base + offset, reason),
+    other => other,
+  }
+}
+
+fn u16_at(fork: &[u8], offset: usize, what: &str) -> Result<usize> {
+  let bytes = bytes(fork, offset, 2, what)?;
+  Ok(u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+fn u32_at(fork: &[u8], offset: usize, what: &str) -> Result<usize> {
+  let bytes = bytes(fork, offset, 4, what)?;
+  Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+/// The data of the first `alis` resource in the resource fork `fork`, such
+/// as the contents of a classic alias file's `..namedfork/rsrc`.
+///
+/// Malformed forks, and forks without an `alis` resource, are reported as
+/// [`Error::InvalidRecord`] with the offset of the offending bytes.
+pub fn extract_from_resource_fork(fork: &[u8]) -> Result<&[u8]> {
+  let data_offset = u32_at(fork, 0, "the resource data offset")?;
+  let map_offset = u32_at(fork, 4, "the resource map offset")?;
+  let data_length = u32_at(fork, 8, "the resource data length")?;
+  let map_length = u32_at(fork, 12, "the resource map length")?;
+  let data = bytes(fork, data_offset, data_length, "the resource data")?;
+  let map = bytes(fork, map_offset, map_length, "the resource map")?;
+  let in_map = within(map_offset);
+  let in_data = within(data_offset);
+
+  let type_list = u16_at(map, 24, "the type list offset").map_err(&in_map)?;
+  // The count is stored minus one, so 0xffff means no types at all.
+  let types = (u16_at(map, type_list, "the type count").map_err(&in_map)? + 1) & 0xffff;
+  for index in 0..types {
+    let entry = type_list + 2 + index * TYPE_ENTRY_LENGTH;
+    if bytes(map, entry, 4, "a resource type").map_err(&in_map)? != ALIS {
+      continue;
+    }
+    let reference =
+      type_list + u16_at(map, entry + 6, "the reference list offset").map_err(&in_map)?;
+    bytes(map, reference, REFERENCE_LENGTH, "a resource reference").map_err(&in_map)?;
+    // The attributes byte shares the word with the 24-bit data offset.
+    let offset =
+      u32_at(map, reference + 4, "a resource data offset").map_err(&in_map)? & 0x00ff_ffff;
+    let length = u32_at(data, offset, "a resource length").map_err(&in_data)?;
+    return bytes(data, offset + 4, length, "the alis resource").map_err(&in_data);
+  }
+  Err(invalid(
+    map_offset + type_list,
+    "the resource fork has no alis resource",
+  ))
+}
+
+#[cfg(test)]
+mod test {
+  use crate::fixture::fixture_bytes;
+  use crate::Error;
+
+  #[test]
+  fn layout() {
+    let alias = fixture_bytes();
+    let fork = super::wrap_as_resource_fork(&alias, 0, None).unwrap();
+    let u32_at = |offset: usize| u32::from_be_bytes(fork[offset..offset + 4].try_into().unwrap());
+    let map = 256 + 4 + alias.len();
+
+    assert_eq!(
+      [u32_at(0), u32_at(4), u32_at(8), u32_at(12)],
+      [256, map as u32, 4 + alias.len() as u32, 50]
+    );
+    assert_eq!(fork[16..256], [0; 240]);
+    assert_eq!(u32_at(256) as usize, alias.len());
+    assert_eq!(fork[260..map], alias);
+    // The copy of the header, the next map handle, the file reference number
+    // and the attributes, then the type and name list offsets.
+    assert_eq!(fork[map..map + 16], fork[..16]);
+    assert_eq!(fork[map + 16..map + 24], [0; 8]);
+    assert_eq!(fork[map + 24..map + 28], [0, 28, 0, 50]);
+    // One type, `alis`, with one resource whose references follow.
+    assert_eq!(fork[map + 28..map + 38], *b"\0\0alis\0\0\0\x0a");
+    // Id 0, no name, no attributes, data offset 0, no handle.
+    assert_eq!(fork[map + 38..], [0, 0, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    assert_eq!(super::extract_from_resource_fork(&fork).unwrap(), alias);
+  }
+
+  #[test]
+  fn id_and_name() {
+    let alias = fixture_bytes();
+    let fork = super::wrap_as_resource_fork(&alias, -16496, Some("Café")).unwrap();
+    let map = 256 + 4 + alias.len();
+
+    assert_eq!(fork[12..16], [0, 0, 0, 55]);
+    assert_eq!(fork[map + 38..map + 42], [0xbf, 0x90, 0, 0]);
+    assert_eq!(fork[map + 50..], *b"\x04Caf\x8e");
+    assert_eq!(super::extract_from_resource_fork(&fork).unwrap(), alias);
+
+    assert!(matches!(
+      super::wrap_as_resource_fork(&alias, 0, Some(&"x".repeat(256))),
+      Err(Error::InvalidInfo(_))
+    ));
+  }
+
+  /// A fork with an `icns` resource before two `alis` ones, laid out the
+  /// way ResEdit lays out forks: the map right after the header.
+  fn fork_with_types() -> Vec<u8> {
+    let data: Vec<u8> = [
+      &[0, 0, 0, 2, 0xaa, 0xbb][..],
+      &[0, 0, 0, 3, 1, 2, 3],
+      &[0, 0, 0, 1, 9],
+    ]
+    .concat();
+    let mut map = vec![0; 24];
+    map.extend_from_slice(&[0, 28, 0, 82]);
+    map.extend_from_slice(&[0, 1]);
+    map.extend_from_slice(b"icns\0\0\0\x12");
+    map.extend_from_slice(b"alis\0\x01\0\x1e");
+    map.extend_from_slice(&[0, 1, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0]);
+    map.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0]);
+    map.extend_from_slice(&[0, 1, 0xff, 0xff, 0, 0, 0, 13, 0, 0, 0, 0]);
+    map.extend_from_slice(b"\x01a");
+    let mut fork = [
+      16u32,
+      16 + data.len() as u32,
+      data.len() as u32,
+      map.len() as u32,
+    ]
+    .iter()
+    .flat_map(|field| field.to_be_bytes())
+    .collect::<Vec<_>>();
+    fork.extend_from_slice(&data);
+    fork.extend_from_slice(&map);
+    fork
+  }
+
+  #[test]
+  fn first_alis_resource() {
+    assert_eq!(
+      super::extract_from_resource_fork(&fork_with_types()).unwrap(),
+      [1, 2, 3]
+    );
+  }
+
+  #[test]
+  fn malformed() {
+    let alias = fixture_bytes();
+    let fork = super::wrap_as_resource_fork(&alias, 0, None).unwrap();
+    let map = 256 + 4 + alias.len();
+    let offset = |fork: &[u8]| match super::extract_from_resource_fork(fork) {
+      Err(Error::InvalidRecord { offset, .. }) => offset,
+      other => panic!("{other:?}"),
+    };
+
+    assert_eq!(offset(&fork[..10]), 8);
+    assert_eq!(offset(&fork[..fork.len() - 1]), map);
+    let mut other_type = fork.clone();
+    other_type[map + 30..map + 34].copy_from_slice(b"icns");
+    assert_eq!(offset(&other_type), map + 28);
+    let mut no_types = fork.clone();
+    no_types[map + 28..map + 30].copy_from_slice(&[0xff, 0xff]);
+    assert_eq!(offset(&no_types), map + 28);
+    let mut past_data = fork.clone();
+    past_data[map + 43] = 1;
+    assert_eq!(offset(&past_data), 256 + 0x1_0000);
+    let mut long_resource = fork;
+    long_resource[256..260].copy_from_slice(&1000u32.to_be_bytes());
+    assert_eq!(offset(&long_resource), 260);
+  }
+}
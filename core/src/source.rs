@@ -0,0 +1,348 @@
+//! The filesystem lookups behind [`create_for_path`](crate::create_for_path),
+//! behind a trait so tests can run them against simulated volumes.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::VolumeUuid;
+
+/// Where the user data volume of the system volume group is mounted.
+///
+/// On macOS 10.15 and later, directories like `/Users` are firmlinks from the
+/// read-only system volume into this volume. Finder records files reached
+/// through them as living on `/`, the volume group as a whole.
+const DATA_VOLUME: &str = "/System/Volumes/Data";
+
+/// What the record needs from `stat(2)`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileStat {
+  pub(crate) dev: u64,
+  pub(crate) ino: u64,
+  pub(crate) is_dir: bool,
+  pub(crate) is_file: bool,
+  /// `st_birthtime`, where the filesystem keeps creation dates.
+  pub(crate) birthtime: Option<i64>,
+  pub(crate) birthtime_nsec: i64,
+  pub(crate) mtime: Option<i64>,
+  pub(crate) mtime_nsec: i64,
+  pub(crate) ctime: i64,
+  pub(crate) ctime_nsec: i64,
+  /// `st_flags`, e.g. `SF_DATALESS`.
+  pub(crate) flags: u32,
+}
+
+#[cfg(target_os = "macos")]
+impl From<&std::fs::Metadata> for FileStat {
+  fn from(metadata: &std::fs::Metadata) -> Self {
+    use std::os::macos::fs::MetadataExt as _;
+    use std::os::unix::fs::MetadataExt;
+
+    FileStat {
+      dev: metadata.dev(),
+      ino: metadata.ino(),
+      is_dir: metadata.is_dir(),
+      is_file: metadata.is_file(),
+      birthtime: Some(metadata.st_birthtime()),
+      birthtime_nsec: metadata.st_birthtime_nsec(),
+      mtime: Some(metadata.mtime()),
+      mtime_nsec: metadata.mtime_nsec(),
+      ctime: metadata.ctime(),
+      ctime_nsec: metadata.ctime_nsec(),
+      flags: metadata.st_flags(),
+    }
+  }
+}
+
+pub(crate) use crate::create::FileIds;
+
+/// An entry of a directory listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DirEntry {
+  pub(crate) name: OsString,
+  /// Whether the entry itself is a symlink, which `stat` would follow.
+  pub(crate) is_symlink: bool,
+}
+
+/// What DiskArbitration's `DADiskCopyDescription` says about the media
+/// behind a volume. Each flag is `None` where the description leaves it out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct DiskDescription {
+  /// `kDADiskDescriptionVolumeNetworkKey`.
+  pub(crate) network: Option<bool>,
+  /// `kDADiskDescriptionMediaRemovableKey`, e.g. an SD card.
+  pub(crate) removable: Option<bool>,
+  /// `kDADiskDescriptionMediaEjectableKey`, e.g. a disk image.
+  pub(crate) ejectable: Option<bool>,
+  /// `kDADiskDescriptionDeviceInternalKey`: false for USB and Thunderbolt
+  /// drives.
+  pub(crate) internal: Option<bool>,
+}
+
+/// What the record needs from `statfs(2)`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FsStat {
+  /// `f_mntonname`, e.g. `/Volumes/Test Title`.
+  pub(crate) mount_point: PathBuf,
+  /// `f_mntfromname`, e.g. `/dev/disk3s1` or `//me@server/share`.
+  pub(crate) device: String,
+  /// `f_fstypename`, e.g. `apfs`, `hfs`, `msdos` or `exfat`.
+  pub(crate) fs_type: String,
+  /// `f_flags`, e.g. [`MNT_LOCAL`].
+  pub(crate) flags: u32,
+}
+
+/// The volume is mounted read-only.
+pub(crate) const MNT_RDONLY: u32 = 0x0000_0001;
+/// The volume is on a local disk rather than a network share.
+pub(crate) const MNT_LOCAL: u32 = 0x0000_1000;
+/// Finder does not show the volume, e.g. `/System/Volumes/VM`.
+pub(crate) const MNT_DONTBROWSE: u32 = 0x0010_0000;
+
+/// Answers the questions [`create_for_path`](crate::create_for_path) asks
+/// about the filesystem. The real one is [`System`](crate::volume::System).
+pub(crate) trait MetadataSource {
+  /// Follows symlinks, like `fs::metadata`.
+  fn stat(&self, path: &Path) -> io::Result<FileStat>;
+
+  fn statfs(&self, path: &Path) -> io::Result<FsStat>;
+
+  /// Every mounted volume, like `getmntinfo(3)`, without waiting on
+  /// unresponsive network mounts.
+  fn mounts(&self) -> io::Result<Vec<FsStat>>;
+
+  /// The ids Finder records for `path`, both from the same lookup. Follows
+  /// symlinks, like [`stat`](MetadataSource::stat).
+  fn file_ids(&self, path: &Path) -> io::Result<FileIds>;
+
+  /// Like `fs::canonicalize`.
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+  /// The entries of the directory at `path`, like `fs::read_dir`, in no
+  /// particular order.
+  fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>>;
+
+  /// The display name of the volume mounted at `volume_path`, from
+  /// CoreFoundation, if it can tell.
+  fn cf_volume_name(&self, volume_path: &str) -> Option<String>;
+
+  /// The name stored on the volume mounted at `volume_path`, from
+  /// `getattrlist(2)`.
+  fn attrlist_volume_name(&self, volume_path: &str) -> Option<String>;
+
+  /// The disk image the volume mounted at `volume_path` is attached from,
+  /// if it is one.
+  fn disk_image(&self, volume_path: &Path) -> Option<PathBuf>;
+
+  /// What DiskArbitration knows about the media of the volume mounted at
+  /// `volume_path`, or `None` if it cannot be asked, as in a sandbox.
+  fn disk_description(&self, volume_path: &Path) -> Option<DiskDescription>;
+
+  /// The current path of the file or directory with id `id` on the volume
+  /// mounted at `volume_path`.
+  fn path_for_id(&self, volume_path: &Path, id: u64) -> Option<PathBuf>;
+
+  /// The UUID of the volume mounted at `volume_path`, from `getattrlist(2)`,
+  /// if its filesystem keeps one.
+  fn volume_uuid(&self, volume_path: &Path) -> Option<VolumeUuid>;
+}
+
+/// A [`MetadataSource`] that remembers the volume lookups of `S`, which are
+/// the same for every file on a volume: the names, the disk image, the
+/// media and the UUID.
+pub(crate) struct Cached<'a, S> {
+  source: &'a S,
+  cf_names: RefCell<HashMap<String, Option<String>>>,
+  attrlist_names: RefCell<HashMap<String, Option<String>>>,
+  disk_images: RefCell<HashMap<PathBuf, Option<PathBuf>>>,
+  disk_descriptions: RefCell<HashMap<PathBuf, Option<DiskDescription>>>,
+  volume_uuids: RefCell<HashMap<PathBuf, Option<VolumeUuid>>>,
+}
+
+impl<'a, S: MetadataSource> Cached<'a, S> {
+  pub(crate) fn new(source: &'a S) -> Self {
+    Cached {
+      source,
+      cf_names: RefCell::default(),
+      attrlist_names: RefCell::default(),
+      disk_images: RefCell::default(),
+      disk_descriptions: RefCell::default(),
+      volume_uuids: RefCell::default(),
+    }
+  }
+}
+
+impl<S: MetadataSource> MetadataSource for Cached<'_, S> {
+  fn stat(&self, path: &Path) -> io::Result<FileStat> {
+    self.source.stat(path)
+  }
+
+  fn statfs(&self, path: &Path) -> io::Result<FsStat> {
+    self.source.statfs(path)
+  }
+
+  fn mounts(&self) -> io::Result<Vec<FsStat>> {
+    self.source.mounts()
+  }
+
+  fn file_ids(&self, path: &Path) -> io::Result<FileIds> {
+    self.source.file_ids(path)
+  }
+
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+    self.source.canonicalize(path)
+  }
+
+  fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+    self.source.read_dir(path)
+  }
+
+  fn cf_volume_name(&self, volume_path: &str) -> Option<String> {
+    let mut names = self.cf_names.borrow_mut();
+    names
+      .entry(volume_path.to_owned())
+      .or_insert_with(|| self.source.cf_volume_name(volume_path))
+      .clone()
+  }
+
+  fn attrlist_volume_name(&self, volume_path: &str) -> Option<String> {
+    let mut names = self.attrlist_names.borrow_mut();
+    names
+      .entry(volume_path.to_owned())
+      .or_insert_with(|| self.source.attrlist_volume_name(volume_path))
+      .clone()
+  }
+
+  fn disk_image(&self, volume_path: &Path) -> Option<PathBuf> {
+    let mut images = self.disk_images.borrow_mut();
+    images
+      .entry(volume_path.to_owned())
+      .or_insert_with(|| self.source.disk_image(volume_path))
+      .clone()
+  }
+
+  fn disk_description(&self, volume_path: &Path) -> Option<DiskDescription> {
+    *self
+      .disk_descriptions
+      .borrow_mut()
+      .entry(volume_path.to_owned())
+      .or_insert_with(|| self.source.disk_description(volume_path))
+  }
+
+  fn path_for_id(&self, volume_path: &Path, id: u64) -> Option<PathBuf> {
+    self.source.path_for_id(volume_path, id)
+  }
+
+  fn volume_uuid(&self, volume_path: &Path) -> Option<VolumeUuid> {
+    *self
+      .volume_uuids
+      .borrow_mut()
+      .entry(volume_path.to_owned())
+      .or_insert_with(|| self.source.volume_uuid(volume_path))
+  }
+}
+
+/// The mount point of the volume holding `path`, which should be canonical:
+/// the `statfs` `f_mntonname`, except that files on the data volume reached
+/// through a firmlink belong to the volume group mounted at `/`.
+pub(crate) fn mount_point(source: &impl MetadataSource, path: &Path) -> io::Result<PathBuf> {
+  let mount_point = source.statfs(path)?.mount_point;
+  if mount_point == Path::new(DATA_VOLUME) && !path.starts_with(DATA_VOLUME) {
+    return Ok(PathBuf::from("/"));
+  }
+  Ok(mount_point)
+}
+
+/// The mount point that contains `start_path`, from walking its ancestors
+/// until the device changes, as node-alias does. Unlike [`mount_point`],
+/// this stops at symlinks and firmlinks from another device.
+///
+/// The walk never goes above the mount point `statfs` reports, so it does
+/// not trigger automounts in the directories above it.
+pub(crate) fn find_volume<'a>(
+  source: &impl MetadataSource,
+  start_path: &'a Path,
+  start_stat: &FileStat,
+) -> io::Result<&'a Path> {
+  let mount = source.statfs(start_path)?.mount_point;
+  let mut last_dev = start_stat.dev;
+  let mut last_ino = start_stat.ino;
+  let mut last_path = start_path;
+
+  loop {
+    if last_path == mount.as_path() {
+      return Ok(last_path);
+    }
+    if let Some(parent_path) = last_path.parent() {
+      let parent_stat = source.stat(parent_path)?;
+
+      if parent_stat.dev != last_dev || parent_stat.ino == last_ino {
+        return Ok(last_path);
+      }
+
+      last_dev = parent_stat.dev;
+      last_ino = parent_stat.ino;
+      last_path = parent_path;
+    } else {
+      return Ok(last_path);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::Path;
+
+  use super::MetadataSource;
+  use crate::fixture::FakeVolumes;
+
+  #[test]
+  fn mount_point() {
+    let volumes = FakeVolumes::test_title();
+    let mount_point = |path| {
+      let path = volumes.canonicalize(Path::new(path)).unwrap();
+      super::mount_point(&volumes, &path).unwrap()
+    };
+    assert_eq!(
+      mount_point("/Volumes/Test Title/.background/TestBkg.tiff"),
+      Path::new("/Volumes/Test Title")
+    );
+    assert_eq!(
+      mount_point("/Volumes/Test Title"),
+      Path::new("/Volumes/Test Title")
+    );
+    assert_eq!(mount_point("/Users/me/bg.tiff"), Path::new("/"));
+    assert_eq!(
+      mount_point("/System/Volumes/Data/Users"),
+      Path::new("/System/Volumes/Data")
+    );
+    assert_eq!(
+      mount_point("/Users/me/Backgrounds/TestBkg.tiff"),
+      Path::new("/Volumes/Test Title")
+    );
+  }
+
+  #[test]
+  fn find_volume() {
+    let volumes = FakeVolumes::test_title();
+    let find = |path: &str| {
+      let path = Path::new(path);
+      let stat = volumes.stat(path).unwrap();
+      super::find_volume(&volumes, path, &stat)
+        .unwrap()
+        .to_owned()
+    };
+    assert_eq!(
+      find("/Volumes/Test Title/.background/TestBkg.tiff"),
+      Path::new("/Volumes/Test Title")
+    );
+    assert_eq!(find("/Users/me/bg.tiff"), Path::new("/Users"));
+    // The walk stops at the symlink.
+    assert_eq!(
+      find("/Users/me/Backgrounds/TestBkg.tiff"),
+      Path::new("/Users/me/Backgrounds")
+    );
+  }
+}
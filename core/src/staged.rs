@@ -0,0 +1,461 @@
+//! Records for files in the staging directory of a disk image that has not
+//! been built yet.
+
+#[cfg(any(target_os = "macos", test))]
+use std::path::Component;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(any(target_os = "macos", test))]
+use crate::{
+  create::{created, created_precisely, not_found, posix_name, precisely},
+  source::MetadataSource,
+  Changes, Compat, Rebase, TargetType,
+};
+use crate::{CreateOptions, Error, Info, Result};
+
+/// A file or directory staged for a disk image, to be recorded as it will be
+/// once the image is mounted at `/Volumes/<volume_name>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StagedTarget {
+  /// The directory whose contents become the root of the image.
+  pub staging_dir: PathBuf,
+  /// The name Finder will show for the mounted image.
+  pub volume_name: String,
+  /// Path of the target inside the image, e.g. `.background/bg.tiff`.
+  pub internal_path: String,
+  /// Whether the target is a directory. Only needed for targets that are
+  /// not staged yet; the record then gets their parent's dates.
+  pub target_is_dir: Option<bool>,
+  /// Id of the target on the image. Ids are not known before the image is
+  /// built, so they are 0 by default.
+  pub target_id: Option<u32>,
+  pub parent_id: Option<u32>,
+  /// Creation date of the image's volume. Defaults to the staging
+  /// directory's.
+  pub volume_created: Option<SystemTime>,
+}
+
+/// The record for `staged` as it will be on the mounted image: the dates
+/// come from the staged files, and the volume name, paths and extras from
+/// where the image will be mounted.
+///
+/// The image is never looked up, so the record carries no disk image extra
+/// (20) unless `options` adds one.
+#[cfg(target_os = "macos")]
+pub fn info_for_staged(staged: &StagedTarget, options: &CreateOptions) -> Result<Info> {
+  info_for_staged_in(&crate::volume::System, staged, options)
+}
+
+/// The record for `staged` as it will be on the mounted image.
+///
+/// Reading the staged files needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn info_for_staged(staged: &StagedTarget, options: &CreateOptions) -> Result<Info> {
+  let _ = (staged, options);
+  Err(Error::Unsupported("Creating an alias for a staged file"))
+}
+
+/// Like [`info_for_staged`], but encoded.
+pub fn create_for_staged(staged: &StagedTarget, options: &CreateOptions) -> Result<Vec<u8>> {
+  crate::encode_with(&info_for_staged(staged, options)?, options.compat)
+}
+
+/// The record for the background image of a disk image, as appdmg and
+/// Finder expect it in the `.DS_Store`: on the volume `volume_name`, mounted
+/// at `/Volumes/<volume_name>`, rather than in `staging_dir`.
+///
+/// `image_path` is the image inside the staging directory, either absolute
+/// or relative to it. A bare file name is taken to be in `.background/`.
+pub fn create_background_alias<P: AsRef<Path>, Q: AsRef<Path>>(
+  staging_dir: P,
+  image_path: Q,
+  volume_name: &str,
+  options: &CreateOptions,
+) -> Result<Vec<u8>> {
+  let staged = StagedTarget {
+    staging_dir: staging_dir.as_ref().to_owned(),
+    volume_name: volume_name.to_owned(),
+    internal_path: background_internal_path(staging_dir.as_ref(), image_path.as_ref())?,
+    ..Default::default()
+  };
+  create_for_staged(&staged, options)
+}
+
+/// Where `image_path` is inside the image built from `staging_dir`.
+fn background_internal_path(staging_dir: &Path, image_path: &Path) -> Result<String> {
+  let internal_path = if image_path.is_absolute() {
+    image_path
+      .strip_prefix(staging_dir)
+      .map_err(|_| {
+        Error::InvalidPath(format!(
+          "The background image {} is not inside the staging directory {}.",
+          image_path.display(),
+          staging_dir.display()
+        ))
+      })?
+      .to_owned()
+  } else if image_path.parent() == Some(Path::new("")) {
+    Path::new(".background").join(image_path)
+  } else {
+    image_path.to_owned()
+  };
+  internal_path.to_str().map(str::to_owned).ok_or_else(|| {
+    Error::InvalidPath("The background image path is not a valid UTF-8 string.".to_owned())
+  })
+}
+
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn info_for_staged_in(
+  source: &impl MetadataSource,
+  staged: &StagedTarget,
+  options: &CreateOptions,
+) -> Result<Info> {
+  let internal_path = Path::new(&staged.internal_path);
+  if internal_path.as_os_str().is_empty()
+    || !internal_path
+      .components()
+      .all(|c| matches!(c, Component::Normal(_)))
+  {
+    return Err(Error::InvalidPath(format!(
+      "The internal path {} must be relative to the root of the image, without `.` or `..`.",
+      staged.internal_path
+    )));
+  }
+  let staging_dir = staged.staging_dir.to_str().ok_or_else(|| {
+    Error::InvalidPath("The staging directory is not a valid UTF-8 string.".to_owned())
+  })?;
+  let staging_stat = source
+    .stat(Path::new(staging_dir))
+    .map_err(|err| not_found(staging_dir, err))?;
+  // Native records spell the path with its symlinks resolved.
+  let from_path = match options.compat {
+    Compat::Native => source
+      .canonicalize(Path::new(staging_dir))
+      .map_err(|err| not_found(staging_dir, err))?
+      .to_str()
+      .ok_or_else(|| {
+        Error::InvalidPath("The staging directory is not a valid UTF-8 string.".to_owned())
+      })?
+      .to_owned(),
+    Compat::NodeAlias => staging_dir.to_owned(),
+  };
+
+  let staged_options = CreateOptions {
+    compat: options.compat,
+    allow_missing: staged.target_is_dir.is_some(),
+    no_io: options.no_io,
+    no_disk_image: true,
+    volume_type: options.volume_type,
+    volume_signature: options.volume_signature,
+    ..Default::default()
+  };
+  let target_path = Path::new(&from_path).join(internal_path);
+  let target_path = target_path.to_str().unwrap_or_default();
+  let target_stat = source.stat(Path::new(target_path)).ok();
+  let missing = target_stat.is_none();
+  let mut info = crate::create::info_for_path_in(source, target_path, &staged_options)?;
+  if missing && staged.target_is_dir == Some(true) {
+    info.target.type_ = TargetType::Directory;
+  }
+
+  crate::apply_rebase(
+    &mut info,
+    &Rebase {
+      from_path: Some(from_path),
+      volume_path: format!("/Volumes/{}", posix_name(&staged.volume_name)),
+      volume_name: staged.volume_name.clone(),
+      zero_ids: true,
+      changes: Changes {
+        target_id: staged.target_id,
+        parent_id: staged.parent_id,
+        volume_created: Some(
+          staged
+            .volume_created
+            .unwrap_or_else(|| created(&staging_stat, options.compat).0),
+        ),
+        ..Default::default()
+      },
+    },
+  )?;
+  crate::create::apply_overrides(&mut info, options);
+  crate::apply_layout(&mut info, options.layout);
+  crate::apply_extras(&mut info, options.extras)?;
+  crate::add_extra_records(&mut info, &options.extra_records, options.append_raw)?;
+  if options.hires_dates && options.layout == crate::Layout::Full {
+    let target_created = options
+      .target_created
+      .or_else(|| target_stat.map(|stat| created_precisely(&stat, options.compat)));
+    let volume_created = options
+      .volume_created
+      .or(staged.volume_created)
+      .unwrap_or_else(|| created_precisely(&staging_stat, options.compat));
+    let (target, volume) = (info.target.created, info.volume.created);
+    crate::create::add_hires_dates(
+      &mut info,
+      precisely(target_created, target),
+      precisely(Some(volume_created), volume),
+    )?;
+  }
+  // The staging directory's volume is not the image's, so only a UUID given
+  // for the image is recorded.
+  crate::create::add_volume_uuid(&mut info, options.volume_uuid)?;
+  crate::create::check_max_size(&info, options)?;
+  Ok(info)
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::PathBuf;
+
+  use crate::fixture::{fixture_info, FakeVolumes};
+  use crate::{CreateOptions, Error, Info, TargetType};
+
+  fn staged(internal_path: &str) -> super::StagedTarget {
+    super::StagedTarget {
+      staging_dir: PathBuf::from("/Users/me/stage"),
+      volume_name: "Test Title".to_owned(),
+      internal_path: internal_path.to_owned(),
+      ..Default::default()
+    }
+  }
+
+  fn stage() -> FakeVolumes {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.directory("/Users/me/stage", 2, 400, 1700000000);
+    volumes.directory("/Users/me/stage/.background", 2, 401, 1700000000);
+    volumes.file(
+      "/Users/me/stage/.background/TestBkg.tiff",
+      2,
+      402,
+      1700000004,
+    );
+    volumes.symlink("/tmp", "/private/tmp");
+    volumes.directory("/private", 1, 103, 0);
+    volumes.directory("/private/tmp", 1, 104, 0);
+    volumes.directory("/private/tmp/stage", 1, 105, 0);
+    volumes
+  }
+
+  /// `info` with the ids and dates, which the staged record cannot know,
+  /// taken from `from`.
+  fn with_ids_and_dates(mut info: Info, from: &Info) -> Info {
+    info.target.id = from.target.id;
+    info.target.created = from.target.created;
+    info.parent.id = from.parent.id;
+    info.volume.created = from.volume.created;
+    if let (Some(extra), Some(from)) = (info.extra.iter_mut().find(|e| e.type_ == 1), from.extra(1))
+    {
+      *extra = from.clone();
+    }
+    info
+  }
+
+  #[test]
+  fn matches_mounted_image() {
+    // The fixture was captured from a mounted image. It leaves out the
+    // unicode file name.
+    let fixture = fixture_info();
+    let mut info = super::info_for_staged_in(
+      &stage(),
+      &staged(".background/TestBkg.tiff"),
+      &CreateOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(info.target.id, 0);
+    assert_eq!(info.parent.id, 0);
+    assert_eq!(info.extra(1).unwrap().data, [0; 4]);
+    assert_eq!(info.posix_path().unwrap(), fixture.posix_path().unwrap());
+    info.extra.retain(|e| e.type_ != 14);
+    assert_eq!(with_ids_and_dates(info, &fixture), fixture);
+  }
+
+  #[test]
+  fn ids_and_dates() {
+    let created = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1700000000);
+    let info = super::info_for_staged_in(
+      &stage(),
+      &super::StagedTarget {
+        target_id: Some(20),
+        parent_id: Some(19),
+        ..staged(".background/TestBkg.tiff")
+      },
+      &CreateOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(info.target.id, 20);
+    assert_eq!(info.parent.id, 19);
+    assert_eq!(info.extra(1).unwrap().data, 19u32.to_be_bytes());
+    // The staging directory stands in for the volume.
+    assert_eq!(info.volume.created, created);
+    assert_eq!(
+      info.target.created,
+      created + std::time::Duration::from_secs(4)
+    );
+  }
+
+  #[test]
+  fn hires_dates() {
+    let created = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1700000000500);
+    let info = super::info_for_staged_in(
+      &stage(),
+      &super::StagedTarget {
+        volume_created: Some(created),
+        ..staged(".background/TestBkg.tiff")
+      },
+      &CreateOptions {
+        hires_dates: true,
+        ..Default::default()
+      },
+    )
+    .unwrap();
+    let date = |type_| u64::from_be_bytes(info.extra(type_).unwrap().data[..].try_into().unwrap());
+    assert_eq!(date(16), (1700000000 + 2082844800) << 16 | 0x8000);
+    assert_eq!(date(17), (1700000004 + 2082844800) << 16);
+  }
+
+  #[test]
+  fn reproducible() {
+    let pinned = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1600000000);
+    let options = CreateOptions {
+      target_created: Some(pinned),
+      volume_created: Some(pinned),
+      hires_dates: true,
+      ..Default::default()
+    };
+    let record = |volumes: &FakeVolumes| {
+      let info =
+        super::info_for_staged_in(volumes, &staged(".background/TestBkg.tiff"), &options).unwrap();
+      crate::encode(&info).unwrap()
+    };
+    // Another machine staged the same files at other times.
+    let mut elsewhere = stage();
+    for path in [
+      "/Users/me/stage",
+      "/Users/me/stage/.background/TestBkg.tiff",
+    ] {
+      elsewhere.set_times(path, Some(1700001234), Some(1700001234));
+    }
+    assert_eq!(record(&stage()), record(&elsewhere));
+    let info = crate::decode(&record(&stage())).unwrap();
+    assert_eq!((info.target.created, info.volume.created), (pinned, pinned));
+  }
+
+  #[test]
+  fn id_overrides() {
+    let info = super::info_for_staged_in(
+      &stage(),
+      &super::StagedTarget {
+        target_id: Some(1),
+        ..staged(".background/TestBkg.tiff")
+      },
+      &CreateOptions {
+        target_id: Some(20),
+        parent_id: Some(19),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+    assert_eq!(info.target.id, 20);
+    assert_eq!(info.parent.id, 19);
+    assert_eq!(info.extra(1).unwrap().data, 19u32.to_be_bytes());
+    assert_eq!(info.parent.name, ".background");
+  }
+
+  #[test]
+  fn volume_root_and_symlinked_staging_dir() {
+    let mut volumes = stage();
+    volumes.file("/private/tmp/stage/bg.tiff", 1, 106, 0);
+    let info = super::info_for_staged_in(
+      &volumes,
+      &super::StagedTarget {
+        staging_dir: PathBuf::from("/tmp/stage"),
+        volume_name: "App 1/2".to_owned(),
+        ..staged("bg.tiff")
+      },
+      &CreateOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(info.parent.name, "App 1/2");
+    assert_eq!(info.posix_path().unwrap(), "/Volumes/App 1:2/bg.tiff");
+  }
+
+  #[test]
+  fn missing_target() {
+    let volumes = stage();
+    assert!(matches!(
+      super::info_for_staged_in(
+        &volumes,
+        &staged(".background/Later"),
+        &CreateOptions::default()
+      ),
+      Err(Error::TargetNotFound(_))
+    ));
+    let info = super::info_for_staged_in(
+      &volumes,
+      &super::StagedTarget {
+        target_is_dir: Some(true),
+        ..staged(".background/Later")
+      },
+      &CreateOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(info.target.type_, TargetType::Directory);
+    assert_eq!(
+      info.posix_path().unwrap(),
+      "/Volumes/Test Title/.background/Later"
+    );
+  }
+
+  #[test]
+  fn background_internal_path() {
+    let staging_dir = std::path::Path::new("/Users/me/stage");
+    for image_path in [
+      "/Users/me/stage/.background/TestBkg.tiff",
+      ".background/TestBkg.tiff",
+      "TestBkg.tiff",
+    ] {
+      assert_eq!(
+        super::background_internal_path(staging_dir, image_path.as_ref()).unwrap(),
+        ".background/TestBkg.tiff"
+      );
+    }
+    assert!(matches!(
+      super::background_internal_path(staging_dir, "/Users/me/TestBkg.tiff".as_ref()),
+      Err(Error::InvalidPath(_))
+    ));
+
+    let internal_path =
+      super::background_internal_path(staging_dir, "/Users/me/stage/TestBkg.tiff".as_ref())
+        .unwrap();
+    let mut volumes = stage();
+    volumes.file("/Users/me/stage/TestBkg.tiff", 2, 403, 1700000004);
+    let info =
+      super::info_for_staged_in(&volumes, &staged(&internal_path), &CreateOptions::default())
+        .unwrap();
+    assert_eq!(
+      info.posix_path().unwrap(),
+      "/Volumes/Test Title/TestBkg.tiff"
+    );
+    assert_eq!(info.extra(19).unwrap().data, b"/Volumes/Test Title");
+  }
+
+  #[test]
+  fn invalid_internal_path() {
+    for internal_path in [
+      "",
+      "/bg.tiff",
+      "../bg.tiff",
+      ".background/../bg.tiff",
+      "./bg.tiff",
+    ] {
+      assert!(
+        matches!(
+          super::info_for_staged_in(&stage(), &staged(internal_path), &CreateOptions::default()),
+          Err(Error::InvalidPath(_))
+        ),
+        "{internal_path:?}"
+      );
+    }
+  }
+}
@@ -0,0 +1,312 @@
+//! Checking whether a record still describes its target, and refreshing it
+//! when it does not.
+
+#[cfg(any(target_os = "macos", test))]
+use std::path::{Path, PathBuf};
+
+#[cfg(any(target_os = "macos", test))]
+use crate::{source::MetadataSource, Changes, CreateOptions, Info};
+use crate::{Error, Result};
+
+/// What [`stat_target`] found on disk for a record.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetStatus {
+  /// Whether the record's volume is mounted: the one with its volume UUID,
+  /// if it has one, or else whatever is mounted where it says (extra 19).
+  pub volume_mounted: bool,
+  /// Where the record's volume is mounted, which differs from extra 19 when
+  /// the volume UUID found it elsewhere, e.g. `/Volumes/Test Title 1`.
+  pub volume_path: Option<String>,
+  /// Whether a file or directory exists at the record's posix path.
+  pub exists: bool,
+  /// Whether the volume still has an object with the record's target id.
+  /// Always false for records without an id, such as ones on FAT volumes.
+  pub id_matches: bool,
+  /// Whether the record's path names its target: the object with the
+  /// target id is still there, or, without an id, something is there and
+  /// nothing in `stale_fields` changed.
+  pub path_matches: bool,
+  /// Fields of the record that differ from the object now at its path, e.g.
+  /// `target.id` for a file that was replaced.
+  pub stale_fields: Vec<&'static str>,
+}
+
+/// Check the target of `record` against the live filesystem, by its posix
+/// path and by its id on the recorded volume. A volume UUID in the record is
+/// looked for among the mounted volumes before its mount point. Nothing is modified, and an
+/// unmounted volume is reported rather than mounted.
+#[cfg(target_os = "macos")]
+pub fn stat_target(record: &[u8]) -> Result<TargetStatus> {
+  let info = crate::decode(record)?;
+  Ok(target_status_in(&crate::volume::System, &info))
+}
+
+/// Check the target of `record` against the live filesystem.
+///
+/// Looking up the target needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn stat_target(record: &[u8]) -> Result<TargetStatus> {
+  crate::decode(record)?;
+  Err(Error::Unsupported("Checking the target of an alias"))
+}
+
+/// Where the volume of `info` is mounted: the mounted volume with its volume
+/// UUID, or else the volume mounted at extra 19, if the record has either.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn mounted_volume(source: &impl MetadataSource, info: &Info) -> Option<PathBuf> {
+  if let Some(uuid) = info.volume_uuid() {
+    let by_uuid = source.mounts().ok().and_then(|mounts| {
+      mounts
+        .into_iter()
+        .map(|mount| mount.mount_point)
+        .find(|mount_point| source.volume_uuid(mount_point) == Some(uuid))
+    });
+    if by_uuid.is_some() {
+      return by_uuid;
+    }
+  }
+  let volume_path = Path::new(std::str::from_utf8(&info.extra(19)?.data).ok()?);
+  source
+    .canonicalize(volume_path)
+    .and_then(|canonical| crate::source::mount_point(source, &canonical))
+    .is_ok_and(|mount_point| mount_point == volume_path)
+    .then(|| volume_path.to_owned())
+}
+
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn target_status_in(source: &impl MetadataSource, info: &Info) -> TargetStatus {
+  let mut status = TargetStatus::default();
+  let Some(volume_path) = mounted_volume(source, info) else {
+    return status;
+  };
+  let Some(path) = info.posix_path_on(&volume_path.to_string_lossy()) else {
+    return status;
+  };
+  let volume_path = volume_path.as_path();
+  status.volume_mounted = true;
+  status.volume_path = Some(volume_path.to_string_lossy().into_owned());
+
+  let options = CreateOptions {
+    no_disk_image: true,
+    ..Default::default()
+  };
+  if let Ok(current) = crate::create::info_for_path_in(source, &path, &options) {
+    status.exists = true;
+    status.stale_fields = crate::verify::mismatches(info, &current)
+      .into_iter()
+      .map(|mismatch| mismatch.field)
+      .collect();
+  }
+
+  if info.target.id == 0 {
+    status.path_matches = status.exists && status.stale_fields.is_empty();
+  } else if let Some(id_path) = source.path_for_id(volume_path, info.target.id.into()) {
+    status.id_matches = true;
+    status.path_matches = source
+      .canonicalize(Path::new(&path))
+      .is_ok_and(|canonical| canonical == id_path);
+  }
+  status
+}
+
+/// Refresh `record` from the file or directory now at its posix path, e.g.
+/// after the target was replaced in place: the target id and creation date,
+/// the parent id and name, and the volume's creation date, signature and
+/// type. The target name and the paths are left as they are.
+///
+/// The target is looked for on the volume with the record's volume UUID, if
+/// it has one and it is mounted. A target that is gone is reported as
+/// [`Error::TargetNotFound`].
+#[cfg(target_os = "macos")]
+pub fn relink_alias(record: &[u8]) -> Result<Vec<u8>> {
+  let mut info = crate::decode(record)?;
+  relink_in(&crate::volume::System, &mut info)?;
+  crate::encode(&info)
+}
+
+/// Refresh `record` from the file or directory now at its posix path.
+///
+/// Looking up the target needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn relink_alias(record: &[u8]) -> Result<Vec<u8>> {
+  crate::decode(record)?;
+  Err(Error::Unsupported("Relinking an alias"))
+}
+
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn relink_in(source: &impl MetadataSource, info: &mut Info) -> Result<()> {
+  let path = match mounted_volume(source, info) {
+    Some(volume_path) => info.posix_path_on(&volume_path.to_string_lossy()),
+    None => info.posix_path(),
+  };
+  let path = path.ok_or_else(|| {
+    Error::InvalidPath("The record has no posix path to find its target by.".to_owned())
+  })?;
+  let options = CreateOptions {
+    no_disk_image: true,
+    ..Default::default()
+  };
+  let current = crate::create::info_for_path_in(source, &path, &options)?;
+  crate::apply_changes(
+    info,
+    &Changes {
+      parent_name: Some(current.parent.name),
+      target_id: Some(current.target.id),
+      parent_id: Some(current.parent.id),
+      target_created: Some(current.target.created),
+      volume_created: Some(current.volume.created),
+      ..Default::default()
+    },
+  );
+  info.volume.signature = current.volume.signature;
+  info.volume.type_ = current.volume.type_;
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use crate::fixture::{fixture_info, FakeVolumes};
+  use crate::Error;
+
+  const TEST_BKG: &str = "/Volumes/Test Title/.background/TestBkg.tiff";
+
+  fn status(volumes: &FakeVolumes) -> super::TargetStatus {
+    super::target_status_in(volumes, &fixture_info())
+  }
+
+  #[test]
+  fn fresh() {
+    let status = status(&FakeVolumes::test_title());
+    assert_eq!(
+      status,
+      super::TargetStatus {
+        volume_mounted: true,
+        volume_path: Some("/Volumes/Test Title".to_owned()),
+        exists: true,
+        id_matches: true,
+        path_matches: true,
+        stale_fields: vec![],
+      }
+    );
+  }
+
+  #[test]
+  fn replaced() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.remove(TEST_BKG);
+    volumes.file(TEST_BKG, 3, 21, 1388686900);
+    let status = status(&volumes);
+    assert!(status.exists);
+    assert!(!status.id_matches);
+    assert!(!status.path_matches);
+    assert_eq!(status.stale_fields, ["target.id", "target.created"]);
+  }
+
+  #[test]
+  fn moved() {
+    let mut volumes = FakeVolumes::test_title();
+    let stat = volumes.remove(TEST_BKG);
+    volumes.insert_stat("/Volumes/Test Title/TestBkg.tiff", stat);
+    let status = status(&volumes);
+    assert!(!status.exists);
+    assert!(status.id_matches);
+    assert!(!status.path_matches);
+    assert_eq!(status.stale_fields, [] as [&str; 0]);
+  }
+
+  #[test]
+  fn renamed_volume() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.mount(3, "/Volumes/Test Title", "hfs", "Other Title");
+    let status = status(&volumes);
+    assert!(status.path_matches);
+    assert_eq!(status.stale_fields, ["volume.name"]);
+  }
+
+  #[test]
+  fn unmounted_volume() {
+    let mut volumes = FakeVolumes::new();
+    volumes.mount(1, "/", "apfs", "Macintosh HD");
+    volumes.directory("/", 1, 2, 0);
+    volumes.directory("/Volumes", 1, 3, 0);
+    assert_eq!(status(&volumes), super::TargetStatus::default());
+
+    // A leftover mount point directory is not the volume.
+    volumes.directory("/Volumes/Test Title", 1, 4, 0);
+    assert!(!status(&volumes).volume_mounted);
+  }
+
+  #[test]
+  fn volume_found_by_uuid() {
+    // A second volume named Test Title took the mount point, and the
+    // record's volume was mounted after it.
+    let uuid = crate::VolumeUuid([7; 16]);
+    let mut volumes = FakeVolumes::test_title();
+    volumes.set_volume_uuid(3, crate::VolumeUuid([8; 16]));
+    volumes.mount(4, "/Volumes/Test Title 1", "hfs", "Test Title");
+    volumes.set_volume_uuid(4, uuid);
+    volumes.directory("/Volumes/Test Title 1", 4, 2, 1388686804);
+    volumes.directory("/Volumes/Test Title 1/.background", 4, 19, 1388686804);
+    volumes.file(
+      "/Volumes/Test Title 1/.background/TestBkg.tiff",
+      4,
+      20,
+      1388686808,
+    );
+    let mut info = fixture_info();
+    crate::add_extra_records(
+      &mut info,
+      &[crate::Extra::new(crate::VOLUME_UUID_EXTRA, uuid.0.to_vec())],
+      false,
+    )
+    .unwrap();
+    let status = super::target_status_in(&volumes, &info);
+    assert_eq!(status.volume_path.as_deref(), Some("/Volumes/Test Title 1"));
+    assert!(status.id_matches);
+    assert!(status.path_matches);
+
+    // Once it is unmounted, the mount point is checked as before.
+    volumes.set_volume_uuid(4, crate::VolumeUuid([9; 16]));
+    let status = super::target_status_in(&volumes, &info);
+    assert_eq!(status.volume_path.as_deref(), Some("/Volumes/Test Title"));
+  }
+
+  fn relinked(volumes: &FakeVolumes) -> crate::Result<crate::Info> {
+    let mut info = fixture_info();
+    super::relink_in(volumes, &mut info).map(|()| info)
+  }
+
+  #[test]
+  fn relink_fresh() {
+    assert_eq!(
+      relinked(&FakeVolumes::test_title()).unwrap(),
+      fixture_info()
+    );
+  }
+
+  #[test]
+  fn relink_replaced() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.remove(TEST_BKG);
+    volumes.file(TEST_BKG, 3, 21, 1388686900);
+    volumes.mount(3, "/Volumes/Test Title", "hfs", "Other Title");
+    let info = relinked(&volumes).unwrap();
+    assert_eq!(info.target.id, 21);
+    assert!(super::target_status_in(&volumes, &info).path_matches);
+    // Only the name differs from a fresh record, since names are kept.
+    assert_eq!(info.volume.name, "Test Title");
+    let current = crate::create::info_for_path_in(&volumes, TEST_BKG, &Default::default()).unwrap();
+    let stale: Vec<_> = crate::verify::mismatches(&info, &current)
+      .into_iter()
+      .map(|mismatch| mismatch.field)
+      .collect();
+    assert_eq!(stale, ["volume.name"]);
+  }
+
+  #[test]
+  fn relink_missing() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.remove(TEST_BKG);
+    assert!(matches!(relinked(&volumes), Err(Error::TargetNotFound(_))));
+  }
+}
@@ -0,0 +1,141 @@
+//! Alias records from the system's own Alias Manager, for checking ours
+//! against.
+//!
+//! `FSNewAlias` has been deprecated since macOS 10.8 but still writes the
+//! classic records Finder stores in `.DS_Store` files. It is only linked
+//! with the `system-alias` feature, so regular builds do not depend on it.
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+use crate::{Error, Result};
+
+/// An opaque File Manager reference to an existing file or directory.
+#[repr(C)]
+struct FSRef {
+  hidden: [u8; 80],
+}
+
+type Handle = *mut *mut u8;
+
+/// `fnfErr`: the file was not found.
+const FNF_ERR: i32 = -43;
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+  fn FSPathMakeRef(path: *const libc::c_char, fs_ref: *mut FSRef, is_directory: *mut u8) -> i32;
+  fn FSNewAlias(from_file: *const FSRef, target: *const FSRef, alias: *mut Handle) -> i16;
+  fn GetHandleSize(handle: Handle) -> libc::c_long;
+  fn DisposeHandle(handle: Handle);
+}
+
+/// The alias record the Alias Manager creates for the file or directory at
+/// `target_path`, with every field it knows how to fill in.
+pub fn system_alias_for<P: AsRef<Path>>(target_path: P) -> Result<Vec<u8>> {
+  let target_path = target_path.as_ref();
+  let path = CString::new(target_path.as_os_str().as_bytes())
+    .map_err(|_| Error::InvalidPath("The target path contains a NUL byte.".to_owned()))?;
+  let mut target = FSRef { hidden: [0; 80] };
+  let status = unsafe { FSPathMakeRef(path.as_ptr(), &mut target, ptr::null_mut()) };
+  match status {
+    0 => {}
+    FNF_ERR => return Err(Error::TargetNotFound(target_path.display().to_string())),
+    status => {
+      return Err(Error::Io(std::io::Error::other(format!(
+        "FSPathMakeRef failed with error {status}"
+      ))))
+    }
+  }
+
+  let mut alias: Handle = ptr::null_mut();
+  let status = unsafe { FSNewAlias(ptr::null(), &target, &mut alias) };
+  if status != 0 || alias.is_null() {
+    return Err(Error::Io(std::io::Error::other(format!(
+      "FSNewAlias failed with error {status}"
+    ))));
+  }
+  let record = unsafe {
+    let length = GetHandleSize(alias) as usize;
+    let record = std::slice::from_raw_parts(*alias, length).to_vec();
+    DisposeHandle(alias);
+    record
+  };
+  Ok(record)
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::{Path, PathBuf};
+
+  use crate::fixture::DiskImage;
+  use crate::{Extra, ExtraValue, Info, TargetType};
+
+  /// What a resolver relies on, with the posix path joined from extras 18
+  /// and 19 however each record splits it.
+  #[derive(Debug, PartialEq)]
+  struct Semantic {
+    volume_name: String,
+    filename: String,
+    parent_name: String,
+    posix_path: PathBuf,
+    type_: TargetType,
+  }
+
+  fn semantic(info: &Info) -> Semantic {
+    let text = |type_| match info.extra(type_).and_then(Extra::value) {
+      Some(ExtraValue::String(text)) => text,
+      _ => panic!("no extra {type_} in {info:?}"),
+    };
+    Semantic {
+      volume_name: info.volume.name.clone(),
+      filename: info.target.filename.clone(),
+      parent_name: info.parent.name.clone(),
+      posix_path: Path::new(&text(19)).join(text(18).trim_start_matches('/')),
+      type_: info.target.type_,
+    }
+  }
+
+  fn assert_agrees(target: &Path) {
+    let system = crate::decode(&super::system_alias_for(target).unwrap()).unwrap();
+    let ours = crate::decode(&crate::create_for_path(target).unwrap()).unwrap();
+    assert_eq!(semantic(&ours), semantic(&system), "{}", target.display());
+  }
+
+  #[test]
+  fn agrees_with_system() {
+    let dir = std::env::temp_dir()
+      .canonicalize()
+      .unwrap()
+      .join(format!("macos-alias-system-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("Über Ordner")).unwrap();
+    std::fs::write(dir.join("background.tiff"), b"").unwrap();
+    std::fs::write(dir.join("Über Ordner").join("Café ☕.tiff"), b"").unwrap();
+
+    let image = DiskImage::attach("system", "System Alias");
+    std::fs::create_dir(image.mount_point.join(".background")).unwrap();
+    std::fs::write(image.mount_point.join(".background/bg.tiff"), b"").unwrap();
+    std::fs::write(image.mount_point.join("bg.tiff"), b"").unwrap();
+
+    for target in [
+      dir.join("background.tiff"),
+      dir.join("Über Ordner"),
+      dir.join("Über Ordner").join("Café ☕.tiff"),
+      image.mount_point.join(".background/bg.tiff"),
+      image.mount_point.join(".background"),
+      image.mount_point.join("bg.tiff"),
+    ] {
+      assert_agrees(&target);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn missing_target() {
+    assert!(matches!(
+      super::system_alias_for("/nonexistent/macos-alias"),
+      Err(crate::Error::TargetNotFound(_))
+    ));
+  }
+}
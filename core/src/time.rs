@@ -0,0 +1,197 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::DateRounding;
+
+// From 1904, 1, 1 to 1970, 1, 1
+static APPLE_EPOCH: i64 = -2082844800000;
+
+/// Milliseconds since the Unix epoch, negative for dates before 1970.
+pub(crate) fn unix_millis(value: SystemTime) -> i64 {
+  match value.duration_since(UNIX_EPOCH) {
+    Ok(since) => since.as_millis() as i64,
+    Err(before) => -(before.duration().as_millis() as i64),
+  }
+}
+
+pub(crate) fn from_unix_millis(millis: i64) -> SystemTime {
+  if millis >= 0 {
+    UNIX_EPOCH + Duration::from_millis(millis as u64)
+  } else {
+    UNIX_EPOCH - Duration::from_millis(millis.unsigned_abs())
+  }
+}
+
+/// Seconds since 1904-01-01, as stored in the classic date fields.
+pub(crate) fn apple_date(value: SystemTime, rounding: DateRounding) -> u32 {
+  ((unix_millis(whole_seconds(value, rounding)) - APPLE_EPOCH) / 1000).clamp(0, u32::MAX.into())
+    as u32
+}
+
+/// `value` without its milliseconds, rounded the way `rounding` says. The
+/// Apple epoch falls on a whole second, so this is the second
+/// [`apple_date`] records.
+pub(crate) fn whole_seconds(value: SystemTime, rounding: DateRounding) -> SystemTime {
+  let millis = unix_millis(value);
+  let seconds = match rounding {
+    DateRounding::Floor => millis.div_euclid(1000),
+    DateRounding::Round => (millis + 500).div_euclid(1000),
+  };
+  from_unix_millis(seconds * 1000)
+}
+
+pub(crate) fn from_apple_date(seconds: u32) -> SystemTime {
+  from_unix_millis(seconds as i64 * 1000 + APPLE_EPOCH)
+}
+
+/// 16.16 fixed-point seconds since 1904-01-01, as stored in the
+/// high-resolution date extras (16 and 17). Dates before 1904 are clamped
+/// to it.
+#[cfg_attr(not(any(target_os = "macos", test)), allow(dead_code))]
+pub(crate) fn hires_apple_date(value: SystemTime) -> u64 {
+  let epoch = UNIX_EPOCH - Duration::from_millis(APPLE_EPOCH.unsigned_abs());
+  let Ok(since) = value.duration_since(epoch) else {
+    return 0;
+  };
+  let fraction = ((since.subsec_nanos() as u64) << 16) / 1_000_000_000;
+  since.as_secs() << 16 | fraction
+}
+
+/// A 16.16 fixed-point count of seconds since 1904-01-01, as stored in the
+/// version 3 date fields.
+pub(crate) fn from_hires_apple_date(value: u64) -> SystemTime {
+  let seconds = value >> 16;
+  let nanos = ((value & 0xffff) * 1_000_000_000) >> 16;
+  UNIX_EPOCH - Duration::from_millis(APPLE_EPOCH.unsigned_abs())
+    + Duration::new(seconds, nanos as u32)
+}
+
+/// Serde representation of dates as Unix milliseconds.
+///
+/// Whole-number floats are accepted too, as JavaScript numbers above `2^32`
+/// reach serde as `f64`.
+#[cfg(feature = "serde")]
+pub(crate) mod millis {
+  use std::fmt;
+  use std::time::SystemTime;
+
+  use serde::de::{self, Visitor};
+  use serde::{Deserializer, Serializer};
+
+  pub fn serialize<S: Serializer>(value: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(super::unix_millis(*value))
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+    deserializer
+      .deserialize_i64(MillisVisitor)
+      .map(super::from_unix_millis)
+  }
+
+  struct MillisVisitor;
+
+  impl Visitor<'_> for MillisVisitor {
+    type Value = i64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+      formatter.write_str("a whole number of milliseconds")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<i64, E> {
+      Ok(value)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<i64, E> {
+      i64::try_from(value).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<i64, E> {
+      if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        Ok(value as i64)
+      } else {
+        Err(E::invalid_value(de::Unexpected::Float(value), &self))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::{Duration, UNIX_EPOCH};
+
+  #[test]
+  fn apple_date_round_trip() {
+    let date = UNIX_EPOCH + Duration::from_millis(1388686808000);
+    assert_eq!(
+      super::from_apple_date(super::apple_date(date, Default::default())),
+      date
+    );
+  }
+
+  #[test]
+  fn apple_date_before_unix_epoch() {
+    assert_eq!(
+      super::apple_date(super::from_apple_date(0), Default::default()),
+      0
+    );
+    assert_eq!(
+      super::from_apple_date(0),
+      UNIX_EPOCH - Duration::from_secs(2082844800)
+    );
+  }
+
+  #[test]
+  fn apple_date_rounding() {
+    use crate::DateRounding::{Floor, Round};
+
+    let seconds = |millis, rounding| {
+      super::apple_date(UNIX_EPOCH + Duration::from_millis(millis), rounding) - 2082844800
+    };
+    assert_eq!(seconds(1388686808499, Floor), 1388686808);
+    assert_eq!(seconds(1388686808500, Floor), 1388686808);
+    assert_eq!(seconds(1388686808999, Floor), 1388686808);
+    assert_eq!(seconds(1388686808499, Round), 1388686808);
+    assert_eq!(seconds(1388686808500, Round), 1388686809);
+  }
+
+  #[test]
+  fn whole_second_rounding() {
+    use crate::DateRounding::{Floor, Round};
+
+    let date = |millis| super::from_unix_millis(millis);
+    for (millis, floor, round) in [
+      (1388686808000, 1388686808000, 1388686808000),
+      (1388686808499, 1388686808000, 1388686808000),
+      (1388686808500, 1388686808000, 1388686809000),
+      (1388686807500, 1388686807000, 1388686808000),
+      (1388686808999, 1388686808000, 1388686809000),
+      // Before 1970, halves still go up and drops still go down.
+      (-1500, -2000, -1000),
+      (-1499, -2000, -1000),
+    ] {
+      assert_eq!(
+        super::whole_seconds(date(millis), Floor),
+        date(floor),
+        "{millis}"
+      );
+      assert_eq!(
+        super::whole_seconds(date(millis), Round),
+        date(round),
+        "{millis}"
+      );
+    }
+  }
+
+  #[test]
+  fn hires_apple_date() {
+    let seconds = 1388686808 + 2082844800u64;
+    assert_eq!(
+      super::from_hires_apple_date(seconds << 16 | 0x8000),
+      UNIX_EPOCH + Duration::from_millis(1388686808500)
+    );
+    let date = UNIX_EPOCH + Duration::from_millis(1388686808500);
+    assert_eq!(super::hires_apple_date(date), seconds << 16 | 0x8000);
+    assert_eq!(super::hires_apple_date(super::from_apple_date(0)), 0);
+    let before = super::from_apple_date(0) - Duration::from_secs(1);
+    assert_eq!(super::hires_apple_date(before), 0);
+  }
+}
@@ -0,0 +1,428 @@
+//! Records for everything under a directory, as DMG layouts that pin the
+//! position of every item need.
+
+#[cfg(any(target_os = "macos", test))]
+use std::collections::HashSet;
+use std::path::Path;
+
+#[cfg(any(target_os = "macos", test))]
+use crate::source::{Cached, DirEntry, FileStat, MetadataSource};
+#[cfg(not(target_os = "macos"))]
+use crate::Error;
+use crate::{CreateOptions, Result};
+
+/// Knobs for [`create_for_tree`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeOptions {
+  /// How each record is made; see [`crate::create_for_path_with`].
+  pub create: CreateOptions,
+  /// Only record entries whose path relative to the root matches this
+  /// glob. `*` and `?` stay within a path component and `**` spans any
+  /// number of them. A pattern without a `/` is matched against the name
+  /// of the entry alone, so `*.png` matches at every depth.
+  pub glob: Option<String>,
+  /// How many levels below the root to list: `Some(1)` is only its own
+  /// entries, and `Some(0)` nothing. Unlimited by default.
+  pub max_depth: Option<usize>,
+  /// Also record directories, not only files.
+  pub include_directories: bool,
+  /// Record symlinks as the file or directory they point to, and descend
+  /// into symlinked directories. Symlinks are skipped by default.
+  pub follow_symlinks: bool,
+}
+
+/// One file or directory found by [`create_for_tree`].
+#[derive(Debug)]
+pub struct TreeEntry {
+  /// The path relative to the root, with `/` between components.
+  pub path: String,
+  /// The record, or why it could not be made. A directory that could not
+  /// be listed reports that error here instead of its record.
+  pub record: Result<Vec<u8>>,
+}
+
+/// The records of the files below `root`, in path order, looked up with
+/// one set of volume lookups for the whole walk rather than one per file.
+///
+/// The walk stays on the volume `root` is on: entries on other volumes,
+/// such as a volume mounted inside the tree, are skipped. Failing to
+/// create one record does not stop the walk; only a `root` that cannot be
+/// listed fails it.
+pub fn create_for_tree<P: AsRef<Path>>(root: P, options: &TreeOptions) -> Result<Vec<TreeEntry>> {
+  create_for_tree_filtered(root, options, |_, _| true)
+}
+
+/// Like [`create_for_tree`], also skipping the entries for which `filter`,
+/// called with the relative path and whether the entry is a directory,
+/// returns false. Directories are descended into either way.
+#[cfg(target_os = "macos")]
+pub fn create_for_tree_filtered<P, F>(
+  root: P,
+  options: &TreeOptions,
+  filter: F,
+) -> Result<Vec<TreeEntry>>
+where
+  P: AsRef<Path>,
+  F: FnMut(&str, bool) -> bool,
+{
+  let root = root.as_ref();
+  let root_str = root.to_string_lossy();
+  let mut entries = crate::create::without_materializing(&root_str, &options.create, || {
+    tree_in(&crate::volume::System, root, options, filter)
+  })?;
+  if options.create.no_io {
+    for entry in &mut entries {
+      if let Err(err) = std::mem::replace(&mut entry.record, Ok(Vec::new())) {
+        let path = root.join(&entry.path);
+        entry.record = Err(crate::create::would_materialize(
+          &path.to_string_lossy(),
+          err,
+        ));
+      }
+    }
+  }
+  Ok(entries)
+}
+
+/// Like [`create_for_tree`], also skipping the entries `filter` rejects.
+///
+/// Looking up the files needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn create_for_tree_filtered<P, F>(
+  root: P,
+  options: &TreeOptions,
+  filter: F,
+) -> Result<Vec<TreeEntry>>
+where
+  P: AsRef<Path>,
+  F: FnMut(&str, bool) -> bool,
+{
+  let _ = (root, options, filter);
+  Err(Error::Unsupported("Creating aliases for a directory tree"))
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn tree_in<F: FnMut(&str, bool) -> bool>(
+  source: &impl MetadataSource,
+  root: &Path,
+  options: &TreeOptions,
+  mut filter: F,
+) -> Result<Vec<TreeEntry>> {
+  let source = Cached::new(source);
+  let root_str = root.to_string_lossy();
+  let root = source
+    .canonicalize(root)
+    .map_err(|err| crate::create::not_found(&root_str, err))?;
+  let root_stat = source.stat(&root)?;
+  if !root_stat.is_dir {
+    return Err(crate::Error::InvalidPath(format!(
+      "{root_str} is not a directory."
+    )));
+  }
+  let mut entries = Vec::new();
+  if options.max_depth == Some(0) {
+    return Ok(entries);
+  }
+  let glob = options.glob.as_deref();
+  let mut visited = HashSet::from([root_stat.ino]);
+  // Directories still to walk, with their path relative to the root, their
+  // depth and their entries. Popping from the end walks them in path order.
+  let listing = source.read_dir(&root)?;
+  let mut pending: Vec<(_, String, usize, Vec<DirEntry>)> = vec![(root, String::new(), 0, listing)];
+  while let Some((dir, relative, depth, mut listing)) = pending.pop() {
+    listing.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut subdirectories = Vec::new();
+    for entry in listing {
+      if entry.is_symlink && !options.follow_symlinks {
+        continue;
+      }
+      let name = entry.name.to_string_lossy();
+      let path = if relative.is_empty() {
+        name.into_owned()
+      } else {
+        format!("{relative}/{name}")
+      };
+      let full_path = dir.join(&entry.name);
+      let stat = match source.stat(&full_path) {
+        Ok(stat) => stat,
+        Err(err) => {
+          let record = Err(crate::create::not_found(&full_path.to_string_lossy(), err));
+          entries.push(TreeEntry { path, record });
+          continue;
+        }
+      };
+      if stat.dev != root_stat.dev {
+        continue;
+      }
+      let descend = stat.is_dir
+        && options.max_depth.is_none_or(|max| depth + 1 < max)
+        && visited.insert(stat.ino);
+      let listing = if descend {
+        match source.read_dir(&full_path) {
+          Ok(listing) => Some(listing),
+          Err(err) => {
+            entries.push(TreeEntry {
+              path,
+              record: Err(err.into()),
+            });
+            continue;
+          }
+        }
+      } else {
+        None
+      };
+      let wanted = (!stat.is_dir || options.include_directories)
+        && glob.is_none_or(|glob| glob_matches(glob, &path))
+        && filter(&path, stat.is_dir);
+      if wanted {
+        let record = record_in(&source, &full_path, stat, &options.create);
+        entries.push(TreeEntry {
+          path: path.clone(),
+          record,
+        });
+      }
+      if let Some(listing) = listing {
+        subdirectories.push((full_path, path, depth + 1, listing));
+      }
+    }
+    // The entries of each directory come before those of the next one.
+    pending.extend(subdirectories.into_iter().rev());
+  }
+  entries.sort_by(|a, b| a.path.cmp(&b.path));
+  Ok(entries)
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn record_in(
+  source: &impl MetadataSource,
+  path: &Path,
+  stat: FileStat,
+  options: &CreateOptions,
+) -> Result<Vec<u8>> {
+  let path = path.to_str().ok_or_else(|| {
+    crate::Error::InvalidPath("The target path is not a valid UTF-8 string.".to_owned())
+  })?;
+  let (info, ..) = crate::create::info_for_stat_in(source, path, Some(stat), options)?;
+  crate::encode_with(&info, options.compat)
+}
+
+/// Whether `path`, relative with `/` between components, matches `glob`.
+#[cfg(any(target_os = "macos", test))]
+fn glob_matches(glob: &str, path: &str) -> bool {
+  fn matches(glob: &[u8], path: &[u8]) -> bool {
+    match glob {
+      [] => path.is_empty(),
+      [b'*', b'*', rest @ ..] => {
+        // `**/` also matches no directories at all.
+        if let [b'/', after @ ..] = rest {
+          if matches(after, path) {
+            return true;
+          }
+        }
+        (0..=path.len()).any(|skip| matches(rest, &path[skip..]))
+      }
+      [b'*', rest @ ..] => {
+        let component = path.iter().position(|&c| c == b'/').unwrap_or(path.len());
+        (0..=component).any(|skip| matches(rest, &path[skip..]))
+      }
+      [b'?', rest @ ..] => match path {
+        [c, after @ ..] if *c != b'/' => {
+          // Skip the rest of a multi-byte character.
+          let len = path[1..].iter().take_while(|&&b| b & 0xc0 == 0x80).count();
+          matches(rest, &after[len..])
+        }
+        _ => false,
+      },
+      [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+    }
+  }
+  let path = if glob.contains('/') {
+    path
+  } else {
+    path.rsplit('/').next().unwrap_or(path)
+  };
+  matches(glob.as_bytes(), path.as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::Path;
+
+  use super::{glob_matches, TreeEntry, TreeOptions};
+  use crate::fixture::FakeVolumes;
+  use crate::{Error, VolumeNameSource};
+
+  fn volumes() -> FakeVolumes {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.directory("/Volumes/Test Title/Applications", 3, 30, 1388686804);
+    volumes.file("/Volumes/Test Title/ReadMe.txt", 3, 31, 1388686808);
+    volumes.file(
+      "/Volumes/Test Title/.background/Retina.png",
+      3,
+      32,
+      1388686808,
+    );
+    volumes.file(
+      "/Volumes/Test Title/Applications/App.png",
+      3,
+      33,
+      1388686808,
+    );
+    volumes
+  }
+
+  fn tree(volumes: &FakeVolumes, options: &TreeOptions) -> Vec<TreeEntry> {
+    super::tree_in(
+      volumes,
+      Path::new("/Volumes/Test Title"),
+      options,
+      |_, _| true,
+    )
+    .unwrap()
+  }
+
+  fn paths(entries: &[TreeEntry]) -> Vec<&str> {
+    entries.iter().map(|entry| entry.path.as_str()).collect()
+  }
+
+  #[test]
+  fn files() {
+    let volumes = volumes();
+    let entries = tree(&volumes, &Default::default());
+    assert_eq!(
+      paths(&entries),
+      [
+        ".background/Retina.png",
+        ".background/TestBkg.tiff",
+        "Applications/App.png",
+        "ReadMe.txt"
+      ]
+    );
+    // The same record as for the file alone.
+    let path = "/Volumes/Test Title/.background/TestBkg.tiff";
+    let info = crate::create::info_for_path_in(&volumes, path, &Default::default()).unwrap();
+    assert_eq!(
+      *entries[1].record.as_ref().unwrap(),
+      crate::encode(&info).unwrap()
+    );
+  }
+
+  #[test]
+  fn one_volume_lookup() {
+    let volumes = volumes();
+    let options = TreeOptions {
+      create: crate::CreateOptions {
+        volume_name_source: VolumeNameSource::CoreFoundation,
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+    assert_eq!(tree(&volumes, &options).len(), 4);
+    assert_eq!(
+      *volumes.name_lookups.borrow(),
+      [VolumeNameSource::CoreFoundation]
+    );
+  }
+
+  #[test]
+  fn depth_and_directories() {
+    let volumes = volumes();
+    let options = TreeOptions {
+      max_depth: Some(1),
+      include_directories: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      paths(&tree(&volumes, &options)),
+      [".background", "Applications", "ReadMe.txt"]
+    );
+    let directory = &tree(&volumes, &options)[1];
+    let info = crate::decode(directory.record.as_ref().unwrap()).unwrap();
+    assert_eq!(info.target.type_, crate::TargetType::Directory);
+  }
+
+  #[test]
+  fn filters() {
+    let volumes = volumes();
+    let options = TreeOptions {
+      glob: Some("*.png".to_owned()),
+      ..Default::default()
+    };
+    assert_eq!(
+      paths(&tree(&volumes, &options)),
+      [".background/Retina.png", "Applications/App.png"]
+    );
+
+    let root = Path::new("/Volumes/Test Title");
+    let entries =
+      super::tree_in(&volumes, root, &options, |path, _| !path.starts_with('.')).unwrap();
+    assert_eq!(paths(&entries), ["Applications/App.png"]);
+  }
+
+  #[test]
+  fn symlinks_and_other_volumes() {
+    let mut volumes = volumes();
+    volumes.symlink(
+      "/Volumes/Test Title/Link",
+      "/Volumes/Test Title/Applications",
+    );
+    volumes.symlink("/Volumes/Test Title/Loop", "/Volumes/Test Title");
+    volumes.symlink("/Volumes/Test Title/Broken", "/Volumes/Test Title/Gone");
+    // A volume mounted inside the tree.
+    volumes.mount(4, "/Volumes/Test Title/Mounted", "hfs", "Mounted");
+    volumes.directory("/Volumes/Test Title/Mounted", 4, 2, 1388686804);
+    volumes.file("/Volumes/Test Title/Mounted/Other.txt", 4, 20, 1388686808);
+
+    assert_eq!(tree(&volumes, &Default::default()).len(), 4);
+    let options = TreeOptions {
+      follow_symlinks: true,
+      ..Default::default()
+    };
+    let entries = tree(&volumes, &options);
+    assert_eq!(
+      paths(&entries),
+      [
+        ".background/Retina.png",
+        ".background/TestBkg.tiff",
+        "Applications/App.png",
+        "Broken",
+        "ReadMe.txt"
+      ]
+    );
+    // Applications was listed before Link, which points at it.
+    assert!(matches!(entries[3].record, Err(Error::TargetNotFound(_))));
+  }
+
+  #[test]
+  fn not_a_directory() {
+    let volumes = volumes();
+    let path = Path::new("/Volumes/Test Title/ReadMe.txt");
+    assert!(matches!(
+      super::tree_in(&volumes, path, &Default::default(), |_, _| true),
+      Err(Error::InvalidPath(_))
+    ));
+  }
+
+  #[test]
+  fn globs() {
+    for (glob, path) in [
+      ("*.png", "a/b/c.png"),
+      ("a/*.png", "a/c.png"),
+      ("a/**/c.png", "a/c.png"),
+      ("a/**/c.png", "a/b/d/c.png"),
+      ("**/*.png", "c.png"),
+      ("?.png", "ä.png"),
+      ("ReadMe.txt", "ReadMe.txt"),
+    ] {
+      assert!(glob_matches(glob, path), "{glob} {path}");
+    }
+    for (glob, path) in [
+      ("*.png", "c.tiff"),
+      ("a/*.png", "a/b/c.png"),
+      ("a/?", "a/bc"),
+      ("*.png", "a.png/b"),
+    ] {
+      assert!(!glob_matches(glob, path), "{glob} {path}");
+    }
+  }
+}
@@ -0,0 +1,441 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::create::{display_name, posix_name, unicode_name};
+use crate::{decode, encode, Error, Extra, ExtraValue, Info, Result, VolumeType};
+
+/// Fields to replace in an existing record. `None` leaves a field untouched.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Changes {
+  pub volume_name: Option<String>,
+  pub target_filename: Option<String>,
+  pub parent_name: Option<String>,
+  pub target_id: Option<u32>,
+  pub parent_id: Option<u32>,
+  pub target_created: Option<SystemTime>,
+  pub volume_created: Option<SystemTime>,
+}
+
+fn set_data(info: &mut Info, type_: i16, data: Vec<u8>) {
+  if let Some(extra) = info.extra.iter_mut().find(|e| e.type_ == type_) {
+    *extra = Extra::new(type_, data);
+  }
+}
+
+/// Replace the component `from_end` places before the last one of the `sep`
+/// separated extra `type_`, if it currently reads `old`.
+pub(crate) fn rename_component(
+  info: &mut Info,
+  type_: i16,
+  sep: char,
+  from_end: usize,
+  old: &str,
+  new: &str,
+) {
+  let Some(ExtraValue::String(path)) = info.extra(type_).and_then(Extra::value) else {
+    return;
+  };
+  let mut components: Vec<&str> = path.split(sep).collect();
+  if from_end >= components.len() {
+    return;
+  }
+  let index = components.len() - 1 - from_end;
+  // The carbon path marks the file name with a leading NUL.
+  let marked;
+  let replacement = if components[index] == old {
+    new
+  } else if components[index].strip_prefix('\0') == Some(old) {
+    marked = format!("\0{new}");
+    &marked
+  } else {
+    return;
+  };
+  components[index] = replacement;
+  let path = components.join(&sep.to_string());
+  set_value(info, type_, path);
+}
+
+/// Re-encode the text extra `type_`, if present, to read `value`.
+fn set_value(info: &mut Info, type_: i16, value: String) {
+  if let Ok(extra) = Extra::from_value(type_, ExtraValue::String(value)) {
+    set_data(info, type_, extra.data);
+  }
+}
+
+/// Apply `changes` to `info`, keeping its classic fields and the matching
+/// extras in sync.
+///
+/// Extras that do not mention a changed field are left exactly as they are.
+pub fn apply_changes(info: &mut Info, changes: &Changes) {
+  if let Some(name) = &changes.volume_name {
+    let old = std::mem::replace(&mut info.volume.name, name.clone());
+    set_data(info, 15, unicode_name(name));
+    let depth = info
+      .extra(2)
+      .map(|e| e.data.iter().filter(|&&b| b == b':').count())
+      .unwrap_or(0);
+    rename_component(info, 2, ':', depth, &old, name);
+    if info
+      .extra(19)
+      .is_some_and(|e| e.data.starts_with(b"/Volumes/"))
+    {
+      // Mounted under the name with its `/`s stored as `:`s.
+      rename_component(info, 19, '/', 0, &posix_name(&old), &posix_name(name));
+    }
+  }
+  if let Some(filename) = &changes.target_filename {
+    let old = std::mem::replace(&mut info.target.filename, filename.clone());
+    set_data(info, 14, unicode_name(filename));
+    rename_component(info, 2, ':', 0, &old, filename);
+    rename_component(info, 18, '/', 0, &posix_name(&old), &posix_name(filename));
+  }
+  if let Some(name) = &changes.parent_name {
+    let old = std::mem::replace(&mut info.parent.name, name.clone());
+    set_value(info, 0, name.clone());
+    rename_component(info, 2, ':', 1, &old, name);
+    // On the volume root the parent is the volume itself, which `/` stands for.
+    if info
+      .extra(18)
+      .is_some_and(|e| e.data.iter().filter(|&&b| b == b'/').count() > 1)
+    {
+      rename_component(info, 18, '/', 1, &posix_name(&old), &posix_name(name));
+    }
+  }
+  if let Some(id) = changes.target_id {
+    info.target.id = id;
+  }
+  if let Some(id) = changes.parent_id {
+    info.parent.id = id;
+    set_data(info, 1, id.to_be_bytes().to_vec());
+  }
+  if let Some(created) = changes.target_created {
+    info.target.created = created;
+  }
+  if let Some(created) = changes.volume_created {
+    info.volume.created = created;
+  }
+}
+
+/// Decode `record`, apply `changes` and encode it again.
+pub fn update_alias(record: &[u8], changes: &Changes) -> Result<Vec<u8>> {
+  let mut info = decode(record)?;
+  apply_changes(&mut info, changes);
+  encode(&info)
+}
+
+/// Where [`rebase_alias`] moves a record to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rebase {
+  /// The directory in the record's posix path that becomes the root of the
+  /// new volume, e.g. a staging directory, spelled as the record spells it.
+  /// `None` stands for the recorded mount point (extra 19).
+  pub from_path: Option<String>,
+  /// Mount point of the new volume, e.g. `/Volumes/My App`.
+  pub volume_path: String,
+  /// The name Finder shows for the new volume.
+  pub volume_name: String,
+  /// Zero the target and parent ids, which belong to the old volume.
+  pub zero_ids: bool,
+  /// Fields to set after moving, e.g. the ids the target will have on the
+  /// new volume. The names are ignored; they follow from the new path.
+  pub changes: Changes,
+}
+
+/// Move `info` from whatever directory `rebase.from_path` names to the root
+/// of the volume mounted at `rebase.volume_path`.
+///
+/// The volume name (classic and extra 15), the mount point (19), the
+/// volume-relative path (18), the carbon path (2) and the parent name (0)
+/// are rewritten for the new location. The disk image record (20) belongs
+/// to the old volume and is dropped. Ids, dates and the volume signature
+/// cannot be known for the new volume: they are kept unless `rebase` zeroes
+/// or overrides them.
+pub fn apply_rebase(info: &mut Info, rebase: &Rebase) -> Result<()> {
+  let target_path = info.posix_path().ok_or_else(|| {
+    Error::InvalidInfo("The record has no posix path (extras 18 and 19) to rebase.".to_owned())
+  })?;
+  let from_path = rebase
+    .from_path
+    .clone()
+    .or_else(|| String::from_utf8(info.extra(19)?.data.clone()).ok())
+    .unwrap_or_default();
+  let components: Vec<&str> = Path::new(&target_path)
+    .strip_prefix(&from_path)
+    .ok()
+    .and_then(|inner| inner.iter().map(|c| c.to_str()).collect())
+    .ok_or_else(|| {
+      Error::InvalidPath(format!(
+        "The target path {target_path} is not inside {from_path}."
+      ))
+    })?;
+  let [parents @ .., _] = components.as_slice() else {
+    return Err(Error::InvalidPath(format!(
+      "The target path {target_path} is {from_path} itself, which would be the new volume."
+    )));
+  };
+
+  let volume_path = rebase.volume_path.trim_end_matches('/');
+  let volume_path = if volume_path.is_empty() {
+    "/"
+  } else {
+    volume_path
+  };
+
+  info.volume.name = rebase.volume_name.clone();
+  info.volume.type_ = if volume_path == "/" {
+    VolumeType::Local
+  } else {
+    VolumeType::Other
+  };
+  set_data(info, 15, unicode_name(&rebase.volume_name));
+  set_value(info, 18, format!("/{}", components.join("/")));
+  set_value(info, 19, volume_path.to_owned());
+
+  let parent_name = match parents.last() {
+    Some(parent) => display_name(parent),
+    None => rebase.volume_name.clone(),
+  };
+  info.parent.name = parent_name.clone();
+  set_value(info, 0, parent_name);
+  let mut carbon_path = vec![rebase.volume_name.clone()];
+  carbon_path.extend(parents.iter().map(|c| display_name(c)));
+  carbon_path.push(format!("\0{}", info.target.filename));
+  set_value(info, 2, carbon_path.join(":"));
+  info.extra.retain(|e| e.type_ != 20);
+
+  if rebase.zero_ids {
+    info.target.id = 0;
+    info.parent.id = 0;
+    set_data(info, 1, vec![0; 4]);
+  }
+  apply_changes(
+    info,
+    &Changes {
+      volume_name: None,
+      target_filename: None,
+      parent_name: None,
+      ..rebase.changes.clone()
+    },
+  );
+  Ok(())
+}
+
+/// Decode `record`, move it with [`apply_rebase`] and encode it again.
+pub fn rebase_alias(record: &[u8], rebase: &Rebase) -> Result<Vec<u8>> {
+  let mut info = decode(record)?;
+  apply_rebase(&mut info, rebase)?;
+  encode(&info)
+}
+
+#[cfg(test)]
+mod test {
+  use crate::fixture::{fixture_bytes, fixture_info};
+  use crate::{decode, Changes};
+
+  fn updated(changes: Changes) -> crate::Info {
+    let record = super::update_alias(&fixture_bytes(), &changes).unwrap();
+    let info = decode(&record).unwrap();
+    assert_eq!(
+      u16::from_be_bytes([record[4], record[5]]) as usize,
+      record.len()
+    );
+    info
+  }
+
+  fn data(info: &crate::Info, type_: i16) -> &[u8] {
+    &info.extra(type_).unwrap().data
+  }
+
+  #[test]
+  fn no_changes_is_byte_exact() {
+    assert_eq!(
+      super::update_alias(&fixture_bytes(), &Changes::default()).unwrap(),
+      fixture_bytes()
+    );
+  }
+
+  #[test]
+  fn rename_volume() {
+    let info = updated(Changes {
+      volume_name: Some("My Longer App Title".to_owned()),
+      ..Default::default()
+    });
+    assert_eq!(info.volume.name, "My Longer App Title");
+    assert_eq!(
+      data(&info, 2),
+      b"My Longer App Title:.background:\0TestBkg.tiff"
+    );
+    assert_eq!(&data(&info, 15)[..2], &[0, 19]);
+    assert_eq!(data(&info, 19), b"/Volumes/My Longer App Title");
+    // Untouched extras survive as they were.
+    let fixture = fixture_info();
+    assert_eq!(info.extra[0], fixture.extra[0]);
+    assert_eq!(info.extra[1], fixture.extra[1]);
+    assert_eq!(info.extra[4], fixture.extra[4]);
+  }
+
+  #[test]
+  fn rename_volume_with_slash() {
+    let info = updated(Changes {
+      volume_name: Some("App 1/2".to_owned()),
+      ..Default::default()
+    });
+    assert_eq!(info.volume.name, "App 1/2");
+    assert_eq!(data(&info, 2), b"App 1/2:.background:\0TestBkg.tiff");
+    assert_eq!(data(&info, 19), b"/Volumes/App 1:2");
+
+    let record = super::update_alias(
+      &crate::encode(&info).unwrap(),
+      &Changes {
+        volume_name: Some("App 2/2".to_owned()),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+    assert_eq!(data(&decode(&record).unwrap(), 19), b"/Volumes/App 2:2");
+  }
+
+  #[test]
+  fn rebase_staging_directory() {
+    let staged = crate::build_info(
+      "/private/tmp/dmgroot/.background/bkg.tiff",
+      "/",
+      crate::Target {
+        id: 1200,
+        filename: "bkg.tiff".to_owned(),
+        ..fixture_info().target
+      },
+      crate::Parent {
+        id: 1100,
+        name: ".background".to_owned(),
+      },
+      crate::Volume {
+        name: "Macintosh HD".to_owned(),
+        type_: crate::VolumeType::Local,
+        ..fixture_info().volume
+      },
+    )
+    .unwrap();
+    let rebase = super::Rebase {
+      from_path: Some("/private/tmp/dmgroot".to_owned()),
+      volume_path: "/Volumes/My App".to_owned(),
+      volume_name: "My App".to_owned(),
+      zero_ids: true,
+      changes: Changes {
+        volume_created: Some(fixture_info().volume.created),
+        ..Default::default()
+      },
+    };
+    let rebased =
+      decode(&super::rebase_alias(&crate::encode(&staged).unwrap(), &rebase).unwrap()).unwrap();
+
+    let mounted = crate::build_info(
+      "/Volumes/My App/.background/bkg.tiff",
+      "/Volumes/My App",
+      crate::Target {
+        id: 0,
+        ..staged.target.clone()
+      },
+      crate::Parent {
+        id: 0,
+        name: ".background".to_owned(),
+      },
+      crate::Volume {
+        name: "My App".to_owned(),
+        ..fixture_info().volume
+      },
+    )
+    .unwrap();
+    assert_eq!(rebased, mounted);
+  }
+
+  #[test]
+  fn rebase_mounted_volume() {
+    let info = decode(
+      &super::rebase_alias(
+        &fixture_bytes(),
+        &super::Rebase {
+          from_path: Some("/Volumes/Test Title/.background".to_owned()),
+          volume_path: "/Volumes/App 1:2/".to_owned(),
+          volume_name: "App 1/2".to_owned(),
+          changes: Changes {
+            // Names follow from the new path.
+            target_filename: Some("ignored.tiff".to_owned()),
+            target_id: Some(7),
+            ..Default::default()
+          },
+          ..Default::default()
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    let fixture = fixture_info();
+    assert_eq!(info.volume.name, "App 1/2");
+    assert_eq!(info.volume.created, fixture.volume.created);
+    assert_eq!(info.parent.name, "App 1/2");
+    assert_eq!(info.parent.id, fixture.parent.id);
+    assert_eq!(info.target.id, 7);
+    assert_eq!(data(&info, 0), b"App 1/2");
+    assert_eq!(data(&info, 2), b"App 1/2:\0TestBkg.tiff");
+    assert_eq!(data(&info, 18), b"/TestBkg.tiff");
+    assert_eq!(data(&info, 19), b"/Volumes/App 1:2");
+  }
+
+  #[test]
+  fn rebase_outside_from_path() {
+    for from_path in [
+      "/Volumes/Other",
+      "/Volumes/Test",
+      "/Volumes/Test Title/.background/TestBkg.tiff",
+    ] {
+      let rebase = super::Rebase {
+        from_path: Some(from_path.to_owned()),
+        volume_path: "/Volumes/My App".to_owned(),
+        volume_name: "My App".to_owned(),
+        ..Default::default()
+      };
+      assert!(
+        matches!(
+          super::rebase_alias(&fixture_bytes(), &rebase),
+          Err(crate::Error::InvalidPath(_))
+        ),
+        "{from_path}"
+      );
+    }
+  }
+
+  #[test]
+  fn rename_target_and_parent() {
+    let info = updated(Changes {
+      target_filename: Some("bg.png".to_owned()),
+      parent_name: Some(".bg".to_owned()),
+      parent_id: Some(42),
+      target_id: Some(43),
+      ..Default::default()
+    });
+    assert_eq!(info.target.filename, "bg.png");
+    assert_eq!(info.target.id, 43);
+    assert_eq!(info.parent.id, 42);
+    assert_eq!(data(&info, 0), b".bg");
+    assert_eq!(data(&info, 1), &[0, 0, 0, 42]);
+    assert_eq!(data(&info, 2), b"Test Title:.bg:\0bg.png");
+    assert_eq!(data(&info, 18), b"/.bg/bg.png");
+    assert_eq!(data(&info, 19), b"/Volumes/Test Title");
+  }
+
+  #[test]
+  fn rename_target_and_parent_with_slash() {
+    let info = updated(Changes {
+      target_filename: Some("Report 2023/Q4.pdf".to_owned()),
+      parent_name: Some("Q1/Q4".to_owned()),
+      ..Default::default()
+    });
+    assert_eq!(info.target.filename, "Report 2023/Q4.pdf");
+    assert_eq!(data(&info, 0), b"Q1/Q4");
+    // The carbon path separates with `:` and keeps the `/`s Finder shows,
+    // while the posix path stores them as `:`.
+    assert_eq!(data(&info, 2), b"Test Title:Q1/Q4:\0Report 2023/Q4.pdf");
+    assert_eq!(data(&info, 18), b"/Q1:Q4/Report 2023:Q4.pdf");
+  }
+}
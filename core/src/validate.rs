@@ -0,0 +1,385 @@
+//! Checking the framing of a record, for records that do not decode or do
+//! not resolve.
+
+use std::fmt;
+
+use crate::decode::{decode_header, V2_HEADER_LENGTH, V3_HEADER_LENGTH};
+use crate::Error;
+
+/// Something wrong with the framing of a record, found by [`validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+  /// The buffer is too short to hold the record length and version.
+  Truncated { available: usize },
+  /// The record length in the header is more than the buffer holds.
+  LengthExceedsBuffer { declared: usize, available: usize },
+  /// Bytes other than zero padding follow the declared length, which
+  /// decoders ignore.
+  TrailingData { declared: usize, available: usize },
+  /// The version is neither 2 nor 3.
+  UnsupportedVersion(u16),
+  /// The record length is less than the fixed-size header of its version.
+  LengthShorterThanHeader { declared: usize, header: usize },
+  /// A field of the fixed-size header has no valid value.
+  InvalidHeader { offset: usize, reason: String },
+  /// The extra at `offset` claims more bytes than remain in the record.
+  ExtraOverrun {
+    offset: usize,
+    index: usize,
+    type_: i16,
+    length: u16,
+    remaining: usize,
+  },
+  /// The byte that pads the odd length extra at `offset` is not zero.
+  NonzeroPadding {
+    offset: usize,
+    index: usize,
+    type_: i16,
+  },
+  /// The extras run up to the end of the record without the -1 marker.
+  MissingEndMarker { offset: usize },
+  /// The -1 marker at `offset` has a length other than 0.
+  EndMarkerLength { offset: usize, length: u16 },
+  /// The record goes on past its -1 marker.
+  DataAfterEndMarker { offset: usize, length: usize },
+}
+
+impl Problem {
+  /// A stable identifier for the kind of problem, e.g.
+  /// `LENGTH_EXCEEDS_BUFFER`. Unlike the message, it does not change
+  /// between releases.
+  pub fn code(&self) -> &'static str {
+    match self {
+      Problem::Truncated { .. } => "TRUNCATED",
+      Problem::LengthExceedsBuffer { .. } => "LENGTH_EXCEEDS_BUFFER",
+      Problem::TrailingData { .. } => "TRAILING_DATA",
+      Problem::UnsupportedVersion(_) => "UNSUPPORTED_VERSION",
+      Problem::LengthShorterThanHeader { .. } => "LENGTH_SHORTER_THAN_HEADER",
+      Problem::InvalidHeader { .. } => "INVALID_HEADER",
+      Problem::ExtraOverrun { .. } => "EXTRA_OVERRUN",
+      Problem::NonzeroPadding { .. } => "NONZERO_PADDING",
+      Problem::MissingEndMarker { .. } => "MISSING_END_MARKER",
+      Problem::EndMarkerLength { .. } => "END_MARKER_LENGTH",
+      Problem::DataAfterEndMarker { .. } => "DATA_AFTER_END_MARKER",
+    }
+  }
+
+  /// The offset in the buffer of the bytes at fault.
+  pub fn offset(&self) -> usize {
+    match self {
+      Problem::Truncated { .. } => 0,
+      Problem::LengthExceedsBuffer { .. } | Problem::LengthShorterThanHeader { .. } => 4,
+      Problem::TrailingData { declared, .. } => *declared,
+      Problem::UnsupportedVersion(_) => 6,
+      Problem::InvalidHeader { offset, .. }
+      | Problem::ExtraOverrun { offset, .. }
+      | Problem::NonzeroPadding { offset, .. }
+      | Problem::MissingEndMarker { offset }
+      | Problem::EndMarkerLength { offset, .. }
+      | Problem::DataAfterEndMarker { offset, .. } => *offset,
+    }
+  }
+}
+
+impl fmt::Display for Problem {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Problem::Truncated { available } => write!(
+        f,
+        "The buffer holds {available} bytes, too few for the record length and version."
+      ),
+      Problem::LengthExceedsBuffer {
+        declared,
+        available,
+      } => write!(
+        f,
+        "The header declares {declared} bytes, but the buffer only holds {available}."
+      ),
+      Problem::TrailingData {
+        declared,
+        available,
+      } => write!(
+        f,
+        "The header declares {declared} bytes, but the buffer holds {available}, and not just zero padding."
+      ),
+      Problem::UnsupportedVersion(version) => {
+        write!(f, "The version is {version}, where 2 or 3 is expected.")
+      }
+      Problem::LengthShorterThanHeader { declared, header } => write!(
+        f,
+        "The header declares {declared} bytes, fewer than the {header} byte header."
+      ),
+      Problem::InvalidHeader { reason, .. } => write!(f, "The header is invalid: {reason}."),
+      Problem::ExtraOverrun {
+        index,
+        type_,
+        length,
+        remaining,
+        ..
+      } => write!(
+        f,
+        "Extra #{index} (type {type_}) is {length} bytes long, but only {remaining} remain."
+      ),
+      Problem::NonzeroPadding { index, type_, .. } => write!(
+        f,
+        "Extra #{index} (type {type_}) is padded with a byte other than zero."
+      ),
+      Problem::MissingEndMarker { .. } => {
+        write!(f, "The extras end without the -1 end-of-record marker.")
+      }
+      Problem::EndMarkerLength { length, .. } => write!(
+        f,
+        "The -1 end-of-record marker has length {length}, where 0 is expected."
+      ),
+      Problem::DataAfterEndMarker { length, .. } => write!(
+        f,
+        "{length} bytes follow the -1 end-of-record marker within the record."
+      ),
+    }
+  }
+}
+
+/// Check the framing of the record in `buf`: its declared length against
+/// the buffer, its version and header, the length and padding of each
+/// extra, and the -1 marker that ends them. Unlike [`crate::decode`], this
+/// goes on past what it can and lists every problem it finds, in the order
+/// of their offsets; a record without problems is well formed, though it
+/// may still not resolve.
+pub fn validate(buf: &[u8]) -> Vec<Problem> {
+  let mut problems = Vec::new();
+  if buf.len() < 8 {
+    problems.push(Problem::Truncated {
+      available: buf.len(),
+    });
+    return problems;
+  }
+  let declared = u16::from_be_bytes([buf[4], buf[5]]) as usize;
+  let version = u16::from_be_bytes([buf[6], buf[7]]);
+  if declared > buf.len() {
+    problems.push(Problem::LengthExceedsBuffer {
+      declared,
+      available: buf.len(),
+    });
+  }
+  let header = match version {
+    2 => V2_HEADER_LENGTH,
+    3 => V3_HEADER_LENGTH,
+    _ => {
+      problems.push(Problem::UnsupportedVersion(version));
+      return problems;
+    }
+  };
+  if declared < header {
+    problems.push(Problem::LengthShorterThanHeader { declared, header });
+    return problems;
+  }
+  // Go on with what there is of a record that is cut short.
+  let record = &buf[..declared.min(buf.len())];
+  if record.len() < header {
+    return problems;
+  }
+  if let Err(Error::InvalidRecord { offset, reason }) = decode_header(record, version) {
+    problems.push(Problem::InvalidHeader { offset, reason });
+  }
+  extras(record, header, &mut problems);
+  if buf[record.len()..].iter().any(|&byte| byte != 0) {
+    problems.push(Problem::TrailingData {
+      declared,
+      available: buf.len(),
+    });
+  }
+  problems
+}
+
+/// Walk the extras of `record` from `offset` to the -1 marker.
+fn extras(record: &[u8], mut offset: usize, problems: &mut Vec<Problem>) {
+  let mut index = 0;
+  loop {
+    if record.len() - offset < 4 {
+      problems.push(Problem::MissingEndMarker { offset });
+      return;
+    }
+    let type_ = i16::from_be_bytes([record[offset], record[offset + 1]]);
+    let length = u16::from_be_bytes([record[offset + 2], record[offset + 3]]);
+    if type_ == -1 {
+      if length != 0 {
+        problems.push(Problem::EndMarkerLength { offset, length });
+      }
+      let end = offset + 4;
+      if end < record.len() {
+        problems.push(Problem::DataAfterEndMarker {
+          offset: end,
+          length: record.len() - end,
+        });
+      }
+      return;
+    }
+    let remaining = record.len() - offset - 4;
+    let padded = length as usize + (length % 2) as usize;
+    if padded > remaining {
+      problems.push(Problem::ExtraOverrun {
+        offset,
+        index,
+        type_,
+        length,
+        remaining,
+      });
+      return;
+    }
+    let padding = offset + 4 + length as usize;
+    if length % 2 == 1 && record[padding] != 0 {
+      problems.push(Problem::NonzeroPadding {
+        offset: padding,
+        index,
+        type_,
+      });
+    }
+    offset += 4 + padded;
+    index += 1;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{validate, Problem};
+  use crate::fixture::fixture_bytes;
+
+  fn codes(buf: &[u8]) -> Vec<&'static str> {
+    validate(buf).iter().map(Problem::code).collect()
+  }
+
+  fn set_length(buf: &mut [u8], length: usize) {
+    buf[4..6].copy_from_slice(&(length as u16).to_be_bytes());
+  }
+
+  #[test]
+  fn well_formed() {
+    let record = fixture_bytes();
+    assert_eq!(validate(&record), []);
+    // Zero padding after the record is fine, as in a `.DS_Store` blob.
+    let mut padded = record.clone();
+    padded.extend_from_slice(&[0; 6]);
+    assert_eq!(validate(&padded), []);
+    let info = crate::fixture::fixture_info();
+    assert_eq!(validate(&crate::encode(&info).unwrap()), []);
+  }
+
+  #[test]
+  fn lengths() {
+    let record = fixture_bytes();
+    assert_eq!(
+      validate(&record[..6]),
+      [Problem::Truncated { available: 6 }]
+    );
+    assert_eq!(
+      validate(&record[..record.len() - 2]),
+      [
+        Problem::LengthExceedsBuffer {
+          declared: record.len(),
+          available: record.len() - 2
+        },
+        Problem::MissingEndMarker {
+          offset: record.len() - 4
+        },
+      ]
+    );
+
+    let mut trailing = record.clone();
+    trailing.push(1);
+    assert_eq!(codes(&trailing), ["TRAILING_DATA"]);
+    assert_eq!(validate(&trailing)[0].offset(), record.len());
+
+    let mut short = record.clone();
+    set_length(&mut short, 100);
+    assert_eq!(
+      validate(&short)[0],
+      Problem::LengthShorterThanHeader {
+        declared: 100,
+        header: 150
+      }
+    );
+
+    let mut version = record.clone();
+    version[7] = 4;
+    assert_eq!(validate(&version), [Problem::UnsupportedVersion(4)]);
+  }
+
+  #[test]
+  fn header_and_extras() {
+    let record = fixture_bytes();
+    let mut bad = record.clone();
+    // An unknown volume type and a nonzero pad after the 11 byte parent
+    // name, the first extra.
+    bad[45] = 9;
+    bad[150 + 4 + 11] = 0xff;
+    assert_eq!(
+      validate(&bad),
+      [
+        Problem::InvalidHeader {
+          offset: 44,
+          reason: "unknown volume type 9".to_owned()
+        },
+        Problem::NonzeroPadding {
+          offset: 165,
+          index: 0,
+          type_: 0
+        },
+      ]
+    );
+
+    let mut overrun = record.clone();
+    overrun[152..154].copy_from_slice(&1000u16.to_be_bytes());
+    assert!(matches!(
+      validate(&overrun)[..],
+      [Problem::ExtraOverrun {
+        offset: 150,
+        index: 0,
+        type_: 0,
+        length: 1000,
+        ..
+      }]
+    ));
+  }
+
+  #[test]
+  fn end_marker() {
+    let record = fixture_bytes();
+    let marker = record.len() - 4;
+    let mut length = record.clone();
+    length[marker + 3] = 2;
+    assert_eq!(
+      validate(&length),
+      [Problem::EndMarkerLength {
+        offset: marker,
+        length: 2
+      }]
+    );
+
+    let mut after = record.clone();
+    after.extend_from_slice(&[0; 4]);
+    set_length(&mut after, record.len() + 4);
+    assert_eq!(
+      validate(&after),
+      [Problem::DataAfterEndMarker {
+        offset: record.len(),
+        length: 4
+      }]
+    );
+    // Decoding ignores both.
+    assert!(crate::decode(&after).is_ok());
+    assert!(crate::decode(&length).is_ok());
+  }
+
+  #[test]
+  fn never_panics_on_truncation_or_corruption() {
+    let record = fixture_bytes();
+    for length in 0..record.len() {
+      let mut buf = record[..length].to_vec();
+      let _ = validate(&buf);
+      if length >= 8 {
+        set_length(&mut buf, length);
+        assert_eq!(validate(&buf).is_empty(), crate::decode(&buf).is_ok());
+      }
+    }
+  }
+}
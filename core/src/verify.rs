@@ -0,0 +1,201 @@
+//! Round-tripping our records through the system resolver.
+
+#[cfg(target_os = "macos")]
+use std::path::Path;
+
+#[cfg(any(target_os = "macos", test))]
+use crate::time::unix_millis;
+#[cfg(not(target_os = "macos"))]
+use crate::Error;
+#[cfg(any(target_os = "macos", test))]
+use crate::Info;
+use crate::{CreateOptions, Result};
+
+/// What [`verify_path`] found when it resolved the record for a target.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Verification {
+  /// Where the system resolver took the record, or `None` if it could not
+  /// resolve it at all.
+  pub resolved_path: Option<String>,
+  /// Fields of the record that do not match the file it resolved to.
+  pub mismatches: Vec<Mismatch>,
+}
+
+impl Verification {
+  /// Whether the record resolved, and to the file it was made for.
+  pub fn is_ok(&self) -> bool {
+    self.resolved_path.is_some() && self.mismatches.is_empty()
+  }
+}
+
+/// A field whose value in the record differs from the resolved file's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+  /// The field, e.g. `target.id`.
+  pub field: &'static str,
+  /// The value in the record.
+  pub expected: String,
+  /// The value for the file the record resolved to.
+  pub actual: String,
+}
+
+/// Compare the fields that identify a target. Paths are left out: the
+/// resolver hands back canonical paths, e.g. `/private/var` for `/var`.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn mismatches(expected: &Info, actual: &Info) -> Vec<Mismatch> {
+  let fields = [
+    (
+      "volume.name",
+      expected.volume.name.clone(),
+      actual.volume.name.clone(),
+    ),
+    (
+      "volume.created",
+      unix_millis(expected.volume.created).to_string(),
+      unix_millis(actual.volume.created).to_string(),
+    ),
+    (
+      "parent.id",
+      expected.parent.id.to_string(),
+      actual.parent.id.to_string(),
+    ),
+    (
+      "parent.name",
+      expected.parent.name.clone(),
+      actual.parent.name.clone(),
+    ),
+    (
+      "target.type",
+      format!("{:?}", expected.target.type_),
+      format!("{:?}", actual.target.type_),
+    ),
+    (
+      "target.id",
+      expected.target.id.to_string(),
+      actual.target.id.to_string(),
+    ),
+    (
+      "target.filename",
+      expected.target.filename.clone(),
+      actual.target.filename.clone(),
+    ),
+    (
+      "target.created",
+      unix_millis(expected.target.created).to_string(),
+      unix_millis(actual.target.created).to_string(),
+    ),
+  ];
+  fields
+    .into_iter()
+    .filter(|(_, expected, actual)| expected != actual)
+    .map(|(field, expected, actual)| Mismatch {
+      field,
+      expected,
+      actual,
+    })
+    .collect()
+}
+
+/// Create the record for `target_path`, resolve it with
+/// `CFURLCreateByResolvingBookmarkData` (after converting it to bookmark
+/// data, as the classic Alias Manager is gone), and compare the file it lands
+/// on against the target.
+///
+/// Resolution never shows UI or mounts volumes.
+#[cfg(target_os = "macos")]
+pub fn verify_path<P: AsRef<Path>>(
+  target_path: P,
+  options: &CreateOptions,
+) -> Result<Verification> {
+  use std::ptr;
+
+  use core_foundation::base::{kCFAllocatorDefault, TCFType};
+  use core_foundation::data::CFData;
+  use core_foundation::error::CFError;
+  use core_foundation::url::{
+    kCFBookmarkResolutionWithoutMountingMask, kCFBookmarkResolutionWithoutUIMask,
+    CFURLCreateBookmarkDataFromAliasRecord, CFURLCreateByResolvingBookmarkData, CFURL,
+  };
+
+  let expected = crate::info_for_path_with(target_path, options)?;
+  let record = CFData::from_buffer(&crate::encode_with(&expected, options.compat)?);
+
+  let bookmark = unsafe {
+    CFURLCreateBookmarkDataFromAliasRecord(kCFAllocatorDefault, record.as_concrete_TypeRef())
+  };
+  if bookmark.is_null() {
+    return Ok(Verification::default());
+  }
+  let bookmark = unsafe { CFData::wrap_under_create_rule(bookmark) };
+
+  let mut is_stale = 0;
+  let mut error = ptr::null_mut();
+  let url = unsafe {
+    CFURLCreateByResolvingBookmarkData(
+      kCFAllocatorDefault,
+      bookmark.as_concrete_TypeRef(),
+      kCFBookmarkResolutionWithoutUIMask | kCFBookmarkResolutionWithoutMountingMask,
+      ptr::null(),
+      ptr::null(),
+      &mut is_stale,
+      &mut error,
+    )
+  };
+  if !error.is_null() {
+    drop(unsafe { CFError::wrap_under_create_rule(error) });
+  }
+  if url.is_null() {
+    return Ok(Verification::default());
+  }
+  let Some(resolved_path) = unsafe { CFURL::wrap_under_create_rule(url) }.to_path() else {
+    return Ok(Verification::default());
+  };
+
+  let actual = crate::info_for_path_with(&resolved_path, options)?;
+  Ok(Verification {
+    resolved_path: Some(resolved_path.to_string_lossy().into_owned()),
+    mismatches: mismatches(&expected, &actual),
+  })
+}
+
+/// Create the record for `target_path` and check that the system resolver
+/// takes it back to the target.
+///
+/// Resolving records needs CoreFoundation.
+#[cfg(not(target_os = "macos"))]
+pub fn verify_path<P: AsRef<std::path::Path>>(
+  _target_path: P,
+  _options: &CreateOptions,
+) -> Result<Verification> {
+  Err(Error::Unsupported("Verifying an alias"))
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::Duration;
+
+  use crate::fixture::fixture_info;
+
+  #[test]
+  fn mismatches() {
+    let expected = fixture_info();
+    assert_eq!(super::mismatches(&expected, &expected), []);
+
+    let mut actual = fixture_info();
+    actual.target.id += 1;
+    actual.volume.created += Duration::from_secs(1);
+    let mismatches = super::mismatches(&expected, &actual);
+    let fields: Vec<_> = mismatches.iter().map(|m| m.field).collect();
+    assert_eq!(fields, ["volume.created", "target.id"]);
+    assert_eq!(mismatches[1].expected, expected.target.id.to_string());
+    assert_eq!(mismatches[1].actual, actual.target.id.to_string());
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn verify_path() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+    let verification = super::verify_path(path, &Default::default()).unwrap();
+    assert!(verification.is_ok(), "{verification:?}");
+  }
+}
@@ -0,0 +1,664 @@
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::PathBuf;
+use std::{fs, path::Path};
+
+use core_foundation::{
+  base::{Boolean, CFAllocatorRef, CFIndex, CFRange, CFType, CFTypeRef, TCFType},
+  dictionary::CFDictionaryRef,
+  error::{CFError, CFErrorRef},
+  string::{kCFStringEncodingUTF8, CFString, CFStringGetBytes, CFStringRef},
+  url::{kCFURLVolumeNameKey, CFURLRef, CFURL},
+};
+
+use crate::source::{DirEntry, DiskDescription, FileIds, FileStat, FsStat, MetadataSource};
+use crate::{Error, Result, VolumeUuid};
+
+/// The real filesystem.
+pub(crate) struct System;
+
+impl MetadataSource for System {
+  fn stat(&self, path: &Path) -> std::io::Result<FileStat> {
+    Ok(FileStat::from(&fs::metadata(path)?))
+  }
+
+  fn statfs(&self, path: &Path) -> std::io::Result<FsStat> {
+    Ok(fs_stat(&statfs(path)?))
+  }
+
+  fn mounts(&self) -> std::io::Result<Vec<FsStat>> {
+    // getmntinfo's buffer belongs to libc and is reused by the next call on
+    // any thread, so copy it out of a fresh getfsstat buffer instead.
+    let count = unsafe { libc::getfsstat(std::ptr::null_mut(), 0, libc::MNT_NOWAIT) };
+    if count < 0 {
+      return Err(std::io::Error::last_os_error());
+    }
+    // Leave room for volumes mounted in between.
+    let mut stats = Vec::<libc::statfs>::with_capacity(count as usize + 8);
+    let size = (stats.capacity() * std::mem::size_of::<libc::statfs>()) as libc::c_int;
+    let count = unsafe { libc::getfsstat(stats.as_mut_ptr(), size, libc::MNT_NOWAIT) };
+    if count < 0 {
+      return Err(std::io::Error::last_os_error());
+    }
+    unsafe { stats.set_len(count as usize) };
+    Ok(stats.iter().map(fs_stat).collect())
+  }
+
+  fn file_ids(&self, path: &Path) -> std::io::Result<FileIds> {
+    file_ids(path)
+  }
+
+  fn canonicalize(&self, path: &Path) -> std::io::Result<PathBuf> {
+    fs::canonicalize(path)
+  }
+
+  fn read_dir(&self, path: &Path) -> std::io::Result<Vec<DirEntry>> {
+    fs::read_dir(path)?
+      .map(|entry| {
+        let entry = entry?;
+        Ok(DirEntry {
+          name: entry.file_name(),
+          is_symlink: entry.file_type()?.is_symlink(),
+        })
+      })
+      .collect()
+  }
+
+  fn cf_volume_name(&self, volume_path: &str) -> Option<String> {
+    get_volume_name(volume_path).filter(|name| !name.is_empty())
+  }
+
+  fn attrlist_volume_name(&self, volume_path: &str) -> Option<String> {
+    attrlist_volume_name(volume_path)
+  }
+
+  fn disk_image(&self, volume_path: &Path) -> Option<PathBuf> {
+    // Disk images are mounted under /Volumes unless told otherwise; don't
+    // pay for running hdiutil on every other volume.
+    if !volume_path.starts_with("/Volumes") {
+      return None;
+    }
+    let output = std::process::Command::new("/usr/bin/hdiutil")
+      .args(["info", "-plist"])
+      .stderr(std::process::Stdio::null())
+      .output()
+      .ok()?;
+    if !output.status.success() {
+      return None;
+    }
+    disk_image_from_info(&output.stdout, volume_path)
+  }
+
+  fn disk_description(&self, volume_path: &Path) -> Option<DiskDescription> {
+    disk_description(volume_path)
+  }
+
+  fn volume_uuid(&self, volume_path: &Path) -> Option<VolumeUuid> {
+    attrlist_volume_uuid(volume_path)
+  }
+
+  fn path_for_id(&self, volume_path: &Path, id: u64) -> Option<PathBuf> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+    use std::os::unix::fs::MetadataExt;
+
+    // volfs names every object by device and id. Opening for events only
+    // reads nothing, so it does not materialize dataless files either.
+    let dev = fs::metadata(volume_path).ok()?.dev();
+    let path = CString::new(format!("/.vol/{dev}/{id}")).ok()?;
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_EVTONLY | libc::O_CLOEXEC) };
+    if fd == -1 {
+      return None;
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    fd_path(std::os::fd::AsRawFd::as_raw_fd(&fd)).ok()
+  }
+}
+
+/// Attach the disk image at `image` where it mounts by default, without
+/// showing it in Finder.
+pub(crate) fn attach_disk_image(image: &Path) -> Result<()> {
+  let output = std::process::Command::new("/usr/bin/hdiutil")
+    .args(["attach", "-nobrowse", "-noautoopen", "-noverify"])
+    .arg(image)
+    .output()?;
+  if output.status.success() {
+    return Ok(());
+  }
+  Err(Error::VolumeLookupFailed {
+    path: image.to_string_lossy().into_owned(),
+    error: std::io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_owned()),
+  })
+}
+
+/// `DADiskCopyDescription` of the disk mounted at `volume_path`.
+///
+/// Describing a disk is synchronous and needs no run loop, so a session
+/// that is never scheduled is enough, on any thread.
+fn disk_description(volume_path: &Path) -> Option<DiskDescription> {
+  use core_foundation::base::kCFAllocatorDefault;
+  use core_foundation::boolean::CFBoolean;
+  use core_foundation::dictionary::CFDictionary;
+
+  let url = CFURL::from_path(volume_path, true)?;
+  let session = unsafe { DASessionCreate(kCFAllocatorDefault) };
+  if session.is_null() {
+    return None;
+  }
+  let session = unsafe { CFType::wrap_under_create_rule(session) };
+  let disk = unsafe {
+    DADiskCreateFromVolumePath(
+      kCFAllocatorDefault,
+      session.as_CFTypeRef(),
+      url.as_concrete_TypeRef(),
+    )
+  };
+  if disk.is_null() {
+    return None;
+  }
+  let disk = unsafe { CFType::wrap_under_create_rule(disk) };
+  let description = unsafe { DADiskCopyDescription(disk.as_CFTypeRef()) };
+  if description.is_null() {
+    return None;
+  }
+  let description: CFDictionary = unsafe { CFDictionary::wrap_under_create_rule(description) };
+  let flag = |key: CFStringRef| {
+    let value = description.find(key.cast())?;
+    unsafe { CFType::wrap_under_get_rule(*value) }
+      .downcast_into::<CFBoolean>()
+      .map(bool::from)
+  };
+  unsafe {
+    Some(DiskDescription {
+      network: flag(kDADiskDescriptionVolumeNetworkKey),
+      removable: flag(kDADiskDescriptionMediaRemovableKey),
+      ejectable: flag(kDADiskDescriptionMediaEjectableKey),
+      internal: flag(kDADiskDescriptionDeviceInternalKey),
+    })
+  }
+}
+
+/// The `image-path` of the image in `hdiutil info -plist` output that has a
+/// partition mounted at `volume_path`.
+fn disk_image_from_info(plist: &[u8], volume_path: &Path) -> Option<PathBuf> {
+  use core_foundation::array::CFArray;
+  use core_foundation::base::CFType;
+  use core_foundation::data::CFData;
+  use core_foundation::dictionary::CFDictionary;
+  use core_foundation::propertylist::{create_with_data, kCFPropertyListImmutable};
+
+  fn value<T: core_foundation::ConcreteCFType>(
+    dictionary: &CFDictionary,
+    key: &'static str,
+  ) -> Option<T> {
+    let key = CFString::from_static_string(key);
+    let value = dictionary.find(key.as_CFTypeRef())?;
+    unsafe { CFType::wrap_under_get_rule(*value) }.downcast_into::<T>()
+  }
+  fn dictionaries(array: &CFArray) -> impl Iterator<Item = CFDictionary> + '_ {
+    array
+      .iter()
+      .filter_map(|item| unsafe { CFType::wrap_under_get_rule(*item) }.downcast_into())
+  }
+
+  let (list, _) = create_with_data(CFData::from_buffer(plist), kCFPropertyListImmutable).ok()?;
+  let info = unsafe { CFType::wrap_under_create_rule(list) }.downcast_into::<CFDictionary>()?;
+  let images = value::<CFArray>(&info, "images")?;
+  let image = dictionaries(&images).find(|image| {
+    value::<CFArray>(image, "system-entities").is_some_and(|entities| {
+      dictionaries(&entities).any(|entity| {
+        value::<CFString>(&entity, "mount-point")
+          .is_some_and(|mount_point| Path::new(&mount_point.to_string()) == volume_path)
+      })
+    })
+  })?;
+  value::<CFString>(&image, "image-path").map(|path| PathBuf::from(path.to_string()))
+}
+
+fn fs_stat(stat: &libc::statfs) -> FsStat {
+  let c_str = |chars: &[libc::c_char]| unsafe { CStr::from_ptr(chars.as_ptr()) };
+  FsStat {
+    mount_point: PathBuf::from(OsStr::from_bytes(c_str(&stat.f_mntonname).to_bytes())),
+    device: c_str(&stat.f_mntfromname).to_string_lossy().into_owned(),
+    fs_type: c_str(&stat.f_fstypename).to_string_lossy().into_owned(),
+    flags: stat.f_flags,
+  }
+}
+
+fn statfs<P: AsRef<OsStr> + ?Sized>(path: &P) -> std::io::Result<libc::statfs> {
+  let path = CString::new(path.as_ref().as_bytes())?;
+  let mut stat = MaybeUninit::<libc::statfs>::uninit();
+  if unsafe { libc::statfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+  Ok(unsafe { stat.assume_init() })
+}
+
+/// `ATTR_CMN_FILEID` and `ATTR_CMN_PARENTID` of `path`.
+fn file_ids(path: &Path) -> std::io::Result<FileIds> {
+  let path = CString::new(path.as_os_str().as_bytes())?;
+  let mut attributes = libc::attrlist {
+    bitmapcount: libc::ATTR_BIT_MAP_COUNT,
+    reserved: 0,
+    commonattr: libc::ATTR_CMN_FILEID | libc::ATTR_CMN_PARENTID,
+    volattr: 0,
+    dirattr: 0,
+    fileattr: 0,
+    forkattr: 0,
+  };
+  // The length of the returned attributes, then the ids in the order of
+  // their bits.
+  let mut buf = [0u8; 4 + 8 + 8];
+  if unsafe {
+    libc::getattrlist(
+      path.as_ptr(),
+      (&mut attributes as *mut libc::attrlist).cast(),
+      buf.as_mut_ptr().cast(),
+      buf.len(),
+      0,
+    )
+  } != 0
+  {
+    return Err(std::io::Error::last_os_error());
+  }
+  let id = |start: usize| {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[start..start + 8]);
+    u64::from_ne_bytes(bytes)
+  };
+  Ok(FileIds {
+    id: id(4),
+    parent_id: id(12),
+  })
+}
+
+/// `ATTR_VOL_NAME` of the volume holding `path`.
+fn attrlist_volume_name(path: &str) -> Option<String> {
+  let path = CString::new(path).ok()?;
+  let mut attributes = libc::attrlist {
+    bitmapcount: libc::ATTR_BIT_MAP_COUNT,
+    reserved: 0,
+    commonattr: 0,
+    volattr: libc::ATTR_VOL_INFO | libc::ATTR_VOL_NAME,
+    dirattr: 0,
+    fileattr: 0,
+    forkattr: 0,
+  };
+  // The length of the returned attributes, then an `attrreference_t`
+  // pointing at the NUL-terminated name, relative to itself. Names are at
+  // most 255 UTF-16 code units, each at most 3 bytes of UTF-8.
+  let mut buf = [0u8; 4 + 8 + 255 * 3 + 1];
+  if unsafe {
+    libc::getattrlist(
+      path.as_ptr(),
+      (&mut attributes as *mut libc::attrlist).cast(),
+      buf.as_mut_ptr().cast(),
+      buf.len(),
+      0,
+    )
+  } != 0
+  {
+    return None;
+  }
+  let offset = i32::from_ne_bytes(buf[4..8].try_into().ok()?);
+  let length = u32::from_ne_bytes(buf[8..12].try_into().ok()?);
+  let start = 4usize.checked_add(usize::try_from(offset).ok()?)?;
+  let name = buf.get(start..start.checked_add(length as usize)?)?;
+  let name = CStr::from_bytes_until_nul(name).ok()?.to_str().ok()?;
+  Some(name.to_owned()).filter(|name| !name.is_empty())
+}
+
+/// `ATTR_VOL_UUID` of the volume holding `path`. Filesystems that keep no
+/// UUID leave it all zeros.
+fn attrlist_volume_uuid(path: &Path) -> Option<VolumeUuid> {
+  let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+  let mut attributes = libc::attrlist {
+    bitmapcount: libc::ATTR_BIT_MAP_COUNT,
+    reserved: 0,
+    commonattr: 0,
+    volattr: libc::ATTR_VOL_INFO | libc::ATTR_VOL_UUID,
+    dirattr: 0,
+    fileattr: 0,
+    forkattr: 0,
+  };
+  // The length of the returned attributes, then the `uuid_t`.
+  let mut buf = [0u8; 4 + 16];
+  if unsafe {
+    libc::getattrlist(
+      path.as_ptr(),
+      (&mut attributes as *mut libc::attrlist).cast(),
+      buf.as_mut_ptr().cast(),
+      buf.len(),
+      0,
+    )
+  } != 0
+  {
+    return None;
+  }
+  let uuid: [u8; 16] = buf[4..].try_into().ok()?;
+  Some(VolumeUuid(uuid)).filter(|uuid| uuid.0 != [0; 16])
+}
+
+/// The path the kernel reports for the open file `fd`.
+pub(crate) fn fd_path(fd: i32) -> std::io::Result<PathBuf> {
+  let mut buf = vec![0u8; libc::PATH_MAX as usize];
+  if unsafe { libc::fcntl(fd, libc::F_GETPATH, buf.as_mut_ptr()) } == -1 {
+    return Err(std::io::Error::last_os_error());
+  }
+  let length = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+  buf.truncate(length);
+  Ok(PathBuf::from(OsString::from_vec(buf)))
+}
+
+const IOPOL_TYPE_VFS_MATERIALIZE_DATALESS_FILES: i32 = 3;
+const IOPOL_SCOPE_THREAD: i32 = 1;
+const IOPOL_MATERIALIZE_DATALESS_FILES_OFF: i32 = 1;
+
+/// While alive, filesystem calls on this thread fail with `EDEADLK` instead
+/// of downloading the contents of dataless files and directories.
+pub(crate) struct MaterializationOff {
+  previous: i32,
+}
+
+impl MaterializationOff {
+  pub(crate) fn new() -> std::io::Result<Self> {
+    let previous = unsafe {
+      getiopolicy_np(
+        IOPOL_TYPE_VFS_MATERIALIZE_DATALESS_FILES,
+        IOPOL_SCOPE_THREAD,
+      )
+    };
+    if previous == -1
+      || unsafe {
+        setiopolicy_np(
+          IOPOL_TYPE_VFS_MATERIALIZE_DATALESS_FILES,
+          IOPOL_SCOPE_THREAD,
+          IOPOL_MATERIALIZE_DATALESS_FILES_OFF,
+        )
+      } == -1
+    {
+      return Err(std::io::Error::last_os_error());
+    }
+    Ok(MaterializationOff { previous })
+  }
+}
+
+impl Drop for MaterializationOff {
+  fn drop(&mut self) {
+    unsafe {
+      setiopolicy_np(
+        IOPOL_TYPE_VFS_MATERIALIZE_DATALESS_FILES,
+        IOPOL_SCOPE_THREAD,
+        self.previous,
+      )
+    };
+  }
+}
+
+static FALSE: Boolean = false as Boolean;
+
+/// The display name of the volume mounted at `path`, from
+/// `kCFURLVolumeNameKey`, or `None` if CoreFoundation cannot tell.
+///
+/// CFURL resource properties can be read on any thread. Each call makes its
+/// own URL, so the values CoreFoundation caches on it are never shared
+/// either.
+pub(crate) fn get_volume_name(path: &str) -> Option<String> {
+  let url = CFURL::from_path(path, true)?;
+  let mut value: CFTypeRef = std::ptr::null();
+  let mut error: CFErrorRef = std::ptr::null_mut();
+  let copied = unsafe {
+    CFURLCopyResourcePropertyForKey(
+      url.as_concrete_TypeRef(),
+      kCFURLVolumeNameKey,
+      &mut value,
+      &mut error,
+    )
+  };
+  if copied == FALSE {
+    if !error.is_null() {
+      drop(unsafe { CFError::wrap_under_create_rule(error) });
+    }
+    return None;
+  }
+  if value.is_null() {
+    return None;
+  }
+  // A Copy function hands over its reference to the value.
+  let value = unsafe { CFType::wrap_under_create_rule(value) };
+  Some(string_from_cf(&value.downcast_into::<CFString>()?))
+}
+
+/// `string` in UTF-8. Unlike the C string accessors, `CFStringGetBytes`
+/// keeps a string with an embedded NUL whole.
+fn string_from_cf(string: &CFString) -> String {
+  let range = CFRange {
+    location: 0,
+    length: string.char_len(),
+  };
+  let mut length: CFIndex = 0;
+  unsafe {
+    CFStringGetBytes(
+      string.as_concrete_TypeRef(),
+      range,
+      kCFStringEncodingUTF8,
+      0,
+      FALSE,
+      std::ptr::null_mut(),
+      0,
+      &mut length,
+    )
+  };
+  let mut bytes = vec![0; length as usize];
+  let mut used: CFIndex = 0;
+  unsafe {
+    CFStringGetBytes(
+      string.as_concrete_TypeRef(),
+      range,
+      kCFStringEncodingUTF8,
+      0,
+      FALSE,
+      bytes.as_mut_ptr(),
+      length,
+      &mut used,
+    )
+  };
+  bytes.truncate(used as usize);
+  String::from_utf8_lossy(&bytes).into_owned()
+}
+
+extern "C" {
+  pub fn CFURLCopyResourcePropertyForKey(
+    url: CFURLRef,
+    key: CFStringRef,
+    propertyValueTypeRefPtr: *mut CFTypeRef,
+    error: *mut CFErrorRef,
+  ) -> Boolean;
+
+  fn getiopolicy_np(iotype: i32, scope: i32) -> i32;
+  fn setiopolicy_np(iotype: i32, scope: i32, policy: i32) -> i32;
+}
+
+// `DASessionRef`, `DADiskRef` and the description are CoreFoundation
+// objects, released like any other.
+#[link(name = "DiskArbitration", kind = "framework")]
+extern "C" {
+  fn DASessionCreate(allocator: CFAllocatorRef) -> CFTypeRef;
+  fn DADiskCreateFromVolumePath(
+    allocator: CFAllocatorRef,
+    session: CFTypeRef,
+    path: CFURLRef,
+  ) -> CFTypeRef;
+  fn DADiskCopyDescription(disk: CFTypeRef) -> CFDictionaryRef;
+
+  static kDADiskDescriptionVolumeNetworkKey: CFStringRef;
+  static kDADiskDescriptionMediaRemovableKey: CFStringRef;
+  static kDADiskDescriptionMediaEjectableKey: CFStringRef;
+  static kDADiskDescriptionDeviceInternalKey: CFStringRef;
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::Path;
+
+  use crate::source::{find_volume, mount_point, MetadataSource};
+
+  #[test]
+  fn filesystem_type() {
+    let name = super::System.statfs(Path::new("/")).unwrap().fs_type;
+    assert!(["apfs", "hfs"].contains(&name.as_str()), "{name}");
+  }
+
+  #[test]
+  fn mount_point_through_firmlink() {
+    // Home directories live on the data volume but, like Finder, we report
+    // them as part of the volume group mounted at `/`.
+    let home = super::System
+      .canonicalize(Path::new(&std::env::var("HOME").unwrap()))
+      .unwrap();
+    assert_eq!(mount_point(&super::System, &home).unwrap(), Path::new("/"));
+    let stat = super::System.stat(&home).unwrap();
+    assert_ne!(
+      find_volume(&super::System, &home, &stat).unwrap(),
+      Path::new("/")
+    );
+
+    let data = Path::new("/System/Volumes/Data/Users");
+    if data.exists() {
+      assert_eq!(
+        mount_point(&super::System, data).unwrap(),
+        Path::new("/System/Volumes/Data")
+      );
+    }
+  }
+
+  #[test]
+  fn disk_image_from_info() {
+    // The shape of `hdiutil info -plist`, trimmed to the keys we read.
+    let plist = br#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>framework</key><string>671.100.2</string>
+  <key>images</key>
+  <array>
+    <dict>
+      <key>image-path</key><string>/Users/me/Other.dmg</string>
+      <key>system-entities</key>
+      <array>
+        <dict><key>dev-entry</key><string>/dev/disk4</string></dict>
+        <dict>
+          <key>dev-entry</key><string>/dev/disk4s1</string>
+          <key>mount-point</key><string>/Volumes/Other</string>
+        </dict>
+      </array>
+    </dict>
+    <dict>
+      <key>image-path</key><string>/Users/me/Test Title.dmg</string>
+      <key>system-entities</key>
+      <array>
+        <dict>
+          <key>dev-entry</key><string>/dev/disk5s1</string>
+          <key>mount-point</key><string>/Volumes/Test Title</string>
+        </dict>
+      </array>
+    </dict>
+  </array>
+</dict>
+</plist>"#;
+    assert_eq!(
+      super::disk_image_from_info(plist, Path::new("/Volumes/Test Title")),
+      Some("/Users/me/Test Title.dmg".into())
+    );
+    assert_eq!(
+      super::disk_image_from_info(plist, Path::new("/Volumes/Missing")),
+      None
+    );
+    assert_eq!(
+      super::disk_image_from_info(b"not a plist", Path::new("/")),
+      None
+    );
+  }
+
+  #[test]
+  fn get_volume_name() {
+    let name = super::get_volume_name("/");
+    assert_eq!(name.as_deref(), Some("Macintosh HD"));
+    assert_eq!(super::get_volume_name("/nonexistent/path"), None);
+  }
+
+  #[test]
+  fn multi_byte_volume_name() {
+    // Characters that take two or three bytes each in UTF-8, so the name
+    // is much longer in bytes than in characters. None of them decomposes,
+    // since HFS+ stores names decomposed.
+    let name = "ßæøœĦŁ€→日本語の名前ßæøœĦŁ€→日本語";
+    let image = crate::fixture::DiskImage::attach("multi-byte-name", name);
+    let mount_point = image.mount_point.to_str().unwrap();
+    assert_eq!(super::get_volume_name(mount_point).as_deref(), Some(name));
+  }
+
+  #[test]
+  fn get_volume_name_from_threads() {
+    let expected = super::get_volume_name("/");
+    let threads: Vec<_> = (0..8)
+      .map(|_| {
+        std::thread::spawn(|| {
+          (0..100)
+            .map(|_| super::get_volume_name("/"))
+            .collect::<Vec<_>>()
+        })
+      })
+      .collect();
+    for thread in threads {
+      assert!(thread.join().unwrap().iter().all(|name| *name == expected));
+    }
+  }
+
+  #[test]
+  fn string_from_cf() {
+    use core_foundation::string::CFString;
+
+    for string in ["", "Test Title", "Test\0Title", "日本語\0ÄÖÜ"] {
+      assert_eq!(super::string_from_cf(&CFString::new(string)), string);
+    }
+  }
+
+  #[test]
+  fn file_ids() {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = std::env::temp_dir().join(format!("macos-alias-ids-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("other")).unwrap();
+    let target = dir.join("background.tiff");
+    let link = dir.join("other/background.tiff");
+    std::fs::write(&target, b"").unwrap();
+    std::fs::hard_link(&target, &link).unwrap();
+    let ino = |path: &Path| std::fs::metadata(path).unwrap().ino();
+    let (target_ids, link_ids) = (super::file_ids(&target), super::file_ids(&link));
+    let (dir_ino, other_ino) = (ino(&dir), ino(&dir.join("other")));
+    let file_ino = ino(&target);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Both links name the same file, each from its own directory.
+    let (target_ids, link_ids) = (target_ids.unwrap(), link_ids.unwrap());
+    assert_eq!((target_ids.id, target_ids.parent_id), (file_ino, dir_ino));
+    assert_eq!((link_ids.id, link_ids.parent_id), (file_ino, other_ino));
+    assert!(super::file_ids(Path::new("/nonexistent/path")).is_err());
+  }
+
+  #[test]
+  fn disk_description() {
+    let root = super::disk_description(Path::new("/")).unwrap();
+    assert_eq!(root.network, Some(false));
+    let image = crate::fixture::DiskImage::attach("disk-description", "Disk Description");
+    let image = super::disk_description(&image.mount_point).unwrap();
+    assert_eq!(image.ejectable, Some(true));
+  }
+
+  #[test]
+  fn attrlist_volume_name() {
+    assert!(super::attrlist_volume_name("/").is_some_and(|name| !name.is_empty()));
+    assert_eq!(super::attrlist_volume_name("/nonexistent/path"), None);
+  }
+}
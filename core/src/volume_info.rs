@@ -0,0 +1,577 @@
+//! Everything the record and its callers need to know about a volume.
+
+#[cfg(any(target_os = "macos", test))]
+use std::path::Path;
+use std::time::SystemTime;
+
+#[cfg(any(target_os = "macos", test))]
+use crate::create::{created, display_name};
+#[cfg(any(target_os = "macos", test))]
+use crate::filesystem::Filesystem;
+#[cfg(any(target_os = "macos", test))]
+use crate::source::{
+  mount_point, DiskDescription, MetadataSource, MNT_DONTBROWSE, MNT_LOCAL, MNT_RDONLY,
+};
+#[cfg(any(target_os = "macos", test))]
+use crate::Compat;
+use crate::{DateSource, Error, Result, VolumeNameSource, VolumeType};
+
+/// A mounted volume, as [`create_for_path`](crate::create_for_path) sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeInfo {
+  /// The name stored on the volume, which records carry.
+  pub name: String,
+  /// Which lookup `name` came from.
+  pub name_source: VolumeNameSource,
+  /// The name CoreFoundation shows, if it differs from `name`, say because
+  /// it is localized. Only [`volume_info`] looks it up.
+  pub display_name: Option<String>,
+  /// The mount point, e.g. `/Volumes/Test Title`.
+  pub path: String,
+  /// What is mounted, e.g. `/dev/disk3s1` or `//me@server/share`.
+  pub device: String,
+  /// The `statfs` filesystem type, e.g. `apfs`, `hfs`, `msdos` or `exfat`.
+  pub fs_type: String,
+  /// The date records give the volume: the creation date of its root
+  /// directory, to the second.
+  pub created: SystemTime,
+  /// Which timestamp `created` was taken from, for filesystems that do not
+  /// keep creation dates.
+  pub created_source: DateSource,
+  /// Whether the volume is on a local disk rather than a network share.
+  pub local: bool,
+  pub read_only: bool,
+  /// Whether Finder shows the volume. System volumes such as
+  /// `/System/Volumes/VM` are mounted hidden.
+  pub browsable: bool,
+  /// The disk image the volume is attached from, if it is one. Only
+  /// [`volume_info`] and [`list_volumes`] look it up.
+  pub disk_image: Option<String>,
+  /// The kind of media records give the volume, as Finder does: `Local`
+  /// for internal disks, `Network` for shares and `Other` for removable
+  /// and ejectable media such as SD cards, USB drives and disk images.
+  pub type_: VolumeType,
+  /// Where `type_` was decided.
+  pub type_source: VolumeTypeSource,
+}
+
+/// Where the [`VolumeType`] of a record was decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeTypeSource {
+  /// DiskArbitration's description of the media.
+  DiskArbitration,
+  /// A guess from `statfs` when DiskArbitration cannot be asked, as in a
+  /// sandbox: network mounts are `Network`, `/` is `Local`, and every other
+  /// volume is `Other`.
+  Statfs,
+}
+
+/// The type records give a volume whose flags are `mount_flags`, from what
+/// DiskArbitration says about its media, like Finder.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn volume_type(
+  disk: Option<&DiskDescription>,
+  mount_flags: u32,
+  volume_path: &str,
+) -> (VolumeType, VolumeTypeSource) {
+  let network = mount_flags & MNT_LOCAL == 0;
+  let Some(disk) = disk else {
+    let type_ = if network {
+      VolumeType::Network
+    } else if volume_path == "/" {
+      VolumeType::Local
+    } else {
+      VolumeType::Other
+    };
+    return (type_, VolumeTypeSource::Statfs);
+  };
+  let type_ = if network || disk.network == Some(true) {
+    VolumeType::Network
+  } else if disk.removable == Some(true)
+    || disk.ejectable == Some(true)
+    || disk.internal == Some(false)
+  {
+    VolumeType::Other
+  } else {
+    VolumeType::Local
+  };
+  (type_, VolumeTypeSource::DiskArbitration)
+}
+
+/// The volume holding `path`, which may be any file or directory on it.
+#[cfg(target_os = "macos")]
+pub fn volume_info<P: AsRef<Path>>(path: P) -> Result<VolumeInfo> {
+  volume_info_for_path_in(&crate::volume::System, path.as_ref())
+}
+
+/// The volume holding `path`.
+///
+/// Looking up volumes needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn volume_info<P: AsRef<std::path::Path>>(path: P) -> Result<VolumeInfo> {
+  let _ = path;
+  Err(Error::Unsupported("Looking up a volume"))
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn volume_info_for_path_in(source: &impl MetadataSource, path: &Path) -> Result<VolumeInfo> {
+  let path_str = path.to_string_lossy();
+  let volume_error = |error| Error::VolumeLookupFailed {
+    path: path_str.clone().into_owned(),
+    error,
+  };
+  let canonical = source
+    .canonicalize(path)
+    .map_err(|err| crate::create::not_found(&path_str, err))?;
+  let volume_path = mount_point(source, &canonical).map_err(volume_error)?;
+  let volume_path = volume_path
+    .to_str()
+    .ok_or_else(|| Error::InvalidPath("The volume path is not a valid UTF-8 string.".to_owned()))?;
+  listed_volume_in(source, volume_path).map_err(volume_error)
+}
+
+/// Every mounted volume, in mount order, as [`volume_info`] describes it.
+///
+/// Automounter triggers, such as `/System/Volumes/Data/home`, are left out
+/// rather than mounted, and so are volumes that cannot be looked up, such
+/// as ones the process may not read.
+#[cfg(target_os = "macos")]
+pub fn list_volumes() -> Result<Vec<VolumeInfo>> {
+  list_volumes_in(&crate::volume::System)
+}
+
+/// Every mounted volume.
+///
+/// Listing volumes needs the macOS filesystem APIs.
+#[cfg(not(target_os = "macos"))]
+pub fn list_volumes() -> Result<Vec<VolumeInfo>> {
+  Err(Error::Unsupported("Listing volumes"))
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn list_volumes_in(source: &impl MetadataSource) -> Result<Vec<VolumeInfo>> {
+  let mounts = source.mounts().map_err(|error| Error::VolumeLookupFailed {
+    path: "/".to_owned(),
+    error,
+  })?;
+  Ok(
+    mounts
+      .iter()
+      .filter(|mount| mount.fs_type != "autofs")
+      .filter_map(|mount| listed_volume_in(source, mount.mount_point.to_str()?).ok())
+      .collect(),
+  )
+}
+
+/// [`volume_info_in`] with the lookups only [`volume_info`] and
+/// [`list_volumes`] make.
+#[cfg(any(target_os = "macos", test))]
+fn listed_volume_in(
+  source: &impl MetadataSource,
+  volume_path: &str,
+) -> std::io::Result<VolumeInfo> {
+  let mut info = volume_info_in(
+    source,
+    volume_path,
+    Compat::Native,
+    VolumeNameSource::default(),
+  )?;
+  info.display_name = source
+    .cf_volume_name(volume_path)
+    .filter(|name| *name != info.name);
+  info.disk_image = source
+    .disk_image(Path::new(volume_path))
+    .map(|image| image.to_string_lossy().into_owned());
+  Ok(info)
+}
+
+/// The name of the volume at `volume_path`, from the first of the sources
+/// starting at `first` that knows one, and which one that was.
+#[cfg(any(target_os = "macos", test))]
+fn volume_name_in(
+  source: &impl MetadataSource,
+  volume_path: &str,
+  first: VolumeNameSource,
+) -> std::io::Result<(String, VolumeNameSource)> {
+  let cf = || {
+    source
+      .cf_volume_name(volume_path)
+      .map(|name| (name, VolumeNameSource::CoreFoundation))
+  };
+  let attrlist = || {
+    source
+      .attrlist_volume_name(volume_path)
+      .map(|name| (name, VolumeNameSource::Attrlist))
+  };
+  let mount_point = || {
+    Path::new(volume_path).file_name().map(|name| {
+      (
+        display_name(&name.to_string_lossy()),
+        VolumeNameSource::MountPoint,
+      )
+    })
+  };
+  let name = match first {
+    VolumeNameSource::CoreFoundation => cf().or_else(attrlist).or_else(mount_point),
+    VolumeNameSource::Attrlist => attrlist().or_else(mount_point),
+    VolumeNameSource::MountPoint => mount_point(),
+  };
+  name.ok_or_else(|| {
+    std::io::Error::new(
+      std::io::ErrorKind::NotFound,
+      "neither CoreFoundation, getattrlist nor the mount point tell the volume's name",
+    )
+  })
+}
+
+/// What `source` knows about the volume at `volume_path`, with the date
+/// rounded the way `compat` does and the name looked up from `name_source`
+/// on.
+///
+/// This is the one place volume facts come from, for records and for
+/// [`volume_info`] alike.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn volume_info_in(
+  source: &impl MetadataSource,
+  volume_path: &str,
+  compat: Compat,
+  name_source: VolumeNameSource,
+) -> std::io::Result<VolumeInfo> {
+  let root = source.stat(Path::new(volume_path))?;
+  let fs_stat = source.statfs(Path::new(volume_path))?;
+  let (name, name_source) = match (
+    Filesystem::from_type_name(&fs_stat.fs_type),
+    Path::new(volume_path).file_name(),
+  ) {
+    // CoreFoundation reports the label as stored in the boot sector, which
+    // is often uppercased; the mount point keeps the case Finder shows.
+    (Filesystem::Fat, Some(name)) => (
+      display_name(&name.to_string_lossy()),
+      VolumeNameSource::MountPoint,
+    ),
+    _ => volume_name_in(source, volume_path, name_source)?,
+  };
+  let (created, created_source) = created(&root, compat);
+  let disk = source.disk_description(Path::new(volume_path));
+  let (type_, type_source) = volume_type(disk.as_ref(), fs_stat.flags, volume_path);
+  Ok(VolumeInfo {
+    name,
+    name_source,
+    display_name: None,
+    path: volume_path.to_owned(),
+    device: fs_stat.device,
+    fs_type: fs_stat.fs_type,
+    created,
+    created_source,
+    local: fs_stat.flags & MNT_LOCAL != 0,
+    read_only: fs_stat.flags & MNT_RDONLY != 0,
+    browsable: fs_stat.flags & MNT_DONTBROWSE == 0,
+    disk_image: None,
+    type_,
+    type_source,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::Path;
+  use std::time::{Duration, UNIX_EPOCH};
+
+  use crate::fixture::FakeVolumes;
+  use crate::source::{DiskDescription, MNT_DONTBROWSE, MNT_LOCAL, MNT_RDONLY};
+  use crate::{Compat, CreateOptions, Error, VolumeNameSource, VolumeType};
+
+  fn native(volumes: &FakeVolumes, volume_path: &str) -> std::io::Result<super::VolumeInfo> {
+    super::volume_info_in(
+      volumes,
+      volume_path,
+      Compat::Native,
+      VolumeNameSource::default(),
+    )
+  }
+
+  #[test]
+  fn fake_volumes() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.set_mount_flags(3, MNT_LOCAL | MNT_RDONLY);
+    let info = |path| super::volume_info_for_path_in(&volumes, Path::new(path)).unwrap();
+
+    let test_title = info("/Volumes/Test Title/.background/TestBkg.tiff");
+    assert_eq!(
+      test_title,
+      super::VolumeInfo {
+        name: "Test Title".to_owned(),
+        name_source: VolumeNameSource::Attrlist,
+        display_name: None,
+        path: "/Volumes/Test Title".to_owned(),
+        device: "/dev/disk3s1".to_owned(),
+        fs_type: "hfs".to_owned(),
+        created: UNIX_EPOCH + Duration::from_secs(1388686804),
+        created_source: crate::DateSource::Ctime,
+        local: true,
+        read_only: true,
+        browsable: true,
+        disk_image: None,
+        type_: VolumeType::Other,
+        type_source: super::VolumeTypeSource::DiskArbitration,
+      }
+    );
+    assert_eq!(info("/Users/me/Backgrounds"), test_title);
+
+    let root = info("/Users/me/bg.tiff");
+    assert_eq!(root.name, "Macintosh HD");
+    assert_eq!(root.path, "/");
+    assert_eq!(root.fs_type, "apfs");
+    assert!(root.local && !root.read_only);
+
+    assert!(matches!(
+      super::volume_info_for_path_in(&volumes, Path::new("/Volumes/Gone")),
+      Err(Error::TargetNotFound(_))
+    ));
+  }
+
+  #[test]
+  fn list_volumes() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.attach("/Users/me/Test Title.dmg", "/Volumes/Test Title");
+    volumes.mount(4, "/System/Volumes/VM", "apfs", "VM");
+    volumes.set_mount_flags(4, MNT_LOCAL | MNT_DONTBROWSE);
+    volumes.directory("/System/Volumes/VM", 4, 2, 0);
+    // Looking up an automounter trigger would mount it.
+    volumes.mount(5, "/System/Volumes/Data/home", "autofs", "home");
+    volumes.directory("/System/Volumes/Data/home", 5, 2, 0);
+    // A volume whose root cannot be read.
+    volumes.mount(6, "/Volumes/Private", "apfs", "Private");
+
+    let mut listed = super::list_volumes_in(&volumes).unwrap();
+    listed.sort_by(|a, b| a.path.cmp(&b.path));
+    let paths: Vec<_> = listed.iter().map(|info| info.path.as_str()).collect();
+    assert_eq!(
+      paths,
+      [
+        "/",
+        "/System/Volumes/Data",
+        "/System/Volumes/VM",
+        "/Volumes/Test Title"
+      ]
+    );
+    assert_eq!(
+      listed[0],
+      super::volume_info_for_path_in(&volumes, Path::new("/")).unwrap()
+    );
+    assert!(!listed[2].browsable);
+    assert_eq!(listed[3].device, "/dev/disk3s1");
+    assert_eq!(
+      listed[3].disk_image.as_deref(),
+      Some("/Users/me/Test Title.dmg")
+    );
+    assert_eq!(listed[0].disk_image, None);
+  }
+
+  #[test]
+  fn volume_types() {
+    use super::VolumeTypeSource::{DiskArbitration, Statfs};
+    use crate::fixture::INTERNAL_DISK;
+    use VolumeType::{Local, Network, Other};
+
+    let disk = |change: fn(&mut DiskDescription)| {
+      let mut disk = INTERNAL_DISK;
+      change(&mut disk);
+      Some(disk)
+    };
+    for (disk, flags, path, expected) in [
+      (
+        Some(INTERNAL_DISK),
+        MNT_LOCAL,
+        "/",
+        (Local, DiskArbitration),
+      ),
+      // A second internal drive.
+      (
+        Some(INTERNAL_DISK),
+        MNT_LOCAL,
+        "/Volumes/Data",
+        (Local, DiskArbitration),
+      ),
+      // An SD card, a disk image and a USB drive.
+      (
+        disk(|d| d.removable = Some(true)),
+        MNT_LOCAL,
+        "/Volumes/SD",
+        (Other, DiskArbitration),
+      ),
+      (
+        disk(|d| d.ejectable = Some(true)),
+        MNT_LOCAL,
+        "/Volumes/Image",
+        (Other, DiskArbitration),
+      ),
+      (
+        disk(|d| d.internal = Some(false)),
+        MNT_LOCAL,
+        "/Volumes/USB",
+        (Other, DiskArbitration),
+      ),
+      // An SMB share, whose description may only say it is not local.
+      (
+        disk(|d| d.network = Some(true)),
+        0,
+        "/Volumes/Share",
+        (Network, DiskArbitration),
+      ),
+      (
+        Some(DiskDescription::default()),
+        0,
+        "/Volumes/Share",
+        (Network, DiskArbitration),
+      ),
+      (
+        Some(DiskDescription::default()),
+        MNT_LOCAL,
+        "/Volumes/Disk",
+        (Local, DiskArbitration),
+      ),
+      // Without DiskArbitration, as in a sandbox.
+      (None, MNT_LOCAL, "/", (Local, Statfs)),
+      (None, MNT_LOCAL, "/Volumes/Data", (Other, Statfs)),
+      (None, 0, "/Volumes/Share", (Network, Statfs)),
+    ] {
+      assert_eq!(
+        super::volume_type(disk.as_ref(), flags, path),
+        expected,
+        "{path} {disk:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn fat_volume_name() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.mount(4, "/Volumes/USB 1:2", "exfat", "USB 1:2 LABEL");
+    volumes.directory("/Volumes/USB 1:2", 4, 1, 0);
+    let info = native(&volumes, "/Volumes/USB 1:2").unwrap();
+    assert_eq!(info.name, "USB 1/2");
+    assert_eq!(info.fs_type, "exfat");
+
+    // Other filesystems go by the name CoreFoundation reports.
+    volumes.mount(4, "/Volumes/USB 1:2", "apfs", "USB LABEL");
+    let info = native(&volumes, "/Volumes/USB 1:2").unwrap();
+    assert_eq!(info.name, "USB LABEL");
+  }
+
+  #[test]
+  fn volume_name_fallbacks() {
+    use VolumeNameSource::{Attrlist, CoreFoundation, MountPoint};
+
+    let mut volumes = FakeVolumes::test_title();
+    volumes.mount(4, "/Volumes/App 1:2", "apfs", "App Label");
+    volumes.directory("/Volumes/App 1:2", 4, 1, 0);
+    let mut name = |failing: &[VolumeNameSource], first, volume_path| {
+      volumes.fail_volume_names(failing);
+      volumes.name_lookups.borrow_mut().clear();
+      let name = super::volume_info_in(&volumes, volume_path, Compat::Native, first)
+        .map(|info| info.name)
+        .ok();
+      (name, volumes.name_lookups.take())
+    };
+
+    assert_eq!(
+      name(&[], CoreFoundation, "/Volumes/App 1:2"),
+      (Some("App Label".to_owned()), vec![CoreFoundation])
+    );
+    assert_eq!(
+      name(&[CoreFoundation], CoreFoundation, "/Volumes/App 1:2"),
+      (Some("App Label".to_owned()), vec![CoreFoundation, Attrlist])
+    );
+    assert_eq!(
+      name(
+        &[CoreFoundation, Attrlist],
+        CoreFoundation,
+        "/Volumes/App 1:2"
+      ),
+      (Some("App 1/2".to_owned()), vec![CoreFoundation, Attrlist])
+    );
+    // The root volume has no mount point name to fall back to.
+    assert_eq!(
+      name(&[CoreFoundation, Attrlist], CoreFoundation, "/"),
+      (None, vec![CoreFoundation, Attrlist])
+    );
+
+    // Later sources skip the earlier ones.
+    assert_eq!(
+      name(&[], Attrlist, "/Volumes/App 1:2"),
+      (Some("App Label".to_owned()), vec![Attrlist])
+    );
+    assert_eq!(
+      name(&[], MountPoint, "/Volumes/App 1:2"),
+      (Some("App 1/2".to_owned()), vec![])
+    );
+  }
+
+  #[test]
+  fn localized_volume_name() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.localize("/", "Disque Macintosh");
+
+    // Records get the name on disk unless asked for the display name.
+    let record = |volume_name_source| {
+      let options = CreateOptions {
+        volume_name_source,
+        ..Default::default()
+      };
+      crate::create::info_for_path_in(&volumes, "/Users/me/bg.tiff", &options)
+        .unwrap()
+        .volume
+        .name
+    };
+    assert_eq!(record(VolumeNameSource::default()), "Macintosh HD");
+    assert_eq!(record(VolumeNameSource::CoreFoundation), "Disque Macintosh");
+
+    let info = super::volume_info_for_path_in(&volumes, Path::new("/Users/me")).unwrap();
+    assert_eq!(info.name, "Macintosh HD");
+    assert_eq!(info.display_name.as_deref(), Some("Disque Macintosh"));
+  }
+
+  #[test]
+  fn record_without_core_foundation() {
+    let mut volumes = FakeVolumes::test_title();
+    volumes.fail_volume_names(&[VolumeNameSource::CoreFoundation, VolumeNameSource::Attrlist]);
+    let path = "/Volumes/Test Title/.background/TestBkg.tiff";
+    let info = crate::create::info_for_path_in(&volumes, path, &CreateOptions::default()).unwrap();
+    assert_eq!(info.volume.name, "Test Title");
+
+    let error = crate::create::info_for_path_in(&volumes, "/Users/me/bg.tiff", &Default::default());
+    assert!(matches!(error, Err(Error::VolumeLookupFailed { .. })));
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn root_volume() {
+    let info = super::volume_info("/").unwrap();
+    assert_eq!(info.path, "/");
+    assert!(!info.name.is_empty());
+    assert!(info.local);
+    assert_eq!(info.fs_type, "apfs");
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn list_root_volume() {
+    let volumes = super::list_volumes().unwrap();
+    let root = volumes.iter().find(|info| info.path == "/").unwrap();
+    assert_eq!(*root, super::volume_info("/").unwrap());
+    assert!(root.browsable);
+  }
+
+  #[test]
+  #[cfg(target_os = "macos")]
+  fn disk_image() {
+    let image = crate::fixture::DiskImage::attach("volume-info", "Volume Info");
+    std::fs::create_dir(image.mount_point.join(".background")).unwrap();
+    let info = super::volume_info(image.mount_point.join(".background")).unwrap();
+    assert_eq!(info.name, "Volume Info");
+    assert_eq!(info.path, image.mount_point.to_str().unwrap());
+    assert_eq!(info.fs_type, "hfs");
+    assert!(info.local && !info.read_only);
+  }
+}
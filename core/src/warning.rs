@@ -0,0 +1,101 @@
+use std::fmt;
+
+use crate::{DateSource, VolumeNameSource, VolumeType};
+
+/// Something a record was made with less than the filesystem should have
+/// told. The record is still valid, but may resolve less reliably.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+  /// The filesystem keeps no creation date for `field`, so it was taken
+  /// from `source` instead of `st_birthtime`.
+  DateFallback {
+    field: &'static str,
+    source: DateSource,
+  },
+  /// The lookups of the volume name before `source` failed.
+  VolumeNameFallback(VolumeNameSource),
+  /// DiskArbitration could not describe the volume's media, so its type
+  /// was guessed from `statfs`.
+  VolumeTypeGuessed(VolumeType),
+  /// The file id `ino` of `field` does not fit the record's 32 bits, so the
+  /// record stores 0.
+  IdTooLarge { field: &'static str, ino: u64 },
+  /// The parent directory could not be looked up, say for want of
+  /// permission, so the record stores parent id 0.
+  ParentUnreadable,
+}
+
+impl Warning {
+  /// A stable identifier for the kind of warning, e.g.
+  /// `WARN_DATE_FALLBACK`. Unlike the message, it does not change between
+  /// releases.
+  pub fn code(&self) -> &'static str {
+    match self {
+      Warning::DateFallback { .. } => "WARN_DATE_FALLBACK",
+      Warning::VolumeNameFallback(_) => "WARN_VOLUME_NAME_FALLBACK",
+      Warning::VolumeTypeGuessed(_) => "WARN_VOLUME_TYPE_GUESSED",
+      Warning::IdTooLarge { .. } => "WARN_ID_TOO_LARGE",
+      Warning::ParentUnreadable => "WARN_PARENT_UNREADABLE",
+    }
+  }
+
+  /// The field of the record affected, e.g. `target.created`.
+  pub fn field(&self) -> &'static str {
+    match self {
+      Warning::DateFallback { field, .. } | Warning::IdTooLarge { field, .. } => field,
+      Warning::VolumeNameFallback(_) => "volume.name",
+      Warning::VolumeTypeGuessed(_) => "volume.type",
+      Warning::ParentUnreadable => "parent.id",
+    }
+  }
+}
+
+impl fmt::Display for Warning {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Warning::DateFallback { field, source } => {
+        let source = match source {
+          DateSource::Birthtime => "creation date",
+          DateSource::Mtime => "modification date",
+          DateSource::Ctime => "change date",
+          DateSource::Epoch => "Unix epoch",
+        };
+        write!(
+          f,
+          "The filesystem keeps no creation date for {field}, so the {source} was used."
+        )
+      }
+      Warning::VolumeNameFallback(source) => {
+        let source = match source {
+          VolumeNameSource::CoreFoundation => "CoreFoundation",
+          VolumeNameSource::Attrlist => "getattrlist",
+          VolumeNameSource::MountPoint => "the mount point",
+        };
+        write!(
+          f,
+          "The volume name was taken from {source}, since the lookups before it failed."
+        )
+      }
+      Warning::VolumeTypeGuessed(type_) => {
+        let type_ = match type_ {
+          VolumeType::Local => "a local disk",
+          VolumeType::Network => "a network share",
+          VolumeType::Floppy400 | VolumeType::Floppy800 | VolumeType::Floppy1400 => "a floppy",
+          VolumeType::Other => "removable media",
+        };
+        write!(
+          f,
+          "DiskArbitration could not describe the volume, so it was taken to be {type_} from its mount."
+        )
+      }
+      Warning::IdTooLarge { field, ino } => write!(
+        f,
+        "The file id {ino} of {field} does not fit in 32 bits, so the record stores 0."
+      ),
+      Warning::ParentUnreadable => write!(
+        f,
+        "The parent directory could not be looked up, so the record stores parent id 0."
+      ),
+    }
+  }
+}
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  // Anything that decodes has to encode again.
+  if let Ok(info) = macos_alias_core::decode(data) {
+    let _ = macos_alias_core::encode(&info);
+  }
+  let _ = macos_alias_core::decode_all(data);
+});
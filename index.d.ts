@@ -3,4 +3,844 @@
 
 /* auto-generated by NAPI-RS */
 
-export function create(targetPath: string): Buffer
+/** For functions that return a record but take no other options. */
+export interface OutputOptions {
+  /** Return a plain `Uint8Array` rather than a `Buffer`. */
+  output?: 'buffer' | 'uint8array'
+}
+/** Metadata describing an alias target, used instead of looking it up on disk. */
+export interface AliasInfo {
+  /** Absolute posix path of the target, e.g. `/Volumes/Title/.background/bg.tiff`. */
+  targetPath: string
+  /** Whether the target is a file (the default) or a directory. */
+  targetType?: 'file' | 'directory'
+  /** Four character file type code, e.g. `TIFF`. Files only. */
+  fileType?: string
+  /** Four character creator code, e.g. `8BIM`. Files only. */
+  fileCreator?: string
+  /** Catalog node id of the target. */
+  targetId: number
+  /** Creation date of the target, in milliseconds since the Unix epoch. */
+  targetCreated: number
+  /** Catalog node id of the directory containing the target. */
+  parentId: number
+  /**
+   * Name of the directory containing the target. Defaults to the last
+   * component of `targetPath`'s parent, or `volumeName` for a target at
+   * the root of the volume.
+   */
+  parentName?: string
+  /** Mount point of the volume, e.g. `/Volumes/Title`. */
+  volumePath: string
+  /** Display name of the volume. A `/` in it reads `:` in `volumePath`. */
+  volumeName: string
+  /** Creation date of the volume, in milliseconds since the Unix epoch. */
+  volumeCreated: number
+  /**
+   * The kind of media the volume is on. Defaults to `local` for `/` and
+   * `other` for everything else.
+   */
+  volumeType?: 'local' | 'network' | 'floppy400' | 'floppy800' | 'floppy1400' | 'other'
+  /**
+   * The volume's two-character signature. Defaults to `H+`; floppies are
+   * `BD`.
+   */
+  volumeSignature?: 'BD' | 'H+' | 'HX'
+}
+export interface CreateOptions {
+  /** Reproduce another implementation's output byte for byte. */
+  compat?: 'node-alias'
+  /**
+   * Write only the posix path extras and leave the classic name fields
+   * empty. `'with-names'` also keeps the unicode file and volume names.
+   */
+  minimal?: boolean | 'with-names'
+  /**
+   * The families of extras to write, by default all of them: `classic`
+   * (the parent name and id and the carbon path, types 0 to 2), `unicode`
+   * (the file and volume names, 14 and 15), `posix` (the paths, 18 and
+   * 19) and `diskImage` (20). Without `unicode` the classic fields must
+   * spell the names in full, and without `posix` they must not be left
+   * empty by `minimal`; otherwise the call throws.
+   */
+  extras?: Array<'classic' | 'unicode' | 'posix' | 'diskImage'>
+  /**
+   * Extras to add. Each replaces the extra of the same type, if any, and is
+   * otherwise slotted into Finder's order (ascending by type).
+   */
+  extraRecords?: Array<ExtraRecord>
+  /** Append `extraRecords` after the other extras, in the order given. */
+  appendRaw?: boolean
+  /**
+   * Create the record even if the target does not exist yet, as a file with
+   * id 0 and its parent directory's dates. The parent still has to exist.
+   */
+  allowMissing?: boolean
+  /**
+   * Throw instead of downloading cloud content, such as an iCloud file
+   * whose contents are not on disk.
+   */
+  noIO?: boolean
+  /**
+   * If the target is a Finder alias file, follow it (and any aliases it
+   * points to) and record the final target instead. Without this, `create`
+   * emits a process warning for alias files.
+   */
+  resolveAliases?: boolean
+  /**
+   * Leave out the record of the disk image the target's volume is mounted
+   * from, which lets Finder mount the image again to resolve the alias.
+   */
+  noDiskImage?: boolean
+  /**
+   * Where the volume name is looked up first, falling back to the sources
+   * after it: CoreFoundation's display name, `getattrlist`'s
+   * `ATTR_VOL_NAME`, then the last component of the mount point. Defaults
+   * to `'attrlist'`.
+   */
+  volumeNameSource?: 'cf' | 'attrlist' | 'mountpoint'
+  /**
+   * Record the volume under the name stored on it (`'canonical'`, the
+   * default, as Finder does) or the possibly localized name Finder shows
+   * (`'display'`). Shorthand for `volumeNameSource: 'attrlist'` or `'cf'`.
+   */
+  volumeNameStyle?: 'display' | 'canonical'
+  /**
+   * Record this volume name instead of the one looked up, e.g. the title of
+   * the disk image the target will end up on. The mount point stays as it
+   * is. Takes precedence over `volumeNameSource` and `volumeNameStyle`.
+   */
+  volumeName?: string
+  /**
+   * Record this volume type instead of the one looked up, e.g.
+   * `'floppy800'` for a volume standing in for an 800K floppy.
+   */
+  volumeType?: 'local' | 'network' | 'floppy400' | 'floppy800' | 'floppy1400' | 'other'
+  /**
+   * Record this signature instead of the filesystem's, e.g. `'BD'` for a
+   * classic HFS volume. Floppy types need `'BD'`.
+   */
+  volumeSignature?: 'BD' | 'H+' | 'HX'
+  /**
+   * Record this target id instead of the file's inode number, e.g. the
+   * CNID the file will get in an HFS+ image built from a staging
+   * directory.
+   */
+  targetId?: number
+  /**
+   * Record this parent id instead of the parent directory's inode number,
+   * in the header and in extra 1.
+   */
+  parentId?: number
+  /**
+   * Record this parent name, in the header, extra 0 and the carbon path.
+   * The posix path keeps the real one.
+   */
+  parentName?: string
+  /**
+   * Record this creation date of the target instead of the file's, in
+   * milliseconds since the Unix epoch, e.g. for reproducible builds.
+   */
+  targetCreated?: number
+  /**
+   * Record this creation date of the volume instead of the volume's, in
+   * milliseconds since the Unix epoch.
+   */
+  volumeCreated?: number
+  /**
+   * Record the UUID of the target's volume in an extra of type `0x2011`,
+   * which `statAliasTarget` and `relinkAlias` then prefer over the mount
+   * point to find the volume by. `true` looks the UUID up; a string such
+   * as `'0A81F3B1-51D9-3335-B3E3-169C3640360D'` gives it. Finder writes no
+   * such extra.
+   */
+  volumeUuid?: boolean | string
+  /**
+   * How creation dates lose their milliseconds: `'floor'` drops them, as
+   * Finder does, and `'round'` rounds halves up, as node-alias does. The
+   * default follows `compat`.
+   */
+  dateRounding?: 'floor' | 'round'
+  /**
+   * Throw `ERR_RECORD_EXCEEDS_MAX_SIZE` rather than return a record
+   * longer than this many bytes, e.g. `DS_STORE_MAX_ALIAS_LENGTH`.
+   */
+  maxSize?: number
+  /**
+   * Add the high-resolution creation dates of the volume and the target
+   * (extras 16 and 17), which keep the fraction of a second the classic
+   * date fields drop, as Finder does. Full layouts only.
+   */
+  hiresDates?: boolean
+  /**
+   * Return a plain `Uint8Array` rather than a `Buffer`. `createDebug` and
+   * `createDetailed` always return a `Buffer`.
+   */
+  output?: 'buffer' | 'uint8array'
+}
+/**
+ * An extra, tagged with its type. Give either the raw `data` or, for the
+ * well-known types, a `value` to encode: a string for 0, 2, 14, 15, 18 and
+ * 19, a number for 1.
+ */
+export interface ExtraRecord {
+  type: number
+  data?: Buffer | Uint8Array | ArrayBuffer
+  value?: string | number
+}
+export function create(target: string | URL, options?: CreateOptions | undefined | null): Buffer | Uint8Array
+/**
+ * Like `create`, but do the lookups on the libuv thread pool and return a
+ * Promise, so that slow or network volumes do not block the event loop.
+ * Options and targets of the wrong shape throw right away.
+ */
+export function createAsync(target: string | URL, options?: CreateOptions | undefined | null): Promise<Buffer | Uint8Array>
+/**
+ * Like `create`, but return the record as a base64 string, the way JSON
+ * configs and snapshot tests store it. Every function that takes a record
+ * also takes such a string.
+ */
+export function createBase64(target: string | URL, options?: CreateOptions | undefined | null): string
+/**
+ * Like `create`, but write the record into the start of `out` instead of
+ * allocating a new buffer, and return its length in bytes. Throws
+ * `ERR_BUFFER_TOO_SMALL`, naming the size needed, if `out` is too short.
+ */
+export function createInto(target: string | URL, out: Buffer | Uint8Array, options?: CreateOptions | undefined | null): number
+/**
+ * The exact length in bytes of the record `create` would return for a
+ * path or `createFromInfo` for an info object, without encoding it. For
+ * a path, pass the options `create` gets; `maxSize` is ignored.
+ */
+export function estimateRecordSize(target: string | URL | AliasInfo, options?: CreateOptions | undefined | null): number
+/**
+ * The longest alias record whose `.DS_Store` entry still fits in one
+ * B-tree node, for `maxSize`.
+ */
+export const DS_STORE_MAX_ALIAS_LENGTH: number
+/**
+ * Something `createDetailed` had to make do without. The record is still
+ * valid, but may resolve less reliably.
+ */
+export interface CreateWarning {
+  /** A stable identifier, e.g. `WARN_DATE_FALLBACK`. */
+  code: string
+  message: string
+  /** The field of the record affected, e.g. `target.created`. */
+  field: string
+}
+/**
+ * The record `createDetailed` made, and the values that went into it
+ * before encoding, e.g. names the classic fields cannot spell.
+ */
+export interface CreateDetailedResult {
+  /** The same record `create` returns. */
+  buffer: Buffer | Uint8Array
+  targetId: number
+  parentId: number
+  parentName: string
+  volumeName: string
+  /** Mount point of the volume, as in the record's posix path extras. */
+  volumePath?: string
+  /** The volume's two-character signature, e.g. `H+` or `BD`. */
+  volumeSignature: string
+  /**
+   * The kind of media the volume is on: `local` for internal disks,
+   * `network` for shares and `other` for removable and ejectable media.
+   */
+  volumeType: 'local' | 'network' | 'floppy400' | 'floppy800' | 'floppy1400' | 'other'
+  /** Creation date of the target, in milliseconds since the Unix epoch. */
+  targetCreated: number
+  /** Creation date of the volume, in milliseconds since the Unix epoch. */
+  volumeCreated: number
+  warnings: Array<CreateWarning>
+}
+/**
+ * Like `create`, but also return what went into the record, and where it
+ * falls short of what the filesystem should have told, such as a
+ * modification date standing in for a missing creation date.
+ */
+export function createDetailed(target: string | URL, options?: CreateOptions | undefined | null): CreateDetailedResult
+/** What `createDebug` derived for the target, right before encoding. */
+export interface DebugInfo {
+  version: number
+  targetType: 'file' | 'directory'
+  targetFilename: string
+  targetId: number
+  /** Creation date of the target, as an ISO 8601 string. */
+  targetCreated: string
+  /**
+   * Which timestamp `targetCreated` was taken from. Filesystems without
+   * creation dates fall back to the modification, then the change date.
+   */
+  targetCreatedSource: 'birthtime' | 'mtime' | 'ctime' | 'epoch'
+  parentName: string
+  parentId: number
+  volumeName: string
+  volumeType: 'local' | 'network' | 'floppy400' | 'floppy800' | 'floppy1400' | 'other'
+  /**
+   * Whether `volumeType` came from DiskArbitration or, where it cannot be
+   * asked, was guessed from the mount.
+   */
+  volumeTypeSource: 'diskarbitration' | 'statfs'
+  /** The volume's two-character signature, e.g. `H+` or `BD`. */
+  volumeSignature: string
+  /** Creation date of the volume, as an ISO 8601 string. */
+  volumeCreated: string
+  /** Which timestamp `volumeCreated` was taken from. */
+  volumeCreatedSource: 'birthtime' | 'mtime' | 'ctime' | 'epoch'
+  extra: Array<DebugExtra>
+}
+export interface DebugExtra {
+  type: number
+  length: number
+  /** The decoded value of a well-known extra, or its data in hex. */
+  preview: string
+}
+/** How long each step of `createDebug` took, in milliseconds. */
+export interface DebugTimings {
+  /** Looking up the target, its parent and its volume. */
+  lookup: number
+  encode: number
+}
+export interface CreateDebugResult {
+  /** The same record `create` returns. */
+  buffer: Buffer | Uint8Array
+  info: DebugInfo
+  timings: DebugTimings
+}
+/**
+ * Like `create`, but also return what was derived for the target and how
+ * long each step took, to debug records that do not resolve.
+ */
+export function createDebug(target: string | URL, options?: CreateOptions | undefined | null): CreateDebugResult
+/** The outcome of `verify`. */
+export interface VerifyResult {
+  /** Whether the system resolver found a file for the record at all. */
+  resolved: boolean
+  resolvedPath?: string
+  /**
+   * Fields of the record that do not match the file it resolved to. Empty
+   * when it resolved to the target.
+   */
+  mismatches: Array<FieldMismatch>
+}
+export interface FieldMismatch {
+  /** The field, e.g. `target.id`. */
+  field: string
+  /** The value in the record. */
+  expected: string
+  /** The value for the file the record resolved to. */
+  actual: string
+}
+/**
+ * Create the record for `target`, resolve it through CoreFoundation and
+ * check that it lands on the same file. Resolution never shows UI or mounts
+ * volumes.
+ */
+export function verify(target: string | URL, options?: CreateOptions | undefined | null): VerifyResult
+/** The outcome of `statAliasTarget`. */
+export interface AliasTargetStatus {
+  /** Whether a file or directory exists at the record's posix path. */
+  exists: boolean
+  /**
+   * Whether the record's path names its target: the object with the
+   * target id is still there, or, for records without an id, something is
+   * there and no field is stale.
+   */
+  pathMatches: boolean
+  /** Whether the volume still has an object with the record's target id. */
+  idMatches: boolean
+  /**
+   * Whether the record's volume is mounted: the one with its volume UUID,
+   * if it has one, or else whatever is mounted where it says.
+   */
+  volumeMounted: boolean
+  /**
+   * Where the record's volume is mounted, which differs from the record's
+   * mount point when its volume UUID found it elsewhere.
+   */
+  volumePath?: string
+  /**
+   * Fields of the record that differ from the object now at its path, e.g.
+   * `target.id` for a file that was replaced.
+   */
+  staleFields: Array<string>
+}
+/**
+ * Check whether the record in `buffer` still describes its target, by its
+ * posix path and by its id. Nothing is modified, and an unmounted volume is
+ * reported rather than mounted.
+ */
+export function statAliasTarget(buffer: string | Buffer | Uint8Array | ArrayBuffer): AliasTargetStatus
+/** How `resolveAlias` looks for the target. */
+export interface ResolveOptions {
+  /**
+   * Try the target id first (`'id'`, the default, as the Alias Manager
+   * does, which follows renames and moves) or the posix path (`'path'`,
+   * which suits records made in a staging directory and targets replaced
+   * in place).
+   */
+  prefer?: 'id' | 'path'
+  /**
+   * Attach the disk image the record's volume was mounted from when the
+   * volume is not mounted. The image is left attached.
+   */
+  mountIfNeeded?: boolean
+  /**
+   * Accept something at the record's posix path even though the record's
+   * volume is not mounted there, e.g. a leftover mount point directory.
+   */
+  crossVolumeFallback?: boolean
+}
+/**
+ * The path of the target of the record in `buffer` on this machine, found
+ * by its id and its posix path on the record's volume, or else by its name
+ * in the directory with its parent id, or `null` if nothing leads anywhere.
+ * The volume is the mounted one with the record's volume UUID, if it has
+ * one, or else the one mounted where the record says.
+ */
+export function resolveAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, options?: ResolveOptions | undefined | null): string | null
+/**
+ * `resolveAlias` with its default options: the path of the record's target
+ * on this machine, or `null`.
+ */
+export function resolve(buffer: string | Buffer | Uint8Array | ArrayBuffer): string | null
+/**
+ * Refresh the record in `buffer` from the file now at its posix path, e.g.
+ * after the target was replaced in place: the target id and creation date,
+ * the parent id and name, and the volume's creation date, signature and
+ * type. The target name and the paths are left as they are.
+ */
+export function relinkAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+/** A mounted volume, as `create` sees it. */
+export interface VolumeInfo {
+  /** The name stored on the volume, which records carry. */
+  name: string
+  /**
+   * The name Finder shows, if it differs from `name`, say because it is
+   * localized.
+   */
+  displayName?: string
+  /** The mount point, e.g. `/Volumes/Test Title`. */
+  path: string
+  /** What is mounted, e.g. `/dev/disk3s1` or `//me@server/share`. */
+  device: string
+  /** The filesystem type, e.g. `apfs`, `hfs`, `msdos` or `exfat`. */
+  fsType: string
+  /**
+   * The date records give the volume, in milliseconds since the Unix
+   * epoch.
+   */
+  created: number
+  /**
+   * Which timestamp `created` was taken from. Filesystems without creation
+   * dates fall back to the modification, then the change date.
+   */
+  createdSource: 'birthtime' | 'mtime' | 'ctime' | 'epoch'
+  /** Whether the volume is on a local disk rather than a network share. */
+  local: boolean
+  readOnly: boolean
+  /**
+   * Whether Finder shows the volume. System volumes such as
+   * `/System/Volumes/VM` are mounted hidden.
+   */
+  browsable: boolean
+  /** The disk image the volume is attached from, if it is one. */
+  diskImage?: string
+}
+/**
+ * Everything `create` knows about the volume holding `path`, which may be
+ * any file or directory on it.
+ */
+export function getVolumeInfo(path: string): VolumeInfo
+/**
+ * Every mounted volume, as `getVolumeInfo` describes it. Automounter
+ * triggers are left out rather than mounted, and so are volumes that
+ * cannot be looked up.
+ */
+export function listVolumes(): Array<VolumeInfo>
+/** The full 64-bit ids `fileIds` looked up. */
+export interface FileIds {
+  /** `ATTR_CMN_FILEID` of the file or directory. */
+  fileId: bigint
+  /** `ATTR_CMN_PARENTID`, the id of its parent directory. */
+  parentId: bigint
+}
+/**
+ * The catalog node ids of `path` and of its parent directory, in full.
+ * Records hold 32 bits, and store the APFS ids past that as 0 with a
+ * `WARN_ID_TOO_LARGE` warning.
+ */
+export function fileIds(path: string | URL): FileIds
+/**
+ * The alias record macOS's own Alias Manager creates for `path`, for
+ * checking `create` against.
+ */
+export function systemAliasFor(path: string, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+/**
+ * Like `create`, for the file or directory open as `fd`. The record
+ * describes the object behind the descriptor; `pathHint` is used when it
+ * still names that object. The descriptor is left open.
+ */
+export function createForFd(fd: number, pathHint?: string | undefined | null, options?: CreateOptions | undefined | null): Buffer | Uint8Array
+/** Which entries `createForTree` makes records for. */
+export interface TreeOptions {
+  /**
+   * Only record the entries whose path relative to the root matches this
+   * glob, or for which this function returns true. `*` and `?` stay within
+   * a path component and `**` spans any number of them; a glob without a
+   * `/` is matched against the entry's name, so `*.png` matches at every
+   * depth.
+   */
+  filter?: string | ((path: string, isDirectory: boolean) => boolean)
+  /**
+   * How many levels below the root to list: 1 is only its own entries.
+   * Unlimited by default.
+   */
+  maxDepth?: number
+  /** Also record directories, not only files. */
+  includeDirectories?: boolean
+  /**
+   * Record symlinks as the file or directory they point to, and descend
+   * into symlinked directories. Symlinks are skipped by default.
+   */
+  followSymlinks?: boolean
+}
+/**
+ * Records for the files under `root`, keyed by their path relative to it,
+ * with one set of volume lookups for the whole walk. An entry whose record
+ * could not be made maps to the error instead. The walk does not leave the
+ * volume `root` is on.
+ */
+export function createForTree(root: string | URL, tree?: TreeOptions | undefined | null, options?: CreateOptions | undefined | null): Record<string, Buffer | Uint8Array | Error>
+/** A target of `createMany` with options of its own. */
+export interface ManyTarget {
+  path: string | URL
+  /** Used instead of the options `createMany` was given. */
+  options?: CreateOptions
+}
+/**
+ * The records of `targets`, in the order given, with one set of volume
+ * lookups for all of them. A target may come with options of its own,
+ * which replace `options` for it. A target whose record could not be made
+ * gets the error instead.
+ */
+export function createMany(targets: Array<string | URL | ManyTarget>, options?: CreateOptions | undefined | null): Array<Buffer | Uint8Array | Error>
+/** A file or directory staged for a disk image that is not built yet. */
+export interface StagedDmgTarget {
+  /** The directory whose contents become the root of the image. */
+  stagingDir: string
+  /** The name Finder will show for the mounted image. */
+  volumeName: string
+  /** Path of the target inside the image, e.g. `.background/bg.tiff`. */
+  internalPath: string
+  /**
+   * Whether the target is a directory. Only needed for targets that are
+   * not staged yet.
+   */
+  targetIsDir?: boolean
+  /** Ids on the image, which are 0 unless given. */
+  targetId?: number
+  parentId?: number
+  /**
+   * Creation date of the image's volume, in milliseconds since the Unix
+   * epoch. Defaults to the staging directory's.
+   */
+  volumeCreated?: number
+}
+/**
+ * The record for a file in the staging directory of a disk image, as it
+ * will be once the image is mounted at `/Volumes/<volumeName>`. The dates
+ * come from the staged file; the volume name and paths from the arguments.
+ */
+export function createForStagedDmg(target: StagedDmgTarget, options?: CreateOptions | undefined | null): Buffer | Uint8Array
+/**
+ * The record for the background image of a disk image, as appdmg and
+ * Finder expect it: on the volume `volumeName`, mounted at
+ * `/Volumes/<volumeName>`, rather than in `stagingDir`. `imagePath` is the
+ * image inside the staging directory, either absolute or relative to it; a
+ * bare file name is taken to be in `.background/`.
+ */
+export function createBackgroundAlias(stagingDir: string, imagePath: string, volumeName: string, options?: CreateOptions | undefined | null): Buffer | Uint8Array
+/**
+ * Build an alias record from caller-supplied metadata without touching the
+ * filesystem. Unlike `create`, this works on every platform.
+ */
+export function createFromInfo(info: AliasInfo, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+/**
+ * Decode an alias record into JSON, with dates as Unix milliseconds and
+ * extra data as base64.
+ */
+export function toJSON(buffer: string | Buffer | Uint8Array | ArrayBuffer): string
+/**
+ * Decode an alias record into an object in the shape `toJSON` gives: the
+ * header's `target`, `parent` and `volume`, the classic fields and the
+ * `extra` records, with the well-known ones' `value`s.
+ */
+export function decode(buffer: string | Buffer | Uint8Array | ArrayBuffer): any
+/**
+ * Decode the records stored one after another in `buffer`, as some
+ * `.DS_Store` blobs and resource forks hold them, each in the shape
+ * `toJSON` decodes records into. Zero padding after the last record is
+ * skipped.
+ */
+export function decodeAll(buffer: string | Buffer | Uint8Array | ArrayBuffer): Array<any>
+/** Something wrong with the framing of a record, found by `validate`. */
+export interface RecordProblem {
+  /** A stable identifier, e.g. `LENGTH_EXCEEDS_BUFFER`. */
+  code: string
+  message: string
+  /** The offset in the buffer of the bytes at fault. */
+  offset: number
+}
+/**
+ * Check the framing of the record in `buffer`: its declared length, its
+ * version and header, each extra's length and padding, and the -1 marker
+ * that ends them. Every problem found is returned, rather than thrown as
+ * by `decode`; a well formed record has none.
+ */
+export function validate(buffer: string | Buffer | Uint8Array | ArrayBuffer): Array<RecordProblem>
+/** Encode JSON produced by `toJSON` back into an alias record. */
+export function fromJSON(json: string, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+/**
+ * What `create` would encode for `target`, in the shape `toJSON` decodes
+ * records into, without encoding it.
+ */
+export function recordInfo(target: string | URL, options?: CreateOptions | undefined | null): any
+/**
+ * Encode an object from `recordInfo`, or a parsed `toJSON` result. Of the
+ * options, only `compat` and `dateRounding` matter; pass the ones
+ * `recordInfo` got for the same bytes `create` returns.
+ */
+export function encodeInfo(info: any, options?: CreateOptions | undefined | null): Buffer | Uint8Array
+/**
+ * Package an alias record as the `blob` value of a `.DS_Store` `pict` entry.
+ *
+ * Emits a process warning when the entry would not fit in a single
+ * `.DS_Store` B-tree node.
+ */
+export function asDsStoreBlob(buffer: string | Buffer | Uint8Array | ArrayBuffer, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+/**
+ * Wrap an alias record in the resource fork of a classic alias file, as
+ * its only `alis` resource. Finder uses id 0 and no name.
+ */
+export function wrapAsResourceFork(buffer: string | Buffer | Uint8Array | ArrayBuffer, resourceId?: number | undefined | null, name?: string | undefined | null, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+/**
+ * The alias record in a resource fork, such as the contents of a classic
+ * alias file's `..namedfork/rsrc`: the data of its first `alis` resource.
+ */
+export function extractFromResourceFork(buffer: string | Buffer | Uint8Array | ArrayBuffer, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+export interface WriteAliasFileOptions {
+  /**
+   * `'classic'`, the default, for the record in an `alis` resource of the
+   * resource fork, or `'bookmark'` for bookmark data as current Finder
+   * writes it.
+   */
+  format?: 'classic' | 'bookmark'
+  /** How the record of a classic alias file is made, as for `create`. */
+  record?: CreateOptions
+}
+/**
+ * Write a Finder alias file at `aliasPath` pointing to `target`, with its
+ * `kIsAlias` Finder flag set so that Finder opens the target when it is
+ * double-clicked. An existing file at `aliasPath` is replaced.
+ */
+export function writeAliasFile(target: string | URL, aliasPath: string, options?: WriteAliasFileOptions | undefined | null): void
+/** What `readAliasFile` found in an alias file. */
+export interface AliasFileContents {
+  /**
+   * `'classic'` for a record from the resource fork, `'bookmark'` for
+   * bookmark data.
+   */
+  format: 'classic' | 'bookmark'
+  /** The raw record or bookmark data. */
+  record: Buffer
+  /** The record decoded as `decode` does, for classic alias files. */
+  info?: any
+  /** The bookmark parsed as `parseBookmark` does, for modern ones. */
+  bookmark?: Bookmark
+}
+/**
+ * Read the alias record or bookmark stored in the alias file at `path`,
+ * without resolving it: the `alis` resource of its resource fork, if it has
+ * one, or else the bookmark data of a modern Finder alias.
+ */
+export function readAliasFile(path: string): AliasFileContents
+/** Fields to replace in an existing alias record. */
+export interface AliasChanges {
+  volumeName?: string
+  targetFilename?: string
+  parentName?: string
+  targetId?: number
+  parentId?: number
+  /** Creation date of the target, in milliseconds since the Unix epoch. */
+  targetCreated?: number
+  /** Creation date of the volume, in milliseconds since the Unix epoch. */
+  volumeCreated?: number
+}
+/**
+ * Patch fields of an existing alias record, keeping the classic fields and
+ * the matching extras in sync. Extras that are not affected are preserved
+ * byte for byte.
+ */
+export function updateAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, changes: AliasChanges, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+/** Where `rebaseAlias` moves a record to. */
+export interface RebaseOptions {
+  /** Mount point of the new volume, e.g. `/Volumes/My App`. */
+  volumePath: string
+  /** The name Finder shows for the new volume. */
+  volumeName: string
+  /**
+   * The directory in the record's path that becomes the root of the new
+   * volume, e.g. a staging directory, spelled as the record spells it.
+   * Defaults to the recorded mount point.
+   */
+  fromPath?: string
+  /**
+   * Creation date of the new volume, in milliseconds since the Unix epoch.
+   * The old volume's date is kept otherwise.
+   */
+  volumeCreated?: number
+  /** Zero the target and parent ids, which belong to the old volume. */
+  zeroIds?: boolean
+  targetId?: number
+  parentId?: number
+  /** Return a plain `Uint8Array` rather than a `Buffer`. */
+  output?: 'buffer' | 'uint8array'
+}
+/**
+ * Rewrite an alias record to point into a different volume, e.g. from the
+ * staging directory of a disk image to where the image will be mounted, so
+ * the image does not have to be mounted to create the alias.
+ */
+export function rebaseAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, options: RebaseOptions): Buffer | Uint8Array
+/**
+ * The properties `parseBookmark` understands. Anything the bookmark does not
+ * carry is left out.
+ */
+export interface Bookmark {
+  /** Components of the target's absolute path, from the root down. */
+  path: Array<string>
+  /** Absolute posix path of the target, joined from `path`. */
+  targetPath?: string
+  /** File ids of the components of `path`. */
+  cnidPath: Array<number>
+  fileId?: number
+  /** Creation date of the target, in milliseconds since the Unix epoch. */
+  created?: number
+  /**
+   * The target's `kCFURLResourceIs*` bits (1 regular file, 2 directory,
+   * 4 symbolic link, 8 volume, 16 package, ...).
+   */
+  fileFlags?: number
+  /** Mount point of the target's volume. */
+  volumePath?: string
+  volumeName?: string
+  volumeUuid?: string
+  /** Creation date of the volume, in milliseconds since the Unix epoch. */
+  volumeCreated?: number
+  /** The volume's property bits (1 local, 2 automounted, ...). */
+  volumeFlags?: number
+}
+/**
+ * Parse bookmark (`book`) data, as found in alias files written by modern
+ * macOS, without resolving it.
+ */
+export function parseBookmark(buffer: string | Buffer | Uint8Array | ArrayBuffer): Bookmark
+/** One entry of a bookmark's table of contents. */
+export interface BookmarkEntry {
+  /**
+   * The numeric key, e.g. `0x1004` for the path. For keys naming a string
+   * item, the high bit is set.
+   */
+  key: number
+  /**
+   * The string a string key names, or the usual name of a well-known
+   * numeric key, e.g. `path`.
+   */
+  name?: string
+  value: import('./types').BookmarkValue
+}
+export interface BookmarkToc {
+  identifier: number
+  entries: Array<BookmarkEntry>
+}
+/** Everything `decodeBookmark` reads from bookmark data. */
+export interface DecodedBookmark {
+  /** What `parseBookmark` returns. */
+  properties: Bookmark
+  /** The tables of contents, usually one, in the order they are chained. */
+  tocs: Array<BookmarkToc>
+}
+/**
+ * Parse bookmark (`book`) data into every entry of its tables of
+ * contents, along with what `parseBookmark` returns, for inspecting what
+ * Finder and other apps stored.
+ */
+export function decodeBookmark(buffer: string | Buffer | Uint8Array | ArrayBuffer): DecodedBookmark
+export interface BookmarkOptions {
+  /** Record this volume name instead of the one looked up. */
+  volumeName?: string
+  /**
+   * Record this volume UUID, like `0A81F3B1-51D9-3335-B3E3-169C3640360D`,
+   * instead of the volume's own.
+   */
+  volumeUuid?: string
+  /** Return a plain `Uint8Array` rather than a `Buffer`. */
+  output?: 'buffer' | 'uint8array'
+}
+/**
+ * Bookmark (`book`) data for the file or directory at `target`, as current
+ * macOS writes into Finder aliases: its path with the file id of each
+ * component, its creation date and its volume's mount point, URL, name,
+ * UUID, creation date and properties.
+ */
+export function createBookmark(target: string | URL, options?: BookmarkOptions | undefined | null): Buffer | Uint8Array
+/**
+ * Encode bookmark data from known properties, as `parseBookmark` returns
+ * them, without touching the filesystem. Unlike `createBookmark`, this
+ * works on every platform. `targetPath` is ignored in favor of `path`.
+ */
+export function encodeBookmark(bookmark: Bookmark, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+/**
+ * Run the `macos-alias` command line tool with `args` and return its exit
+ * code. This backs the npm package's `macos-alias` bin.
+ */
+export function runCli(args: Array<string>): number
+/**
+ * An alias record. The record is only decoded when a getter needs it, and
+ * only re-encoded by `toBuffer()` after a setter changed it.
+ */
+export class Alias {
+  /** The record `create` would return for `target`. */
+  static fromPath(target: string | URL, options?: CreateOptions | undefined | null): Alias
+  /** Wrap an existing record. It is decoded on first use. */
+  static fromBuffer(buffer: string | Buffer | Uint8Array | ArrayBuffer): Alias
+  /** The record `createFromInfo` would return for `info`. */
+  static fromInfo(info: AliasInfo): Alias
+  get targetFilename(): string
+  set targetFilename(value: string)
+  get targetId(): number
+  set targetId(value: number)
+  get volumeName(): string
+  set volumeName(value: string)
+  /** Mount point of the volume, from the posix path extras. */
+  get volumePath(): string | null
+  /** Creation date of the target, in milliseconds since the Unix epoch. */
+  get created(): number
+  set created(value: number)
+  /** The extras, in record order, with the value of the well-known ones. */
+  get extras(): Array<ExtraRecord>
+  /** The encoded record: the original bytes if nothing was changed. */
+  toBuffer(): Buffer
+  /** Like `toBuffer()`, as a plain `Uint8Array`. */
+  toUint8Array(): Uint8Array
+  /** The record as `toJSON` decodes it, as an object. */
+  toJSON(): any
+  /**
+   * The path of the target on this machine, found the way `resolve`
+   * finds it.
+   */
+  resolve(): string | null
+}
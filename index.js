@@ -295,6 +295,48 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { create } = nativeBinding
+const { Alias, create, createAsync, createBase64, createDebug, createDetailed, createInto, estimateRecordSize, DS_STORE_MAX_ALIAS_LENGTH, createForFd, createForTree, createMany, createForStagedDmg, createBackgroundAlias, createFromInfo, toJSON, decode, fromJSON, decodeAll, validate, recordInfo, encodeInfo, asDsStoreBlob, wrapAsResourceFork, extractFromResourceFork, writeAliasFile, readAliasFile, updateAlias, rebaseAlias, parseBookmark, decodeBookmark, createBookmark, encodeBookmark, runCli, statAliasTarget, resolveAlias, resolve, relinkAlias, getVolumeInfo, listVolumes, fileIds, systemAliasFor, verify } = nativeBinding
 
+module.exports.Alias = Alias
 module.exports.create = create
+module.exports.createAsync = createAsync
+module.exports.createBase64 = createBase64
+module.exports.createDebug = createDebug
+module.exports.createDetailed = createDetailed
+module.exports.createInto = createInto
+module.exports.estimateRecordSize = estimateRecordSize
+module.exports.DS_STORE_MAX_ALIAS_LENGTH = DS_STORE_MAX_ALIAS_LENGTH
+module.exports.createForFd = createForFd
+module.exports.createForTree = createForTree
+module.exports.createMany = createMany
+module.exports.createForStagedDmg = createForStagedDmg
+module.exports.createBackgroundAlias = createBackgroundAlias
+module.exports.createFromInfo = createFromInfo
+module.exports.toJSON = toJSON
+module.exports.fromJSON = fromJSON
+module.exports.decode = decode
+module.exports.decodeAll = decodeAll
+module.exports.validate = validate
+module.exports.recordInfo = recordInfo
+module.exports.encodeInfo = encodeInfo
+module.exports.asDsStoreBlob = asDsStoreBlob
+module.exports.wrapAsResourceFork = wrapAsResourceFork
+module.exports.extractFromResourceFork = extractFromResourceFork
+module.exports.writeAliasFile = writeAliasFile
+module.exports.readAliasFile = readAliasFile
+module.exports.updateAlias = updateAlias
+module.exports.rebaseAlias = rebaseAlias
+module.exports.parseBookmark = parseBookmark
+module.exports.decodeBookmark = decodeBookmark
+module.exports.createBookmark = createBookmark
+module.exports.encodeBookmark = encodeBookmark
+module.exports.runCli = runCli
+module.exports.statAliasTarget = statAliasTarget
+module.exports.resolveAlias = resolveAlias
+module.exports.resolve = resolve
+module.exports.relinkAlias = relinkAlias
+module.exports.getVolumeInfo = getVolumeInfo
+module.exports.listVolumes = listVolumes
+module.exports.fileIds = fileIds
+module.exports.systemAliasFor = systemAliasFor
+module.exports.verify = verify
@@ -0,0 +1,51 @@
+// Hand-maintained entry point: `index.js` is generated by `napi build`.
+//
+// Where no prebuilt binary loads, such as on Alpine or unusual
+// architectures, the WebAssembly build of the encoder stands in for it.
+// It has `createFromInfo`, `decode`, `toJSON`, `fromJSON` and the
+// constants; every other function throws `ERR_UNSUPPORTED_PLATFORM`.
+
+function wasmBinding(wasm, loadError) {
+  const bytes = (buffer) => {
+    if (typeof buffer === 'string') return Buffer.from(buffer, 'base64')
+    if (buffer instanceof ArrayBuffer) return new Uint8Array(buffer)
+    return buffer
+  }
+  const output = (record, options) =>
+    options && options.output === 'uint8array'
+      ? record
+      : Buffer.from(record.buffer, record.byteOffset, record.byteLength)
+  const binding = {
+    toJSON: (buffer) => wasm.toJSON(bytes(buffer)),
+    decode: (buffer) => JSON.parse(wasm.toJSON(bytes(buffer))),
+    fromJSON: (json, options) => output(wasm.fromJSON(json), options),
+    createFromInfo: (info, options) => output(wasm.createFromInfo(JSON.stringify(info)), options),
+    DS_STORE_MAX_ALIAS_LENGTH: wasm.dsStoreMaxAliasLength(),
+  }
+  return new Proxy(binding, {
+    get(target, name) {
+      if (name in target || typeof name === 'symbol' || name === 'then' || name === '__esModule') {
+        return target[name]
+      }
+      return () => {
+        const error = new Error(
+          `${name} is not in the WebAssembly build, which stands in for the native binding: ${loadError.message}`,
+        )
+        error.code = 'ERR_UNSUPPORTED_PLATFORM'
+        throw error
+      }
+    },
+  })
+}
+
+try {
+  module.exports = require('./index.js')
+} catch (loadError) {
+  let wasm
+  try {
+    wasm = require('./wasm/pkg')
+  } catch {
+    throw loadError
+  }
+  module.exports = wasmBinding(wasm, loadError)
+}
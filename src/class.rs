@@ -0,0 +1,200 @@
+//! The `Alias` class, for keeping one record around while reading and
+//! editing it.
+
+use macos_alias_core as alias;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use once_cell::unsync::OnceCell;
+
+use crate::{
+  info_from_alias_info, millis_from_system_time, record_bytes, system_time_from_millis,
+  target_path, to_napi_error, AliasInfo, CreateOptions, ExtraRecord, RecordData, Result,
+};
+
+#[napi]
+/// An alias record. The record is only decoded when a getter needs it, and
+/// only re-encoded by `toBuffer()` after a setter changed it.
+pub struct Alias {
+  /// The encoded record, until a setter changes `info`.
+  record: Option<Vec<u8>>,
+  info: OnceCell<alias::Info>,
+  /// How a changed `info` is encoded again.
+  compat: alias::Compat,
+}
+
+impl Alias {
+  fn info(&self) -> Result<&alias::Info> {
+    self.info.get_or_try_init(|| {
+      alias::decode(self.record.as_deref().unwrap_or_default()).map_err(to_napi_error)
+    })
+  }
+
+  fn record(&mut self) -> Result<Vec<u8>> {
+    if self.record.is_none() {
+      let record = alias::encode_with(self.info()?, self.compat).map_err(to_napi_error)?;
+      self.record = Some(record);
+    }
+    Ok(self.record.clone().unwrap_or_default())
+  }
+
+  fn change(&mut self, changes: alias::Changes) -> Result<()> {
+    self.info()?;
+    if let Some(info) = self.info.get_mut() {
+      alias::apply_changes(info, &changes);
+    }
+    self.record = None;
+    Ok(())
+  }
+}
+
+#[napi]
+impl Alias {
+  #[napi(factory)]
+  /// The record `create` would return for `target`.
+  pub fn from_path(
+    #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+    options: Option<CreateOptions>,
+  ) -> Result<Self> {
+    let options: alias::CreateOptions = match options {
+      Some(options) => options.try_into()?,
+      None => alias::CreateOptions::default(),
+    };
+    let info = alias::info_for_path_with(target_path(target)?, &options).map_err(to_napi_error)?;
+    let record = alias::encode_with(&info, options.compat).map_err(to_napi_error)?;
+    Ok(Alias {
+      record: Some(record),
+      info: OnceCell::with_value(info),
+      compat: options.compat,
+    })
+  }
+
+  #[napi(factory)]
+  /// Wrap an existing record. It is decoded on first use.
+  pub fn from_buffer(
+    #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+  ) -> Result<Self> {
+    Ok(Alias {
+      record: Some(record_bytes(buffer)?),
+      info: OnceCell::new(),
+      compat: Default::default(),
+    })
+  }
+
+  #[napi(factory)]
+  /// The record `createFromInfo` would return for `info`.
+  pub fn from_info(info: AliasInfo) -> Result<Self> {
+    Ok(Alias {
+      record: None,
+      info: OnceCell::with_value(info_from_alias_info(info)?),
+      compat: Default::default(),
+    })
+  }
+
+  #[napi(getter)]
+  pub fn target_filename(&self) -> Result<String> {
+    Ok(self.info()?.target.filename.clone())
+  }
+
+  #[napi(setter, js_name = "targetFilename")]
+  pub fn set_target_filename(&mut self, value: String) -> Result<()> {
+    self.change(alias::Changes {
+      target_filename: Some(value),
+      ..Default::default()
+    })
+  }
+
+  #[napi(getter)]
+  pub fn target_id(&self) -> Result<u32> {
+    Ok(self.info()?.target.id)
+  }
+
+  #[napi(setter, js_name = "targetId")]
+  pub fn set_target_id(&mut self, value: u32) -> Result<()> {
+    self.change(alias::Changes {
+      target_id: Some(value),
+      ..Default::default()
+    })
+  }
+
+  #[napi(getter)]
+  pub fn volume_name(&self) -> Result<String> {
+    Ok(self.info()?.volume.name.clone())
+  }
+
+  #[napi(setter, js_name = "volumeName")]
+  pub fn set_volume_name(&mut self, value: String) -> Result<()> {
+    self.change(alias::Changes {
+      volume_name: Some(value),
+      ..Default::default()
+    })
+  }
+
+  #[napi(getter)]
+  /// Mount point of the volume, from the posix path extras.
+  pub fn volume_path(&self) -> Result<Option<String>> {
+    Ok(match self.info()?.extra(19).and_then(alias::Extra::value) {
+      Some(alias::ExtraValue::String(path)) => Some(path),
+      _ => None,
+    })
+  }
+
+  #[napi(getter)]
+  /// Creation date of the target, in milliseconds since the Unix epoch.
+  pub fn created(&self) -> Result<f64> {
+    Ok(millis_from_system_time(self.info()?.target.created))
+  }
+
+  #[napi(setter, js_name = "created")]
+  pub fn set_created(&mut self, value: f64) -> Result<()> {
+    self.change(alias::Changes {
+      target_created: Some(system_time_from_millis("created", value)?),
+      ..Default::default()
+    })
+  }
+
+  #[napi(getter)]
+  /// The extras, in record order, with the value of the well-known ones.
+  pub fn extras(&self) -> Result<Vec<ExtraRecord>> {
+    Ok(
+      self
+        .info()?
+        .extra
+        .iter()
+        .map(|extra| ExtraRecord {
+          type_: i32::from(extra.type_),
+          data: Some(Either3::A(extra.data.clone().into())),
+          value: extra.value().map(|value| match value {
+            alias::ExtraValue::String(s) => Either::A(s),
+            alias::ExtraValue::Number(n) => Either::B(n),
+          }),
+        })
+        .collect(),
+    )
+  }
+
+  #[napi(js_name = "toBuffer")]
+  /// The encoded record: the original bytes if nothing was changed.
+  pub fn buffer(&mut self) -> Result<Buffer> {
+    Ok(self.record()?.into())
+  }
+
+  #[napi(js_name = "toUint8Array")]
+  /// Like `toBuffer()`, as a plain `Uint8Array`.
+  pub fn uint8_array(&mut self) -> Result<Uint8Array> {
+    Ok(Uint8Array::new(self.record()?))
+  }
+
+  #[napi(js_name = "toJSON", ts_return_type = "any")]
+  /// The record as `toJSON` decodes it, as an object.
+  pub fn to_json(&self) -> Result<serde_json::Value> {
+    serde_json::to_value(self.info()?)
+      .map_err(|err| Error::new("ERR_INVALID_RECORD", err.to_string()))
+  }
+
+  #[napi]
+  /// The path of the target on this machine, found the way `resolve`
+  /// finds it.
+  pub fn resolve(&mut self) -> Result<Option<String>> {
+    alias::resolve_alias(&self.record()?, &Default::default()).map_err(to_napi_error)
+  }
+}
@@ -1,487 +1,2166 @@
 #![deny(clippy::all)]
 
-use std::ffi::OsStr;
-use std::fs::Metadata;
-use std::io::Cursor;
-use std::io::Write;
-use std::os::unix::fs::MetadataExt;
-use std::time::{SystemTime, UNIX_EPOCH};
-use std::{fs, path::Path};
-
-use byteorder::BigEndian;
-use byteorder::WriteBytesExt;
-use core_foundation::{
-  base::{kCFAllocatorDefault, kCFAllocatorNull, Boolean, CFIndex, CFIndexConvertible, TCFType},
-  error::CFErrorRef,
-  string::{
-    kCFStringEncodingUTF8, CFString, CFStringCreateWithBytesNoCopy, CFStringGetCString,
-    CFStringGetLength, CFStringGetMaximumSizeForEncoding, CFStringRef,
-  },
-  url::{kCFURLPOSIXPathStyle, kCFURLVolumeNameKey, CFURLCreateWithFileSystemPath, CFURLRef},
-};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use macos_alias_core as alias;
 use napi::bindgen_prelude::*;
+use napi::{JsArrayBuffer, JsFunction, JsObject};
 use napi_derive::napi;
 
-// From 1904, 1, 1 to 1970, 1, 1
-static APPLE_EPOCH: i64 = -2082844800000;
+mod class;
+
+/// Errors thrown to JS carry a stable `code`, e.g. `ERR_TARGET_NOT_FOUND`,
+/// rather than a napi status.
+type Result<T> = std::result::Result<T, Error<&'static str>>;
+
+/// The `code` of errors about the arguments themselves, before the core gets
+/// to see them.
+const ERR_INVALID_ARG: &str = "ERR_INVALID_ARG";
+
+fn to_napi_error(err: alias::Error) -> Error<&'static str> {
+  Error::new(err.code(), err.to_string())
+}
+
+fn system_time_from_millis(field: &str, millis: f64) -> Result<SystemTime> {
+  if !millis.is_finite() || millis < 0.0 {
+    return Err(Error::new(
+      ERR_INVALID_ARG,
+      format!("{field} must be a non-negative number of milliseconds since the Unix epoch."),
+    ));
+  }
+  Ok(UNIX_EPOCH + Duration::from_millis(millis as u64))
+}
+
+fn whole_number(field: &str, value: f64) -> Result<u64> {
+  if !(0.0..=MAX_SAFE_INTEGER).contains(&value) || value.fract() != 0.0 {
+    return Err(Error::new(
+      ERR_INVALID_ARG,
+      format!("{field} must be a non-negative integer, not {value}."),
+    ));
+  }
+  Ok(value as u64)
+}
+
+/// `Number.MAX_SAFE_INTEGER`.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+fn millis_from_system_time(time: SystemTime) -> f64 {
+  match time.duration_since(UNIX_EPOCH) {
+    Ok(since) => since.as_secs_f64() * 1000.0,
+    Err(before) => -before.duration().as_secs_f64() * 1000.0,
+  }
+}
+
+fn target_type(value: Option<&str>) -> Result<alias::TargetType> {
+  match value {
+    None | Some("file") => Ok(alias::TargetType::File),
+    Some("directory") => Ok(alias::TargetType::Directory),
+    Some(other) => Err(Error::new(
+      ERR_INVALID_ARG,
+      format!("Unknown target type {other:?}, expected \"file\" or \"directory\"."),
+    )),
+  }
+}
+
+fn volume_type(value: Option<&str>) -> Result<Option<alias::VolumeType>> {
+  use alias::VolumeType::*;
 
-#[repr(u16)]
-enum TargetType {
-  File = 0,
-  Directory = 1,
+  let Some(value) = value else {
+    return Ok(None);
+  };
+  [Local, Network, Floppy400, Floppy800, Floppy1400, Other]
+    .into_iter()
+    .find(|type_| type_.as_ref() == value)
+    .map(Some)
+    .ok_or_else(|| {
+      Error::new(
+        ERR_INVALID_ARG,
+        format!(
+          "Unknown volume type {value:?}, expected \"local\", \"network\", \"floppy400\", \"floppy800\", \"floppy1400\" or \"other\"."
+        ),
+      )
+    })
 }
 
-#[repr(u16)]
-#[allow(dead_code)]
-enum VolumeType {
-  Local = 0,
-  Network,
-  Floppy400,
-  Floppy800,
-  Floppy1400,
-  Other,
+fn volume_signature(value: Option<&str>) -> Result<Option<alias::VolumeSignature>> {
+  use alias::VolumeSignature::*;
+
+  let Some(value) = value else {
+    return Ok(None);
+  };
+  [Bd, HPlus, Hx]
+    .into_iter()
+    .find(|signature| signature.as_ref() == value)
+    .map(Some)
+    .ok_or_else(|| {
+      Error::new(
+        ERR_INVALID_ARG,
+        format!("Unknown volume signature {value:?}, expected \"BD\", \"H+\" or \"HX\"."),
+      )
+    })
 }
 
-#[allow(dead_code)]
-enum VolumeSignature {
-  Bd,
-  HPlus,
-  Hx,
+fn four_char_code(field: &str, value: Option<&str>) -> Result<[u8; 4]> {
+  match value {
+    None => Ok([0; 4]),
+    Some(code) => code.as_bytes().try_into().map_err(|_| {
+      Error::new(
+        ERR_INVALID_ARG,
+        format!("{field} must be a four character code, got {code:?}."),
+      )
+    }),
+  }
+}
+
+fn file_name(path: &Path) -> Result<String> {
+  path
+    .file_name()
+    .and_then(|s| s.to_str())
+    .map(|s| s.to_string())
+    .ok_or_else(|| {
+      Error::new(
+        ERR_INVALID_ARG,
+        format!("{} has no file name.", path.display()),
+      )
+    })
 }
 
-impl AsRef<str> for VolumeSignature {
-  fn as_ref(&self) -> &str {
+/// Binary data from JS. Node's `Buffer` is a `Uint8Array`, but Bun and Deno
+/// code mostly holds plain `Uint8Array`s and `ArrayBuffer`s.
+type Binary = Either3<Buffer, Uint8Array, JsArrayBuffer>;
+
+fn binary_bytes(data: Binary) -> Result<Vec<u8>> {
+  match data {
+    Either3::A(buffer) => Ok(buffer.to_vec()),
+    Either3::B(array) => Ok(array.to_vec()),
+    Either3::C(array_buffer) => array_buffer
+      .into_value()
+      .map(|value| value.to_vec())
+      .map_err(|err| Error::new(ERR_INVALID_ARG, err.reason)),
+  }
+}
+
+/// A record from JS: binary data, or its bytes as a base64 string, as JSON
+/// configs and snapshot tests store records.
+type RecordData = Either4<String, Buffer, Uint8Array, JsArrayBuffer>;
+
+fn record_bytes(data: RecordData) -> Result<Vec<u8>> {
+  match data {
+    Either4::A(base64) => BASE64.decode(base64).map_err(|err| {
+      Error::new(
+        ERR_INVALID_ARG,
+        format!(
+          "A string must hold the data as base64: {}.",
+          err.to_string().trim_end_matches('.')
+        ),
+      )
+    }),
+    Either4::B(buffer) => binary_bytes(Either3::A(buffer)),
+    Either4::C(array) => binary_bytes(Either3::B(array)),
+    Either4::D(array_buffer) => binary_bytes(Either3::C(array_buffer)),
+  }
+}
+
+/// A record returned to JS: a `Buffer`, or a plain `Uint8Array` when the
+/// options ask for `output: 'uint8array'`.
+type Output = Either<Buffer, Uint8Array>;
+
+#[derive(Clone, Copy, Default)]
+enum OutputType {
+  #[default]
+  Buffer,
+  Uint8Array,
+}
+
+impl OutputType {
+  fn parse(value: Option<&str>) -> Result<Self> {
+    match value {
+      None | Some("buffer") => Ok(OutputType::Buffer),
+      Some("uint8array") => Ok(OutputType::Uint8Array),
+      Some(other) => Err(Error::new(
+        ERR_INVALID_ARG,
+        format!("Unknown output type {other:?}, expected \"buffer\" or \"uint8array\"."),
+      )),
+    }
+  }
+
+  fn wrap(self, bytes: Vec<u8>) -> Output {
     match self {
-      VolumeSignature::Bd => "BD",
-      VolumeSignature::HPlus => "H+",
-      VolumeSignature::Hx => "HX",
+      OutputType::Buffer => Either::A(bytes.into()),
+      OutputType::Uint8Array => Either::B(Uint8Array::new(bytes)),
     }
   }
 }
 
-struct Info {
-  version: u16,
-  target: Target,
-  volume: Volume,
-  parent: Parent,
-  extra: Vec<Extra>,
+#[napi(object)]
+/// For functions that return a record but take no other options.
+pub struct OutputOptions {
+  /// Return a plain `Uint8Array` rather than a `Buffer`.
+  #[napi(ts_type = "'buffer' | 'uint8array'")]
+  pub output: Option<String>,
 }
 
-struct Target {
-  type_: TargetType,
-  filename: String,
-  id: u32,
-  created: SystemTime,
+fn output_type(options: Option<&OutputOptions>) -> Result<OutputType> {
+  OutputType::parse(options.and_then(|options| options.output.as_deref()))
 }
 
-struct Volume {
-  name: String,
-  created: SystemTime,
-  signature: VolumeSignature,
-  type_: VolumeType,
+#[napi(object)]
+/// Metadata describing an alias target, used instead of looking it up on disk.
+pub struct AliasInfo {
+  /// Absolute posix path of the target, e.g. `/Volumes/Title/.background/bg.tiff`.
+  pub target_path: String,
+  /// Whether the target is a file (the default) or a directory.
+  #[napi(ts_type = "'file' | 'directory'")]
+  pub target_type: Option<String>,
+  /// Four character file type code, e.g. `TIFF`. Files only.
+  pub file_type: Option<String>,
+  /// Four character creator code, e.g. `8BIM`. Files only.
+  pub file_creator: Option<String>,
+  /// Catalog node id of the target.
+  pub target_id: u32,
+  /// Creation date of the target, in milliseconds since the Unix epoch.
+  pub target_created: f64,
+  /// Catalog node id of the directory containing the target.
+  pub parent_id: u32,
+  /// Name of the directory containing the target. Defaults to the last
+  /// component of `targetPath`'s parent, or `volumeName` for a target at
+  /// the root of the volume.
+  pub parent_name: Option<String>,
+  /// Mount point of the volume, e.g. `/Volumes/Title`.
+  pub volume_path: String,
+  /// Display name of the volume. A `/` in it reads `:` in `volumePath`.
+  pub volume_name: String,
+  /// Creation date of the volume, in milliseconds since the Unix epoch.
+  pub volume_created: f64,
+  /// The kind of media the volume is on. Defaults to `local` for `/` and
+  /// `other` for everything else.
+  #[napi(ts_type = "'local' | 'network' | 'floppy400' | 'floppy800' | 'floppy1400' | 'other'")]
+  pub volume_type: Option<String>,
+  /// The volume's two-character signature. Defaults to `H+`; floppies are
+  /// `BD`.
+  #[napi(ts_type = "'BD' | 'H+' | 'HX'")]
+  pub volume_signature: Option<String>,
 }
 
-struct Parent {
-  id: u32,
-  name: String,
+#[napi(object)]
+pub struct CreateOptions {
+  /// Reproduce another implementation's output byte for byte.
+  #[napi(ts_type = "'node-alias'")]
+  pub compat: Option<String>,
+  /// Write only the posix path extras and leave the classic name fields
+  /// empty. `'with-names'` also keeps the unicode file and volume names.
+  #[napi(ts_type = "boolean | 'with-names'")]
+  pub minimal: Option<Either<bool, String>>,
+  /// The families of extras to write, by default all of them: `classic`
+  /// (the parent name and id and the carbon path, types 0 to 2), `unicode`
+  /// (the file and volume names, 14 and 15), `posix` (the paths, 18 and
+  /// 19) and `diskImage` (20). Without `unicode` the classic fields must
+  /// spell the names in full, and without `posix` they must not be left
+  /// empty by `minimal`; otherwise the call throws.
+  #[napi(ts_type = "Array<'classic' | 'unicode' | 'posix' | 'diskImage'>")]
+  pub extras: Option<Vec<String>>,
+  /// Extras to add. Each replaces the extra of the same type, if any, and is
+  /// otherwise slotted into Finder's order (ascending by type).
+  pub extra_records: Option<Vec<ExtraRecord>>,
+  /// Append `extraRecords` after the other extras, in the order given.
+  pub append_raw: Option<bool>,
+  /// Create the record even if the target does not exist yet, as a file with
+  /// id 0 and its parent directory's dates. The parent still has to exist.
+  pub allow_missing: Option<bool>,
+  /// Throw instead of downloading cloud content, such as an iCloud file
+  /// whose contents are not on disk.
+  #[napi(js_name = "noIO")]
+  pub no_io: Option<bool>,
+  /// If the target is a Finder alias file, follow it (and any aliases it
+  /// points to) and record the final target instead. Without this, `create`
+  /// emits a process warning for alias files.
+  pub resolve_aliases: Option<bool>,
+  /// Leave out the record of the disk image the target's volume is mounted
+  /// from, which lets Finder mount the image again to resolve the alias.
+  pub no_disk_image: Option<bool>,
+  /// Where the volume name is looked up first, falling back to the sources
+  /// after it: CoreFoundation's display name, `getattrlist`'s
+  /// `ATTR_VOL_NAME`, then the last component of the mount point. Defaults
+  /// to `'attrlist'`.
+  #[napi(ts_type = "'cf' | 'attrlist' | 'mountpoint'")]
+  pub volume_name_source: Option<String>,
+  /// Record the volume under the name stored on it (`'canonical'`, the
+  /// default, as Finder does) or the possibly localized name Finder shows
+  /// (`'display'`). Shorthand for `volumeNameSource: 'attrlist'` or `'cf'`.
+  #[napi(ts_type = "'display' | 'canonical'")]
+  pub volume_name_style: Option<String>,
+  /// Record this volume name instead of the one looked up, e.g. the title of
+  /// the disk image the target will end up on. The mount point stays as it
+  /// is. Takes precedence over `volumeNameSource` and `volumeNameStyle`.
+  pub volume_name: Option<String>,
+  /// Record this volume type instead of the one looked up, e.g.
+  /// `'floppy800'` for a volume standing in for an 800K floppy.
+  #[napi(ts_type = "'local' | 'network' | 'floppy400' | 'floppy800' | 'floppy1400' | 'other'")]
+  pub volume_type: Option<String>,
+  /// Record this signature instead of the filesystem's, e.g. `'BD'` for a
+  /// classic HFS volume. Floppy types need `'BD'`.
+  #[napi(ts_type = "'BD' | 'H+' | 'HX'")]
+  pub volume_signature: Option<String>,
+  /// Record this target id instead of the file's inode number, e.g. the
+  /// CNID the file will get in an HFS+ image built from a staging
+  /// directory.
+  pub target_id: Option<u32>,
+  /// Record this parent id instead of the parent directory's inode number,
+  /// in the header and in extra 1.
+  pub parent_id: Option<u32>,
+  /// Record this parent name, in the header, extra 0 and the carbon path.
+  /// The posix path keeps the real one.
+  pub parent_name: Option<String>,
+  /// Record this creation date of the target instead of the file's, in
+  /// milliseconds since the Unix epoch, e.g. for reproducible builds.
+  pub target_created: Option<f64>,
+  /// Record this creation date of the volume instead of the volume's, in
+  /// milliseconds since the Unix epoch.
+  pub volume_created: Option<f64>,
+  /// Record the UUID of the target's volume in an extra of type `0x2011`,
+  /// which `statAliasTarget` and `relinkAlias` then prefer over the mount
+  /// point to find the volume by. `true` looks the UUID up; a string such
+  /// as `'0A81F3B1-51D9-3335-B3E3-169C3640360D'` gives it. Finder writes no
+  /// such extra.
+  #[napi(ts_type = "boolean | string")]
+  pub volume_uuid: Option<Either<bool, String>>,
+  /// How creation dates lose their milliseconds: `'floor'` drops them, as
+  /// Finder does, and `'round'` rounds halves up, as node-alias does. The
+  /// default follows `compat`.
+  #[napi(ts_type = "'floor' | 'round'")]
+  pub date_rounding: Option<String>,
+  /// Throw `ERR_RECORD_EXCEEDS_MAX_SIZE` rather than return a record
+  /// longer than this many bytes, e.g. `DS_STORE_MAX_ALIAS_LENGTH`.
+  pub max_size: Option<u32>,
+  /// Add the high-resolution creation dates of the volume and the target
+  /// (extras 16 and 17), which keep the fraction of a second the classic
+  /// date fields drop, as Finder does. Full layouts only.
+  pub hires_dates: Option<bool>,
+  /// Return a plain `Uint8Array` rather than a `Buffer`. `createDebug` and
+  /// `createDetailed` always return a `Buffer`.
+  #[napi(ts_type = "'buffer' | 'uint8array'")]
+  pub output: Option<String>,
 }
 
-struct Extra {
-  type_: i16,
-  length: u16,
-  data: Vec<u8>,
+/// The output type `options` ask for.
+fn create_output_type(options: Option<&CreateOptions>) -> Result<OutputType> {
+  OutputType::parse(options.and_then(|options| options.output.as_deref()))
 }
 
-fn apple_date(value: SystemTime) -> u32 {
-  let since_the_epoch = value
-    .duration_since(UNIX_EPOCH)
-    .expect("Time went backwards");
-  ((since_the_epoch.as_millis() as f64 - APPLE_EPOCH as f64) / 1000.0).round() as u32
+#[napi(object)]
+/// An extra, tagged with its type. Give either the raw `data` or, for the
+/// well-known types, a `value` to encode: a string for 0, 2, 14, 15, 18 and
+/// 19, a number for 1.
+pub struct ExtraRecord {
+  #[napi(js_name = "type")]
+  pub type_: i32,
+  #[napi(ts_type = "Buffer | Uint8Array | ArrayBuffer")]
+  pub data: Option<Binary>,
+  pub value: Option<Either<String, u32>>,
 }
 
-fn encode(info: Info) -> Result<Vec<u8>> {
-  let base_length = 150;
-  let extra_length: usize = info
-    .extra
-    .iter()
-    .map(|e| 4 + e.length as usize + (e.length % 2) as usize)
-    .sum();
-  let trailer_length = 4;
+impl TryFrom<CreateOptions> for alias::CreateOptions {
+  type Error = Error<&'static str>;
 
-  let total = base_length + extra_length + trailer_length;
-  let buf: Vec<u8> = vec![0; total];
+  fn try_from(options: CreateOptions) -> Result<Self> {
+    let compat = match options.compat.as_deref() {
+      None => alias::Compat::Native,
+      Some("node-alias") => alias::Compat::NodeAlias,
+      Some(other) => {
+        return Err(Error::new(
+          ERR_INVALID_ARG,
+          format!("Unknown compat mode {other:?}, expected \"node-alias\"."),
+        ))
+      }
+    };
+    let layout = match options.minimal {
+      None | Some(Either::A(false)) => alias::Layout::Full,
+      Some(Either::A(true)) => alias::Layout::Minimal,
+      Some(Either::B(mode)) if mode == "with-names" => alias::Layout::MinimalWithNames,
+      Some(Either::B(other)) => {
+        return Err(Error::new(
+          ERR_INVALID_ARG,
+          format!("Unknown minimal mode {other:?}, expected a boolean or \"with-names\"."),
+        ))
+      }
+    };
+    let extras = match options.extras {
+      None => alias::Extras::default(),
+      Some(families) => {
+        let mut extras = alias::Extras {
+          classic: false,
+          unicode: false,
+          posix: false,
+          disk_image: false,
+        };
+        for family in families {
+          match family.as_str() {
+            "classic" => extras.classic = true,
+            "unicode" => extras.unicode = true,
+            "posix" => extras.posix = true,
+            "diskImage" => extras.disk_image = true,
+            other => {
+              return Err(Error::new(
+                ERR_INVALID_ARG,
+                format!(
+                  "Unknown extras family {other:?}, expected \"classic\", \"unicode\", \"posix\" or \"diskImage\"."
+                ),
+              ))
+            }
+          }
+        }
+        extras
+      }
+    };
+    let extra_records = options
+      .extra_records
+      .unwrap_or_default()
+      .into_iter()
+      .map(|record| {
+        let type_ = i16::try_from(record.type_).map_err(|_| {
+          Error::new(
+            ERR_INVALID_ARG,
+            format!("Extra type {} does not fit in 16 bits.", record.type_),
+          )
+        })?;
+        match (record.value, record.data) {
+          (Some(Either::A(s)), _) => {
+            alias::Extra::from_value(type_, alias::ExtraValue::String(s)).map_err(to_napi_error)
+          }
+          (Some(Either::B(n)), _) => {
+            alias::Extra::from_value(type_, alias::ExtraValue::Number(n)).map_err(to_napi_error)
+          }
+          (None, Some(data)) => Ok(alias::Extra::new(type_, binary_bytes(data)?)),
+          (None, None) => Err(Error::new(
+            ERR_INVALID_ARG,
+            format!("Extra type {type_} needs either data or a value."),
+          )),
+        }
+      })
+      .collect::<Result<_>>()?;
+    let volume_name_source = match (
+      options.volume_name_source.as_deref(),
+      options.volume_name_style.as_deref(),
+    ) {
+      (Some(_), Some(_)) => {
+        return Err(Error::new(
+          ERR_INVALID_ARG,
+          "Pass either volumeNameSource or volumeNameStyle, not both.",
+        ))
+      }
+      (None, None) => alias::VolumeNameSource::default(),
+      (Some("cf"), None) | (None, Some("display")) => alias::VolumeNameSource::CoreFoundation,
+      (Some("attrlist"), None) | (None, Some("canonical")) => alias::VolumeNameSource::Attrlist,
+      (Some("mountpoint"), None) => alias::VolumeNameSource::MountPoint,
+      (Some(other), None) => {
+        return Err(Error::new(
+          ERR_INVALID_ARG,
+          format!(
+            "Unknown volume name source {other:?}, expected \"cf\", \"attrlist\" or \"mountpoint\"."
+          ),
+        ))
+      }
+      (None, Some(other)) => {
+        return Err(Error::new(
+          ERR_INVALID_ARG,
+          format!("Unknown volume name style {other:?}, expected \"display\" or \"canonical\"."),
+        ))
+      }
+    };
+    let (volume_uuid, embed_volume_uuid) = match options.volume_uuid {
+      None => (None, false),
+      Some(Either::A(embed)) => (None, embed),
+      Some(Either::B(uuid)) => (
+        Some(
+          uuid
+            .parse()
+            .map_err(|err: alias::Error| Error::new(ERR_INVALID_ARG, err.to_string()))?,
+        ),
+        false,
+      ),
+    };
+    let date_rounding = match options.date_rounding.as_deref() {
+      None => None,
+      Some("floor") => Some(alias::DateRounding::Floor),
+      Some("round") => Some(alias::DateRounding::Round),
+      Some(other) => {
+        return Err(Error::new(
+          ERR_INVALID_ARG,
+          format!("Unknown date rounding {other:?}, expected \"floor\" or \"round\"."),
+        ))
+      }
+    };
+    Ok(alias::CreateOptions {
+      compat,
+      layout,
+      extras,
+      extra_records,
+      append_raw: options.append_raw.unwrap_or(false),
+      allow_missing: options.allow_missing.unwrap_or(false),
+      no_io: options.no_io.unwrap_or(false),
+      resolve_aliases: options.resolve_aliases.unwrap_or(false),
+      no_disk_image: options.no_disk_image.unwrap_or(false),
+      volume_name_source,
+      volume_name: options.volume_name,
+      volume_type: volume_type(options.volume_type.as_deref())?,
+      volume_signature: volume_signature(options.volume_signature.as_deref())?,
+      target_id: options.target_id,
+      parent_id: options.parent_id,
+      parent_name: options.parent_name,
+      target_created: options
+        .target_created
+        .map(|millis| system_time_from_millis("targetCreated", millis))
+        .transpose()?,
+      volume_created: options
+        .volume_created
+        .map(|millis| system_time_from_millis("volumeCreated", millis))
+        .transpose()?,
+      volume_uuid,
+      embed_volume_uuid,
+      date_rounding,
+      max_size: options.max_size.map(|max| max as usize),
+      hires_dates: options.hires_dates.unwrap_or(false),
+    })
+  }
+}
+
+/// The target of `create` and friends, given as a path or as a `file:` URL
+/// object such as `new URL(import.meta.url)`.
+fn target_path(target: Either<String, Object>) -> Result<String> {
+  let url = match target {
+    Either::A(path) => return Ok(path),
+    Either::B(url) => url,
+  };
+  let href = url
+    .get::<_, String>("href")
+    .ok()
+    .flatten()
+    .ok_or_else(|| Error::new(ERR_INVALID_ARG, "The target must be a path or a URL."))?;
+  alias::file_url_to_path(&href)
+    .map(|path| path.to_string_lossy().into_owned())
+    .map_err(to_napi_error)
+}
 
-  let mut cursor = Cursor::new(buf);
+fn emit_warning(env: Env, message: &str) -> napi::Result<()> {
+  let process: JsObject = env.get_global()?.get_named_property("process")?;
+  let emit_warning: JsFunction = process.get_named_property("emitWarning")?;
+  emit_warning.call(Some(&process), &[env.create_string(message)?])?;
+  Ok(())
+}
 
-  cursor.write_u32::<BigEndian>(0)?;
+#[napi]
+pub fn create(
+  env: Env,
+  #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+  options: Option<CreateOptions>,
+) -> Result<Output> {
+  let output = create_output_type(options.as_ref())?;
+  create_record(env, target, options).map(|record| output.wrap(record))
+}
 
-  cursor.write_u16::<BigEndian>(total as u16)?;
-  cursor.write_u16::<BigEndian>(info.version)?;
+/// The lookups of `createAsync`, run on the libuv thread pool.
+pub struct CreateTask {
+  target_path: String,
+  options: alias::CreateOptions,
+  output: OutputType,
+}
 
-  cursor.write_u16::<BigEndian>(info.target.type_ as _)?;
+impl Task for CreateTask {
+  /// The record, and the alias file warning to emit for it.
+  type Output = std::result::Result<(Vec<u8>, Option<String>), alias::Error>;
+  type JsValue = Output;
 
-  let vol_name_length = info.volume.name.len();
-  if vol_name_length > 27 {
-    return Err(Error::new(
-      Status::GenericFailure,
-      "Volume name is not longer than 27 chars",
-    ));
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    Ok(
+      alias::create_for_path_with(&self.target_path, &self.options)
+        .map(|record| (record, alias_file_warning(&self.target_path, &self.options))),
+    )
   }
 
-  cursor.write_u8(vol_name_length as u8)?;
-  let padding = vec![0u8; 27 - info.volume.name.bytes().len()];
+  fn resolve(&mut self, env: Env, output: Self::Output) -> napi::Result<Output> {
+    // Rejected with the error's code, as `create` throws it.
+    let (record, warning) = output
+      .map_err(|err| napi::Error::from(JsError::from(to_napi_error(err)).into_unknown(env)))?;
+    if let Some(warning) = warning {
+      emit_warning(env, &warning)?;
+    }
+    Ok(self.output.wrap(record))
+  }
+}
 
-  cursor.write_all(info.volume.name.as_bytes())?;
-  cursor.write_all(&padding)?;
-  cursor.write_u32::<BigEndian>(apple_date(info.volume.created))?;
-  let signature = info.volume.signature.as_ref().as_bytes();
-  cursor.write_all(signature)?;
-  cursor.write_u16::<BigEndian>(info.volume.type_ as _)?;
-  cursor.write_u32::<BigEndian>(info.parent.id)?;
+#[napi(ts_return_type = "Promise<Buffer | Uint8Array>")]
+/// Like `create`, but do the lookups on the libuv thread pool and return a
+/// Promise, so that slow or network volumes do not block the event loop.
+/// Options and targets of the wrong shape throw right away.
+pub fn create_async(
+  #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+  options: Option<CreateOptions>,
+) -> Result<AsyncTask<CreateTask>> {
+  let output = create_output_type(options.as_ref())?;
+  let target_path = target_path(target)?;
+  let options = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  Ok(AsyncTask::new(CreateTask {
+    target_path,
+    options,
+    output,
+  }))
+}
 
-  let file_name_len = info.target.filename.len();
-  if file_name_len > 63 {
-    return Err(Error::new(
-      Status::GenericFailure,
-      "File name is not longer than 63 chars",
+#[napi]
+/// Like `create`, but return the record as a base64 string, the way JSON
+/// configs and snapshot tests store it. Every function that takes a record
+/// also takes such a string.
+pub fn create_base64(
+  env: Env,
+  #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+  options: Option<CreateOptions>,
+) -> Result<String> {
+  create_record(env, target, options).map(|record| BASE64.encode(record))
+}
+
+fn create_record(
+  env: Env,
+  target: Either<String, Object>,
+  options: Option<CreateOptions>,
+) -> Result<Vec<u8>> {
+  let target_path = target_path(target)?;
+  let options: alias::CreateOptions = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  let record = alias::create_for_path_with(&target_path, &options).map_err(to_napi_error)?;
+  warn_about_alias_file(env, &target_path, &options)?;
+  Ok(record)
+}
+
+#[napi]
+/// Like `create`, but write the record into the start of `out` instead of
+/// allocating a new buffer, and return its length in bytes. Throws
+/// `ERR_BUFFER_TOO_SMALL`, naming the size needed, if `out` is too short.
+pub fn create_into(
+  env: Env,
+  #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+  #[napi(ts_arg_type = "Buffer | Uint8Array")] mut out: Output,
+  options: Option<CreateOptions>,
+) -> Result<u32> {
+  let target_path = target_path(target)?;
+  let options: alias::CreateOptions = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  let info = alias::info_for_path_with(&target_path, &options).map_err(to_napi_error)?;
+  let out: &mut [u8] = match &mut out {
+    Either::A(buffer) => buffer,
+    Either::B(array) => array,
+  };
+  let length = alias::encode_into_with(&info, options.compat, out).map_err(to_napi_error)?;
+  warn_about_alias_file(env, &target_path, &options)?;
+  Ok(length as u32)
+}
+
+#[napi]
+/// The exact length in bytes of the record `create` would return for a
+/// path or `createFromInfo` for an info object, without encoding it. For
+/// a path, pass the options `create` gets; `maxSize` is ignored.
+pub fn estimate_record_size(
+  #[napi(ts_arg_type = "string | URL | AliasInfo")] target: Either3<String, AliasInfo, Object>,
+  options: Option<CreateOptions>,
+) -> Result<u32> {
+  let info = match target {
+    Either3::B(info) => info_from_alias_info(info)?,
+    Either3::A(path) => estimated_info(path, options)?,
+    Either3::C(url) if url.has_named_property("href").unwrap_or(false) => {
+      estimated_info(target_path(Either::B(url))?, options)?
+    }
+    Either3::C(_) => {
+      return Err(Error::new(
+        ERR_INVALID_ARG,
+        "The target must be a path, a URL or an object with every required field of AliasInfo.",
+      ))
+    }
+  };
+  alias::encoded_length(&info)
+    .map(|length| length as u32)
+    .map_err(to_napi_error)
+}
+
+/// What `create` would encode for `target_path`, whatever its size.
+fn estimated_info(target_path: String, options: Option<CreateOptions>) -> Result<alias::Info> {
+  let options = match options {
+    Some(options) => alias::CreateOptions {
+      max_size: None,
+      ..options.try_into()?
+    },
+    None => alias::CreateOptions::default(),
+  };
+  alias::info_for_path_with(&target_path, &options).map_err(to_napi_error)
+}
+
+#[napi]
+/// The longest alias record whose `.DS_Store` entry still fits in one
+/// B-tree node, for `maxSize`.
+pub const DS_STORE_MAX_ALIAS_LENGTH: u32 = alias::DS_STORE_MAX_ALIAS_LENGTH as u32;
+
+/// Tell the user that the record for `target_path` points to an alias file
+/// rather than through it, unless `options` asked to follow aliases.
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+fn warn_about_alias_file(
+  env: Env,
+  target_path: &str,
+  options: &alias::CreateOptions,
+) -> Result<()> {
+  match alias_file_warning(target_path, options) {
+    Some(warning) => emit_warning(env, &warning).map_err(|err| Error::new("ERR_IO", err.reason)),
+    None => Ok(()),
+  }
+}
+
+/// The process warning `create` emits for a record of a Finder alias file
+/// that was not followed, if `target_path` is one.
+fn alias_file_warning(target_path: &str, options: &alias::CreateOptions) -> Option<String> {
+  #[cfg(target_os = "macos")]
+  if !options.resolve_aliases && alias::is_alias_file(target_path).unwrap_or(false) {
+    return Some(format!(
+      "{target_path} is a Finder alias file, so the record points to the alias rather than its target. Pass resolveAliases: true to follow it."
     ));
   }
-  cursor.write_u8(file_name_len as u8)?;
-  let filename_padding = vec![0u8; 63 - info.target.filename.bytes().len()];
-  cursor.write_all(info.target.filename.as_bytes())?;
-  cursor.write_all(&filename_padding)?;
-  cursor.write_u32::<BigEndian>(info.target.id)?;
-  cursor.write_u32::<BigEndian>(apple_date(info.target.created))?;
-
-  let file_type_name = "\0\0\0\0";
-  let file_creator_name = "\0\0\0\0";
-  // I have only encountered 00 00 00 00
-  cursor.write_all(file_type_name.as_bytes())?;
-  cursor.write_all(file_creator_name.as_bytes())?;
-
-  let nlvl_from: i16 = -1;
-  let nlvl_to: i16 = -1;
-  // I have only encountered -1
-  cursor.write_i16::<BigEndian>(nlvl_from)?;
-  cursor.write_i16::<BigEndian>(nlvl_to)?;
-
-  let vol_attributes: u32 = 3330;
-  cursor.write_u32::<BigEndian>(vol_attributes)?;
-
-  let vol_fs_id: u16 = 0x0000;
-  cursor.write_u16::<BigEndian>(vol_fs_id)?;
-
-  let reserved_space = [0; 10];
-
-  cursor.write_all(&reserved_space)?;
-  for e in info.extra.iter() {
-    cursor.write_i16::<BigEndian>(e.type_)?;
-    cursor.write_u16::<BigEndian>(e.length)?;
-    cursor.write_all(&e.data)?;
-
-    if e.length % 2 == 1 {
-      cursor.write_u8(0)?;
+  #[cfg(not(target_os = "macos"))]
+  let _ = (target_path, options);
+  None
+}
+
+#[napi(object)]
+/// Something `createDetailed` had to make do without. The record is still
+/// valid, but may resolve less reliably.
+pub struct CreateWarning {
+  /// A stable identifier, e.g. `WARN_DATE_FALLBACK`.
+  pub code: String,
+  pub message: String,
+  /// The field of the record affected, e.g. `target.created`.
+  pub field: String,
+}
+
+#[napi(object)]
+/// The record `createDetailed` made, and the values that went into it
+/// before encoding, e.g. names the classic fields cannot spell.
+pub struct CreateDetailedResult {
+  /// The same record `create` returns.
+  #[napi(ts_type = "Buffer | Uint8Array")]
+  pub buffer: Output,
+  pub target_id: u32,
+  pub parent_id: u32,
+  pub parent_name: String,
+  pub volume_name: String,
+  /// Mount point of the volume, as in the record's posix path extras.
+  pub volume_path: Option<String>,
+  /// The volume's two-character signature, e.g. `H+` or `BD`.
+  pub volume_signature: String,
+  /// The kind of media the volume is on: `local` for internal disks,
+  /// `network` for shares and `other` for removable and ejectable media.
+  #[napi(ts_type = "'local' | 'network' | 'floppy400' | 'floppy800' | 'floppy1400' | 'other'")]
+  pub volume_type: String,
+  /// Creation date of the target, in milliseconds since the Unix epoch.
+  pub target_created: f64,
+  /// Creation date of the volume, in milliseconds since the Unix epoch.
+  pub volume_created: f64,
+  pub warnings: Vec<CreateWarning>,
+}
+
+#[napi]
+/// Like `create`, but also return what went into the record, and where it
+/// falls short of what the filesystem should have told, such as a
+/// modification date standing in for a missing creation date.
+pub fn create_detailed(
+  env: Env,
+  #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+  options: Option<CreateOptions>,
+) -> Result<CreateDetailedResult> {
+  let target_path = target_path(target)?;
+  let output = create_output_type(options.as_ref())?;
+  let options = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  let (info, warnings) =
+    alias::info_with_warnings_for_path(&target_path, &options).map_err(to_napi_error)?;
+  let buffer = alias::encode_with(&info, options.compat).map_err(to_napi_error)?;
+  warn_about_alias_file(env, &target_path, &options)?;
+  let volume_path = info
+    .extra(19)
+    .map(|extra| String::from_utf8_lossy(&extra.data).into_owned());
+  Ok(CreateDetailedResult {
+    buffer: output.wrap(buffer),
+    target_id: info.target.id,
+    parent_id: info.parent.id,
+    parent_name: info.parent.name,
+    volume_name: info.volume.name,
+    volume_path,
+    volume_signature: info.volume.signature.as_ref().to_owned(),
+    volume_type: info.volume.type_.as_ref().to_owned(),
+    target_created: millis_from_system_time(info.target.created),
+    volume_created: millis_from_system_time(info.volume.created),
+    warnings: warnings
+      .into_iter()
+      .map(|warning| CreateWarning {
+        code: warning.code().to_owned(),
+        message: warning.to_string(),
+        field: warning.field().to_owned(),
+      })
+      .collect(),
+  })
+}
+
+#[napi(object)]
+/// What `createDebug` derived for the target, right before encoding.
+pub struct DebugInfo {
+  pub version: u32,
+  #[napi(ts_type = "'file' | 'directory'")]
+  pub target_type: String,
+  pub target_filename: String,
+  pub target_id: u32,
+  /// Creation date of the target, as an ISO 8601 string.
+  pub target_created: String,
+  /// Which timestamp `targetCreated` was taken from. Filesystems without
+  /// creation dates fall back to the modification, then the change date.
+  #[napi(ts_type = "'birthtime' | 'mtime' | 'ctime' | 'epoch'")]
+  pub target_created_source: String,
+  pub parent_name: String,
+  pub parent_id: u32,
+  pub volume_name: String,
+  #[napi(ts_type = "'local' | 'network' | 'floppy400' | 'floppy800' | 'floppy1400' | 'other'")]
+  pub volume_type: String,
+  /// Whether `volumeType` came from DiskArbitration or, where it cannot be
+  /// asked, was guessed from the mount.
+  #[napi(ts_type = "'diskarbitration' | 'statfs'")]
+  pub volume_type_source: String,
+  /// The volume's two-character signature, e.g. `H+` or `BD`.
+  pub volume_signature: String,
+  /// Creation date of the volume, as an ISO 8601 string.
+  pub volume_created: String,
+  /// Which timestamp `volumeCreated` was taken from.
+  #[napi(ts_type = "'birthtime' | 'mtime' | 'ctime' | 'epoch'")]
+  pub volume_created_source: String,
+  pub extra: Vec<DebugExtra>,
+}
+
+#[napi(object)]
+pub struct DebugExtra {
+  #[napi(js_name = "type")]
+  pub type_: i32,
+  pub length: u32,
+  /// The decoded value of a well-known extra, or its data in hex.
+  pub preview: String,
+}
+
+#[napi(object)]
+/// How long each step of `createDebug` took, in milliseconds.
+pub struct DebugTimings {
+  /// Looking up the target, its parent and its volume.
+  pub lookup: f64,
+  pub encode: f64,
+}
+
+#[napi(object)]
+pub struct CreateDebugResult {
+  /// The same record `create` returns.
+  #[napi(ts_type = "Buffer | Uint8Array")]
+  pub buffer: Output,
+  pub info: DebugInfo,
+  pub timings: DebugTimings,
+}
+
+/// Format `time` like `Date.prototype.toISOString`.
+fn iso_date(time: SystemTime) -> String {
+  chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+fn date_source(source: alias::DateSource) -> String {
+  match source {
+    alias::DateSource::Birthtime => "birthtime",
+    alias::DateSource::Mtime => "mtime",
+    alias::DateSource::Ctime => "ctime",
+    alias::DateSource::Epoch => "epoch",
+  }
+  .to_owned()
+}
+
+fn debug_info(info: &alias::Info, sources: alias::DateSources) -> DebugInfo {
+  let volume_signature = match info.volume.signature {
+    alias::VolumeSignature::Bd => "BD",
+    alias::VolumeSignature::HPlus => "H+",
+    alias::VolumeSignature::Hx => "HX",
+  };
+  DebugInfo {
+    version: u32::from(info.version),
+    target_type: match info.target.type_ {
+      alias::TargetType::File => "file",
+      alias::TargetType::Directory => "directory",
+    }
+    .to_owned(),
+    target_filename: info.target.filename.clone(),
+    target_id: info.target.id,
+    target_created: iso_date(info.target.created),
+    target_created_source: date_source(sources.target_created),
+    parent_name: info.parent.name.clone(),
+    parent_id: info.parent.id,
+    volume_name: info.volume.name.clone(),
+    volume_type: info.volume.type_.as_ref().to_owned(),
+    volume_type_source: match sources.volume_type {
+      alias::VolumeTypeSource::DiskArbitration => "diskarbitration",
+      alias::VolumeTypeSource::Statfs => "statfs",
     }
+    .to_owned(),
+    volume_signature: volume_signature.to_owned(),
+    volume_created: iso_date(info.volume.created),
+    volume_created_source: date_source(sources.volume_created),
+    extra: info
+      .extra
+      .iter()
+      .map(|extra| DebugExtra {
+        type_: i32::from(extra.type_),
+        length: u32::from(extra.length),
+        preview: match extra.value() {
+          Some(alias::ExtraValue::String(value)) => value,
+          Some(alias::ExtraValue::Number(value)) => value.to_string(),
+          None => extra.data.iter().map(|b| format!("{b:02x}")).collect(),
+        },
+      })
+      .collect(),
   }
+}
 
-  cursor.write_i16::<BigEndian>(-1)?;
-  cursor.write_u16::<BigEndian>(0)?;
-  Ok(cursor.into_inner())
+#[napi]
+/// Like `create`, but also return what was derived for the target and how
+/// long each step took, to debug records that do not resolve.
+pub fn create_debug(
+  env: Env,
+  #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+  options: Option<CreateOptions>,
+) -> Result<CreateDebugResult> {
+  let target_path = target_path(target)?;
+  let output = create_output_type(options.as_ref())?;
+  let options = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  warn_about_alias_file(env, &target_path, &options)?;
+  let start = std::time::Instant::now();
+  let (info, sources) =
+    alias::info_with_sources_for_path(target_path, &options).map_err(to_napi_error)?;
+  let lookup = start.elapsed();
+  let start = std::time::Instant::now();
+  let buffer = alias::encode_with(&info, options.compat).map_err(to_napi_error)?;
+  let encode = start.elapsed();
+  Ok(CreateDebugResult {
+    buffer: output.wrap(buffer),
+    info: debug_info(&info, sources),
+    timings: DebugTimings {
+      lookup: lookup.as_secs_f64() * 1000.0,
+      encode: encode.as_secs_f64() * 1000.0,
+    },
+  })
 }
 
-fn find_volume<'a, P: AsRef<OsStr> + ?Sized>(
-  start_path: &'a P,
-  start_stat: &'a Metadata,
-) -> std::io::Result<&'a Path> {
-  let mut last_dev = start_stat.dev();
-  let mut last_ino = start_stat.ino();
-  let mut last_path = Path::new(start_path);
+#[napi(object)]
+/// The outcome of `verify`.
+pub struct VerifyResult {
+  /// Whether the system resolver found a file for the record at all.
+  pub resolved: bool,
+  pub resolved_path: Option<String>,
+  /// Fields of the record that do not match the file it resolved to. Empty
+  /// when it resolved to the target.
+  pub mismatches: Vec<FieldMismatch>,
+}
 
-  loop {
-    if let Some(parent_path) = last_path.parent() {
-      let parent_stat = fs::metadata(parent_path)?;
+#[napi(object)]
+pub struct FieldMismatch {
+  /// The field, e.g. `target.id`.
+  pub field: String,
+  /// The value in the record.
+  pub expected: String,
+  /// The value for the file the record resolved to.
+  pub actual: String,
+}
 
-      if parent_stat.dev() != last_dev {
-        return Ok(last_path);
-      }
+#[napi]
+/// Create the record for `target`, resolve it through CoreFoundation and
+/// check that it lands on the same file. Resolution never shows UI or mounts
+/// volumes.
+pub fn verify(
+  #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+  options: Option<CreateOptions>,
+) -> Result<VerifyResult> {
+  let target_path = target_path(target)?;
+  let options = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  let verification = alias::verify_path(target_path, &options).map_err(to_napi_error)?;
+  Ok(VerifyResult {
+    resolved: verification.resolved_path.is_some(),
+    resolved_path: verification.resolved_path,
+    mismatches: verification
+      .mismatches
+      .into_iter()
+      .map(|mismatch| FieldMismatch {
+        field: mismatch.field.to_owned(),
+        expected: mismatch.expected,
+        actual: mismatch.actual,
+      })
+      .collect(),
+  })
+}
 
-      if parent_stat.ino() == last_ino {
-        return Ok(last_path);
-      }
+#[napi(object)]
+/// The outcome of `statAliasTarget`.
+pub struct AliasTargetStatus {
+  /// Whether a file or directory exists at the record's posix path.
+  pub exists: bool,
+  /// Whether the record's path names its target: the object with the
+  /// target id is still there, or, for records without an id, something is
+  /// there and no field is stale.
+  pub path_matches: bool,
+  /// Whether the volume still has an object with the record's target id.
+  pub id_matches: bool,
+  /// Whether the record's volume is mounted: the one with its volume UUID,
+  /// if it has one, or else whatever is mounted where it says.
+  pub volume_mounted: bool,
+  /// Where the record's volume is mounted, which differs from the record's
+  /// mount point when its volume UUID found it elsewhere.
+  pub volume_path: Option<String>,
+  /// Fields of the record that differ from the object now at its path, e.g.
+  /// `target.id` for a file that was replaced.
+  pub stale_fields: Vec<String>,
+}
+
+#[napi]
+/// Check whether the record in `buffer` still describes its target, by its
+/// posix path and by its id. Nothing is modified, and an unmounted volume is
+/// reported rather than mounted.
+pub fn stat_alias_target(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+) -> Result<AliasTargetStatus> {
+  let status = alias::stat_target(&record_bytes(buffer)?).map_err(to_napi_error)?;
+  Ok(AliasTargetStatus {
+    exists: status.exists,
+    path_matches: status.path_matches,
+    id_matches: status.id_matches,
+    volume_mounted: status.volume_mounted,
+    volume_path: status.volume_path,
+    stale_fields: status.stale_fields.into_iter().map(str::to_owned).collect(),
+  })
+}
+
+#[napi(object)]
+/// How `resolveAlias` looks for the target.
+pub struct ResolveOptions {
+  /// Try the target id first (`'id'`, the default, as the Alias Manager
+  /// does, which follows renames and moves) or the posix path (`'path'`,
+  /// which suits records made in a staging directory and targets replaced
+  /// in place).
+  #[napi(ts_type = "'id' | 'path'")]
+  pub prefer: Option<String>,
+  /// Attach the disk image the record's volume was mounted from when the
+  /// volume is not mounted. The image is left attached.
+  pub mount_if_needed: Option<bool>,
+  /// Accept something at the record's posix path even though the record's
+  /// volume is not mounted there, e.g. a leftover mount point directory.
+  pub cross_volume_fallback: Option<bool>,
+}
+
+#[napi]
+/// The path of the target of the record in `buffer` on this machine, found
+/// by its id and its posix path on the record's volume, or else by its name
+/// in the directory with its parent id, or `null` if nothing leads anywhere.
+/// The volume is the mounted one with the record's volume UUID, if it has
+/// one, or else the one mounted where the record says.
+pub fn resolve_alias(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+  options: Option<ResolveOptions>,
+) -> Result<Option<String>> {
+  let options = options.unwrap_or(ResolveOptions {
+    prefer: None,
+    mount_if_needed: None,
+    cross_volume_fallback: None,
+  });
+  let prefer = match options.prefer.as_deref() {
+    None | Some("id") => alias::Prefer::Id,
+    Some("path") => alias::Prefer::Path,
+    Some(other) => {
+      return Err(Error::new(
+        ERR_INVALID_ARG,
+        format!("Unknown preference {other:?}, expected \"id\" or \"path\"."),
+      ))
+    }
+  };
+  let options = alias::ResolveOptions {
+    prefer,
+    mount_if_needed: options.mount_if_needed.unwrap_or(false),
+    cross_volume_fallback: options.cross_volume_fallback.unwrap_or(false),
+  };
+  alias::resolve_alias(&record_bytes(buffer)?, &options).map_err(to_napi_error)
+}
+
+#[napi]
+/// `resolveAlias` with its default options: the path of the record's target
+/// on this machine, or `null`.
+pub fn resolve(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+) -> Result<Option<String>> {
+  alias::resolve_alias(&record_bytes(buffer)?, &Default::default()).map_err(to_napi_error)
+}
+
+#[napi]
+/// Refresh the record in `buffer` from the file now at its posix path, e.g.
+/// after the target was replaced in place: the target id and creation date,
+/// the parent id and name, and the volume's creation date, signature and
+/// type. The target name and the paths are left as they are.
+pub fn relink_alias(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+  options: Option<OutputOptions>,
+) -> Result<Output> {
+  let output = output_type(options.as_ref())?;
+  alias::relink_alias(&record_bytes(buffer)?)
+    .map(|record| output.wrap(record))
+    .map_err(to_napi_error)
+}
+
+#[napi(object)]
+/// A mounted volume, as `create` sees it.
+pub struct VolumeInfo {
+  /// The name stored on the volume, which records carry.
+  pub name: String,
+  /// The name Finder shows, if it differs from `name`, say because it is
+  /// localized.
+  pub display_name: Option<String>,
+  /// The mount point, e.g. `/Volumes/Test Title`.
+  pub path: String,
+  /// What is mounted, e.g. `/dev/disk3s1` or `//me@server/share`.
+  pub device: String,
+  /// The filesystem type, e.g. `apfs`, `hfs`, `msdos` or `exfat`.
+  pub fs_type: String,
+  /// The date records give the volume, in milliseconds since the Unix
+  /// epoch.
+  pub created: f64,
+  /// Which timestamp `created` was taken from. Filesystems without creation
+  /// dates fall back to the modification, then the change date.
+  #[napi(ts_type = "'birthtime' | 'mtime' | 'ctime' | 'epoch'")]
+  pub created_source: String,
+  /// Whether the volume is on a local disk rather than a network share.
+  pub local: bool,
+  pub read_only: bool,
+  /// Whether Finder shows the volume. System volumes such as
+  /// `/System/Volumes/VM` are mounted hidden.
+  pub browsable: bool,
+  /// The disk image the volume is attached from, if it is one.
+  pub disk_image: Option<String>,
+}
+
+impl From<alias::VolumeInfo> for VolumeInfo {
+  fn from(info: alias::VolumeInfo) -> Self {
+    VolumeInfo {
+      name: info.name,
+      display_name: info.display_name,
+      path: info.path,
+      device: info.device,
+      fs_type: info.fs_type,
+      created: millis_from_system_time(info.created),
+      created_source: date_source(info.created_source),
+      local: info.local,
+      read_only: info.read_only,
+      browsable: info.browsable,
+      disk_image: info.disk_image,
+    }
+  }
+}
+
+#[napi]
+/// Everything `create` knows about the volume holding `path`, which may be
+/// any file or directory on it.
+pub fn get_volume_info(path: String) -> Result<VolumeInfo> {
+  alias::volume_info(path)
+    .map(VolumeInfo::from)
+    .map_err(to_napi_error)
+}
+
+#[napi]
+/// Every mounted volume, as `getVolumeInfo` describes it. Automounter
+/// triggers are left out rather than mounted, and so are volumes that
+/// cannot be looked up.
+pub fn list_volumes() -> Result<Vec<VolumeInfo>> {
+  alias::list_volumes()
+    .map(|volumes| volumes.into_iter().map(VolumeInfo::from).collect())
+    .map_err(to_napi_error)
+}
+
+#[napi(object)]
+/// The full 64-bit ids `fileIds` looked up.
+pub struct FileIds {
+  /// `ATTR_CMN_FILEID` of the file or directory.
+  pub file_id: BigInt,
+  /// `ATTR_CMN_PARENTID`, the id of its parent directory.
+  pub parent_id: BigInt,
+}
+
+#[napi]
+/// The catalog node ids of `path` and of its parent directory, in full.
+/// Records hold 32 bits, and store the APFS ids past that as 0 with a
+/// `WARN_ID_TOO_LARGE` warning.
+pub fn file_ids(
+  #[napi(ts_arg_type = "string | URL")] path: Either<String, Object>,
+) -> Result<FileIds> {
+  alias::file_ids_for_path(target_path(path)?)
+    .map(|ids| FileIds {
+      file_id: ids.id.into(),
+      parent_id: ids.parent_id.into(),
+    })
+    .map_err(to_napi_error)
+}
+
+#[cfg(all(target_os = "macos", feature = "system-alias"))]
+#[napi]
+/// The alias record macOS's own Alias Manager creates for `path`, for
+/// checking `create` against.
+pub fn system_alias_for(path: String, options: Option<OutputOptions>) -> Result<Output> {
+  let output = output_type(options.as_ref())?;
+  alias::system_alias_for(path)
+    .map(|record| output.wrap(record))
+    .map_err(to_napi_error)
+}
+
+#[napi]
+/// Like `create`, for the file or directory open as `fd`. The record
+/// describes the object behind the descriptor; `pathHint` is used when it
+/// still names that object. The descriptor is left open.
+pub fn create_for_fd(
+  fd: i32,
+  path_hint: Option<String>,
+  options: Option<CreateOptions>,
+) -> Result<Output> {
+  let output = create_output_type(options.as_ref())?;
+  let options = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  alias::create_for_fd(fd, path_hint.as_deref().map(Path::new), &options)
+    .map(|record| output.wrap(record))
+    .map_err(to_napi_error)
+}
 
-      last_dev = parent_stat.dev();
-      last_ino = parent_stat.ino();
-      last_path = parent_path;
-    } else {
-      return Ok(last_path);
+#[napi(object, object_to_js = false)]
+/// Which entries `createForTree` makes records for.
+pub struct TreeOptions {
+  /// Only record the entries whose path relative to the root matches this
+  /// glob, or for which this function returns true. `*` and `?` stay within
+  /// a path component and `**` spans any number of them; a glob without a
+  /// `/` is matched against the entry's name, so `*.png` matches at every
+  /// depth.
+  #[napi(ts_type = "string | ((path: string, isDirectory: boolean) => boolean)")]
+  pub filter: Option<Either<String, JsFunction>>,
+  /// How many levels below the root to list: 1 is only its own entries.
+  /// Unlimited by default.
+  pub max_depth: Option<u32>,
+  /// Also record directories, not only files.
+  pub include_directories: Option<bool>,
+  /// Record symlinks as the file or directory they point to, and descend
+  /// into symlinked directories. Symlinks are skipped by default.
+  pub follow_symlinks: Option<bool>,
+}
+
+#[napi(ts_return_type = "Record<string, Buffer | Uint8Array | Error>")]
+/// Records for the files under `root`, keyed by their path relative to it,
+/// with one set of volume lookups for the whole walk. An entry whose record
+/// could not be made maps to the error instead. The walk does not leave the
+/// volume `root` is on.
+pub fn create_for_tree(
+  env: Env,
+  #[napi(ts_arg_type = "string | URL")] root: Either<String, Object>,
+  tree: Option<TreeOptions>,
+  options: Option<CreateOptions>,
+) -> Result<JsObject> {
+  let root = target_path(root)?;
+  let output = create_output_type(options.as_ref())?;
+  let create = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  let (filter, max_depth, include_directories, follow_symlinks) = match tree {
+    Some(tree) => (
+      tree.filter,
+      tree.max_depth,
+      tree.include_directories,
+      tree.follow_symlinks,
+    ),
+    None => Default::default(),
+  };
+  let (glob, callback) = match filter {
+    Some(Either::A(glob)) => (Some(glob), None),
+    Some(Either::B(callback)) => (None, Some(callback)),
+    None => (None, None),
+  };
+  let tree = alias::TreeOptions {
+    create,
+    glob,
+    max_depth: max_depth.map(|depth| depth as usize),
+    include_directories: include_directories.unwrap_or(false),
+    follow_symlinks: follow_symlinks.unwrap_or(false),
+  };
+
+  // The first error calling the callback failed with, which ends the walk.
+  let mut thrown = None;
+  let filter = |path: &str, is_dir: bool| -> napi::Result<bool> {
+    let Some(callback) = &callback else {
+      return Ok(true);
+    };
+    let args = [
+      env.create_string(path)?.into_unknown(),
+      env.get_boolean(is_dir)?.into_unknown(),
+    ];
+    callback.call(None, &args)?.coerce_to_bool()?.get_value()
+  };
+  let entries = alias::create_for_tree_filtered(&root, &tree, |path, is_dir| {
+    if thrown.is_some() {
+      return false;
     }
+    filter(path, is_dir).unwrap_or_else(|err| {
+      thrown = Some(err);
+      false
+    })
+  })
+  .map_err(to_napi_error)?;
+  match thrown {
+    // What the callback threw is still pending, and is what JS sees.
+    Some(err) if err.status == Status::PendingException => {}
+    Some(err) => return Err(Error::new(ERR_INVALID_ARG, err.reason)),
+    None => {}
   }
+  records_object(env, entries, output).map_err(|err| Error::new("ERR_IO", err.reason))
 }
 
-fn utf16be(s: &str) -> Vec<u8> {
-  let b: Vec<u16> = s.encode_utf16().collect();
-  let mut result: Vec<u8> = Vec::new();
-  for &number in &b {
-    result.extend_from_slice(&number.to_be_bytes());
+fn records_object(
+  env: Env,
+  entries: Vec<alias::TreeEntry>,
+  output: OutputType,
+) -> napi::Result<JsObject> {
+  let mut records = env.create_object()?;
+  for entry in entries {
+    match entry.record {
+      Ok(record) => records.set_named_property(&entry.path, output.wrap(record))?,
+      Err(err) => records.set_named_property(&entry.path, error_object(env, &err)?)?,
+    }
   }
-  result
+  Ok(records)
+}
+
+/// `err` as a JS `Error` with its `code`, for results that hold errors
+/// rather than throw them.
+fn error_object(env: Env, err: &alias::Error) -> napi::Result<JsObject> {
+  let mut error = env.create_error(napi::Error::from_reason(err.to_string()))?;
+  error.set_named_property("code", err.code())?;
+  Ok(error)
+}
+
+#[napi(object, object_to_js = false)]
+/// A target of `createMany` with options of its own.
+pub struct ManyTarget {
+  #[napi(ts_type = "string | URL")]
+  pub path: Either<String, Object>,
+  /// Used instead of the options `createMany` was given.
+  pub options: Option<CreateOptions>,
+}
+
+#[napi(ts_return_type = "Array<Buffer | Uint8Array | Error>")]
+/// The records of `targets`, in the order given, with one set of volume
+/// lookups for all of them. A target may come with options of its own,
+/// which replace `options` for it. A target whose record could not be made
+/// gets the error instead.
+pub fn create_many(
+  env: Env,
+  #[napi(ts_arg_type = "Array<string | URL | ManyTarget>")] targets: Vec<Either<String, Object>>,
+  options: Option<CreateOptions>,
+) -> Result<Vec<Either<Output, JsObject>>> {
+  let shared_output = create_output_type(options.as_ref())?;
+  let shared: alias::CreateOptions = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  let mut outputs = Vec::with_capacity(targets.len());
+  let mut parsed = Vec::with_capacity(targets.len());
+  for target in targets {
+    // A `ManyTarget` has a `path`, where a URL has an `href`.
+    let invalid = |err: napi::Error| Error::new(ERR_INVALID_ARG, err.reason);
+    let (target, options) = match target {
+      Either::B(object) => match object
+        .get::<_, Either<String, Object>>("path")
+        .map_err(invalid)?
+      {
+        Some(path) => (
+          path,
+          object.get::<_, CreateOptions>("options").map_err(invalid)?,
+        ),
+        None => (Either::B(object), None),
+      },
+      target => (target, None),
+    };
+    let path = target_path(target)?;
+    match options {
+      Some(options) => {
+        outputs.push(create_output_type(Some(&options))?);
+        parsed.push((path, options.try_into()?));
+      }
+      None => {
+        outputs.push(shared_output);
+        parsed.push((path, shared.clone()));
+      }
+    }
+  }
+  let records = alias::create_many(&parsed).map_err(to_napi_error)?;
+  records
+    .into_iter()
+    .zip(outputs)
+    .zip(&parsed)
+    .map(|((record, output), (path, options))| match record {
+      Ok(record) => {
+        warn_about_alias_file(env, path, options)?;
+        Ok(Either::A(output.wrap(record)))
+      }
+      Err(err) => error_object(env, &err)
+        .map(Either::B)
+        .map_err(|err| Error::new("ERR_IO", err.reason)),
+    })
+    .collect()
+}
+
+#[napi(object)]
+/// A file or directory staged for a disk image that is not built yet.
+pub struct StagedDmgTarget {
+  /// The directory whose contents become the root of the image.
+  pub staging_dir: String,
+  /// The name Finder will show for the mounted image.
+  pub volume_name: String,
+  /// Path of the target inside the image, e.g. `.background/bg.tiff`.
+  pub internal_path: String,
+  /// Whether the target is a directory. Only needed for targets that are
+  /// not staged yet.
+  pub target_is_dir: Option<bool>,
+  /// Ids on the image, which are 0 unless given.
+  pub target_id: Option<u32>,
+  pub parent_id: Option<u32>,
+  /// Creation date of the image's volume, in milliseconds since the Unix
+  /// epoch. Defaults to the staging directory's.
+  pub volume_created: Option<f64>,
 }
 
 #[napi]
-pub fn create(target_path: String) -> Result<Buffer> {
-  let mut extra = Vec::new();
+/// The record for a file in the staging directory of a disk image, as it
+/// will be once the image is mounted at `/Volumes/<volumeName>`. The dates
+/// come from the staged file; the volume name and paths from the arguments.
+pub fn create_for_staged_dmg(
+  target: StagedDmgTarget,
+  options: Option<CreateOptions>,
+) -> Result<Output> {
+  let output = create_output_type(options.as_ref())?;
+  let options = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  let staged = alias::StagedTarget {
+    staging_dir: target.staging_dir.into(),
+    volume_name: target.volume_name,
+    internal_path: target.internal_path,
+    target_is_dir: target.target_is_dir,
+    target_id: target.target_id,
+    parent_id: target.parent_id,
+    volume_created: target
+      .volume_created
+      .map(|millis| system_time_from_millis("volumeCreated", millis))
+      .transpose()?,
+  };
+  alias::create_for_staged(&staged, &options)
+    .map(|record| output.wrap(record))
+    .map_err(to_napi_error)
+}
 
-  let parent_path = Path::new(&target_path).parent().ok_or_else(|| {
-    Error::new(
-      Status::InvalidArg,
-      "The target path has no parent directory.",
-    )
-  })?;
-  let target_metadata = fs::metadata(&target_path)?;
-  let parent_metadata = fs::metadata(parent_path)?;
-  let volume_path = find_volume(&target_path, &target_metadata)?;
-  let volume_metadata = fs::metadata(volume_path)?;
-
-  assert!(target_metadata.is_file() || target_metadata.is_dir());
-
-  let target = Target {
-    id: target_metadata.ino() as u32,
-    type_: if target_metadata.is_dir() {
-      TargetType::Directory
-    } else {
-      TargetType::File
-    },
-    filename: Path::new(&target_path)
-      .file_name()
-      .unwrap()
-      .to_str()
-      .unwrap()
-      .to_string(),
-    created: UNIX_EPOCH + std::time::Duration::from_secs(target_metadata.ctime() as u64),
-  };
-
-  let parent = Parent {
-    id: parent_metadata.ino() as u32,
-    name: parent_path
-      .file_name()
-      .and_then(|s| s.to_str())
-      .map(|s| s.to_string())
-      .ok_or_else(|| Error::new(Status::InvalidArg, ""))?,
-  };
-
-  let volume = Volume {
-    name: get_volume_name(volume_path.to_str().ok_or_else(|| {
-      Error::new(
-        Status::InvalidArg,
-        "The volume path is not a valid UTF-8 string.",
-      )
-    })?),
-    created: UNIX_EPOCH + std::time::Duration::from_secs(volume_metadata.ctime() as u64),
-    signature: VolumeSignature::HPlus,
-    type_: if volume_path.to_str() == Some("/") {
-      VolumeType::Local
-    } else {
-      VolumeType::Other
-    },
+#[napi]
+/// The record for the background image of a disk image, as appdmg and
+/// Finder expect it: on the volume `volumeName`, mounted at
+/// `/Volumes/<volumeName>`, rather than in `stagingDir`. `imagePath` is the
+/// image inside the staging directory, either absolute or relative to it; a
+/// bare file name is taken to be in `.background/`.
+pub fn create_background_alias(
+  staging_dir: String,
+  image_path: String,
+  volume_name: String,
+  options: Option<CreateOptions>,
+) -> Result<Output> {
+  let output = create_output_type(options.as_ref())?;
+  let options = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
   };
+  alias::create_background_alias(staging_dir, image_path, &volume_name, &options)
+    .map(|record| output.wrap(record))
+    .map_err(to_napi_error)
+}
 
-  extra.push(Extra {
-    type_: 0,
-    length: parent.name.len() as u16,
-    data: parent.name.as_bytes().to_vec(),
-  });
+#[napi]
+/// Build an alias record from caller-supplied metadata without touching the
+/// filesystem. Unlike `create`, this works on every platform.
+pub fn create_from_info(info: AliasInfo, options: Option<OutputOptions>) -> Result<Output> {
+  let output = output_type(options.as_ref())?;
+  alias::encode(&info_from_alias_info(info)?)
+    .map(|record| output.wrap(record))
+    .map_err(to_napi_error)
+}
 
-  extra.push(Extra {
-    type_: 1,
-    length: 4,
-    data: parent.id.to_be_bytes().to_vec(),
-  });
+/// The record `createFromInfo` encodes for `info`.
+fn info_from_alias_info(info: AliasInfo) -> Result<alias::Info> {
+  let target_path = Path::new(&info.target_path);
+  let parent_path = target_path
+    .parent()
+    .ok_or_else(|| Error::new(ERR_INVALID_ARG, "The target path has no parent directory."))?;
+  let target = alias::Target {
+    type_: target_type(info.target_type.as_deref())?,
+    filename: file_name(target_path)?,
+    id: info.target_id,
+    created: system_time_from_millis("targetCreated", info.target_created)?,
+  };
+  let parent = alias::Parent {
+    id: info.parent_id,
+    // The root directory of a volume goes by the volume's name.
+    name: match info.parent_name {
+      Some(name) => name,
+      None if parent_path == Path::new(&info.volume_path) => info.volume_name.clone(),
+      None => file_name(parent_path)?,
+    },
+  };
+  let volume = alias::Volume {
+    type_: match volume_type(info.volume_type.as_deref())? {
+      Some(type_) => type_,
+      None if info.volume_path == "/" => alias::VolumeType::Local,
+      None => alias::VolumeType::Other,
+    },
+    name: info.volume_name,
+    created: system_time_from_millis("volumeCreated", info.volume_created)?,
+    signature: volume_signature(info.volume_signature.as_deref())?
+      .unwrap_or(alias::VolumeSignature::HPlus),
+  };
+  volume.check_kind().map_err(to_napi_error)?;
+  let classic = alias::ClassicFields {
+    file_type: four_char_code("fileType", info.file_type.as_deref())?,
+    file_creator: four_char_code("fileCreator", info.file_creator.as_deref())?,
+    ..Default::default()
+  };
+  alias::build_info(&info.target_path, &info.volume_path, target, parent, volume)
+    .map(|info| alias::Info { classic, ..info })
+    .map_err(to_napi_error)
+}
 
-  let filename_length = target.filename.len();
-  let mut buffer = vec![0; 2 + filename_length * 2];
-  buffer[0..2].copy_from_slice(&(filename_length as u16).to_be_bytes());
-  buffer[2..].copy_from_slice(&utf16be(&target.filename));
-  extra.push(Extra {
-    type_: 14,
-    length: buffer.len() as _,
-    data: buffer,
-  });
+#[napi(js_name = "toJSON")]
+/// Decode an alias record into JSON, with dates as Unix milliseconds and
+/// extra data as base64.
+pub fn to_json(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+) -> Result<String> {
+  let info = alias::decode(&record_bytes(buffer)?).map_err(to_napi_error)?;
+  serde_json::to_string(&info).map_err(|err| Error::new("ERR_INVALID_RECORD", err.to_string()))
+}
 
-  let volume_name_length = volume.name.len();
-  let mut buffer = vec![0; 2 + volume_name_length * 2];
-  buffer[0..2].copy_from_slice(&(volume_name_length as u16).to_be_bytes());
-  buffer[2..].copy_from_slice(&utf16be(&volume.name));
-  extra.push(Extra {
-    type_: 15,
-    length: buffer.len() as _,
-    data: buffer,
-  });
+#[napi(ts_return_type = "any")]
+/// Decode an alias record into an object in the shape `toJSON` gives: the
+/// header's `target`, `parent` and `volume`, the classic fields and the
+/// `extra` records, with the well-known ones' `value`s.
+pub fn decode(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+) -> Result<serde_json::Value> {
+  let info = alias::decode(&record_bytes(buffer)?).map_err(to_napi_error)?;
+  serde_json::to_value(info).map_err(|err| Error::new("ERR_INVALID_RECORD", err.to_string()))
+}
 
-  let volume_path_length = volume_path.to_string_lossy().len();
+#[napi(ts_return_type = "Array<any>")]
+/// Decode the records stored one after another in `buffer`, as some
+/// `.DS_Store` blobs and resource forks hold them, each in the shape
+/// `toJSON` decodes records into. Zero padding after the last record is
+/// skipped.
+pub fn decode_all(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+) -> Result<Vec<serde_json::Value>> {
+  alias::decode_all(&record_bytes(buffer)?)
+    .map_err(to_napi_error)?
+    .iter()
+    .map(|info| {
+      serde_json::to_value(info).map_err(|err| Error::new("ERR_INVALID_RECORD", err.to_string()))
+    })
+    .collect()
+}
 
-  let lp = &target_path[volume_path_length..];
-  extra.push(Extra {
-    type_: 18,
-    length: lp.len() as u16,
-    data: lp.as_bytes().to_vec(),
-  });
+#[napi(object)]
+/// Something wrong with the framing of a record, found by `validate`.
+pub struct RecordProblem {
+  /// A stable identifier, e.g. `LENGTH_EXCEEDS_BUFFER`.
+  pub code: String,
+  pub message: String,
+  /// The offset in the buffer of the bytes at fault.
+  pub offset: u32,
+}
 
-  extra.push(Extra {
-    type_: 19,
-    length: volume_path_length as _,
-    data: volume_path.to_string_lossy().as_bytes().to_vec(),
-  });
+#[napi]
+/// Check the framing of the record in `buffer`: its declared length, its
+/// version and header, each extra's length and padding, and the -1 marker
+/// that ends them. Every problem found is returned, rather than thrown as
+/// by `decode`; a well formed record has none.
+pub fn validate(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+) -> Result<Vec<RecordProblem>> {
   Ok(
-    encode(Info {
-      version: 2,
-      target,
-      volume,
-      parent,
-      extra,
-    })?
-    .into(),
+    alias::validate(&record_bytes(buffer)?)
+      .into_iter()
+      .map(|problem| RecordProblem {
+        code: problem.code().to_owned(),
+        message: problem.to_string(),
+        offset: problem.offset() as u32,
+      })
+      .collect(),
   )
 }
 
-static FALSE: Boolean = false as Boolean;
-static TRUE: Boolean = true as Boolean;
+#[napi(ts_return_type = "any")]
+/// What `create` would encode for `target`, in the shape `toJSON` decodes
+/// records into, without encoding it.
+pub fn record_info(
+  #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+  options: Option<CreateOptions>,
+) -> Result<serde_json::Value> {
+  let options: alias::CreateOptions = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
+  };
+  let info = alias::info_for_path_with(target_path(target)?, &options).map_err(to_napi_error)?;
+  serde_json::to_value(&info).map_err(|err| Error::new("ERR_INVALID_RECORD", err.to_string()))
+}
 
-fn get_volume_name(path: &str) -> String {
-  let a_string = unsafe {
-    CFStringCreateWithBytesNoCopy(
-      kCFAllocatorDefault,
-      path.as_ptr(),
-      path.len().to_CFIndex(),
-      kCFStringEncodingUTF8,
-      FALSE,
-      kCFAllocatorNull,
-    )
+#[napi]
+/// Encode an object from `recordInfo`, or a parsed `toJSON` result. Of the
+/// options, only `compat` and `dateRounding` matter; pass the ones
+/// `recordInfo` got for the same bytes `create` returns.
+pub fn encode_info(
+  #[napi(ts_arg_type = "any")] info: serde_json::Value,
+  options: Option<CreateOptions>,
+) -> Result<Output> {
+  let output = create_output_type(options.as_ref())?;
+  let options: alias::CreateOptions = match options {
+    Some(options) => options.try_into()?,
+    None => alias::CreateOptions::default(),
   };
-  if a_string.is_null() {
-    return String::new();
+  let mut info: alias::Info =
+    serde_json::from_value(info).map_err(|err| Error::new(ERR_INVALID_ARG, err.to_string()))?;
+  if let Some(rounding) = options.date_rounding {
+    info.round_dates(rounding);
   }
+  alias::encode_with(&info, options.compat)
+    .map(|record| output.wrap(record))
+    .map_err(to_napi_error)
+}
+
+#[napi(js_name = "fromJSON")]
+/// Encode JSON produced by `toJSON` back into an alias record.
+pub fn from_json(json: String, options: Option<OutputOptions>) -> Result<Output> {
+  let output = output_type(options.as_ref())?;
+  let info: alias::Info =
+    serde_json::from_str(&json).map_err(|err| Error::new(ERR_INVALID_ARG, err.to_string()))?;
+  alias::encode(&info)
+    .map(|record| output.wrap(record))
+    .map_err(to_napi_error)
+}
+
+#[napi]
+/// Package an alias record as the `blob` value of a `.DS_Store` `pict` entry.
+///
+/// Emits a process warning when the entry would not fit in a single
+/// `.DS_Store` B-tree node.
+pub fn as_ds_store_blob(
+  env: Env,
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+  options: Option<OutputOptions>,
+) -> Result<Output> {
+  let output = output_type(options.as_ref())?;
+  let buffer = record_bytes(buffer)?;
+  if buffer.len() > alias::DS_STORE_MAX_ALIAS_LENGTH {
+    emit_warning(
+      env,
+      &format!(
+        "The alias record is {} bytes, but a .DS_Store entry holds at most {} bytes of alias data.",
+        buffer.len(),
+        alias::DS_STORE_MAX_ALIAS_LENGTH
+      ),
+    )
+    .map_err(|err| Error::new("ERR_IO", err.reason))?;
+  }
+  Ok(output.wrap(alias::ds_store_blob(&buffer)))
+}
+
+#[napi]
+/// Wrap an alias record in the resource fork of a classic alias file, as
+/// its only `alis` resource. Finder uses id 0 and no name.
+pub fn wrap_as_resource_fork(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+  resource_id: Option<i32>,
+  name: Option<String>,
+  options: Option<OutputOptions>,
+) -> Result<Output> {
+  let output = output_type(options.as_ref())?;
+  let id = i16::try_from(resource_id.unwrap_or(0)).map_err(|_| {
+    Error::new(
+      ERR_INVALID_ARG,
+      format!(
+        "resourceId must fit in 16 bits, got {}.",
+        resource_id.unwrap_or(0)
+      ),
+    )
+  })?;
+  alias::wrap_as_resource_fork(&record_bytes(buffer)?, id, name.as_deref())
+    .map(|fork| output.wrap(fork))
+    .map_err(to_napi_error)
+}
+
+#[napi]
+/// The alias record in a resource fork, such as the contents of a classic
+/// alias file's `..namedfork/rsrc`: the data of its first `alis` resource.
+pub fn extract_from_resource_fork(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+  options: Option<OutputOptions>,
+) -> Result<Output> {
+  let output = output_type(options.as_ref())?;
+  alias::extract_from_resource_fork(&record_bytes(buffer)?)
+    .map(|record| output.wrap(record.to_vec()))
+    .map_err(to_napi_error)
+}
+
+#[napi(object)]
+pub struct WriteAliasFileOptions {
+  /// `'classic'`, the default, for the record in an `alis` resource of the
+  /// resource fork, or `'bookmark'` for bookmark data as current Finder
+  /// writes it.
+  #[napi(ts_type = "'classic' | 'bookmark'")]
+  pub format: Option<String>,
+  /// How the record of a classic alias file is made, as for `create`.
+  pub record: Option<CreateOptions>,
+}
 
-  let url = unsafe {
-    CFURLCreateWithFileSystemPath(kCFAllocatorDefault, a_string, kCFURLPOSIXPathStyle, TRUE)
+#[napi]
+/// Write a Finder alias file at `aliasPath` pointing to `target`, with its
+/// `kIsAlias` Finder flag set so that Finder opens the target when it is
+/// double-clicked. An existing file at `aliasPath` is replaced.
+pub fn write_alias_file(
+  #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+  alias_path: String,
+  options: Option<WriteAliasFileOptions>,
+) -> Result<()> {
+  let target_path = target_path(target)?;
+  let options = match options {
+    Some(options) => alias::AliasFileOptions {
+      format: match options.format.as_deref() {
+        None | Some("classic") => alias::AliasFileFormat::Classic,
+        Some("bookmark") => alias::AliasFileFormat::Bookmark,
+        Some(other) => {
+          return Err(Error::new(
+            ERR_INVALID_ARG,
+            format!("Unknown alias file format {other:?}, expected \"classic\" or \"bookmark\"."),
+          ))
+        }
+      },
+      record: options
+        .record
+        .map(TryInto::try_into)
+        .transpose()?
+        .unwrap_or_default(),
+    },
+    None => Default::default(),
   };
+  alias::write_alias_file(target_path, alias_path, &options).map_err(to_napi_error)
+}
 
-  let mut error = std::ptr::null_mut();
-  let mut a_string = std::ptr::null();
+#[napi(object)]
+/// What `readAliasFile` found in an alias file.
+pub struct AliasFileContents {
+  /// `'classic'` for a record from the resource fork, `'bookmark'` for
+  /// bookmark data.
+  #[napi(ts_type = "'classic' | 'bookmark'")]
+  pub format: String,
+  /// The raw record or bookmark data.
+  pub record: Buffer,
+  /// The record decoded as `decode` does, for classic alias files.
+  #[napi(ts_type = "any")]
+  pub info: Option<serde_json::Value>,
+  /// The bookmark parsed as `parseBookmark` does, for modern ones.
+  pub bookmark: Option<Bookmark>,
+}
 
-  if unsafe { CFURLCopyResourcePropertyForKey(url, kCFURLVolumeNameKey, &mut a_string, &mut error) }
-    == FALSE
-  {
-    return String::new();
+#[napi]
+/// Read the alias record or bookmark stored in the alias file at `path`,
+/// without resolving it: the `alis` resource of its resource fork, if it has
+/// one, or else the bookmark data of a modern Finder alias.
+pub fn read_alias_file(path: String) -> Result<AliasFileContents> {
+  match alias::read_alias_file(path).map_err(to_napi_error)? {
+    alias::AliasFileContents::Classic { record, info } => Ok(AliasFileContents {
+      format: "classic".to_owned(),
+      record: record.into(),
+      info: Some(
+        serde_json::to_value(info)
+          .map_err(|err| Error::new("ERR_INVALID_RECORD", err.to_string()))?,
+      ),
+      bookmark: None,
+    }),
+    alias::AliasFileContents::Bookmark { data, bookmark } => Ok(AliasFileContents {
+      format: "bookmark".to_owned(),
+      record: data.into(),
+      info: None,
+      bookmark: Some(bookmark_object(bookmark)),
+    }),
   }
+}
 
-  let len: CFIndex = unsafe { CFStringGetLength(a_string) };
-  let max_size: CFIndex = unsafe { CFStringGetMaximumSizeForEncoding(len, kCFStringEncodingUTF8) };
-  let mut string = String::with_capacity(max_size as usize);
-  if unsafe {
-    CFStringGetCString(
-      a_string,
-      string.as_mut_ptr().cast(),
-      max_size,
-      kCFStringEncodingUTF8,
-    ) == FALSE
-  } {
-    return String::new();
-  }
-  let string = unsafe { CFString::wrap_under_get_rule(a_string) };
-  string.to_string()
+#[napi(object)]
+/// Fields to replace in an existing alias record.
+pub struct AliasChanges {
+  pub volume_name: Option<String>,
+  pub target_filename: Option<String>,
+  pub parent_name: Option<String>,
+  pub target_id: Option<u32>,
+  pub parent_id: Option<u32>,
+  /// Creation date of the target, in milliseconds since the Unix epoch.
+  pub target_created: Option<f64>,
+  /// Creation date of the volume, in milliseconds since the Unix epoch.
+  pub volume_created: Option<f64>,
 }
 
-extern "C" {
-  pub fn CFURLCopyResourcePropertyForKey(
-    url: CFURLRef,
-    key: CFStringRef,
-    propertyValueTypeRefPtr: *mut CFStringRef,
-    error: *mut CFErrorRef,
-  ) -> Boolean;
+#[napi]
+/// Patch fields of an existing alias record, keeping the classic fields and
+/// the matching extras in sync. Extras that are not affected are preserved
+/// byte for byte.
+pub fn update_alias(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+  changes: AliasChanges,
+  options: Option<OutputOptions>,
+) -> Result<Output> {
+  let output = output_type(options.as_ref())?;
+  let changes = alias::Changes {
+    volume_name: changes.volume_name,
+    target_filename: changes.target_filename,
+    parent_name: changes.parent_name,
+    target_id: changes.target_id,
+    parent_id: changes.parent_id,
+    target_created: changes
+      .target_created
+      .map(|millis| system_time_from_millis("targetCreated", millis))
+      .transpose()?,
+    volume_created: changes
+      .volume_created
+      .map(|millis| system_time_from_millis("volumeCreated", millis))
+      .transpose()?,
+  };
+  alias::update_alias(&record_bytes(buffer)?, &changes)
+    .map(|record| output.wrap(record))
+    .map_err(to_napi_error)
 }
 
-#[cfg(test)]
-mod test {
-  use base64::Engine;
-  use std::time::{Duration, UNIX_EPOCH};
+#[napi(object)]
+/// Where `rebaseAlias` moves a record to.
+pub struct RebaseOptions {
+  /// Mount point of the new volume, e.g. `/Volumes/My App`.
+  pub volume_path: String,
+  /// The name Finder shows for the new volume.
+  pub volume_name: String,
+  /// The directory in the record's path that becomes the root of the new
+  /// volume, e.g. a staging directory, spelled as the record spells it.
+  /// Defaults to the recorded mount point.
+  pub from_path: Option<String>,
+  /// Creation date of the new volume, in milliseconds since the Unix epoch.
+  /// The old volume's date is kept otherwise.
+  pub volume_created: Option<f64>,
+  /// Zero the target and parent ids, which belong to the old volume.
+  pub zero_ids: Option<bool>,
+  pub target_id: Option<u32>,
+  pub parent_id: Option<u32>,
+  /// Return a plain `Uint8Array` rather than a `Buffer`.
+  #[napi(ts_type = "'buffer' | 'uint8array'")]
+  pub output: Option<String>,
+}
 
-  const FIXTURE: &str = "AAAAAAEqAAIAAApUZXN0IFRpdGxlAAAAAAAAAAAAAAAAAAAAAADO615USCsABQAAABMMVGVzdEJrZy50aWZmAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFM7rXlgAAAAAAAAAAP////8AAA0CAAAAAAAAAAAAAAAAAAAACy5iYWNrZ3JvdW5kAAABAAQAAAATAAIAJFRlc3QgVGl0bGU6LmJhY2tncm91bmQ6AFRlc3RCa2cudGlmZgAPABYACgBUAGUAcwB0ACAAVABpAHQAbABlABIAGS8uYmFja2dyb3VuZC9UZXN0QmtnLnRpZmYAABMAEy9Wb2x1bWVzL1Rlc3QgVGl0bGUA//8AAA==";
+#[napi]
+/// Rewrite an alias record to point into a different volume, e.g. from the
+/// staging directory of a disk image to where the image will be mounted, so
+/// the image does not have to be mounted to create the alias.
+pub fn rebase_alias(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+  options: RebaseOptions,
+) -> Result<Output> {
+  let output = OutputType::parse(options.output.as_deref())?;
+  let rebase = alias::Rebase {
+    from_path: options.from_path,
+    volume_path: options.volume_path,
+    volume_name: options.volume_name,
+    zero_ids: options.zero_ids.unwrap_or(false),
+    changes: alias::Changes {
+      target_id: options.target_id,
+      parent_id: options.parent_id,
+      volume_created: options
+        .volume_created
+        .map(|millis| system_time_from_millis("volumeCreated", millis))
+        .transpose()?,
+      ..Default::default()
+    },
+  };
+  alias::rebase_alias(&record_bytes(buffer)?, &rebase)
+    .map(|record| output.wrap(record))
+    .map_err(to_napi_error)
+}
 
-  #[test]
-  fn get_volume_name() {
-    let name = super::get_volume_name("/");
-    assert_eq!(name, "Macintosh HD");
-  }
+#[napi(object)]
+/// The properties `parseBookmark` understands. Anything the bookmark does not
+/// carry is left out.
+pub struct Bookmark {
+  /// Components of the target's absolute path, from the root down.
+  pub path: Vec<String>,
+  /// Absolute posix path of the target, joined from `path`.
+  pub target_path: Option<String>,
+  /// File ids of the components of `path`.
+  pub cnid_path: Vec<f64>,
+  pub file_id: Option<f64>,
+  /// Creation date of the target, in milliseconds since the Unix epoch.
+  pub created: Option<f64>,
+  /// The target's `kCFURLResourceIs*` bits (1 regular file, 2 directory,
+  /// 4 symbolic link, 8 volume, 16 package, ...).
+  pub file_flags: Option<f64>,
+  /// Mount point of the target's volume.
+  pub volume_path: Option<String>,
+  pub volume_name: Option<String>,
+  pub volume_uuid: Option<String>,
+  /// Creation date of the volume, in milliseconds since the Unix epoch.
+  pub volume_created: Option<f64>,
+  /// The volume's property bits (1 local, 2 automounted, ...).
+  pub volume_flags: Option<f64>,
+}
 
-  #[test]
-  fn decode() {
-    let encoded = super::encode(super::Info {
-      version: 2,
-      volume: super::Volume {
-        name: "Test Title".to_owned(),
-        created: UNIX_EPOCH + Duration::from_millis(1388686804000),
-        signature: crate::VolumeSignature::HPlus,
-        type_: crate::VolumeType::Other,
-      },
-      parent: super::Parent {
-        id: 19,
-        name: ".background".to_owned(),
-      },
-      target: super::Target {
-        id: 20,
-        type_: super::TargetType::File,
-        filename: "TestBkg.tiff".to_owned(),
-        created: UNIX_EPOCH + Duration::from_millis(1388686808000),
-      },
-      extra: vec![
-        super::Extra {
-          type_: 0,
-          length: 11,
-          data: ".background".as_bytes().to_vec(),
-        },
-        super::Extra {
-          type_: 1,
-          length: 4,
-          data: vec![0, 0, 0, 19],
-        },
-        super::Extra {
-          type_: 2,
-          length: 36,
-          data: vec![
-            84, 101, 115, 116, 32, 84, 105, 116, 108, 101, 58, 46, 98, 97, 99, 107, 103, 114, 111,
-            117, 110, 100, 58, 0, 84, 101, 115, 116, 66, 107, 103, 46, 116, 105, 102, 102,
-          ],
-        },
-        super::Extra {
-          type_: 15,
-          length: 22,
-          data: vec![
-            0, 10, 0, 84, 0, 101, 0, 115, 0, 116, 0, 32, 0, 84, 0, 105, 0, 116, 0, 108, 0, 101,
-          ],
-        },
-        super::Extra {
-          type_: 18,
-          length: 25,
-          data: vec![
-            47, 46, 98, 97, 99, 107, 103, 114, 111, 117, 110, 100, 47, 84, 101, 115, 116, 66, 107,
-            103, 46, 116, 105, 102, 102,
-          ],
-        },
-        super::Extra {
-          type_: 19,
-          length: 19,
-          data: vec![
-            47, 86, 111, 108, 117, 109, 101, 115, 47, 84, 101, 115, 116, 32, 84, 105, 116, 108, 101,
-          ],
-        },
-      ],
-    })
-    .expect("Should be able to encode");
-    assert_eq!(
-      base64::engine::general_purpose::STANDARD
-        .decode(FIXTURE)
-        .unwrap(),
-      encoded
-    );
+#[napi]
+/// Parse bookmark (`book`) data, as found in alias files written by modern
+/// macOS, without resolving it.
+pub fn parse_bookmark(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+) -> Result<Bookmark> {
+  alias::parse_bookmark(&record_bytes(buffer)?)
+    .map(bookmark_object)
+    .map_err(to_napi_error)
+}
+
+fn bookmark_object(bookmark: alias::Bookmark) -> Bookmark {
+  Bookmark {
+    target_path: bookmark.posix_path(),
+    cnid_path: bookmark.cnid_path.iter().map(|id| *id as f64).collect(),
+    file_id: bookmark.file_id.map(|id| id as f64),
+    created: bookmark.created.map(millis_from_system_time),
+    file_flags: bookmark.file_flags.map(|flags| flags as f64),
+    volume_created: bookmark.volume_created.map(millis_from_system_time),
+    volume_flags: bookmark.volume_flags.map(|flags| flags as f64),
+    path: bookmark.path,
+    volume_path: bookmark.volume_path,
+    volume_name: bookmark.volume_name,
+    volume_uuid: bookmark.volume_uuid,
   }
 }
+
+#[napi(object)]
+/// One entry of a bookmark's table of contents.
+pub struct BookmarkEntry {
+  /// The numeric key, e.g. `0x1004` for the path. For keys naming a string
+  /// item, the high bit is set.
+  pub key: u32,
+  /// The string a string key names, or the usual name of a well-known
+  /// numeric key, e.g. `path`.
+  pub name: Option<String>,
+  #[napi(ts_type = "import('./types').BookmarkValue")]
+  pub value: serde_json::Value,
+}
+
+#[napi(object)]
+pub struct BookmarkToc {
+  pub identifier: u32,
+  pub entries: Vec<BookmarkEntry>,
+}
+
+#[napi(object)]
+/// Everything `decodeBookmark` reads from bookmark data.
+pub struct DecodedBookmark {
+  /// What `parseBookmark` returns.
+  pub properties: Bookmark,
+  /// The tables of contents, usually one, in the order they are chained.
+  pub tocs: Vec<BookmarkToc>,
+}
+
+/// `value` as `{ type, value }`, with data as base64 and dates in
+/// milliseconds since the Unix epoch.
+fn bookmark_value(value: alias::BookmarkValue) -> serde_json::Value {
+  use alias::BookmarkValue as V;
+  use serde_json::json;
+
+  let (type_, value) = match value {
+    V::String(s) => ("string", json!(s)),
+    V::Data(data) => ("data", json!(BASE64.encode(data))),
+    V::Number(n) => ("number", json!(n)),
+    V::Float(f) => ("float", json!(f)),
+    V::Date(date) => ("date", json!(millis_from_system_time(date))),
+    V::Bool(b) => ("boolean", json!(b)),
+    V::Array(items) => (
+      "array",
+      items
+        .into_iter()
+        .map(bookmark_value)
+        .collect::<serde_json::Value>(),
+    ),
+    V::Dictionary(pairs) => (
+      "dictionary",
+      pairs
+        .into_iter()
+        .map(|(key, value)| json!([bookmark_value(key), bookmark_value(value)]))
+        .collect::<serde_json::Value>(),
+    ),
+    V::Uuid(uuid) => ("uuid", json!(uuid)),
+    V::Url(url) => ("url", json!(url)),
+    V::Null => ("null", serde_json::Value::Null),
+    V::Other { type_, data } => {
+      return json!({ "type": "other", "code": type_, "value": BASE64.encode(data) })
+    }
+  };
+  json!({ "type": type_, "value": value })
+}
+
+#[napi]
+/// Parse bookmark (`book`) data into every entry of its tables of
+/// contents, along with what `parseBookmark` returns, for inspecting what
+/// Finder and other apps stored.
+pub fn decode_bookmark(
+  #[napi(ts_arg_type = "string | Buffer | Uint8Array | ArrayBuffer")] buffer: RecordData,
+) -> Result<DecodedBookmark> {
+  let decoded = alias::decode_bookmark(&record_bytes(buffer)?).map_err(to_napi_error)?;
+  Ok(DecodedBookmark {
+    properties: bookmark_object(decoded.properties),
+    tocs: decoded
+      .tocs
+      .into_iter()
+      .map(|toc| BookmarkToc {
+        identifier: toc.identifier,
+        entries: toc
+          .entries
+          .into_iter()
+          .map(|entry| BookmarkEntry {
+            key: entry.key,
+            name: entry.name,
+            value: bookmark_value(entry.value),
+          })
+          .collect(),
+      })
+      .collect(),
+  })
+}
+
+#[napi(object)]
+pub struct BookmarkOptions {
+  /// Record this volume name instead of the one looked up.
+  pub volume_name: Option<String>,
+  /// Record this volume UUID, like `0A81F3B1-51D9-3335-B3E3-169C3640360D`,
+  /// instead of the volume's own.
+  pub volume_uuid: Option<String>,
+  /// Return a plain `Uint8Array` rather than a `Buffer`.
+  #[napi(ts_type = "'buffer' | 'uint8array'")]
+  pub output: Option<String>,
+}
+
+#[napi]
+/// Bookmark (`book`) data for the file or directory at `target`, as current
+/// macOS writes into Finder aliases: its path with the file id of each
+/// component, its creation date and its volume's mount point, URL, name,
+/// UUID, creation date and properties.
+pub fn create_bookmark(
+  #[napi(ts_arg_type = "string | URL")] target: Either<String, Object>,
+  options: Option<BookmarkOptions>,
+) -> Result<Output> {
+  let output = OutputType::parse(
+    options
+      .as_ref()
+      .and_then(|options| options.output.as_deref()),
+  )?;
+  let options = match options {
+    Some(options) => alias::BookmarkOptions {
+      volume_uuid: options
+        .volume_uuid
+        .map(|uuid| uuid.parse())
+        .transpose()
+        .map_err(|err: alias::Error| Error::new(ERR_INVALID_ARG, err.to_string()))?,
+      volume_name: options.volume_name,
+    },
+    None => Default::default(),
+  };
+  alias::create_bookmark(target_path(target)?, &options)
+    .map(|bookmark| output.wrap(bookmark))
+    .map_err(to_napi_error)
+}
+
+#[napi]
+/// Encode bookmark data from known properties, as `parseBookmark` returns
+/// them, without touching the filesystem. Unlike `createBookmark`, this
+/// works on every platform. `targetPath` is ignored in favor of `path`.
+pub fn encode_bookmark(bookmark: Bookmark, options: Option<OutputOptions>) -> Result<Output> {
+  let output = output_type(options.as_ref())?;
+  let bookmark = alias::Bookmark {
+    path: bookmark.path,
+    cnid_path: bookmark
+      .cnid_path
+      .into_iter()
+      .map(|id| whole_number("cnidPath", id))
+      .collect::<Result<_>>()?,
+    file_id: bookmark
+      .file_id
+      .map(|id| whole_number("fileId", id))
+      .transpose()?,
+    created: bookmark
+      .created
+      .map(|millis| system_time_from_millis("created", millis))
+      .transpose()?,
+    file_flags: bookmark
+      .file_flags
+      .map(|flags| whole_number("fileFlags", flags))
+      .transpose()?,
+    volume_path: bookmark.volume_path,
+    volume_name: bookmark.volume_name,
+    volume_uuid: bookmark.volume_uuid,
+    volume_created: bookmark
+      .volume_created
+      .map(|millis| system_time_from_millis("volumeCreated", millis))
+      .transpose()?,
+    volume_flags: bookmark
+      .volume_flags
+      .map(|flags| whole_number("volumeFlags", flags))
+      .transpose()?,
+  };
+  alias::encode_bookmark(&bookmark)
+    .map(|bookmark| output.wrap(bookmark))
+    .map_err(to_napi_error)
+}
+
+#[napi]
+/// Run the `macos-alias` command line tool with `args` and return its exit
+/// code. This backs the npm package's `macos-alias` bin.
+pub fn run_cli(args: Vec<String>) -> i32 {
+  macos_alias_cli::run(
+    &args,
+    &mut std::io::stdin(),
+    &mut std::io::stdout(),
+    &mut std::io::stderr(),
+  )
+}
@@ -0,0 +1,111 @@
+/*
+ * Hand-maintained: what `napi build` cannot declare in the generated
+ * `index.d.ts`, which is re-exported below with its documentation.
+ */
+
+import type { AliasChanges, AliasInfo, Bookmark, BookmarkOptions, CreateDebugResult, CreateDetailedResult, CreateOptions, OutputOptions, RebaseOptions, StagedDmgTarget, TreeOptions } from './index'
+
+export * from './index'
+
+// Every function returning a record declares `Buffer | Uint8Array`; these
+// overloads narrow that to the type the `output` option picks.
+
+export function create(target: string | URL, options: CreateOptions & { output: 'uint8array' }): Uint8Array
+export function create(target: string | URL, options?: (CreateOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function create(target: string | URL, options?: CreateOptions | undefined | null): Buffer | Uint8Array
+
+export function createAsync(target: string | URL, options: CreateOptions & { output: 'uint8array' }): Promise<Uint8Array>
+export function createAsync(target: string | URL, options?: (CreateOptions & { output?: 'buffer' }) | undefined | null): Promise<Buffer>
+export function createAsync(target: string | URL, options?: CreateOptions | undefined | null): Promise<Buffer | Uint8Array>
+
+export function createDetailed(target: string | URL, options: CreateOptions & { output: 'uint8array' }): CreateDetailedResult & { buffer: Uint8Array }
+export function createDetailed(target: string | URL, options?: (CreateOptions & { output?: 'buffer' }) | undefined | null): CreateDetailedResult & { buffer: Buffer }
+export function createDetailed(target: string | URL, options?: CreateOptions | undefined | null): CreateDetailedResult
+
+export function createDebug(target: string | URL, options: CreateOptions & { output: 'uint8array' }): CreateDebugResult & { buffer: Uint8Array }
+export function createDebug(target: string | URL, options?: (CreateOptions & { output?: 'buffer' }) | undefined | null): CreateDebugResult & { buffer: Buffer }
+export function createDebug(target: string | URL, options?: CreateOptions | undefined | null): CreateDebugResult
+
+export function relinkAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, options: OutputOptions & { output: 'uint8array' }): Uint8Array
+export function relinkAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, options?: (OutputOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function relinkAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+
+export function createForFd(fd: number, pathHint: string | undefined | null, options: CreateOptions & { output: 'uint8array' }): Uint8Array
+export function createForFd(fd: number, pathHint?: string | undefined | null, options?: (CreateOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function createForFd(fd: number, pathHint?: string | undefined | null, options?: CreateOptions | undefined | null): Buffer | Uint8Array
+
+export function createForTree(root: string | URL, tree: TreeOptions | undefined | null, options: CreateOptions & { output: 'uint8array' }): Record<string, Uint8Array | Error>
+export function createForTree(root: string | URL, tree?: TreeOptions | undefined | null, options?: (CreateOptions & { output?: 'buffer' }) | undefined | null): Record<string, Buffer | Error>
+export function createForTree(root: string | URL, tree?: TreeOptions | undefined | null, options?: CreateOptions | undefined | null): Record<string, Buffer | Uint8Array | Error>
+
+export function createForStagedDmg(target: StagedDmgTarget, options: CreateOptions & { output: 'uint8array' }): Uint8Array
+export function createForStagedDmg(target: StagedDmgTarget, options?: (CreateOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function createForStagedDmg(target: StagedDmgTarget, options?: CreateOptions | undefined | null): Buffer | Uint8Array
+
+export function createBackgroundAlias(stagingDir: string, imagePath: string, volumeName: string, options: CreateOptions & { output: 'uint8array' }): Uint8Array
+export function createBackgroundAlias(stagingDir: string, imagePath: string, volumeName: string, options?: (CreateOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function createBackgroundAlias(stagingDir: string, imagePath: string, volumeName: string, options?: CreateOptions | undefined | null): Buffer | Uint8Array
+
+export function createFromInfo(info: AliasInfo, options: OutputOptions & { output: 'uint8array' }): Uint8Array
+export function createFromInfo(info: AliasInfo, options?: (OutputOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function createFromInfo(info: AliasInfo, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+
+export function fromJSON(json: string, options: OutputOptions & { output: 'uint8array' }): Uint8Array
+export function fromJSON(json: string, options?: (OutputOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function fromJSON(json: string, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+
+export function encodeInfo(info: any, options: CreateOptions & { output: 'uint8array' }): Uint8Array
+export function encodeInfo(info: any, options?: (CreateOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function encodeInfo(info: any, options?: CreateOptions | undefined | null): Buffer | Uint8Array
+
+export function asDsStoreBlob(buffer: string | Buffer | Uint8Array | ArrayBuffer, options: OutputOptions & { output: 'uint8array' }): Uint8Array
+export function asDsStoreBlob(buffer: string | Buffer | Uint8Array | ArrayBuffer, options?: (OutputOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function asDsStoreBlob(buffer: string | Buffer | Uint8Array | ArrayBuffer, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+
+export function wrapAsResourceFork(buffer: string | Buffer | Uint8Array | ArrayBuffer, resourceId: number | undefined | null, name: string | undefined | null, options: OutputOptions & { output: 'uint8array' }): Uint8Array
+export function wrapAsResourceFork(buffer: string | Buffer | Uint8Array | ArrayBuffer, resourceId?: number | undefined | null, name?: string | undefined | null, options?: (OutputOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function wrapAsResourceFork(buffer: string | Buffer | Uint8Array | ArrayBuffer, resourceId?: number | undefined | null, name?: string | undefined | null, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+
+export function extractFromResourceFork(buffer: string | Buffer | Uint8Array | ArrayBuffer, options: OutputOptions & { output: 'uint8array' }): Uint8Array
+export function extractFromResourceFork(buffer: string | Buffer | Uint8Array | ArrayBuffer, options?: (OutputOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function extractFromResourceFork(buffer: string | Buffer | Uint8Array | ArrayBuffer, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+
+export function updateAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, changes: AliasChanges, options: OutputOptions & { output: 'uint8array' }): Uint8Array
+export function updateAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, changes: AliasChanges, options?: (OutputOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function updateAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, changes: AliasChanges, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+
+export function rebaseAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, options: RebaseOptions & { output: 'uint8array' }): Uint8Array
+export function rebaseAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, options: RebaseOptions & { output?: 'buffer' }): Buffer
+export function rebaseAlias(buffer: string | Buffer | Uint8Array | ArrayBuffer, options: RebaseOptions): Buffer | Uint8Array
+
+export function createBookmark(target: string | URL, options: BookmarkOptions & { output: 'uint8array' }): Uint8Array
+export function createBookmark(target: string | URL, options?: (BookmarkOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function createBookmark(target: string | URL, options?: BookmarkOptions | undefined | null): Buffer | Uint8Array
+
+export function encodeBookmark(bookmark: Bookmark, options: OutputOptions & { output: 'uint8array' }): Uint8Array
+export function encodeBookmark(bookmark: Bookmark, options?: (OutputOptions & { output?: 'buffer' }) | undefined | null): Buffer
+export function encodeBookmark(bookmark: Bookmark, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+
+// `napi build` declares `systemAliasFor` unconditionally, but the binding
+// only has it in macOS builds with the `system-alias` cargo feature.
+export const systemAliasFor:
+  | {
+      (path: string, options: OutputOptions & { output: 'uint8array' }): Uint8Array
+      (path: string, options?: (OutputOptions & { output?: 'buffer' }) | undefined | null): Buffer
+      (path: string, options?: OutputOptions | undefined | null): Buffer | Uint8Array
+    }
+  | undefined
+
+// `napi build` cannot declare the `value` of a `BookmarkEntry`.
+/**
+ * An item of bookmark data, with data as base64 and dates in milliseconds
+ * since the Unix epoch. Items of other types keep their type code.
+ */
+export type BookmarkValue =
+  | { type: 'string' | 'data' | 'uuid' | 'url'; value: string }
+  | { type: 'number' | 'float' | 'date'; value: number }
+  | { type: 'boolean'; value: boolean }
+  | { type: 'null'; value: null }
+  | { type: 'array'; value: Array<BookmarkValue> }
+  | { type: 'dictionary'; value: Array<[BookmarkValue, BookmarkValue]> }
+  | { type: 'other'; code: number; value: string }
@@ -0,0 +1,154 @@
+#![deny(clippy::all)]
+
+//! The byte-level half of the addon for platforms without a prebuilt native
+//! binary, which `main.js` loads in its place. Records are made from
+//! supplied metadata, as an `AliasInfo` or in the JSON shape of `toJSON`;
+//! nothing is looked up on the filesystem.
+
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use macos_alias_core as alias;
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+/// A JS `Error` with a `code`, as the native binding throws.
+fn js_error(code: &str, message: impl fmt::Display) -> JsValue {
+  let error = js_sys::Error::new(&message.to_string());
+  // Setting a property on a fresh `Error` cannot fail.
+  let _ = js_sys::Reflect::set(&error, &"code".into(), &code.into());
+  error.into()
+}
+
+fn alias_error(err: alias::Error) -> JsValue {
+  js_error(err.code(), err)
+}
+
+/// The metadata `createFromInfo` takes, in the shape of the native
+/// binding's `AliasInfo`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AliasInfo {
+  target_path: String,
+  target_type: Option<alias::TargetType>,
+  file_type: Option<String>,
+  file_creator: Option<String>,
+  target_id: u32,
+  target_created: f64,
+  parent_id: u32,
+  parent_name: Option<String>,
+  volume_path: String,
+  volume_name: String,
+  volume_created: f64,
+  volume_type: Option<alias::VolumeType>,
+  volume_signature: Option<alias::VolumeSignature>,
+}
+
+fn system_time_from_millis(field: &str, millis: f64) -> Result<SystemTime, JsValue> {
+  if !millis.is_finite() || millis < 0.0 {
+    return Err(js_error(
+      "ERR_INVALID_ARG",
+      format!("{field} must be a non-negative number of milliseconds since the Unix epoch."),
+    ));
+  }
+  Ok(UNIX_EPOCH + Duration::from_millis(millis as u64))
+}
+
+fn file_name(path: &Path) -> Result<String, JsValue> {
+  path
+    .file_name()
+    .and_then(|s| s.to_str())
+    .map(|s| s.to_string())
+    .ok_or_else(|| {
+      js_error(
+        "ERR_INVALID_ARG",
+        format!("{} has no file name.", path.display()),
+      )
+    })
+}
+
+fn four_char_code(field: &str, value: Option<&str>) -> Result<[u8; 4], JsValue> {
+  match value {
+    None => Ok([0; 4]),
+    Some(code) => code.as_bytes().try_into().map_err(|_| {
+      js_error(
+        "ERR_INVALID_ARG",
+        format!("{field} must be a four character code, got {code:?}."),
+      )
+    }),
+  }
+}
+
+/// Generate an alias record from the JSON of an `AliasInfo` object, with
+/// the same defaults as the native `createFromInfo`.
+#[wasm_bindgen(js_name = createFromInfo)]
+pub fn create_from_info(json: &str) -> Result<Vec<u8>, JsValue> {
+  let info: AliasInfo =
+    serde_json::from_str(json).map_err(|err| js_error("ERR_INVALID_ARG", err))?;
+  let target_path = Path::new(&info.target_path);
+  let parent_path = target_path.parent().ok_or_else(|| {
+    js_error(
+      "ERR_INVALID_ARG",
+      "The target path has no parent directory.",
+    )
+  })?;
+  let target = alias::Target {
+    type_: info.target_type.unwrap_or(alias::TargetType::File),
+    filename: file_name(target_path)?,
+    id: info.target_id,
+    created: system_time_from_millis("targetCreated", info.target_created)?,
+  };
+  let parent = alias::Parent {
+    id: info.parent_id,
+    // The root directory of a volume goes by the volume's name.
+    name: match info.parent_name {
+      Some(name) => name,
+      None if parent_path == Path::new(&info.volume_path) => info.volume_name.clone(),
+      None => file_name(parent_path)?,
+    },
+  };
+  let volume = alias::Volume {
+    type_: match info.volume_type {
+      Some(type_) => type_,
+      None if info.volume_path == "/" => alias::VolumeType::Local,
+      None => alias::VolumeType::Other,
+    },
+    name: info.volume_name,
+    created: system_time_from_millis("volumeCreated", info.volume_created)?,
+    signature: info
+      .volume_signature
+      .unwrap_or(alias::VolumeSignature::HPlus),
+  };
+  volume.check_kind().map_err(alias_error)?;
+  let classic = alias::ClassicFields {
+    file_type: four_char_code("fileType", info.file_type.as_deref())?,
+    file_creator: four_char_code("fileCreator", info.file_creator.as_deref())?,
+    ..Default::default()
+  };
+  let info = alias::build_info(&info.target_path, &info.volume_path, target, parent, volume)
+    .map_err(alias_error)?;
+  alias::encode(&alias::Info { classic, ..info }).map_err(alias_error)
+}
+
+/// `DS_STORE_MAX_ALIAS_LENGTH`, which `main.js` exports as a value.
+#[wasm_bindgen(js_name = dsStoreMaxAliasLength)]
+pub fn ds_store_max_alias_length() -> u32 {
+  alias::DS_STORE_MAX_ALIAS_LENGTH as u32
+}
+
+/// Encode JSON produced by `toJSON` back into an alias record.
+#[wasm_bindgen(js_name = fromJSON)]
+pub fn from_json(json: &str) -> Result<Vec<u8>, JsValue> {
+  let info: alias::Info =
+    serde_json::from_str(json).map_err(|err| js_error("ERR_INVALID_ARG", err))?;
+  alias::encode(&info).map_err(alias_error)
+}
+
+/// Decode an alias record into JSON, with dates as Unix milliseconds and
+/// extra data as base64.
+#[wasm_bindgen(js_name = toJSON)]
+pub fn to_json(record: &[u8]) -> Result<String, JsValue> {
+  let info = alias::decode(record).map_err(alias_error)?;
+  serde_json::to_string(&info).map_err(|err| js_error("ERR_INVALID_RECORD", err))
+}
@@ -0,0 +1,46 @@
+//! Run with `wasm-pack test --node wasm`.
+
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const FIXTURE: &[u8] = include_bytes!("../../fuzz/corpus/decode/fixture.bin");
+
+fn code(err: JsValue) -> Option<String> {
+  js_sys::Reflect::get(&err, &"code".into())
+    .ok()
+    .and_then(|code| code.as_string())
+}
+
+#[wasm_bindgen_test]
+fn round_trip() {
+  let json = macos_alias_wasm::to_json(FIXTURE).unwrap();
+  assert!(json.contains("\"name\":\"Test Title\""), "{json}");
+  assert_eq!(macos_alias_wasm::from_json(&json).unwrap(), FIXTURE);
+}
+
+#[wasm_bindgen_test]
+fn create_from_info() {
+  let info = r#"{
+    "targetPath": "/Volumes/Test Title/.background/TestBkg.tiff",
+    "targetId": 20,
+    "targetCreated": 1388686808000,
+    "parentId": 19,
+    "volumePath": "/Volumes/Test Title",
+    "volumeName": "Test Title",
+    "volumeCreated": 1388686804000
+  }"#;
+  let record = macos_alias_wasm::create_from_info(info).unwrap();
+  let json = macos_alias_wasm::to_json(&record).unwrap();
+  assert!(json.contains("\"filename\":\"TestBkg.tiff\""), "{json}");
+  let err = macos_alias_wasm::create_from_info(&info.replace("20,", "20, \"fileType\": \"TIF\","))
+    .unwrap_err();
+  assert_eq!(code(err).as_deref(), Some("ERR_INVALID_ARG"));
+}
+
+#[wasm_bindgen_test]
+fn error_codes() {
+  let err = macos_alias_wasm::to_json(&FIXTURE[..10]).unwrap_err();
+  assert_eq!(code(err).as_deref(), Some("ERR_INVALID_RECORD"));
+  let err = macos_alias_wasm::from_json("{").unwrap_err();
+  assert_eq!(code(err).as_deref(), Some("ERR_INVALID_ARG"));
+}